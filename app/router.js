@@ -49,6 +49,7 @@ Router.map(function () {
   this.route('data-access');
   this.route('confirm', { path: '/confirm/:email_token' });
   this.route('accept-invite', { path: '/accept-invite/:token' });
+  this.route('accept-transfer', { path: '/accept-transfer/:token' });
 
   this.route('catch-all', { path: '*path' });
 });
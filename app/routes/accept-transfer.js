@@ -0,0 +1,16 @@
+import Route from '@ember/routing/route';
+
+import ajax from '../utils/ajax';
+
+export default class AcceptTransferRoute extends Route {
+  async model(params) {
+    try {
+      await ajax(`/api/v1/me/crate_ownership_transfers/accept/${params.token}`, { method: 'PUT', body: '{}' });
+      return { ok: true };
+    } catch (error) {
+      let json = await error.json?.();
+      let errorText = json?.errors?.[0]?.detail;
+      return { ok: false, errorText };
+    }
+  }
+}
@@ -0,0 +1,2 @@
+DROP TABLE crate_ownership_transfers;
+DROP TABLE crate_owner_actions;
@@ -0,0 +1,21 @@
+CREATE TABLE crate_owner_actions (
+    id SERIAL PRIMARY KEY,
+    crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id),
+    api_token_id INTEGER REFERENCES api_tokens (id),
+    action INTEGER NOT NULL,
+    time TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX index_crate_owner_actions_by_crate_id ON crate_owner_actions (crate_id);
+
+CREATE TABLE crate_ownership_transfers (
+    crate_id INTEGER PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+    requested_by_user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    recipient_id INTEGER NOT NULL,
+    recipient_kind INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    token TEXT NOT NULL DEFAULT random_string(26)
+);
+
+CREATE UNIQUE INDEX index_crate_ownership_transfers_token ON crate_ownership_transfers (token);
@@ -0,0 +1,3 @@
+-- 1 = yank, 2 = publish (includes yank), 3 = full
+ALTER TABLE crate_owners ADD COLUMN rights INTEGER NOT NULL DEFAULT 2;
+UPDATE crate_owners SET rights = 3 WHERE owner_kind = 0;
@@ -0,0 +1,8 @@
+CREATE TABLE team_memberships (
+    team_id INTEGER NOT NULL REFERENCES teams (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    is_member BOOLEAN NOT NULL,
+    checked_at TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (team_id, user_id)
+);
+CREATE INDEX index_team_memberships_checked_at ON team_memberships (checked_at);
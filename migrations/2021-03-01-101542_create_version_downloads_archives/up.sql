@@ -0,0 +1,6 @@
+CREATE TABLE version_downloads_archives (
+    month DATE NOT NULL PRIMARY KEY,
+    path VARCHAR NOT NULL,
+    downloads BIGINT NOT NULL,
+    archived_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,9 @@
+CREATE TABLE version_downloads_by_client (
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    date DATE NOT NULL DEFAULT CURRENT_DATE,
+    client VARCHAR NOT NULL,
+    downloads INTEGER NOT NULL,
+    PRIMARY KEY (version_id, date, client)
+);
+
+CREATE INDEX index_version_downloads_by_client_date ON version_downloads_by_client (date);
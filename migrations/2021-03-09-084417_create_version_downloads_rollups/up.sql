@@ -0,0 +1,19 @@
+CREATE TABLE version_downloads_rollups (
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    granularity VARCHAR NOT NULL CHECK (granularity IN ('week', 'month')),
+    period DATE NOT NULL,
+    downloads INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (version_id, granularity, period)
+);
+
+-- Only the downloads already propagated by the `update_downloads` task are included, the rest
+-- will be added to the rollups the next time the task runs.
+INSERT INTO version_downloads_rollups (version_id, granularity, period, downloads)
+    SELECT version_id, 'week', date_trunc('week', date)::date, SUM(counted)
+    FROM version_downloads
+    GROUP BY version_id, date_trunc('week', date)::date;
+
+INSERT INTO version_downloads_rollups (version_id, granularity, period, downloads)
+    SELECT version_id, 'month', date_trunc('month', date)::date, SUM(counted)
+    FROM version_downloads
+    GROUP BY version_id, date_trunc('month', date)::date;
@@ -0,0 +1,2 @@
+DROP FUNCTION refresh_crate_rankings();
+DROP MATERIALIZED VIEW crate_rankings;
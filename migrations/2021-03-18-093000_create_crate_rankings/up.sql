@@ -0,0 +1,42 @@
+-- Query-independent signals blended with the text rank to order search results by relevance.
+-- Each signal is normalized between 0 and 1, so that their weights are comparable.
+CREATE MATERIALIZED VIEW crate_rankings (crate_id, downloads_score, dependents_score, recency_score) AS
+  WITH latest_versions AS (
+    SELECT DISTINCT ON (crate_id) id, crate_id, created_at FROM versions
+      WHERE NOT yanked
+      ORDER BY crate_id, id DESC
+  ), dependents AS (
+    SELECT dependencies.crate_id, COUNT(DISTINCT latest_versions.crate_id) AS dependents
+      FROM dependencies
+      INNER JOIN latest_versions
+        ON dependencies.version_id = latest_versions.id
+      GROUP BY dependencies.crate_id
+  ), signals AS (
+    SELECT
+      crates.id AS crate_id,
+      ln(1 + COALESCE(recent_crate_downloads.downloads, 0)::float8) AS downloads,
+      ln(1 + COALESCE(dependents.dependents, 0)::float8) AS dependents,
+      latest_versions.created_at AS last_release_at
+    FROM crates
+      LEFT JOIN recent_crate_downloads
+        ON recent_crate_downloads.crate_id = crates.id
+      LEFT JOIN dependents
+        ON dependents.crate_id = crates.id
+      LEFT JOIN latest_versions
+        ON latest_versions.crate_id = crates.id
+  )
+  SELECT
+    crate_id,
+    COALESCE(downloads / NULLIF(MAX(downloads) OVER (), 0), 0),
+    COALESCE(dependents / NULLIF(MAX(dependents) OVER (), 0), 0),
+    -- Halves every year since the latest non-yanked release
+    COALESCE(
+      power(0.5, EXTRACT(EPOCH FROM CURRENT_TIMESTAMP - last_release_at)::float8 / (365 * 86400)),
+      0
+    )
+  FROM signals;
+CREATE UNIQUE INDEX crate_rankings_crate_id ON crate_rankings (crate_id);
+
+CREATE FUNCTION refresh_crate_rankings() RETURNS VOID AS $$
+  REFRESH MATERIALIZED VIEW CONCURRENTLY crate_rankings;
+$$ LANGUAGE SQL;
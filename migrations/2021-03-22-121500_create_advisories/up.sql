@@ -0,0 +1,17 @@
+CREATE TABLE advisories (
+    id VARCHAR PRIMARY KEY,
+    crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+    title VARCHAR NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    url VARCHAR,
+    date DATE NOT NULL,
+    informational VARCHAR,
+    aliases TEXT[] NOT NULL DEFAULT '{}',
+    patched TEXT[] NOT NULL DEFAULT '{}',
+    unaffected TEXT[] NOT NULL DEFAULT '{}',
+    withdrawn DATE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX advisories_crate_id ON advisories (crate_id);
@@ -0,0 +1,7 @@
+ALTER TABLE crate_owners
+    DROP COLUMN publish_notifications,
+    DROP COLUMN yank_notifications,
+    DROP COLUMN ownership_notifications,
+    DROP COLUMN advisory_notifications;
+
+DROP TABLE notification_preferences;
@@ -0,0 +1,15 @@
+CREATE TABLE notification_preferences (
+    user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    publish BOOLEAN NOT NULL DEFAULT TRUE,
+    yank BOOLEAN NOT NULL DEFAULT TRUE,
+    ownership BOOLEAN NOT NULL DEFAULT TRUE,
+    advisories BOOLEAN NOT NULL DEFAULT TRUE,
+    token_expiry BOOLEAN NOT NULL DEFAULT TRUE,
+    unsubscribe_token TEXT NOT NULL UNIQUE DEFAULT random_string(26)
+);
+
+ALTER TABLE crate_owners
+    ADD COLUMN publish_notifications BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN yank_notifications BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN ownership_notifications BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN advisory_notifications BOOLEAN NOT NULL DEFAULT TRUE;
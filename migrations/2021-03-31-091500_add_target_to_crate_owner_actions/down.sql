@@ -0,0 +1,3 @@
+ALTER TABLE crate_owner_actions
+    DROP COLUMN target_id,
+    DROP COLUMN target_kind;
@@ -0,0 +1,3 @@
+ALTER TABLE crate_owner_actions
+    ADD COLUMN target_id INTEGER,
+    ADD COLUMN target_kind INTEGER;
@@ -0,0 +1,4 @@
+ALTER TABLE crate_owner_actions DROP COLUMN rights;
+
+ALTER TABLE crate_owners ALTER COLUMN rights SET DEFAULT 2;
+UPDATE crate_owners SET rights = CASE rights WHEN 3 THEN 2 WHEN 7 THEN 3 ELSE rights END;
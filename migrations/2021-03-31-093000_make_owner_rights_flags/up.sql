@@ -0,0 +1,6 @@
+-- Rights are now flags: 1 = yank, 2 = publish, 7 = full (which also allows managing owners).
+-- Publishing rights used to include yanking.
+UPDATE crate_owners SET rights = CASE rights WHEN 2 THEN 3 WHEN 3 THEN 7 ELSE rights END;
+ALTER TABLE crate_owners ALTER COLUMN rights SET DEFAULT 3;
+
+ALTER TABLE crate_owner_actions ADD COLUMN rights INTEGER;
@@ -0,0 +1,6 @@
+-- `GET /api/v1/crates/suggest` would shadow the crate with that name. Reserving the name fails if
+-- such a crate was already published, so it's only reserved when the name is still free.
+INSERT INTO reserved_crate_names (name)
+SELECT 'suggest'
+WHERE NOT EXISTS (SELECT 1 FROM crates WHERE canon_crate_name(name) = 'suggest')
+ON CONFLICT DO NOTHING;
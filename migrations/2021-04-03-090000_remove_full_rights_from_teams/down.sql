@@ -0,0 +1,2 @@
+-- This file intentionally left blank; the rights removed from teams can't be told apart
+-- from the ones they already had.
@@ -0,0 +1,2 @@
+-- Team members can't modify owners, so teams only keep the rights to publish and yank.
+UPDATE crate_owners SET rights = 3 WHERE owner_kind = 1 AND rights = 7;
@@ -0,0 +1 @@
+DROP TABLE advisory_db_syncs;
@@ -0,0 +1,6 @@
+CREATE TABLE advisory_db_syncs (
+    synced_at TIMESTAMP PRIMARY KEY DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Registries which already imported advisories don't need to import them silently again
+INSERT INTO advisory_db_syncs (synced_at) SELECT MIN(created_at) FROM advisories HAVING COUNT(*) > 0;
@@ -0,0 +1,65 @@
+use crate::{
+    db,
+    schema::{crates, version_downloads_archives, versions},
+    tasks::archive_version_downloads::fetch_archive,
+    Config,
+};
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use clap::Clap;
+use diesel::prelude::*;
+use reqwest::blocking::Client;
+
+#[derive(Clap, Debug)]
+#[clap(
+    name = "archived-downloads",
+    about = "Prints the monthly downloads of each version of a crate that were moved out of \
+        the `version_downloads` table into archives."
+)]
+pub struct Opts {
+    /// Name of the crate
+    crate_name: String,
+}
+
+pub fn run(opts: Opts) -> Result<()> {
+    let config = Config::default();
+    let conn = db::connect_now()?;
+
+    let versions: HashMap<i32, String> = versions::table
+        .inner_join(crates::table)
+        .filter(crates::name.eq(&opts.crate_name))
+        .select((versions::id, versions::num))
+        .load::<(i32, String)>(&conn)?
+        .into_iter()
+        .collect();
+    if versions.is_empty() {
+        bail!(
+            "Could not find any version of the crate `{}`",
+            opts.crate_name
+        );
+    }
+
+    let months: Vec<NaiveDate> = version_downloads_archives::table
+        .select(version_downloads_archives::month)
+        .order(version_downloads_archives::month)
+        .load(&conn)?;
+
+    let client = Client::new();
+    for month in months {
+        let mut downloads: BTreeMap<&str, i64> = BTreeMap::new();
+        for row in fetch_archive(&config.uploader, &client, month)? {
+            if let Some(num) = versions.get(&row.version_id) {
+                *downloads.entry(num.as_str()).or_default() += i64::from(row.downloads);
+            }
+        }
+
+        println!("{}", month.format("%Y-%m"));
+        for (num, downloads) in downloads {
+            println!("    {:<20} {}", num, downloads);
+        }
+    }
+
+    Ok(())
+}
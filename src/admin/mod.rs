@@ -1,3 +1,4 @@
+pub mod archived_downloads;
 pub mod delete_crate;
 pub mod delete_version;
 pub mod dialoguer;
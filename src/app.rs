@@ -4,7 +4,7 @@ use crate::db::{ConnectionConfig, DieselPool};
 use crate::{Config, Env};
 use std::{sync::Arc, time::Duration};
 
-use crate::downloads_counter::DownloadsCounter;
+use crate::downloads_counter::{DownloadsCounter, DownloadsFilter, SpillLog};
 use crate::email::Emails;
 use crate::github::GitHubClient;
 use crate::metrics::{InstanceMetrics, ServiceMetrics};
@@ -39,8 +39,11 @@ pub struct App {
     /// Count downloads and periodically persist them in the database
     pub downloads_counter: DownloadsCounter,
 
+    /// Decide which downloads shouldn't be counted
+    pub downloads_filter: DownloadsFilter,
+
     /// Backend used to send emails
-    pub emails: Emails,
+    pub emails: Arc<Emails>,
 
     /// Metrics related to the service as a whole
     pub service_metrics: ServiceMetrics,
@@ -147,6 +150,22 @@ impl App {
             None
         };
 
+        let instance_metrics =
+            InstanceMetrics::new().expect("could not initialize instance metrics");
+
+        let downloads_counter = match &config.downloads_spill_path {
+            Some(path) => DownloadsCounter::with_spill_log(
+                SpillLog::open(
+                    path,
+                    instance_metrics.downloads_spilled_total.clone(),
+                    instance_metrics.downloads_replayed_total.clone(),
+                )
+                .expect("could not open the downloads spill log"),
+            ),
+            None => DownloadsCounter::new(),
+        };
+        let downloads_filter = DownloadsFilter::from_config(&config);
+
         App {
             primary_database,
             read_only_replica_database: replica_database,
@@ -154,11 +173,11 @@ impl App {
             github_oauth,
             session_key: config.session_key.clone(),
             config,
-            downloads_counter: DownloadsCounter::new(),
-            emails: Emails::from_environment(),
+            downloads_counter,
+            downloads_filter,
+            emails: Arc::new(Emails::from_environment()),
             service_metrics: ServiceMetrics::new().expect("could not initialize service metrics"),
-            instance_metrics: InstanceMetrics::new()
-                .expect("could not initialize instance metrics"),
+            instance_metrics,
             http_client,
         }
     }
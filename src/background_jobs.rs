@@ -5,7 +5,9 @@ use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use swirl::PerformError;
 
 use crate::db::{DieselPool, DieselPooledConn, PoolError};
+use crate::email::Emails;
 use crate::git::Repository;
+use crate::github::GitHubClient;
 use crate::uploaders::Uploader;
 
 impl<'a> swirl::db::BorrowedConnection<'a> for DieselPool {
@@ -25,6 +27,11 @@ pub struct Environment {
     index: Arc<Mutex<Repository>>,
     pub uploader: Uploader,
     http_client: AssertUnwindSafe<Client>,
+    github: AssertUnwindSafe<GitHubClient>,
+    emails: Arc<Emails>,
+    connection_pool: DieselPool,
+    /// See `Config::webhooks_allow_private_urls`.
+    pub webhooks_allow_private_urls: bool,
 }
 
 impl Clone for Environment {
@@ -33,24 +40,52 @@ impl Clone for Environment {
             index: self.index.clone(),
             uploader: self.uploader.clone(),
             http_client: AssertUnwindSafe(self.http_client.0.clone()),
+            github: AssertUnwindSafe(self.github.0.clone()),
+            emails: self.emails.clone(),
+            connection_pool: self.connection_pool.clone(),
+            webhooks_allow_private_urls: self.webhooks_allow_private_urls,
         }
     }
 }
 
 impl Environment {
-    pub fn new(index: Repository, uploader: Uploader, http_client: Client) -> Self {
-        Self::new_shared(Arc::new(Mutex::new(index)), uploader, http_client)
+    pub fn new(
+        index: Repository,
+        uploader: Uploader,
+        http_client: Client,
+        github: GitHubClient,
+        emails: Arc<Emails>,
+        connection_pool: DieselPool,
+        webhooks_allow_private_urls: bool,
+    ) -> Self {
+        Self::new_shared(
+            Arc::new(Mutex::new(index)),
+            uploader,
+            http_client,
+            github,
+            emails,
+            connection_pool,
+            webhooks_allow_private_urls,
+        )
     }
 
     pub fn new_shared(
         index: Arc<Mutex<Repository>>,
         uploader: Uploader,
         http_client: Client,
+        github: GitHubClient,
+        emails: Arc<Emails>,
+        connection_pool: DieselPool,
+        webhooks_allow_private_urls: bool,
     ) -> Self {
         Self {
             index,
             uploader,
             http_client: AssertUnwindSafe(http_client),
+            github: AssertUnwindSafe(github),
+            emails,
+            connection_pool,
+            webhooks_allow_private_urls,
         }
     }
 
@@ -64,4 +99,20 @@ impl Environment {
     pub(crate) fn http_client(&self) -> &Client {
         &self.http_client
     }
+
+    /// Returns a client for the GitHub API.
+    pub(crate) fn github(&self) -> &GitHubClient {
+        &self.github
+    }
+
+    /// Returns the backend used to send emails.
+    pub(crate) fn emails(&self) -> &Emails {
+        &self.emails
+    }
+
+    /// Returns a database connection separate from the one of the job, for work which must be
+    /// committed before the job finishes, as the job runs in a transaction.
+    pub(crate) fn connection(&self) -> Result<DieselPooledConn<'_>, PoolError> {
+        self.connection_pool.get()
+    }
 }
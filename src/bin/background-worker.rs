@@ -13,7 +13,8 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 use cargo_registry::git::{Repository, RepositoryConfig};
-use cargo_registry::{background_jobs::*, db};
+use cargo_registry::github::GitHubClient;
+use cargo_registry::{background_jobs::*, db, Emails};
 use diesel::r2d2;
 use reqwest::blocking::Client;
 use std::sync::{Arc, Mutex};
@@ -50,13 +51,26 @@ fn main() {
     ));
     println!("Index cloned");
 
+    let emails = Arc::new(Emails::from_environment());
+    // Used by the jobs which need to commit work before they finish, separately from the pool of
+    // the runner whose connections are in a transaction while running a job
+    let connection_pool = db::DieselPool::new(&db_url, r2d2::Pool::builder().min_idle(Some(1)));
+
     let build_runner = || {
         let client = Client::builder()
             .timeout(Duration::from_secs(45))
             .build()
             .expect("Couldn't build client");
-        let environment =
-            Environment::new_shared(repository.clone(), config.uploader.clone(), client);
+        let github = GitHubClient::new(Some(client.clone()), config.gh_base_url.clone());
+        let environment = Environment::new_shared(
+            repository.clone(),
+            config.uploader.clone(),
+            client,
+            github,
+            emails.clone(),
+            connection_pool.clone(),
+            config.webhooks_allow_private_urls,
+        );
         let db_config = r2d2::Pool::builder().min_idle(Some(0));
         swirl::Runner::builder(environment)
             .connection_pool_builder(&db_url, db_config)
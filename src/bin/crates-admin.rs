@@ -1,8 +1,8 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 use cargo_registry::admin::{
-    delete_crate, delete_version, migrate, populate, render_readmes, test_pagerduty,
-    transfer_crates, verify_token,
+    archived_downloads, delete_crate, delete_version, migrate, populate, render_readmes,
+    test_pagerduty, transfer_crates, verify_token,
 };
 
 use clap::Clap;
@@ -24,6 +24,7 @@ enum SubCommand {
     TransferCrates(transfer_crates::Opts),
     VerifyToken(verify_token::Opts),
     Migrate(migrate::Opts),
+    ArchivedDownloads(archived_downloads::Opts),
 }
 
 fn main() {
@@ -38,5 +39,6 @@ fn main() {
         SubCommand::TransferCrates(opts) => transfer_crates::run(opts),
         SubCommand::VerifyToken(opts) => verify_token::run(opts).unwrap(),
         SubCommand::Migrate(opts) => migrate::run(opts).unwrap(),
+        SubCommand::ArchivedDownloads(opts) => archived_downloads::run(opts).unwrap(),
     }
 }
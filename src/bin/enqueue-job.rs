@@ -1,7 +1,7 @@
 #![deny(clippy::all)]
 
 use anyhow::{anyhow, Result};
-use cargo_registry::{db, env, tasks};
+use cargo_registry::{db, env, tasks, OWNERSHIP_INVITATIONS_EXPIRATION_DAYS};
 use diesel::prelude::*;
 use swirl::schema::background_jobs::dsl::*;
 use swirl::Job;
@@ -35,7 +35,37 @@ fn main() -> Result<()> {
                 .unwrap_or_else(|| String::from("db-dump.tar.gz"));
             Ok(tasks::dump_db(database_url, target_name).enqueue(&conn)?)
         }
+        "archive_version_downloads" => {
+            let retention_days = args
+                .next()
+                .map(|days| days.parse())
+                .transpose()?
+                .unwrap_or(90);
+            Ok(tasks::archive_version_downloads(retention_days).enqueue(&conn)?)
+        }
         "daily_db_maintenance" => Ok(tasks::daily_db_maintenance().enqueue(&conn)?),
+        "deliver_webhooks" => Ok(tasks::deliver_webhooks().enqueue(&conn)?),
+        "refresh_crate_rankings" => Ok(tasks::refresh_crate_rankings().enqueue(&conn)?),
+        "refresh_team_memberships" => {
+            let older_than_minutes = args
+                .next()
+                .map(|minutes| minutes.parse())
+                .transpose()?
+                .unwrap_or(30);
+            Ok(tasks::refresh_team_memberships(older_than_minutes).enqueue(&conn)?)
+        }
+        "expire_owner_invitations" => {
+            let expiration_days = args
+                .next()
+                .map(|days| days.parse())
+                .transpose()?
+                .unwrap_or(OWNERSHIP_INVITATIONS_EXPIRATION_DAYS as i32);
+            Ok(tasks::expire_owner_invitations(expiration_days).enqueue(&conn)?)
+        }
+        "sync_advisories" => {
+            let path = args.next().unwrap_or_else(|| env("ADVISORY_DB_PATH"));
+            Ok(tasks::sync_advisories(path).enqueue(&conn)?)
+        }
         other => Err(anyhow!("Unrecognized job type `{}`", other)),
     }
 }
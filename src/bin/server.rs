@@ -151,9 +151,25 @@ fn downloads_counter_thread(app: Arc<App>) {
     std::thread::spawn(move || loop {
         std::thread::sleep(interval);
 
+        if let Err(err) = app.downloads_counter.flush_spill_log() {
+            println!("downloads_counter spill error: {}", err);
+        }
+
         match app.downloads_counter.persist_next_shard(&app) {
-            Ok(stats) => stats.log(),
+            Ok(stats) => {
+                stats.log();
+
+                // The database is reachable again, so the downloads that couldn't be persisted
+                // earlier can be counted too.
+                match app.downloads_counter.replay_spilled(&app) {
+                    Ok(0) => {}
+                    Ok(replayed) => println!("downloads_counter replayed_downloads={}", replayed),
+                    Err(err) => println!("downloads_counter replay error: {}", err),
+                }
+            }
             Err(err) => println!("downloads_counter error: {}", err),
         }
+
+        app.downloads_filter.prune_expired();
     });
 }
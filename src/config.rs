@@ -1,5 +1,9 @@
 use crate::publish_rate_limit::PublishRateLimit;
 use crate::{env, uploaders::Uploader, Env, Replica};
+use std::path::PathBuf;
+
+/// The number of days after which crate owner invitations expire, unless configured otherwise.
+pub const OWNERSHIP_INVITATIONS_EXPIRATION_DAYS: u64 = 30;
 
 #[derive(Debug)]
 pub struct Config {
@@ -17,11 +21,22 @@ pub struct Config {
     pub api_protocol: String,
     pub publish_rate_limit: PublishRateLimit,
     pub blocked_traffic: Vec<(String, Vec<String>)>,
+    /// Whether crate downloads from blocked user agents are served without being counted,
+    /// instead of being blocked like the other requests.
+    pub blocked_traffic_exempt_downloads: bool,
     pub domain_name: String,
     pub allowed_origins: Vec<String>,
     pub downloads_persist_interval_ms: usize,
+    pub downloads_spill_path: Option<PathBuf>,
+    pub downloads_dedup_window_secs: u64,
     pub ownership_invitations_expiration_days: u64,
+    pub team_membership_cache_ttl_minutes: u64,
+    pub team_membership_fallback_max_age_hours: u64,
     pub metrics_authorization_token: Option<String>,
+    pub search_ranking_weights: SearchRankingWeights,
+    /// Whether webhooks can be registered with `http` URLs and URLs of private IP addresses,
+    /// which are otherwise refused to protect the internal network.
+    pub webhooks_allow_private_urls: bool,
     pub use_test_database_pool: bool,
 }
 
@@ -31,6 +46,59 @@ pub struct DbPoolConfig {
     pub read_only_mode: bool,
 }
 
+/// The weights of the signals blended into the relevance score of search results. All signals
+/// except the text rank come from the `crate_rankings` materialized view, normalized between 0
+/// and 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchRankingWeights {
+    /// The full-text search rank of the crate's name, keywords and description.
+    pub text: f64,
+    /// The logarithm of the downloads of the last 90 days.
+    pub downloads: f64,
+    /// The logarithm of the number of crates depending on the crate.
+    pub dependents: f64,
+    /// How recent the latest non-yanked version is, halving every year.
+    pub recency: f64,
+}
+
+impl Default for SearchRankingWeights {
+    fn default() -> Self {
+        Self {
+            text: 1.0,
+            downloads: 0.5,
+            dependents: 0.5,
+            recency: 0.1,
+        }
+    }
+}
+
+impl SearchRankingWeights {
+    /// Parses weights in the form `text=1.0,downloads=0.5`. Missing weights keep their default.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the weights are invalid.
+    fn parse(weights: &str) -> Self {
+        let mut result = Self::default();
+        for pattern in weights.split_terminator(',') {
+            let (name, weight) = parse_pattern(pattern, "SEARCH_RANKING_WEIGHTS", "NAME=WEIGHT");
+            let weight = weight
+                .parse::<f64>()
+                .ok()
+                .filter(|weight| weight.is_finite())
+                .unwrap_or_else(|| panic!("invalid SEARCH_RANKING_WEIGHTS weight {}", weight));
+            match name {
+                "text" => result.text = weight,
+                "downloads" => result.downloads = weight,
+                "dependents" => result.dependents = weight,
+                "recency" => result.recency = weight,
+                _ => panic!("unknown SEARCH_RANKING_WEIGHTS signal {}", name),
+            }
+        }
+        result
+    }
+}
+
 impl Default for Config {
     /// Returns a default value for the application's config
     ///
@@ -39,6 +107,8 @@ impl Default for Config {
     /// - `Config::max_upload_size`: 10MiB
     /// - `Config::api_protocol`: `https`
     /// - `Config::ownership_invitations_expiration_days`: 30
+    /// - `Config::team_membership_cache_ttl_minutes`: 60
+    /// - `Config::team_membership_fallback_max_age_hours`: 24
     ///
     /// Pulls values from the following environment variables:
     ///
@@ -56,9 +126,19 @@ impl Default for Config {
     /// - `READ_ONLY_REPLICA_URL`: The URL of an optional postgres read-only replica database.
     /// - `BLOCKED_TRAFFIC`: A list of headers and environment variables to use for blocking
     ///   traffic. See the `block_traffic` module for more documentation.
+    /// - `BLOCKED_TRAFFIC_EXEMPT_DOWNLOADS`: If defined (even as empty) then crate downloads from
+    ///   blocked user agents are served, but not counted.
     /// - `DOWNLOADS_PERSIST_INTERVAL_MS`: how frequent to persist download counts (in ms).
+    /// - `DOWNLOADS_SPILL_PATH`: file storing the download counts that couldn't be persisted in
+    ///   the database, until they can be replayed. If missing, those download counts are lost.
+    /// - `DOWNLOADS_DEDUP_WINDOW_SECS`: downloads of the same version from the same IP address
+    ///   within this window are only counted once. Defaults to 60 seconds, `0` disables it.
     /// - `METRICS_AUTHORIZATION_TOKEN`: authorization token needed to query metrics. If missing,
     ///   querying metrics will be completely disabled.
+    /// - `SEARCH_RANKING_WEIGHTS`: the weights of the relevance signals of search results, in the
+    ///   form `text=1.0,downloads=0.5,dependents=0.5,recency=0.1`. See `SearchRankingWeights`.
+    /// - `WEBHOOKS_ALLOW_PRIVATE_URLS`: If defined (even as empty) then allow webhooks to be
+    ///   delivered to local and private addresses, e.g. during development.
     /// - `DB_OFFLINE`: If set to `leader` then use the read-only follower as if it was the leader.
     ///   If set to `follower` then act as if `READ_ONLY_REPLICA_URL` was unset.
     /// - `READ_ONLY_MODE`: If defined (even as empty) then force all connections to be read-only.
@@ -201,6 +281,8 @@ impl Default for Config {
             api_protocol,
             publish_rate_limit: Default::default(),
             blocked_traffic: blocked_traffic(),
+            blocked_traffic_exempt_downloads: dotenv::var("BLOCKED_TRAFFIC_EXEMPT_DOWNLOADS")
+                .is_ok(),
             domain_name: domain_name(),
             allowed_origins,
             downloads_persist_interval_ms: dotenv::var("DOWNLOADS_PERSIST_INTERVAL_MS")
@@ -210,8 +292,18 @@ impl Default for Config {
                         .expect("invalid DOWNLOADS_PERSIST_INTERVAL_MS")
                 })
                 .unwrap_or(60_000), // 1 minute
-            ownership_invitations_expiration_days: 30,
+            downloads_spill_path: dotenv::var("DOWNLOADS_SPILL_PATH").ok().map(PathBuf::from),
+            downloads_dedup_window_secs: dotenv::var("DOWNLOADS_DEDUP_WINDOW_SECS")
+                .map(|window| window.parse().expect("invalid DOWNLOADS_DEDUP_WINDOW_SECS"))
+                .unwrap_or(60),
+            ownership_invitations_expiration_days: OWNERSHIP_INVITATIONS_EXPIRATION_DAYS,
+            team_membership_cache_ttl_minutes: 60,
+            team_membership_fallback_max_age_hours: 24,
             metrics_authorization_token: dotenv::var("METRICS_AUTHORIZATION_TOKEN").ok(),
+            search_ranking_weights: dotenv::var("SEARCH_RANKING_WEIGHTS")
+                .map(|weights| SearchRankingWeights::parse(&weights))
+                .unwrap_or_default(),
+            webhooks_allow_private_urls: dotenv::var("WEBHOOKS_ALLOW_PRIVATE_URLS").is_ok(),
             use_test_database_pool: false,
         }
     }
@@ -226,24 +318,36 @@ fn blocked_traffic() -> Vec<(String, Vec<String>)> {
     parse_traffic_patterns(&pattern_list)
         .map(|(header, value_env_var)| {
             let value_list = dotenv::var(value_env_var).unwrap_or_default();
-            let values = value_list.split(',').map(String::from).collect();
-            (header.into(), values)
+            (header.into(), parse_blocked_values(&value_list))
         })
         .collect()
 }
 
+/// Splits the comma separated list of values to block. Empty values are skipped, as they would
+/// otherwise block every request with an empty header when the environment variable is unset.
+fn parse_blocked_values(values: &str) -> Vec<String> {
+    values
+        .split(',')
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 fn parse_traffic_patterns(patterns: &str) -> impl Iterator<Item = (&str, &str)> {
-    patterns.split_terminator(',').map(|pattern| {
-        if let Some(idx) = pattern.find('=') {
-            (&pattern[..idx], &pattern[(idx + 1)..])
-        } else {
-            panic!(
-                "BLOCKED_TRAFFIC must be in the form HEADER=VALUE_ENV_VAR, \
-                 got invalid pattern {}",
-                pattern
-            )
-        }
-    })
+    patterns
+        .split_terminator(',')
+        .map(|pattern| parse_pattern(pattern, "BLOCKED_TRAFFIC", "HEADER=VALUE_ENV_VAR"))
+}
+
+fn parse_pattern<'a>(pattern: &'a str, var: &str, form: &str) -> (&'a str, &'a str) {
+    if let Some(idx) = pattern.find('=') {
+        (&pattern[..idx], &pattern[(idx + 1)..])
+    } else {
+        panic!(
+            "{} must be in the form {}, got invalid pattern {}",
+            var, form, pattern
+        )
+    }
 }
 
 #[test]
@@ -260,3 +364,34 @@ fn parse_traffic_patterns_splits_on_comma_and_looks_for_equal_sign() {
 
     assert_none!(parse_traffic_patterns(pattern_string_3).next());
 }
+
+#[test]
+fn parse_blocked_values_skips_empty_values() {
+    assert_eq!(
+        parse_blocked_values("curl/7.54.0,,1"),
+        vec!["curl/7.54.0", "1"]
+    );
+    assert!(parse_blocked_values("").is_empty());
+}
+
+#[test]
+fn search_ranking_weights_override_the_defaults() {
+    assert_eq!(
+        SearchRankingWeights::parse(""),
+        SearchRankingWeights::default()
+    );
+    assert_eq!(
+        SearchRankingWeights::parse("downloads=2,recency=0"),
+        SearchRankingWeights {
+            downloads: 2.0,
+            recency: 0.0,
+            ..SearchRankingWeights::default()
+        }
+    );
+}
+
+#[test]
+#[should_panic(expected = "unknown SEARCH_RANKING_WEIGHTS signal stars")]
+fn search_ranking_weights_reject_unknown_signals() {
+    SearchRankingWeights::parse("stars=1");
+}
@@ -97,8 +97,10 @@ mod prelude {
 pub mod helpers;
 mod util;
 
+pub mod advisory;
 pub mod category;
 pub mod crate_owner_invitation;
+pub mod feed;
 pub mod keyword;
 pub mod krate;
 pub mod metrics;
@@ -107,3 +109,4 @@ pub mod team;
 pub mod token;
 pub mod user;
 pub mod version;
+pub mod webhook;
@@ -0,0 +1,69 @@
+use super::frontend_prelude::*;
+
+use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
+use crate::controllers::helpers::Paginate;
+use crate::models::{Advisory, Crate};
+use crate::schema::{advisories, crates, versions};
+use crate::views::EncodableAdvisory;
+
+/// Handles the `GET /advisories` route.
+///
+/// Lists the security advisories which weren't withdrawn, most recent first, along with the
+/// crates and versions they affect. The `crate` parameter restricts the list to one crate.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = req.query().get("crate").cloned();
+    let pagination = PaginationOptions::new(req)?;
+
+    let mut query = advisories::table
+        .inner_join(crates::table)
+        .filter(advisories::withdrawn.is_null())
+        .select((advisories::all_columns, crates::name))
+        .order((advisories::date.desc(), advisories::id.desc()))
+        .into_boxed();
+    if let Some(crate_name) = &crate_name {
+        query = query.filter(Crate::with_name(crate_name));
+    }
+
+    let conn = req.db_read_only()?;
+    let data: Paginated<(Advisory, String)> = query.paginate_with(pagination).load(&*conn)?;
+    let total = data.total();
+    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+
+    let crate_ids = data
+        .iter()
+        .map(|(advisory, _)| advisory.crate_id)
+        .collect::<Vec<_>>();
+    let versions: Vec<(i32, semver::Version)> = versions::table
+        .filter(versions::crate_id.eq_any(crate_ids))
+        .select((versions::crate_id, versions::num))
+        .order(versions::id)
+        .load(&*conn)?;
+
+    let advisories = data
+        .into_iter()
+        .map(|(advisory, crate_name)| {
+            let crate_versions = versions
+                .iter()
+                .filter(|(crate_id, _)| *crate_id == advisory.crate_id)
+                .map(|(_, num)| num.clone())
+                .collect::<Vec<_>>();
+            EncodableAdvisory::from(advisory, &crate_name, &crate_versions)
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        advisories: Vec<EncodableAdvisory>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: Option<i64>,
+        next_page: Option<String>,
+    }
+
+    Ok(req.json(&R {
+        advisories,
+        meta: Meta { total, next_page },
+    }))
+}
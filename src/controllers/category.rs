@@ -4,6 +4,7 @@ use super::prelude::*;
 use crate::models::Category;
 use crate::schema::categories;
 use crate::views::{EncodableCategory, EncodableCategoryWithSubcategories};
+use indexmap::IndexMap;
 
 /// Handles the `GET /categories` route.
 pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
@@ -11,17 +12,41 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
     // FIXME: There are 69 categories, 47 top level. This isn't going to
     // grow by an OoM. We need a limit for /summary, but we don't need
     // to paginate this.
-    let options = PaginationOptions::new(req)?;
+    let options = PaginationOptions::with_seek(req)?;
     let offset = options.offset().unwrap_or_default();
+    let per_page = i64::from(options.per_page);
     let sort = query.get("sort").map_or("alpha", String::as_str);
+    // The cursor holds the crates count and name of the last category of the previous page
+    let seek = match options.seek() {
+        Some(seek) => seek.decode::<(i32, String)>()?,
+        None => None,
+    };
 
     let conn = req.db_read_only()?;
-    let categories =
-        Category::toplevel(&conn, sort, i64::from(options.per_page), i64::from(offset))?;
+    let categories = match &seek {
+        Some((crates_cnt, category)) => {
+            Category::toplevel_after(&conn, sort, per_page, (*crates_cnt, category))?
+        }
+        None => Category::toplevel(&conn, sort, per_page, i64::from(offset))?,
+    };
+    let next_page = match categories.last() {
+        Some(last) if options.seek().is_some() && categories.len() as i64 == per_page => {
+            let mut params = IndexMap::new();
+            params.insert(
+                "seek".into(),
+                encode_seek((last.crates_cnt, &last.category))?,
+            );
+            Some(req.query_with_params(params))
+        }
+        _ => None,
+    };
     let categories = categories.into_iter().map(Category::into).collect();
 
-    // Query for the total count of categories
-    let total = Category::count_toplevel(&conn)?;
+    // Query for the total count of categories, which is skipped when seeking
+    let total = match options.seek() {
+        Some(_) => None,
+        None => Some(Category::count_toplevel(&conn)?),
+    };
 
     #[derive(Serialize)]
     struct R {
@@ -30,12 +55,13 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
     }
     #[derive(Serialize)]
     struct Meta {
-        total: i64,
+        total: Option<i64>,
+        next_page: Option<String>,
     }
 
     Ok(req.json(&R {
         categories,
-        meta: Meta { total },
+        meta: Meta { total, next_page },
     }))
 }
 
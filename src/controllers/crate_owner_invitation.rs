@@ -97,15 +97,17 @@ pub fn handle_invite(req: &mut dyn RequestExt) -> EndpointResult {
         serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
 
     let crate_invite = crate_invite.crate_owner_invite;
-    let user_id = req.authenticate()?.user_id();
+    let authenticated_user = req.authenticate()?;
+    let user_id = authenticated_user.user_id();
+    let api_token_id = authenticated_user.api_token_id();
     let conn = &*req.db_conn()?;
     let config = &req.app().config;
 
     let invitation = CrateOwnerInvitation::find_by_id(user_id, crate_invite.crate_id, &conn)?;
     if crate_invite.accepted {
-        invitation.accept(&conn, config)?;
+        invitation.accept(&conn, config, api_token_id)?;
     } else {
-        invitation.decline(&conn)?;
+        invitation.decline(&conn, api_token_id)?;
     }
 
     #[derive(Serialize)]
@@ -125,7 +127,7 @@ pub fn handle_invite_with_token(req: &mut dyn RequestExt) -> EndpointResult {
 
     let invitation = CrateOwnerInvitation::find_by_token(req_token, &conn)?;
     let crate_id = invitation.crate_id;
-    invitation.accept(&conn, config)?;
+    invitation.accept(&conn, config, None)?;
 
     #[derive(Serialize)]
     struct R {
@@ -0,0 +1,287 @@
+//! Atom feeds of new crates and versions, for feed readers.
+//!
+//! The feeds set a `Last-Modified` header to the most recent `updated` timestamp of their
+//! entries, so that the `ConditionalGet` middleware can answer `If-Modified-Since` requests of
+//! feed readers polling them with a `304 Not Modified`.
+
+use super::frontend_prelude::*;
+
+use chrono::NaiveDateTime;
+use conduit::{Body, Response};
+use diesel::pg::Pg;
+use diesel::sql_types::Integer;
+use htmlescape::encode_minimal;
+
+use crate::models::{Category, Crate, FeedToken, Keyword, Version};
+use crate::schema::{crates, crates_categories, crates_keywords, follows, users, versions};
+use crate::util::errors::forbidden;
+
+/// The maximum number of entries in a feed.
+const MAX_FEED_ENTRIES: i64 = 50;
+
+/// Handles the `GET /feeds/crates` route.
+///
+/// Lists the most recently created crates.
+pub fn new_crates(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_read_only()?;
+    let crates: Vec<Crate> = Crate::all()
+        .order((crates::created_at.desc(), crates::id.desc()))
+        .limit(MAX_FEED_ENTRIES)
+        .load(&*conn)?;
+
+    let domain_name = &req.app().config.domain_name;
+    let mut feed = Feed::new(
+        format!("New crates on {}", domain_name),
+        format!("https://{}/crates?sort=new", domain_name),
+    );
+    for krate in crates {
+        let url = format!("https://{}/crates/{}", domain_name, krate.name);
+        feed.entries.push(Entry {
+            title: krate.name,
+            id: url.clone(),
+            url,
+            published: krate.created_at,
+            updated: krate.created_at,
+            author: None,
+            summary: krate.description,
+        });
+    }
+    feed.into_response(domain_name)
+}
+
+/// Handles the `GET /feeds/crates/:crate_id` route.
+///
+/// Lists the most recently published versions of a crate.
+pub fn crate_versions(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(&req.params()["crate_id"]).first(&*conn)?;
+
+    let domain_name = &req.app().config.domain_name;
+    let feed = Feed::new(
+        format!("New versions of {}", krate.name),
+        format!("https://{}/crates/{}", domain_name, krate.name),
+    );
+    let crate_ids = crates::table
+        .select(crates::id)
+        .filter(crates::id.eq(krate.id))
+        .into_boxed();
+    versions_feed(req, &conn, feed, crate_ids)
+}
+
+/// Handles the `GET /feeds/categories/:category_id` route.
+///
+/// Lists the most recently published versions of the crates in a category.
+pub fn category_versions(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_read_only()?;
+    let category: Category = Category::by_slug(&req.params()["category_id"]).first(&*conn)?;
+    let crate_ids = crates::table
+        .select(crates::id)
+        .filter(
+            crates::id.eq_any(
+                crates_categories::table
+                    .filter(crates_categories::category_id.eq(category.id))
+                    .select(crates_categories::crate_id),
+            ),
+        )
+        .into_boxed();
+
+    let domain_name = &req.app().config.domain_name;
+    let feed = Feed::new(
+        format!("New versions in the {} category", category.category),
+        format!("https://{}/categories/{}", domain_name, category.slug),
+    );
+    versions_feed(req, &conn, feed, crate_ids)
+}
+
+/// Handles the `GET /feeds/keywords/:keyword_id` route.
+///
+/// Lists the most recently published versions of the crates with a keyword.
+pub fn keyword_versions(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_read_only()?;
+    let keyword = Keyword::find_by_keyword(&conn, &req.params()["keyword_id"])?;
+    let crate_ids = crates::table
+        .select(crates::id)
+        .filter(
+            crates::id.eq_any(
+                crates_keywords::table
+                    .filter(crates_keywords::keyword_id.eq(keyword.id))
+                    .select(crates_keywords::crate_id),
+            ),
+        )
+        .into_boxed();
+
+    let domain_name = &req.app().config.domain_name;
+    let feed = Feed::new(
+        format!("New versions with the {} keyword", keyword.keyword),
+        format!("https://{}/keywords/{}", domain_name, keyword.keyword),
+    );
+    versions_feed(req, &conn, feed, crate_ids)
+}
+
+/// Handles the `GET /feeds/me/updates` route.
+///
+/// Lists the most recently published versions of the crates followed by a user. Feed readers
+/// can't log in, so the user is authenticated with the feed token passed in the `token`
+/// parameter, which can be created with `PUT /me/feed_token`.
+pub fn followed_versions(req: &mut dyn RequestExt) -> EndpointResult {
+    let token = req.query().get("token").cloned().unwrap_or_default();
+    let conn = req.db_read_only()?;
+    let user = FeedToken::find_user(&conn, &token)?.ok_or_else(forbidden)?;
+    let crate_ids = crates::table
+        .select(crates::id)
+        .filter(
+            crates::id.eq_any(
+                follows::table
+                    .filter(follows::user_id.eq(user.id))
+                    .select(follows::crate_id),
+            ),
+        )
+        .into_boxed();
+
+    let domain_name = &req.app().config.domain_name;
+    let feed = Feed::new(
+        format!("New versions of the crates followed by {}", user.gh_login),
+        format!("https://{}/dashboard", domain_name),
+    );
+    versions_feed(req, &conn, feed, crate_ids)
+}
+
+/// Fills a feed with the most recently published versions of the crates selected by the
+/// `crate_ids` subquery.
+fn versions_feed(
+    req: &dyn RequestExt,
+    conn: &PgConnection,
+    mut feed: Feed,
+    crate_ids: crates::BoxedQuery<'_, Pg, Integer>,
+) -> EndpointResult {
+    let versions: Vec<(Version, String, Option<String>)> = versions::table
+        .inner_join(crates::table)
+        .left_outer_join(users::table)
+        .filter(versions::crate_id.eq_any(crate_ids))
+        .order((versions::created_at.desc(), versions::id.desc()))
+        .select((
+            versions::all_columns,
+            crates::name,
+            users::gh_login.nullable(),
+        ))
+        .limit(MAX_FEED_ENTRIES)
+        .load(conn)?;
+
+    let domain_name = &req.app().config.domain_name;
+    for (version, crate_name, published_by) in versions {
+        let url = format!(
+            "https://{}/crates/{}/{}",
+            domain_name, crate_name, version.num
+        );
+        let summary = if version.yanked {
+            format!("{} {} has been yanked.", crate_name, version.num)
+        } else {
+            format!("{} {} has been published.", crate_name, version.num)
+        };
+        feed.entries.push(Entry {
+            title: format!("{} {}", crate_name, version.num),
+            id: url.clone(),
+            url,
+            published: version.created_at,
+            // The `updated_at` timestamp of a version changes when it is yanked or unyanked
+            updated: version.updated_at,
+            author: published_by,
+            summary: Some(summary),
+        });
+    }
+    feed.into_response(domain_name)
+}
+
+struct Feed {
+    title: String,
+    url: String,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    title: String,
+    id: String,
+    url: String,
+    published: NaiveDateTime,
+    updated: NaiveDateTime,
+    author: Option<String>,
+    summary: Option<String>,
+}
+
+impl Feed {
+    fn new(title: String, url: String) -> Self {
+        Self {
+            title,
+            url,
+            entries: Vec::new(),
+        }
+    }
+
+    fn updated(&self) -> Option<NaiveDateTime> {
+        self.entries.iter().map(|entry| entry.updated).max()
+    }
+
+    fn into_response(self, domain_name: &str) -> EndpointResult {
+        let updated = self.updated();
+        let body = self.to_xml(domain_name);
+
+        let mut response = Response::builder()
+            .header(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")
+            .header(header::CONTENT_LENGTH, body.len());
+        if let Some(updated) = updated {
+            let last_modified = updated.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+            response = response.header(header::LAST_MODIFIED, last_modified);
+        }
+        Ok(response.body(Body::from_vec(body.into_bytes()))?)
+    }
+
+    fn to_xml(&self, domain_name: &str) -> String {
+        // An empty feed has never been updated, but Atom requires a timestamp anyway
+        let updated = self
+            .updated()
+            .unwrap_or_else(|| chrono::Utc::now().naive_utc());
+
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+        );
+        xml += &format!("  <id>{}</id>\n", encode_minimal(&self.url));
+        xml += &format!("  <title>{}</title>\n", encode_minimal(&self.title));
+        xml += &format!(
+            "  <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+            encode_minimal(&self.url)
+        );
+        xml += &format!("  <updated>{}</updated>\n", rfc3339(updated));
+        xml += &format!(
+            "  <author><name>{}</name></author>\n",
+            encode_minimal(domain_name)
+        );
+        for entry in &self.entries {
+            xml += "  <entry>\n";
+            xml += &format!("    <id>{}</id>\n", encode_minimal(&entry.id));
+            xml += &format!("    <title>{}</title>\n", encode_minimal(&entry.title));
+            xml += &format!(
+                "    <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+                encode_minimal(&entry.url)
+            );
+            xml += &format!("    <published>{}</published>\n", rfc3339(entry.published));
+            xml += &format!("    <updated>{}</updated>\n", rfc3339(entry.updated));
+            if let Some(author) = &entry.author {
+                xml += &format!(
+                    "    <author><name>{}</name></author>\n",
+                    encode_minimal(author)
+                );
+            }
+            if let Some(summary) = &entry.summary {
+                xml += &format!("    <summary>{}</summary>\n", encode_minimal(summary));
+            }
+            xml += "  </entry>\n";
+        }
+        xml += "</feed>\n";
+        xml
+    }
+}
+
+fn rfc3339(timestamp: NaiveDateTime) -> String {
+    timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
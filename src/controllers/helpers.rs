@@ -1,7 +1,9 @@
 use crate::util::{json_response, EndpointResult};
 
+pub(crate) mod downloads;
 pub(crate) mod pagination;
 
+pub(crate) use self::downloads::DownloadsRange;
 pub(crate) use self::pagination::Paginate;
 
 pub fn ok_true() -> EndpointResult {
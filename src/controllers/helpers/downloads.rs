@@ -0,0 +1,74 @@
+use crate::controllers::prelude::*;
+use crate::models::Granularity;
+use crate::util::errors::{bad_request, AppResult};
+
+use chrono::{Duration, NaiveDate, Utc};
+
+/// The longest range of daily download counts that can be requested, in days. Longer ranges are
+/// served from the weekly and monthly rollups instead of scanning the daily rows.
+const MAX_DAILY_RANGE_DAYS: i64 = 90;
+
+/// The download counts requested through the `granularity`, `since` and `until` query parameters.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DownloadsRange {
+    pub(crate) granularity: Granularity,
+    pub(crate) since: Option<NaiveDate>,
+    pub(crate) until: NaiveDate,
+}
+
+impl DownloadsRange {
+    /// Parses the query parameters, using `default_until` if `until` is missing. Daily counts
+    /// default to (and are limited to) the 90 days up to `until`, while weekly and monthly counts
+    /// default to all the available history.
+    pub(crate) fn from_request(
+        req: &dyn RequestExt,
+        default_until: Option<NaiveDate>,
+    ) -> AppResult<Self> {
+        let params = req.query();
+
+        let granularity = match params.get("granularity") {
+            Some(granularity) => granularity.parse().map_err(|_| {
+                bad_request("invalid granularity, expected `day`, `week` or `month`")
+            })?,
+            None => Granularity::Day,
+        };
+
+        let parse_date = |name: &str| -> AppResult<Option<NaiveDate>> {
+            params
+                .get(name)
+                .map(|date| NaiveDate::parse_from_str(date, "%F"))
+                .transpose()
+                .map_err(|_| bad_request(&format_args!("invalid `{}` date", name)))
+        };
+
+        let until = parse_date("until")?
+            .or(default_until)
+            .unwrap_or_else(|| Utc::today().naive_utc());
+        let since = match (parse_date("since")?, granularity) {
+            (Some(since), _) => Some(since),
+            (None, Granularity::Day) => Some(until - Duration::days(MAX_DAILY_RANGE_DAYS - 1)),
+            (None, _) => None,
+        };
+
+        if let Some(since) = since {
+            if since > until {
+                return Err(bad_request("`since` must not be after `until`"));
+            }
+            if granularity == Granularity::Day
+                && until - since >= Duration::days(MAX_DAILY_RANGE_DAYS)
+            {
+                return Err(bad_request(&format_args!(
+                    "daily downloads are limited to {} days, use the `week` or `month` \
+                     granularity for longer ranges",
+                    MAX_DAILY_RANGE_DAYS
+                )));
+            }
+        }
+
+        Ok(Self {
+            granularity,
+            since,
+            until,
+        })
+    }
+}
@@ -7,19 +7,31 @@ use diesel::query_builder::*;
 use diesel::query_dsl::LoadQuery;
 use diesel::sql_types::BigInt;
 use indexmap::IndexMap;
+use serde::{de::DeserializeOwned, Serialize};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub(crate) enum Page {
     Numeric(u32),
+    Seek(RawSeekPayload),
     Unspecified,
 }
 
 impl Page {
-    fn new(req: &mut dyn RequestExt) -> AppResult<Self> {
+    fn new(req: &mut dyn RequestExt, allow_seek: bool) -> AppResult<Self> {
         const MAX_PAGE_BEFORE_SUSPECTED_BOT: u32 = 10;
 
         let params = req.query();
-        if let Some(s) = params.get("page") {
+        if let Some(s) = params.get("seek") {
+            if !allow_seek {
+                return Err(bad_request(
+                    "this endpoint doesn't support the `seek` parameter",
+                ));
+            }
+            if params.contains_key("page") {
+                return Err(bad_request("cannot use both `page` and `seek` parameters"));
+            }
+            Ok(Page::Seek(RawSeekPayload(s.clone())))
+        } else if let Some(s) = params.get("page") {
             let numeric_page = s.parse().map_err(|e| bad_request(&e))?;
             if numeric_page < 1 {
                 return Err(bad_request(&format_args!(
@@ -39,14 +51,55 @@ impl Page {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// The opaque cursor of seek-based pagination, as received in the `seek` query parameter.
+///
+/// Instead of skipping rows with an `OFFSET`, which gets slower with each page, the cursor
+/// contains the sort key and ID of the last record of the previous page, and the next page starts
+/// right after it. An empty cursor requests the first page.
+#[derive(Debug, Clone)]
+pub(crate) struct RawSeekPayload(String);
+
+impl RawSeekPayload {
+    /// Decodes the cursor, returning `None` for the first page.
+    pub(crate) fn decode<D: DeserializeOwned>(&self) -> AppResult<Option<D>> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+
+        base64::decode_config(&self.0, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .map(Some)
+            .ok_or_else(|| bad_request("invalid `seek` parameter"))
+    }
+}
+
+pub(crate) fn encode_seek<S: Serialize>(payload: S) -> AppResult<String> {
+    Ok(base64::encode_config(
+        &serde_json::to_vec(&payload)?,
+        base64::URL_SAFE_NO_PAD,
+    ))
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct PaginationOptions {
     page: Page,
     pub(crate) per_page: u32,
 }
 
 impl PaginationOptions {
+    /// Parses the pagination parameters of an endpoint that only supports numeric pages.
     pub(crate) fn new(req: &mut dyn RequestExt) -> AppResult<Self> {
+        Self::parse(req, false)
+    }
+
+    /// Parses the pagination parameters of an endpoint that also supports seek-based
+    /// pagination, which must then filter its query with the `seek` cursor.
+    pub(crate) fn with_seek(req: &mut dyn RequestExt) -> AppResult<Self> {
+        Self::parse(req, true)
+    }
+
+    fn parse(req: &mut dyn RequestExt, allow_seek: bool) -> AppResult<Self> {
         const DEFAULT_PER_PAGE: u32 = 10;
         const MAX_PER_PAGE: u32 = 100;
 
@@ -64,11 +117,19 @@ impl PaginationOptions {
         }
 
         Ok(Self {
-            page: Page::new(req)?,
+            page: Page::new(req, allow_seek)?,
             per_page,
         })
     }
 
+    /// Returns the cursor if the request uses seek-based pagination.
+    pub(crate) fn seek(&self) -> Option<&RawSeekPayload> {
+        match &self.page {
+            Page::Seek(seek) => Some(seek),
+            _ => None,
+        }
+    }
+
     pub(crate) fn offset(&self) -> Option<u32> {
         if let Page::Numeric(p) = self.page {
             Some((p - 1) * self.per_page)
@@ -80,10 +141,16 @@ impl PaginationOptions {
 
 pub(crate) trait Paginate: Sized {
     fn paginate(self, req: &mut dyn RequestExt) -> AppResult<PaginatedQuery<Self>> {
-        Ok(PaginatedQuery {
+        Ok(self.paginate_with(PaginationOptions::new(req)?))
+    }
+
+    /// Paginates the query with options already parsed from the request, for endpoints that need
+    /// the seek cursor to filter the query.
+    fn paginate_with(self, options: PaginationOptions) -> PaginatedQuery<Self> {
+        PaginatedQuery {
             query: self,
-            options: PaginationOptions::new(req)?,
-        })
+            options,
+        }
     }
 }
 
@@ -95,15 +162,27 @@ pub struct Paginated<T> {
 }
 
 impl<T> Paginated<T> {
-    pub(crate) fn total(&self) -> i64 {
-        self.records_and_total
-            .get(0)
-            .map(|row| row.total)
-            .unwrap_or_default() // If there is no first row, then the total is zero.
+    /// Returns the total number of records, or `None` when using seek-based pagination, as
+    /// counting all the records would defeat its purpose.
+    pub(crate) fn total(&self) -> Option<i64> {
+        if self.options.seek().is_some() {
+            return None;
+        }
+
+        Some(
+            self.records_and_total
+                .get(0)
+                .map(|row| row.total)
+                .unwrap_or_default(), // If there is no first row, then the total is zero.
+        )
+    }
+
+    fn is_last_page(&self) -> bool {
+        self.records_and_total.len() < self.options.per_page as usize
     }
 
     pub(crate) fn next_page_params(&self) -> Option<IndexMap<String, String>> {
-        if self.records_and_total.len() < self.options.per_page as usize {
+        if self.is_last_page() {
             return None;
         }
 
@@ -111,19 +190,39 @@ impl<T> Paginated<T> {
         match self.options.page {
             Page::Numeric(n) => opts.insert("page".into(), (n + 1).to_string()),
             Page::Unspecified => opts.insert("page".into(), 2.to_string()),
+            Page::Seek(_) => return None,
         };
         Some(opts)
     }
 
-    pub(crate) fn prev_page_params(&self) -> Option<IndexMap<String, String>> {
-        if let Page::Numeric(1) | Page::Unspecified = self.options.page {
-            return None;
+    /// Returns the parameters of the next page when using seek-based pagination. `seek_key`
+    /// extracts the sort key and ID of a record, which are encoded in the cursor.
+    pub(crate) fn next_seek_params<S, F>(
+        &self,
+        seek_key: F,
+    ) -> AppResult<Option<IndexMap<String, String>>>
+    where
+        S: Serialize,
+        F: Fn(&T) -> S,
+    {
+        if self.options.seek().is_none() || self.is_last_page() {
+            return Ok(None);
         }
 
+        let last = match self.records_and_total.last() {
+            Some(row) => &row.record,
+            None => return Ok(None),
+        };
+        let mut opts = IndexMap::new();
+        opts.insert("seek".into(), encode_seek(seek_key(last))?);
+        Ok(Some(opts))
+    }
+
+    pub(crate) fn prev_page_params(&self) -> Option<IndexMap<String, String>> {
         let mut opts = IndexMap::new();
         match self.options.page {
+            Page::Numeric(1) | Page::Unspecified | Page::Seek(_) => return None,
             Page::Numeric(n) => opts.insert("page".into(), (n - 1).to_string()),
-            Page::Unspecified => unreachable!(),
         };
         Some(opts)
     }
@@ -178,7 +277,12 @@ where
     T: QueryFragment<Pg>,
 {
     fn walk_ast(&self, mut out: AstPass<'_, Pg>) -> QueryResult<()> {
-        out.push_sql("SELECT *, COUNT(*) OVER () FROM (");
+        if self.options.seek().is_some() {
+            // The records are filtered by the query itself, and the total is not needed.
+            out.push_sql("SELECT *, 0::bigint FROM (");
+        } else {
+            out.push_sql("SELECT *, COUNT(*) OVER () FROM (");
+        }
         self.query.walk_ast(out.reborrow())?;
         out.push_sql(") t LIMIT ");
         out.push_bind_param::<BigInt, _>(&i64::from(self.options.per_page))?;
@@ -192,7 +296,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::{Page, PaginationOptions};
+    use super::{encode_seek, Page, PaginationOptions, RawSeekPayload};
 
     use conduit::StatusCode;
     use conduit_test::MockRequest;
@@ -245,4 +349,27 @@ mod tests {
             .unwrap();
         assert_eq!(per_page_error.status(), StatusCode::BAD_REQUEST);
     }
+
+    #[test]
+    fn page_and_seek_are_exclusive() {
+        let mut req = mock("page=2&seek=");
+        let error = Page::new(&mut req).unwrap_err().response().unwrap();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn seek_roundtrip() {
+        let payload = RawSeekPayload(encode_seek((42, "foo")).unwrap());
+        assert_eq!(
+            payload.decode::<(i32, String)>().unwrap(),
+            Some((42, "foo".to_string()))
+        );
+
+        let first_page = RawSeekPayload(String::new());
+        assert_eq!(first_page.decode::<(i32, String)>().unwrap(), None);
+
+        let invalid = RawSeekPayload("not-a-cursor".into());
+        let error = invalid.decode::<(i32, String)>().unwrap_err();
+        assert_eq!(error.response().unwrap().status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -1,6 +1,7 @@
 use super::prelude::*;
 
-use crate::controllers::helpers::{pagination::Paginated, Paginate};
+use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
+use crate::controllers::helpers::Paginate;
 use crate::models::Keyword;
 use crate::views::EncodableKeyword;
 
@@ -11,18 +12,54 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
     let query = req.query();
     let sort = query.get("sort").map(|s| &s[..]).unwrap_or("alpha");
 
+    let pagination = PaginationOptions::with_seek(req)?;
+    let seek = match pagination.seek() {
+        Some(seek) if sort == "crates" => seek
+            .decode::<(i32, i32)>()?
+            .map(|(crates_cnt, id)| Seek::Crates(crates_cnt, id)),
+        Some(seek) => seek
+            .decode::<(String, i32)>()?
+            .map(|(keyword, id)| Seek::Alpha(keyword, id)),
+        None => None,
+    };
+
     let mut query = keywords::table.into_boxed();
 
     if sort == "crates" {
-        query = query.order(keywords::crates_cnt.desc());
+        query = query.order((keywords::crates_cnt.desc(), keywords::id.asc()));
     } else {
-        query = query.order(keywords::keyword.asc());
+        query = query.order((keywords::keyword.asc(), keywords::id.asc()));
+    }
+
+    match seek {
+        Some(Seek::Crates(crates_cnt, id)) => {
+            query = query.filter(
+                keywords::crates_cnt
+                    .lt(crates_cnt)
+                    .or(keywords::crates_cnt.eq(crates_cnt).and(keywords::id.gt(id))),
+            );
+        }
+        Some(Seek::Alpha(keyword, id)) => {
+            query = query.filter(
+                keywords::keyword
+                    .gt(keyword.clone())
+                    .or(keywords::keyword.eq(keyword).and(keywords::id.gt(id))),
+            );
+        }
+        None => {}
     }
 
-    let query = query.paginate(req)?;
+    let query = query.paginate_with(pagination);
     let conn = req.db_read_only()?;
     let data: Paginated<Keyword> = query.load(&*conn)?;
     let total = data.total();
+    let next_page = if sort == "crates" {
+        data.next_seek_params(|kw| (kw.crates_cnt, kw.id))?
+    } else {
+        data.next_seek_params(|kw| (kw.keyword.clone(), kw.id))?
+    }
+    .or_else(|| data.next_page_params())
+    .map(|p| req.query_with_params(p));
     let kws = data.into_iter().map(Keyword::into).collect::<Vec<_>>();
 
     #[derive(Serialize)]
@@ -33,14 +70,22 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
     #[derive(Serialize)]
     struct Meta {
         total: Option<i64>,
+        next_page: Option<String>,
     }
 
     Ok(req.json(&R {
         keywords: kws,
-        meta: Meta { total: Some(total) },
+        meta: Meta { total, next_page },
     }))
 }
 
+/// The cursor of seek-based pagination, holding the sort key and ID of the last keyword of the
+/// previous page.
+enum Seek {
+    Alpha(String, i32),
+    Crates(i32, i32),
+}
+
 /// Handles the `GET /keywords/:keyword_id` route.
 pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     let name = &req.params()["keyword_id"];
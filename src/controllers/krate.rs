@@ -4,3 +4,5 @@ pub mod metadata;
 pub mod owners;
 pub mod publish;
 pub mod search;
+pub mod suggest;
+pub mod transfer;
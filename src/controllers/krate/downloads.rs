@@ -4,12 +4,17 @@
 //! download counts are located in `krate::downloads`.
 
 use std::cmp;
+use std::collections::BTreeMap;
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{Crate, CrateVersions, Version, VersionDownload};
-use crate::schema::version_downloads;
-use crate::views::EncodableVersionDownload;
+use crate::controllers::helpers::DownloadsRange;
+use crate::models::{
+    Crate, CrateVersions, Granularity, Version, VersionDownload, VersionDownloadByClient,
+    VersionDownloadRollup,
+};
+use crate::schema::{version_downloads, version_downloads_by_client};
+use crate::views::{EncodableVersionDownload, EncodableVersionDownloadByClient};
 
 use crate::models::krate::to_char;
 
@@ -23,27 +28,76 @@ pub fn downloads(req: &mut dyn RequestExt) -> EndpointResult {
     let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
 
     let mut versions: Vec<Version> = krate.all_versions().load(&*conn)?;
+
+    match req.query().get("by").map(String::as_str) {
+        None => {}
+        Some("client") => return downloads_by_client(req, &conn, &versions),
+        Some(_) => return Err(bad_request("invalid `by` parameter, expected `client`")),
+    }
+
     versions.sort_by(|a, b| b.num.cmp(&a.num));
     let (latest_five, rest) = versions.split_at(cmp::min(5, versions.len()));
 
-    let downloads = VersionDownload::belonging_to(latest_five)
-        .filter(version_downloads::date.gt(date(now - 90.days())))
-        .order(version_downloads::date.asc())
-        .load(&*conn)?
-        .into_iter()
-        .map(VersionDownload::into)
-        .collect::<Vec<_>>();
-
-    let sum_downloads = sql::<BigInt>("SUM(version_downloads.downloads)");
-    let extra: Vec<ExtraDownload> = VersionDownload::belonging_to(rest)
-        .select((
-            to_char(version_downloads::date, "YYYY-MM-DD"),
-            sum_downloads,
-        ))
-        .filter(version_downloads::date.gt(date(now - 90.days())))
-        .group_by(version_downloads::date)
-        .order(version_downloads::date.asc())
-        .load(&*conn)?;
+    let range = DownloadsRange::from_request(req, None)?;
+    let (downloads, extra) = match range.granularity {
+        Granularity::Day => {
+            let mut query = VersionDownload::belonging_to(latest_five)
+                .filter(version_downloads::date.le(range.until))
+                .order(version_downloads::date.asc())
+                .into_boxed();
+            let sum_downloads = sql::<BigInt>("SUM(version_downloads.downloads)");
+            let mut extra_query = VersionDownload::belonging_to(rest)
+                .select((
+                    to_char(version_downloads::date, "YYYY-MM-DD"),
+                    sum_downloads,
+                ))
+                .filter(version_downloads::date.le(range.until))
+                .group_by(version_downloads::date)
+                .order(version_downloads::date.asc())
+                .into_boxed();
+            if let Some(since) = range.since {
+                query = query.filter(version_downloads::date.ge(since));
+                extra_query = extra_query.filter(version_downloads::date.ge(since));
+            }
+
+            let downloads = query
+                .load(&*conn)?
+                .into_iter()
+                .map(VersionDownload::into)
+                .collect::<Vec<_>>();
+            let extra: Vec<ExtraDownload> = extra_query.load(&*conn)?;
+            (downloads, extra)
+        }
+        granularity => {
+            use crate::schema::version_downloads_rollups as rollups;
+
+            let mut query = VersionDownloadRollup::belonging_to(latest_five)
+                .filter(rollups::granularity.eq(granularity.as_str()))
+                .filter(rollups::period.le(range.until))
+                .order(rollups::period.asc())
+                .into_boxed();
+            let sum_downloads = sql::<BigInt>("SUM(version_downloads_rollups.downloads)");
+            let mut extra_query = VersionDownloadRollup::belonging_to(rest)
+                .select((to_char(rollups::period, "YYYY-MM-DD"), sum_downloads))
+                .filter(rollups::granularity.eq(granularity.as_str()))
+                .filter(rollups::period.le(range.until))
+                .group_by(rollups::period)
+                .order(rollups::period.asc())
+                .into_boxed();
+            if let Some(since) = range.since {
+                query = query.filter(rollups::period.ge(since));
+                extra_query = extra_query.filter(rollups::period.ge(since));
+            }
+
+            let downloads = query
+                .load(&*conn)?
+                .into_iter()
+                .map(VersionDownloadRollup::into)
+                .collect::<Vec<_>>();
+            let extra: Vec<ExtraDownload> = extra_query.load(&*conn)?;
+            (downloads, extra)
+        }
+    };
 
     #[derive(Serialize, Queryable)]
     struct ExtraDownload {
@@ -67,3 +121,51 @@ pub fn downloads(req: &mut dyn RequestExt) -> EndpointResult {
         meta,
     }))
 }
+
+/// Handles the `GET /crates/:crate_id/downloads?by=client` route.
+fn downloads_by_client(
+    req: &dyn RequestExt,
+    conn: &PgConnection,
+    versions: &[Version],
+) -> EndpointResult {
+    use diesel::dsl::*;
+
+    let downloads: Vec<VersionDownloadByClient> = VersionDownloadByClient::belonging_to(versions)
+        .filter(version_downloads_by_client::date.gt(date(now - 90.days())))
+        .order((
+            version_downloads_by_client::date.asc(),
+            version_downloads_by_client::version_id.asc(),
+            version_downloads_by_client::client.asc(),
+        ))
+        .load(conn)?;
+
+    let mut clients = BTreeMap::new();
+    for download in &downloads {
+        *clients.entry(download.client.clone()).or_insert(0) += i64::from(download.downloads);
+    }
+
+    #[derive(Serialize)]
+    struct ClientDownloads {
+        client: String,
+        downloads: i64,
+    }
+    #[derive(Serialize)]
+    struct R {
+        version_downloads_by_client: Vec<EncodableVersionDownloadByClient>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        clients: Vec<ClientDownloads>,
+    }
+    let meta = Meta {
+        clients: clients
+            .into_iter()
+            .map(|(client, downloads)| ClientDownloads { client, downloads })
+            .collect(),
+    };
+    Ok(req.json(&R {
+        version_downloads_by_client: downloads.into_iter().map(Into::into).collect(),
+        meta,
+    }))
+}
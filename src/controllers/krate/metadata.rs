@@ -5,15 +5,19 @@
 //! `Cargo.toml` file.
 
 use crate::controllers::frontend_prelude::*;
-use crate::controllers::helpers::pagination::PaginationOptions;
+use crate::controllers::helpers::pagination::{encode_seek, Paginated, PaginationOptions};
+use crate::controllers::helpers::Paginate;
+use chrono::NaiveDateTime;
+use indexmap::IndexMap;
 
 use crate::models::{
-    Category, Crate, CrateCategory, CrateKeyword, CrateVersions, Keyword, RecentCrateDownloads,
-    TopVersions, User, Version, VersionOwnerAction,
+    Advisory, Category, Crate, CrateCategory, CrateKeyword, CrateOwnerAction, CrateVersions,
+    Keyword, RecentCrateDownloads, TopVersions, User, Version, VersionOwnerAction,
 };
 use crate::schema::*;
 use crate::views::{
-    EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword, EncodableVersion,
+    EncodableAdvisory, EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword,
+    EncodableVersion,
 };
 
 use crate::models::krate::ALL_COLUMNS;
@@ -118,7 +122,12 @@ pub fn summary(req: &mut dyn RequestExt) -> EndpointResult {
 }
 
 /// Handles the `GET /crates/:crate_id` route.
+///
+/// The actions on the owners of the crate are only included with `include=owner_actions`.
 pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let include_owner_actions = req.query().get("include").map_or(false, |include| {
+        include.split(',').any(|include| include == "owner_actions")
+    });
     let name = &req.params()["crate_id"];
     let conn = req.db_read_only()?;
     let krate: Crate = Crate::by_name(name).first(&*conn)?;
@@ -137,7 +146,8 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     let versions_publishers_and_audit_actions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, pb), aas)| (v, pb, aas))
+        .zip(Advisory::for_versions(&conn, &versions)?.into_iter())
+        .map(|(((v, pb), aas), advisories)| (v, pb, aas, advisories))
         .collect::<Vec<_>>();
     let ids = versions_publishers_and_audit_actions
         .iter()
@@ -161,6 +171,16 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
         .filter(badges::crate_id.eq(krate.id))
         .load(&*conn)?;
     let top_versions = krate.top_versions(&conn)?;
+    let owner_actions = if include_owner_actions {
+        Some(CrateOwnerAction::by_crate(&conn, &krate)?)
+    } else {
+        None
+    };
+    let version_nums = versions.into_iter().map(|v| v.num).collect::<Vec<_>>();
+    let advisories = Advisory::by_crate(&conn, &krate)?
+        .into_iter()
+        .map(|advisory| EncodableAdvisory::from(advisory, &krate.name, &version_nums))
+        .collect();
 
     #[derive(Serialize)]
     struct R {
@@ -180,10 +200,14 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
             Some(badges),
             false,
             recent_downloads,
+            owner_actions,
+            Some(advisories),
         ),
         versions: versions_publishers_and_audit_actions
             .into_iter()
-            .map(|(v, pb, aas)| EncodableVersion::from(v, &krate.name, pb, aas))
+            .map(|(v, pb, aas, advisories)| {
+                EncodableVersion::from(v, &krate.name, pb, aas, advisories)
+            })
             .collect(),
         keywords: kws.into_iter().map(Keyword::into).collect(),
         categories: cats.into_iter().map(Category::into).collect(),
@@ -213,18 +237,55 @@ pub fn readme(req: &mut dyn RequestExt) -> EndpointResult {
 }
 
 /// Handles the `GET /crates/:crate_id/versions` route.
+///
+/// All the versions are returned at once, sorted by version number, unless the `seek` parameter
+/// is present. In that case the versions are paginated from the most recently published.
 // FIXME: Not sure why this is necessary since /crates/:crate_id returns
 // this information already, but ember is definitely requesting it
 pub fn versions(req: &mut dyn RequestExt) -> EndpointResult {
-    let crate_name = &req.params()["crate_id"];
+    let crate_name = req.params()["crate_id"].clone();
+    let pagination = if req.query().contains_key("seek") {
+        Some(PaginationOptions::with_seek(req)?)
+    } else {
+        None
+    };
+
     let conn = req.db_read_only()?;
-    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
-    let mut versions_and_publishers: Vec<(Version, Option<User>)> = krate
-        .all_versions()
-        .left_outer_join(users::table)
-        .select((versions::all_columns, users::all_columns.nullable()))
-        .load(&*conn)?;
-    versions_and_publishers.sort_by(|a, b| b.0.num.cmp(&a.0.num));
+    let krate: Crate = Crate::by_name(&crate_name).first(&*conn)?;
+    let (versions_and_publishers, next_page) = match pagination {
+        Some(pagination) => {
+            let mut query = Version::belonging_to(&krate)
+                .left_outer_join(users::table)
+                .select((versions::all_columns, users::all_columns.nullable()))
+                .order((versions::created_at.desc(), versions::id.desc()))
+                .into_boxed();
+            if let Some(seek) = pagination.seek() {
+                if let Some((created_at, id)) = seek.decode::<(NaiveDateTime, i32)>()? {
+                    query = query.filter(
+                        versions::created_at
+                            .lt(created_at)
+                            .or(versions::created_at.eq(created_at).and(versions::id.lt(id))),
+                    );
+                }
+            }
+
+            let data: Paginated<(Version, Option<User>)> =
+                query.paginate_with(pagination).load(&*conn)?;
+            let next_page = data
+                .next_seek_params(|(version, _)| (version.created_at, version.id))?
+                .map(|p| req.query_with_params(p));
+            (data.into_iter().collect::<Vec<_>>(), next_page)
+        }
+        None => {
+            let mut versions_and_publishers: Vec<(Version, Option<User>)> = krate
+                .all_versions()
+                .left_outer_join(users::table)
+                .select((versions::all_columns, users::all_columns.nullable()))
+                .load(&*conn)?;
+            versions_and_publishers.sort_by(|a, b| b.0.num.cmp(&a.0.num));
+            (versions_and_publishers, None)
+        }
+    };
     let versions = versions_and_publishers
         .iter()
         .map(|(v, _)| v)
@@ -233,25 +294,51 @@ pub fn versions(req: &mut dyn RequestExt) -> EndpointResult {
     let versions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, pb), aas)| EncodableVersion::from(v, crate_name, pb, aas))
+        .zip(Advisory::for_versions(&conn, &versions)?.into_iter())
+        .map(|(((v, pb), aas), advisories)| {
+            EncodableVersion::from(v, &crate_name, pb, aas, advisories)
+        })
         .collect();
 
     #[derive(Serialize)]
     struct R {
         versions: Vec<EncodableVersion>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        next_page: Option<String>,
     }
-    Ok(req.json(&R { versions }))
+    Ok(req.json(&R {
+        versions,
+        meta: Meta { next_page },
+    }))
 }
 
 /// Handles the `GET /crates/:crate_id/reverse_dependencies` route.
 pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
     use diesel::dsl::any;
 
-    let pagination_options = PaginationOptions::new(req)?;
+    let pagination_options = PaginationOptions::with_seek(req)?;
+    let is_seek = pagination_options.seek().is_some();
+    let per_page = pagination_options.per_page as usize;
     let name = &req.params()["crate_id"];
     let conn = req.db_read_only()?;
     let krate: Crate = Crate::by_name(name).first(&*conn)?;
     let (rev_deps, total) = krate.reverse_dependencies(&*conn, pagination_options)?;
+    let next_page = match rev_deps.last() {
+        Some(last) if is_seek && rev_deps.len() == per_page => {
+            let mut params = IndexMap::new();
+            params.insert(
+                "seek".into(),
+                encode_seek((last.crate_downloads, &last.name))?,
+            );
+            Some(req.query_with_params(params))
+        }
+        _ => None,
+    };
+    // Counting all the dependents is skipped when seeking
+    let total = if is_seek { None } else { Some(total) };
     let rev_deps: Vec<_> = rev_deps
         .into_iter()
         .map(|dep| EncodableDependency::from_reverse_dep(dep, &krate.name))
@@ -277,8 +364,9 @@ pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
     let versions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((version, krate_name, published_by), actions)| {
-            EncodableVersion::from(version, &krate_name, published_by, actions)
+        .zip(Advisory::for_versions(&conn, &versions)?.into_iter())
+        .map(|(((v, krate_name, pb), aas), advisories)| {
+            EncodableVersion::from(v, &krate_name, pb, aas, advisories)
         })
         .collect();
 
@@ -290,11 +378,12 @@ pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
     }
     #[derive(Serialize)]
     struct Meta {
-        total: i64,
+        total: Option<i64>,
+        next_page: Option<String>,
     }
     Ok(req.json(&R {
         dependencies: rev_deps,
         versions,
-        meta: Meta { total },
+        meta: Meta { total, next_page },
     }))
 }
@@ -1,15 +1,26 @@
 //! All routes related to managing owners of a crate
 
 use crate::controllers::prelude::*;
-use crate::models::{Crate, Owner, Rights, Team, User};
-use crate::views::EncodableOwner;
+use crate::models::{Crate, CrateOwnerAction, Owner, Rights, Team, User};
+use crate::views::{EncodableAuditAction, EncodableOwner};
+use crate::App;
 
 /// Handles the `GET /crates/:crate_id/owners` route.
 pub fn owners(req: &mut dyn RequestExt) -> EndpointResult {
     let crate_name = &req.params()["crate_id"];
     let conn = req.db_read_only()?;
     let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
-    let owners = krate.owners(&conn)?.into_iter().map(Owner::into).collect();
+    let owners = krate
+        .owners_with_rights(&conn)?
+        .into_iter()
+        .map(|(owner, rights)| {
+            let rights: &'static str = rights.into();
+            EncodableOwner {
+                rights: Some(rights.to_string()),
+                ..owner.into()
+            }
+        })
+        .collect();
 
     #[derive(Serialize)]
     struct R {
@@ -52,6 +63,25 @@ pub fn owner_user(req: &mut dyn RequestExt) -> EndpointResult {
     Ok(req.json(&R { users: owners }))
 }
 
+/// Handles the `GET /crates/:crate_id/owner_actions` route.
+pub fn owner_actions(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+    let owner_actions = CrateOwnerAction::by_crate(&conn, &krate)?
+        .into_iter()
+        .map(|(owner_action, user, target)| {
+            EncodableAuditAction::from_crate_owner_action(owner_action, user, target)
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        owner_actions: Vec<EncodableAuditAction>,
+    }
+    Ok(req.json(&R { owner_actions }))
+}
+
 /// Handles the `PUT /crates/:crate_id/owners` route.
 pub fn add_owners(req: &mut dyn RequestExt) -> EndpointResult {
     modify_owners(req, true)
@@ -62,11 +92,48 @@ pub fn remove_owners(req: &mut dyn RequestExt) -> EndpointResult {
     modify_owners(req, false)
 }
 
+/// Handles the `PUT /crates/:crate_id/owner_rights` route.
+pub fn update_owner_rights(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = req.authenticate()?;
+    let (logins, rights) = parse_owners_request(req)?;
+    let rights = rights.ok_or_else(|| cargo_err("missing rights"))?;
+    let app = req.app();
+    let crate_name = &req.params()["crate_id"];
+
+    let conn = req.db_conn()?;
+    let api_token_id = authenticated_user.api_token_id();
+    let user = authenticated_user.user();
+
+    conn.transaction(|| {
+        let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+        let owners = krate.owners_with_rights(&conn)?;
+        ensure_owner_management_rights(app, &conn, &user, &owners)?;
+
+        for login in &logins {
+            krate.set_owner_rights(app, &conn, &user, login, rights, api_token_id)?;
+        }
+
+        #[derive(Serialize)]
+        struct R {
+            ok: bool,
+            msg: String,
+        }
+        let rights: &'static str = rights.into();
+        Ok(req.json(&R {
+            ok: true,
+            msg: format!("owners now have {} rights", rights),
+        }))
+    })
+}
+
 /// Parse the JSON request body of requests to modify the owners of a crate.
 /// The format is
 ///
-///     {"owners": ["username", "github:org:team", ...]}
-fn parse_owners_request(req: &mut dyn RequestExt) -> AppResult<Vec<String>> {
+///     {"owners": ["username", "github:org:team", ...], "rights": "yank"}
+///
+/// where `rights` is optional and either `full` or a comma separated list of `publish` and
+/// `yank`, such as `publish,yank`.
+fn parse_owners_request(req: &mut dyn RequestExt) -> AppResult<(Vec<String>, Option<Rights>)> {
     let mut body = String::new();
     req.body().read_to_string(&mut body)?;
     #[derive(Deserialize)]
@@ -74,56 +141,73 @@ fn parse_owners_request(req: &mut dyn RequestExt) -> AppResult<Vec<String>> {
         // identical, for back-compat (owners preferred)
         users: Option<Vec<String>>,
         owners: Option<Vec<String>>,
+        rights: Option<String>,
     }
     let request: Request =
         serde_json::from_str(&body).map_err(|_| cargo_err("invalid json request"))?;
-    request
+    let rights = request
+        .rights
+        .map(|rights| rights.parse())
+        .transpose()
+        .map_err(|_| {
+            cargo_err(
+                "invalid rights, expected `full` or a comma separated list of `publish` and `yank`",
+            )
+        })?;
+    let logins = request
         .owners
         .or(request.users)
-        .ok_or_else(|| cargo_err("invalid json request"))
+        .ok_or_else(|| cargo_err("invalid json request"))?;
+    Ok((logins, rights))
+}
+
+fn ensure_owner_management_rights(
+    app: &App,
+    conn: &PgConnection,
+    user: &User,
+    owners: &[(Owner, Rights)],
+) -> AppResult<()> {
+    match user.rights(app, conn, owners)? {
+        Rights::FULL => Ok(()),
+        Rights::NONE => Err(cargo_err("only owners have permission to modify owners")),
+        // Yes!
+        _ => Err(cargo_err(
+            "team members don't have permission to modify owners",
+        )),
+    }
 }
 
 fn modify_owners(req: &mut dyn RequestExt, add: bool) -> EndpointResult {
     let authenticated_user = req.authenticate()?;
-    let logins = parse_owners_request(req)?;
+    let (logins, rights) = parse_owners_request(req)?;
     let app = req.app();
     let crate_name = &req.params()["crate_id"];
 
     let conn = req.db_conn()?;
+    let api_token_id = authenticated_user.api_token_id();
     let user = authenticated_user.user();
 
     conn.transaction(|| {
         let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
-        let owners = krate.owners(&conn)?;
-
-        match user.rights(app, &owners)? {
-            Rights::Full => {}
-            // Yes!
-            Rights::Publish => {
-                return Err(cargo_err(
-                    "team members don't have permission to modify owners",
-                ));
-            }
-            Rights::None => {
-                return Err(cargo_err("only owners have permission to modify owners"));
-            }
-        }
+        let owners = krate.owners_with_rights(&conn)?;
+        ensure_owner_management_rights(app, &conn, &user, &owners)?;
 
         let comma_sep_msg = if add {
             let mut msgs = Vec::with_capacity(logins.len());
             for login in &logins {
-                let login_test =
-                    |owner: &Owner| owner.login().to_lowercase() == *login.to_lowercase();
+                let login_test = |(owner, _): &(Owner, Rights)| {
+                    owner.login().to_lowercase() == *login.to_lowercase()
+                };
                 if owners.iter().any(login_test) {
                     return Err(cargo_err(&format_args!("`{}` is already an owner", login)));
                 }
-                let msg = krate.owner_add(app, &conn, &user, login)?;
+                let msg = krate.owner_add(app, &conn, &user, login, rights, api_token_id)?;
                 msgs.push(msg);
             }
             msgs.join(",")
         } else {
             for login in &logins {
-                krate.owner_remove(app, &conn, &user, login)?;
+                krate.owner_remove(app, &conn, &user, login, api_token_id)?;
             }
             if User::owning(&krate, &conn)?.is_empty() {
                 return Err(cargo_err(
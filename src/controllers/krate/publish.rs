@@ -110,8 +110,9 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         let krate =
             persist.create_or_update(&conn, user.id, Some(&app.config.publish_rate_limit))?;
 
-        let owners = krate.owners(&conn)?;
-        if user.rights(req.app(), &owners)? < Rights::Publish {
+        let owners = krate.owners_with_rights(&conn)?;
+        let rights = user.rights(req.app(), &conn, &owners)?;
+        if !rights.contains(Rights::PUBLISH) {
             return Err(cargo_err(MISSING_RIGHTS_ERROR_MESSAGE));
         }
 
@@ -159,6 +160,7 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
             // to get here, and max upload sizes are way less than i32 max
             file_length as i32,
             user.id,
+            new_crate.rust_version.as_deref(),
         )?
         .save(&conn, &new_crate.authors, &verified_email_address)?;
 
@@ -1,19 +1,26 @@
 //! Endpoint for searching and discovery functionality
 
+use chrono::{Duration, NaiveDateTime, Utc};
 use diesel::dsl::*;
+use diesel::pg::Pg;
+use diesel::sql_types::{BigInt, Bool, Double, Integer};
 use diesel_full_text_search::*;
+use indexmap::IndexMap;
 
 use crate::controllers::cargo_prelude::*;
+use crate::controllers::helpers::pagination::PaginationOptions;
 use crate::controllers::helpers::Paginate;
 use crate::models::{
-    Crate, CrateBadge, CrateOwner, CrateVersions, OwnerKind, TopVersions, Version,
+    valid_rust_version, Crate, CrateBadge, CrateOwner, CrateVersions, DependencyKind, OwnerKind,
+    TopVersions, Version,
 };
 use crate::schema::*;
 use crate::util::errors::{bad_request, ChainError};
 use crate::views::EncodableCrate;
+use crate::SearchRankingWeights;
 
 use crate::controllers::helpers::pagination::Paginated;
-use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
+use crate::models::krate::{canon_crate_name, similarity, ALL_COLUMNS};
 
 /// Handles the `GET /crates` route.
 /// Returns a list of crates. Called in a variety of scenarios in the
@@ -22,6 +29,14 @@ use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
 /// - List of crates under a specific owner
 /// - Listing a user's followed crates
 ///
+/// Besides numeric pages, the listing supports seek-based pagination through the `seek`
+/// parameter, which stays fast when walking the whole registry. It can't be combined with `q`.
+///
+/// The filter parameters (`q`, `category`, `keyword`, `all_keywords`, `letter`, `user_id`,
+/// `team_id`, `following`, `ids[]`, `include_yanked`, `license`, `has_readme`, `rust_version`,
+/// `updated_within` and `depends_on`) can be combined freely. With `include_facets=yes`, the `meta` also
+/// counts the matching crates per category and keyword, to offer drill-down options.
+///
 /// Notes:
 /// The different use cases this function covers is handled through passing
 /// in parameters in the GET request.
@@ -37,37 +52,44 @@ use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
 /// function out to cover the different use cases, and create unit tests
 /// for them.
 pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
-    use diesel::sql_types::{Bool, Text};
-
     let params = req.query();
     let sort = params.get("sort").map(|s| &**s);
-    let include_yanked = params
-        .get("include_yanked")
+    let pagination = PaginationOptions::with_seek(req)?;
+    let include_facets = params
+        .get("include_facets")
         .map(|s| s == "yes")
-        .unwrap_or(true);
-
-    let selection = (
-        ALL_COLUMNS,
-        false.into_sql::<Bool>(),
-        recent_crate_downloads::downloads.nullable(),
-    );
-    let mut query = crates::table
-        .left_join(recent_crate_downloads::table)
-        .select(selection)
-        .into_boxed();
-
-    if let Some(q_string) = params.get("q") {
-        if !q_string.is_empty() {
-            let sort = params.get("sort").map(|s| &**s).unwrap_or("relevance");
-
-            let q = sql::<TsQuery>("plainto_tsquery('english', ")
-                .bind::<Text, _>(q_string)
-                .sql(")");
-            query = query.filter(
-                q.clone()
-                    .matches(crates::textsearchable_index_col)
-                    .or(Crate::loosly_matches_name(&q_string)),
-            );
+        .unwrap_or(false);
+
+    let following = match params.get("following") {
+        Some(_) => Some(req.authenticate()?.user_id()),
+        None => None,
+    };
+    let mut filters = FilterParams::from_params(&params, req.query_string(), following)?;
+    if let Some(depends_on) = DependsOn::from_params(&params)? {
+        let conn = req.db_read_only()?;
+        filters.dependents = Some(depends_on.crate_ids(&conn)?);
+    }
+
+    if filters.q_string.is_some() && pagination.seek().is_some() {
+        return Err(bad_request("the `seek` parameter can't be used with `q`"));
+    }
+    let is_first_page = pagination.offset().unwrap_or_default() == 0;
+
+    let conn = req.db_read_only()?;
+    // The search runs a second time, with `filters.fuzzy`, when nothing matches `q`
+    let data: Paginated<(Crate, bool, Option<i64>)> = loop {
+        let selection = (
+            ALL_COLUMNS,
+            false.into_sql::<Bool>(),
+            recent_crate_downloads::downloads.nullable(),
+        );
+        let mut query = crates::table
+            .left_join(recent_crate_downloads::table)
+            .select(selection)
+            .into_boxed();
+
+        if let Some(q_string) = filters.q_string {
+            let sort = sort.unwrap_or("relevance");
 
             query = query.select((
                 ALL_COLUMNS,
@@ -77,132 +99,120 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
             query = query.order(Crate::with_name(q_string).desc());
 
             if sort == "relevance" {
-                let rank = ts_rank_cd(crates::textsearchable_index_col, q);
-                query = query.then_order_by(rank.desc())
+                let weights = &req.app().config.search_ranking_weights;
+                query = query.then_order_by(relevance_score(q_string, weights).desc());
+                query = query.then_order_by(
+                    similarity(canon_crate_name(crates::name), canon_crate_name(q_string)).desc(),
+                );
             }
         }
-    }
-
-    if let Some(cat) = params.get("category") {
-        query = query.filter(
-            crates::id.eq_any(
-                crates_categories::table
-                    .select(crates_categories::crate_id)
-                    .inner_join(categories::table)
-                    .filter(
-                        categories::slug
-                            .eq(cat)
-                            .or(categories::slug.like(format!("{}::%", cat))),
-                    ),
-            ),
-        );
-    }
-
-    if let Some(kws) = params.get("all_keywords") {
-        use diesel::sql_types::Array;
-        sql_function!(#[aggregate] fn array_agg<T>(x: T) -> Array<T>);
-
-        let names: Vec<_> = kws
-            .split_whitespace()
-            .map(|name| name.to_lowercase())
-            .collect();
 
-        query = query.filter(
-            // FIXME: Just use `.contains` in Diesel 2.0
-            // https://github.com/diesel-rs/diesel/issues/2066
-            Contains::new(
-                crates_keywords::table
-                    .inner_join(keywords::table)
-                    .filter(crates_keywords::crate_id.eq(crates::id))
-                    .select(array_agg(keywords::keyword))
-                    .single_value(),
-                names.into_sql::<Array<Text>>(),
-            ),
-        );
-    } else if let Some(kw) = params.get("keyword") {
-        query = query.filter(
-            crates::id.eq_any(
-                crates_keywords::table
-                    .select(crates_keywords::crate_id)
-                    .inner_join(keywords::table)
-                    .filter(crate::lower(keywords::keyword).eq(crate::lower(kw))),
-            ),
-        );
-    } else if let Some(letter) = params.get("letter") {
-        let pattern = format!(
-            "{}%",
-            letter
-                .chars()
-                .next()
-                .chain_error(|| bad_request("letter value must contain 1 character"))?
-                .to_lowercase()
-                .collect::<String>()
-        );
-        query = query.filter(canon_crate_name(crates::name).like(pattern));
-    } else if let Some(user_id) = params.get("user_id").and_then(|s| s.parse::<i32>().ok()) {
-        query = query.filter(
-            crates::id.eq_any(
-                CrateOwner::by_owner_kind(OwnerKind::User)
-                    .select(crate_owners::crate_id)
-                    .filter(crate_owners::owner_id.eq(user_id)),
-            ),
-        );
-    } else if let Some(team_id) = params.get("team_id").and_then(|s| s.parse::<i32>().ok()) {
-        query = query.filter(
-            crates::id.eq_any(
-                CrateOwner::by_owner_kind(OwnerKind::Team)
-                    .select(crate_owners::crate_id)
-                    .filter(crate_owners::owner_id.eq(team_id)),
-            ),
-        );
-    } else if params.get("following").is_some() {
-        let user_id = req.authenticate()?.user_id();
-        query = query.filter(
-            crates::id.eq_any(
-                follows::table
-                    .select(follows::crate_id)
-                    .filter(follows::user_id.eq(user_id)),
-            ),
-        );
-    } else if params.get("ids[]").is_some() {
-        let query_bytes = req.query_string().unwrap_or("").as_bytes();
-        let ids: Vec<_> = url::form_urlencoded::parse(query_bytes)
-            .filter(|(key, _)| key == "ids[]")
-            .map(|(_, value)| value.to_string())
-            .collect();
+        if !filters.is_empty() {
+            query = query.filter(crates::id.eq_any(filters.crate_ids()));
+        }
 
-        query = query.filter(crates::name.eq(any(ids)));
-    }
+        if sort == Some("downloads") {
+            query = query.then_order_by(crates::downloads.desc())
+        } else if sort == Some("recent-downloads") {
+            query = query.then_order_by(recent_crate_downloads::downloads.desc().nulls_last())
+        } else if sort == Some("recent-updates") {
+            query = query.order(crates::updated_at.desc());
+        } else if sort == Some("new") {
+            query = query.order(crates::created_at.desc());
+        } else {
+            query = query.then_order_by(crates::name.asc())
+        }
+        // Break ties between crates with the same sort key, so that the order is stable across
+        // pages
+        query = query.then_order_by(crates::id.asc());
 
-    if !include_yanked {
-        query = query.filter(exists(
-            versions::table
-                .filter(versions::crate_id.eq(crates::id))
-                .filter(versions::yanked.eq(false)),
-        ));
-    }
+        if let Some(seek) = pagination.seek() {
+            if let Some(seek) = seek.decode::<Seek>()? {
+                query = match (sort, seek) {
+                    (Some("downloads"), Seek::Downloads { id, downloads }) => query.filter(
+                        crates::downloads
+                            .lt(downloads)
+                            .or(crates::downloads.eq(downloads).and(crates::id.gt(id))),
+                    ),
+                    (Some("recent-downloads"), Seek::RecentDownloads { id, downloads }) => {
+                        match downloads {
+                            Some(downloads) => query.filter(
+                                recent_crate_downloads::downloads
+                                    .lt(downloads)
+                                    .or(recent_crate_downloads::downloads.is_null())
+                                    .or(recent_crate_downloads::downloads
+                                        .eq(downloads)
+                                        .and(crates::id.gt(id))),
+                            ),
+                            // Crates without recent downloads are sorted last
+                            None => query.filter(
+                                recent_crate_downloads::downloads
+                                    .is_null()
+                                    .and(crates::id.gt(id)),
+                            ),
+                        }
+                    }
+                    (Some("recent-updates"), Seek::RecentUpdates { id, updated_at }) => query
+                        .filter(
+                            crates::updated_at
+                                .lt(updated_at)
+                                .or(crates::updated_at.eq(updated_at).and(crates::id.gt(id))),
+                        ),
+                    (Some("new"), Seek::New { id, created_at }) => query.filter(
+                        crates::created_at
+                            .lt(created_at)
+                            .or(crates::created_at.eq(created_at).and(crates::id.gt(id))),
+                    ),
+                    (sort, Seek::Alpha { id, name }) if Seek::is_alpha(sort) => query.filter(
+                        crates::name
+                            .gt(name.clone())
+                            .or(crates::name.eq(name).and(crates::id.gt(id))),
+                    ),
+                    _ => return Err(bad_request("invalid `seek` parameter")),
+                };
+            }
+        }
 
-    if sort == Some("downloads") {
-        query = query.then_order_by(crates::downloads.desc())
-    } else if sort == Some("recent-downloads") {
-        query = query.then_order_by(recent_crate_downloads::downloads.desc().nulls_last())
-    } else if sort == Some("recent-updates") {
-        query = query.order(crates::updated_at.desc());
-    } else if sort == Some("new") {
-        query = query.order(crates::created_at.desc());
-    } else {
-        query = query.then_order_by(crates::name.asc())
-    }
+        let data: Paginated<(Crate, bool, Option<i64>)> =
+            query.paginate_with(pagination.clone()).load(&*conn)?;
 
-    let query = query.paginate(req)?;
-    let conn = req.db_read_only()?;
-    let data: Paginated<(Crate, bool, Option<i64>)> = query.load(&*conn)?;
+        // Fall back to the crates with a similar name when nothing matches the search, to tolerate
+        // typos like `tokoi`. The total of the first query tells whether that's needed, so that
+        // searches don't have to count their matches separately.
+        let no_matches = is_first_page && data.total() == Some(0);
+        if filters.q_string.is_some() && !filters.fuzzy && no_matches {
+            filters.fuzzy = true;
+            continue;
+        }
+        break data;
+    };
     let total = data.total();
+    // The total of later pages is zero when they're past the last match
+    let few_matches = is_first_page && total.map_or(false, |total| total < FEW_RESULTS);
 
-    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+    let next_page = data
+        .next_seek_params(|(krate, _, recent_downloads)| Seek::new(sort, krate, *recent_downloads))?
+        .or_else(|| data.next_page_params())
+        .map(|p| req.query_with_params(p));
     let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
 
     let perfect_matches = data.iter().map(|&(_, b, _)| b).collect::<Vec<_>>();
+
+    // Suggest a similar crate name when the search has few matches, unless a crate has exactly
+    // the name being searched for.
+    let suggestion = match filters.q_string {
+        Some(q_string) if few_matches && !perfect_matches.contains(&true) => {
+            Crate::most_similar_name(&conn, q_string)?
+        }
+        _ => None,
+    };
+
+    let facets = if include_facets {
+        Some(filters.facets(&conn)?)
+    } else {
+        None
+    };
+
     let recent_downloads = data
         .iter()
         .map(|&(_, _, s)| s.unwrap_or(0))
@@ -251,16 +261,587 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         total: Option<i64>,
         next_page: Option<String>,
         prev_page: Option<String>,
+        /// A crate name to suggest when the search has few results ("did you mean").
+        suggestion: Option<String>,
+        /// The number of matching crates per category and keyword, with `include_facets=yes`.
+        facets: Option<Facets>,
     }
 
     Ok(req.json(&R {
         crates,
         meta: Meta {
-            total: Some(total),
+            total,
             next_page,
             prev_page,
+            suggestion,
+            facets,
         },
     }))
 }
 
+/// Searches with fewer matches than this get a "did you mean" suggestion in their `meta`.
+const FEW_RESULTS: i64 = 3;
+
+/// The number of categories and keywords returned as facets, the most common ones first.
+const FACETS_LIMIT: i64 = 20;
+
+/// Only look this far back when filtering by the `updated_within` parameter (in days).
+const MAX_UPDATED_WITHIN_DAYS: i64 = 10 * 365;
+
+/// The filters of a search. They can be freely combined, and they apply to both the listed
+/// crates and the facet counts.
+#[derive(Debug)]
+struct FilterParams<'a> {
+    q_string: Option<&'a str>,
+    /// Match `q_string` against crate names by similarity, because nothing matched it exactly.
+    fuzzy: bool,
+    category: Option<&'a str>,
+    all_keywords: Option<Vec<String>>,
+    keyword: Option<&'a str>,
+    /// The `LIKE` pattern of the `letter` parameter.
+    letter: Option<String>,
+    user_id: Option<i32>,
+    team_id: Option<i32>,
+    /// The ID of the authenticated user, whose followed crates are listed.
+    following: Option<i32>,
+    ids: Option<Vec<String>>,
+    /// Leave out the crates whose versions are all yanked.
+    exclude_yanked: bool,
+    /// The regex matching the license expressions that contain the `license` parameter.
+    license: Option<String>,
+    has_readme: Option<bool>,
+    /// The `rust_version` parameter, with three components.
+    rust_version: Option<String>,
+    updated_since: Option<NaiveDateTime>,
+    /// The crates matching the `depends_on` parameters, see `DependsOn`.
+    dependents: Option<Vec<i32>>,
+}
+
+impl<'a> FilterParams<'a> {
+    fn from_params(
+        params: &'a IndexMap<String, String>,
+        query_string: Option<&str>,
+        following: Option<i32>,
+    ) -> AppResult<Self> {
+        let letter = match params.get("letter") {
+            Some(letter) => Some(format!(
+                "{}%",
+                letter
+                    .chars()
+                    .next()
+                    .chain_error(|| bad_request("letter value must contain 1 character"))?
+                    .to_lowercase()
+                    .collect::<String>()
+            )),
+            None => None,
+        };
+
+        let has_readme = match params.get("has_readme").map(|s| &**s) {
+            Some("yes") => Some(true),
+            Some("no") => Some(false),
+            Some(_) => return Err(bad_request("`has_readme` must be `yes` or `no`")),
+            None => None,
+        };
+
+        let rust_version = match params.get("rust_version") {
+            Some(version) if !valid_rust_version(version) => {
+                return Err(bad_request(
+                    "`rust_version` must be a version number like `1.50`",
+                ))
+            }
+            // `1.50` means `1.50.0`, as crates requiring `1.50.1` don't build with it
+            Some(version) if version.matches('.').count() == 1 => Some(format!("{}.0", version)),
+            version => version.cloned(),
+        };
+
+        let updated_since = match params.get("updated_within") {
+            Some(days) => match days.parse::<i64>() {
+                Ok(days) if days > 0 && days <= MAX_UPDATED_WITHIN_DAYS => {
+                    Some(Utc::now().naive_utc() - Duration::days(days))
+                }
+                _ => {
+                    return Err(bad_request(&format_args!(
+                        "`updated_within` must be a number of days between 1 and {}",
+                        MAX_UPDATED_WITHIN_DAYS
+                    )))
+                }
+            },
+            None => None,
+        };
+
+        // The query map only keeps the last value of repeated parameters
+        let ids = params.get("ids[]").map(|_| {
+            let query_bytes = query_string.unwrap_or("").as_bytes();
+            url::form_urlencoded::parse(query_bytes)
+                .filter(|(key, _)| key == "ids[]")
+                .map(|(_, value)| value.to_string())
+                .collect()
+        });
+
+        Ok(Self {
+            q_string: params.get("q").map(|s| &**s).filter(|q| !q.is_empty()),
+            fuzzy: false,
+            category: params.get("category").map(|s| &**s),
+            all_keywords: params.get("all_keywords").map(|kws| {
+                kws.split_whitespace()
+                    .map(|name| name.to_lowercase())
+                    .collect()
+            }),
+            keyword: params.get("keyword").map(|s| &**s),
+            letter,
+            user_id: params.get("user_id").and_then(|s| s.parse().ok()),
+            team_id: params.get("team_id").and_then(|s| s.parse().ok()),
+            following,
+            ids,
+            exclude_yanked: params
+                .get("include_yanked")
+                .map(|s| s != "yes")
+                .unwrap_or(false),
+            license: params
+                .get("license")
+                .map(|s| license_regex(s))
+                .transpose()?,
+            has_readme,
+            rust_version,
+            updated_since,
+            dependents: None,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.q_string.is_none()
+            && self.category.is_none()
+            && self.all_keywords.is_none()
+            && self.keyword.is_none()
+            && self.letter.is_none()
+            && self.user_id.is_none()
+            && self.team_id.is_none()
+            && self.following.is_none()
+            && self.ids.is_none()
+            && !self.exclude_yanked
+            && self.license.is_none()
+            && self.has_readme.is_none()
+            && self.rust_version.is_none()
+            && self.updated_since.is_none()
+            && self.dependents.is_none()
+    }
+
+    /// A subquery selecting the IDs of the crates matching all the filters.
+    fn crate_ids(&self) -> crates::BoxedQuery<'_, Pg, Integer> {
+        use diesel::sql_types::{Array, Text};
+        sql_function!(#[aggregate] fn array_agg<T>(x: T) -> Array<T>);
+
+        let mut query = crates::table.select(crates::id).into_boxed();
+
+        if let Some(q_string) = self.q_string {
+            query = if self.fuzzy {
+                query.filter(Crate::fuzzy_matches_name(q_string))
+            } else {
+                query.filter(matches_query(q_string))
+            };
+        }
+
+        if let Some(cat) = self.category {
+            query = query.filter(
+                crates::id.eq_any(
+                    crates_categories::table
+                        .select(crates_categories::crate_id)
+                        .inner_join(categories::table)
+                        .filter(
+                            categories::slug
+                                .eq(cat)
+                                .or(categories::slug.like(format!("{}::%", cat))),
+                        ),
+                ),
+            );
+        }
+
+        if let Some(names) = &self.all_keywords {
+            query = query.filter(
+                // FIXME: Just use `.contains` in Diesel 2.0
+                // https://github.com/diesel-rs/diesel/issues/2066
+                Contains::new(
+                    crates_keywords::table
+                        .inner_join(keywords::table)
+                        .filter(crates_keywords::crate_id.eq(crates::id))
+                        .select(array_agg(keywords::keyword))
+                        .single_value(),
+                    names.into_sql::<Array<Text>>(),
+                ),
+            );
+        }
+
+        if let Some(kw) = self.keyword {
+            query = query.filter(
+                crates::id.eq_any(
+                    crates_keywords::table
+                        .select(crates_keywords::crate_id)
+                        .inner_join(keywords::table)
+                        .filter(crate::lower(keywords::keyword).eq(crate::lower(kw))),
+                ),
+            );
+        }
+
+        if let Some(pattern) = &self.letter {
+            query = query.filter(canon_crate_name(crates::name).like(pattern));
+        }
+
+        if let Some(user_id) = self.user_id {
+            query = query.filter(
+                crates::id.eq_any(
+                    CrateOwner::by_owner_kind(OwnerKind::User)
+                        .select(crate_owners::crate_id)
+                        .filter(crate_owners::owner_id.eq(user_id)),
+                ),
+            );
+        }
+
+        if let Some(team_id) = self.team_id {
+            query = query.filter(
+                crates::id.eq_any(
+                    CrateOwner::by_owner_kind(OwnerKind::Team)
+                        .select(crate_owners::crate_id)
+                        .filter(crate_owners::owner_id.eq(team_id)),
+                ),
+            );
+        }
+
+        if let Some(user_id) = self.following {
+            query = query.filter(
+                crates::id.eq_any(
+                    follows::table
+                        .select(follows::crate_id)
+                        .filter(follows::user_id.eq(user_id)),
+                ),
+            );
+        }
+
+        if let Some(ids) = &self.ids {
+            query = query.filter(crates::name.eq(any(ids)));
+        }
+
+        if self.exclude_yanked {
+            query = query.filter(exists(
+                versions::table
+                    .filter(versions::crate_id.eq(crates::id))
+                    .filter(versions::yanked.eq(false)),
+            ));
+        }
+
+        // The license and `rust-version` of a crate are the ones of its latest non-yanked version
+        if let Some(license) = &self.license {
+            query = query.filter(
+                sql::<Bool>(&format!("({}) ~* ", latest_version_column("license")))
+                    .bind::<Text, _>(license.as_str()),
+            );
+        }
+
+        if let Some(rust_version) = &self.rust_version {
+            query = query.filter(
+                sql::<Bool>(&format!(
+                    "string_to_array(({}), '.')::int[] <= string_to_array(",
+                    latest_version_column("rust_version")
+                ))
+                .bind::<Text, _>(rust_version.as_str())
+                .sql(", '.')::int[]"),
+            );
+        }
+
+        match self.has_readme {
+            Some(true) => query = query.filter(crates::readme.ne("")),
+            Some(false) => query = query.filter(crates::readme.is_null().or(crates::readme.eq(""))),
+            None => {}
+        }
+
+        if let Some(updated_since) = self.updated_since {
+            query = query.filter(crates::updated_at.ge(updated_since));
+        }
+
+        if let Some(dependents) = &self.dependents {
+            query = query.filter(crates::id.eq(any(dependents)));
+        }
+
+        query
+    }
+
+    /// Counts the matching crates of the most common categories and keywords.
+    fn facets(&self, conn: &PgConnection) -> AppResult<Facets> {
+        let categories: Vec<(String, i64)> = crates_categories::table
+            .inner_join(categories::table)
+            .select((categories::slug, sql::<BigInt>("COUNT(*)")))
+            .filter(crates_categories::crate_id.eq_any(self.crate_ids()))
+            .group_by(categories::slug)
+            .order((sql::<BigInt>("COUNT(*)").desc(), categories::slug.asc()))
+            .limit(FACETS_LIMIT)
+            .load(conn)?;
+
+        let keywords: Vec<(String, i64)> = crates_keywords::table
+            .inner_join(keywords::table)
+            .select((keywords::keyword, sql::<BigInt>("COUNT(*)")))
+            .filter(crates_keywords::crate_id.eq_any(self.crate_ids()))
+            .group_by(keywords::keyword)
+            .order((sql::<BigInt>("COUNT(*)").desc(), keywords::keyword.asc()))
+            .limit(FACETS_LIMIT)
+            .load(conn)?;
+
+        Ok(Facets {
+            categories: categories.into_iter().map(FacetCount::from).collect(),
+            keywords: keywords.into_iter().map(FacetCount::from).collect(),
+        })
+    }
+}
+
+/// The `depends_on` filter, listing the crates whose latest non-yanked version depends on a
+/// crate. The dependency can be narrowed down with the `req`, `kind` (`normal`, `build` or
+/// `dev`) and `optional` (`yes` or `no`) parameters, e.g. to assess who is affected by a
+/// breaking change.
+#[derive(Debug)]
+struct DependsOn<'a> {
+    name: &'a str,
+    req: Option<DependsOnReq>,
+    kind: Option<DependencyKind>,
+    optional: Option<bool>,
+}
+
+#[derive(Debug)]
+enum DependsOnReq {
+    /// With a version like `req=2.0.0`, the requirement of the dependency must match it, even if
+    /// it isn't published yet.
+    Version(semver::Version),
+    /// With a requirement like `req=^1`, the requirement of the dependency must match at least
+    /// one of the published versions matching it.
+    Range(semver::VersionReq),
+}
+
+impl<'a> DependsOn<'a> {
+    fn from_params(params: &'a IndexMap<String, String>) -> AppResult<Option<Self>> {
+        let name = match params.get("depends_on") {
+            Some(name) => name,
+            None if ["req", "kind", "optional"]
+                .iter()
+                .any(|param| params.contains_key(*param)) =>
+            {
+                return Err(bad_request(
+                    "the `req`, `kind` and `optional` parameters require `depends_on`",
+                ))
+            }
+            None => return Ok(None),
+        };
+
+        let req = match params.get("req") {
+            Some(req) => Some(match semver::Version::parse(req) {
+                Ok(version) => DependsOnReq::Version(version),
+                Err(_) => DependsOnReq::Range(semver::VersionReq::parse(req).map_err(|_| {
+                    bad_request("`req` must be a version or a version requirement")
+                })?),
+            }),
+            None => None,
+        };
+
+        let kind = match params.get("kind").map(|s| &**s) {
+            Some("normal") => Some(DependencyKind::Normal),
+            Some("build") => Some(DependencyKind::Build),
+            Some("dev") => Some(DependencyKind::Dev),
+            Some(_) => return Err(bad_request("`kind` must be `normal`, `build` or `dev`")),
+            None => None,
+        };
+
+        let optional = match params.get("optional").map(|s| &**s) {
+            Some("yes") => Some(true),
+            Some("no") => Some(false),
+            Some(_) => return Err(bad_request("`optional` must be `yes` or `no`")),
+            None => None,
+        };
+
+        Ok(Some(Self {
+            name,
+            req,
+            kind,
+            optional,
+        }))
+    }
+
+    /// Returns the IDs of the matching crates. The kind and optionality are filtered in SQL, but
+    /// the requirements are matched here, as the database doesn't understand semver.
+    fn crate_ids(&self, conn: &PgConnection) -> AppResult<Vec<i32>> {
+        let krate: Crate = match Crate::by_name(self.name).first(conn).optional()? {
+            Some(krate) => krate,
+            None => return Ok(Vec::new()),
+        };
+
+        let versions = match &self.req {
+            Some(DependsOnReq::Version(version)) => vec![version.clone()],
+            Some(DependsOnReq::Range(range)) => versions::table
+                .filter(versions::crate_id.eq(krate.id))
+                .filter(versions::yanked.eq(false))
+                .select(versions::num)
+                .load::<String>(conn)?
+                .iter()
+                .filter_map(|num| semver::Version::parse(num).ok())
+                .filter(|version| range.matches(version))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let mut ids: Vec<i32> = krate
+            .latest_dependents(conn, self.kind, self.optional)?
+            .into_iter()
+            .filter(|dependent| {
+                self.req.is_none()
+                    || semver::VersionReq::parse(&dependent.req)
+                        .map(|req| versions.iter().any(|version| req.matches(version)))
+                        .unwrap_or(false)
+            })
+            .map(|dependent| dependent.crate_id)
+            .collect();
+        // A crate depending on it more than once is only bound once
+        ids.sort_unstable();
+        ids.dedup();
+
+        Ok(ids)
+    }
+}
+
+/// SQL subquery selecting a column of the latest non-yanked version of the crate.
+fn latest_version_column(column: &str) -> String {
+    format!(
+        "SELECT versions.{} FROM versions \
+         WHERE versions.crate_id = crates.id AND NOT versions.yanked \
+         ORDER BY versions.id DESC LIMIT 1",
+        column
+    )
+}
+
+/// Builds a case-insensitive regex matching the license expressions which contain the given
+/// SPDX identifier, e.g. `MIT` matches `MIT OR Apache-2.0` and `MIT/Apache-2.0`, but not
+/// `MIT-0`.
+fn license_regex(license: &str) -> AppResult<String> {
+    let valid = !license.is_empty()
+        && license
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '+');
+    if !valid {
+        return Err(bad_request(
+            "`license` must be a license identifier like `MIT`",
+        ));
+    }
+
+    let escaped = license.replace('.', "\\.").replace('+', "\\+");
+    Ok(format!("(^|[\\s(/]){}($|[\\s)/])", escaped))
+}
+
+#[derive(Serialize)]
+struct Facets {
+    categories: Vec<FacetCount>,
+    keywords: Vec<FacetCount>,
+}
+
+#[derive(Serialize)]
+struct FacetCount {
+    /// The slug of the category or the keyword.
+    id: String,
+    count: i64,
+}
+
+impl From<(String, i64)> for FacetCount {
+    fn from((id, count): (String, i64)) -> Self {
+        Self { id, count }
+    }
+}
+
+/// SQL filter matching the crates whose description or keywords match the query in a full-text
+/// search, or whose name contains the query.
+fn matches_query<QS>(q_string: &str) -> Box<dyn BoxableExpression<QS, Pg, SqlType = Bool> + '_>
+where
+    crates::textsearchable_index_col: SelectableExpression<QS>,
+    crates::name: SelectableExpression<QS>,
+{
+    use diesel::sql_types::Text;
+
+    let q = sql::<TsQuery>("plainto_tsquery('english', ")
+        .bind::<Text, _>(q_string)
+        .sql(")");
+    Box::new(
+        q.matches(crates::textsearchable_index_col)
+            .or(Crate::loosly_matches_name(q_string)),
+    )
+}
+
+/// The relevance score of a crate for a search query, blending its text rank with the
+/// popularity and recency signals of the `crate_rankings` materialized view. The view is
+/// refreshed by the `refresh_crate_rankings` background job, and crates published since then
+/// are only ranked by text.
+fn relevance_score<'a, QS>(
+    q_string: &'a str,
+    weights: &SearchRankingWeights,
+) -> Box<dyn BoxableExpression<QS, Pg, SqlType = Double> + 'a> {
+    use diesel::sql_types::Text;
+
+    // The weights come from the config, so they can be formatted into the query
+    Box::new(
+        sql::<Double>(&format!(
+            "{}::float8 * ts_rank_cd(crates.textsearchable_index_col, plainto_tsquery('english', ",
+            weights.text
+        ))
+        .bind::<Text, _>(q_string)
+        .sql(&format!(
+            ")) + COALESCE((\
+             SELECT {}::float8 * crate_rankings.downloads_score \
+             + {}::float8 * crate_rankings.dependents_score \
+             + {}::float8 * crate_rankings.recency_score \
+             FROM crate_rankings WHERE crate_rankings.crate_id = crates.id\
+             ), 0)",
+            weights.downloads, weights.dependents, weights.recency
+        )),
+    )
+}
+
+/// The cursor of seek-based pagination, holding the sort key and ID of the last crate of the
+/// previous page.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "sort", rename_all = "kebab-case")]
+enum Seek {
+    Alpha { id: i32, name: String },
+    Downloads { id: i32, downloads: i32 },
+    RecentDownloads { id: i32, downloads: Option<i64> },
+    RecentUpdates { id: i32, updated_at: NaiveDateTime },
+    New { id: i32, created_at: NaiveDateTime },
+}
+
+impl Seek {
+    fn new(sort: Option<&str>, krate: &Crate, recent_downloads: Option<i64>) -> Self {
+        let id = krate.id;
+        match sort {
+            Some("downloads") => Seek::Downloads {
+                id,
+                downloads: krate.downloads,
+            },
+            Some("recent-downloads") => Seek::RecentDownloads {
+                id,
+                downloads: recent_downloads,
+            },
+            Some("recent-updates") => Seek::RecentUpdates {
+                id,
+                updated_at: krate.updated_at,
+            },
+            Some("new") => Seek::New {
+                id,
+                created_at: krate.created_at,
+            },
+            _ => Seek::Alpha {
+                id,
+                name: krate.name.clone(),
+            },
+        }
+    }
+
+    /// Crates are sorted alphabetically unless another known sort is requested.
+    fn is_alpha(sort: Option<&str>) -> bool {
+        !matches!(
+            sort,
+            Some("downloads") | Some("recent-downloads") | Some("recent-updates") | Some("new")
+        )
+    }
+}
+
 diesel_infix_operator!(Contains, "@>");
@@ -0,0 +1,49 @@
+//! Endpoint for autocompleting crate searches
+//!
+//! This is kept separate from `krate::search`, which loads the versions and badges of every crate
+//! it returns, to keep the responses small and fast while the user is typing.
+
+use conduit::header::HeaderValue;
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::Crate;
+
+/// Suggestions change slowly, so they can be cached by browsers and the CDN.
+const CACHE_CONTROL_SUGGESTIONS: &str = "public,max-age=600";
+
+/// Handles the `GET /crates/suggest` route.
+pub fn suggest(req: &mut dyn RequestExt) -> EndpointResult {
+    const DEFAULT_LIMIT: i64 = 10;
+    const MAX_LIMIT: i64 = 20;
+
+    let params = req.query();
+    let query = params.get("q").map(|q| q.trim()).unwrap_or_default();
+    let limit = params
+        .get("limit")
+        .map(|s| s.parse().map_err(|e| bad_request(&e)))
+        .unwrap_or(Ok(DEFAULT_LIMIT))?;
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(bad_request(&format_args!(
+            "the limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+
+    let crates = if query.is_empty() {
+        Vec::new()
+    } else {
+        let conn = req.db_read_only()?;
+        Crate::suggestions(&conn, query, limit)?
+    };
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<String>,
+    }
+    let mut response = req.json(&R { crates });
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(CACHE_CONTROL_SUGGESTIONS),
+    );
+    Ok(response)
+}
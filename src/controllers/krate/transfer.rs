@@ -0,0 +1,126 @@
+//! All routes related to transferring the ownership of a crate
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Crate, CrateOwnershipTransfer, Owner, Rights, User};
+use crate::App;
+
+/// Handles the `PUT /crates/:crate_id/transfer` route.
+pub fn request_transfer(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = req.authenticate()?;
+    let login = parse_transfer_request(req)?;
+    let app = req.app();
+    let crate_name = &req.params()["crate_id"];
+
+    let conn = req.db_conn()?;
+    let api_token_id = authenticated_user.api_token_id();
+    let user = authenticated_user.user();
+
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+    ensure_full_rights(app, &user, &krate, &conn)?;
+
+    let recipient = Owner::find_or_create_by_login(app, &conn, &user, &login)?;
+    if let Owner::User(ref recipient_user) = recipient {
+        if recipient_user.id == user.id {
+            return Err(bad_request("cannot transfer a crate to yourself"));
+        }
+    }
+
+    let token = CrateOwnershipTransfer::create(krate.id, &user, api_token_id, &recipient, &conn)?;
+
+    // Teams don't have an email address, so the token is sent to the requesting user instead,
+    // who passes it on to the member of the team that is going to accept the transfer.
+    let email_recipient = match recipient {
+        Owner::User(ref recipient_user) => recipient_user,
+        Owner::Team(_) => &user,
+    };
+    if let Ok(Some(email)) = email_recipient.verified_email(&conn) {
+        // Swallow any error. Whether or not the email is sent, the transfer request exists in
+        // the database and can be accepted later on.
+        let _ = app.emails.send_ownership_transfer(
+            &conn,
+            &email,
+            &user.gh_login,
+            &krate.name,
+            recipient.login(),
+            &token,
+        );
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        msg: String,
+    }
+    Ok(req.json(&R {
+        ok: true,
+        msg: format!(
+            "the transfer of crate {} to {} has been requested",
+            krate.name,
+            recipient.login()
+        ),
+    }))
+}
+
+/// Handles the `DELETE /crates/:crate_id/transfer` route.
+pub fn cancel_transfer(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = req.authenticate()?;
+    let app = req.app();
+    let crate_name = &req.params()["crate_id"];
+
+    let conn = req.db_conn()?;
+    let api_token_id = authenticated_user.api_token_id();
+    let user = authenticated_user.user();
+
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+    ensure_full_rights(app, &user, &krate, &conn)?;
+
+    let transfer = CrateOwnershipTransfer::find_by_crate_id(krate.id, &conn)?;
+    transfer.cancel(&conn, user.id, api_token_id)?;
+
+    ok_true()
+}
+
+/// Handles the `PUT /me/crate_ownership_transfers/accept/:token` route.
+pub fn accept_transfer(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = req.authenticate()?;
+    let app = req.app();
+    let req_token = &req.params()["token"];
+
+    let conn = req.db_conn()?;
+    let api_token_id = authenticated_user.api_token_id();
+    let user = authenticated_user.user();
+
+    let transfer = CrateOwnershipTransfer::find_by_token(req_token, &conn)?;
+    transfer.accept(app, &conn, &user, api_token_id)?;
+
+    ok_true()
+}
+
+/// Parse the JSON request body of requests to transfer a crate. The format is
+///
+///     {"owner": "username"} or {"owner": "github:org:team"}
+fn parse_transfer_request(req: &mut dyn RequestExt) -> AppResult<String> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    #[derive(Deserialize)]
+    struct Request {
+        owner: String,
+    }
+    let request: Request =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    Ok(request.owner)
+}
+
+fn ensure_full_rights(app: &App, user: &User, krate: &Crate, conn: &PgConnection) -> AppResult<()> {
+    let owners = krate.owners_with_rights(conn)?;
+    match user.rights(app, conn, &owners)? {
+        Rights::FULL => Ok(()),
+        Rights::NONE => Err(bad_request(
+            "only owners have permission to transfer crates",
+        )),
+        _ => Err(bad_request(
+            "team members don't have permission to transfer crates",
+        )),
+    }
+}
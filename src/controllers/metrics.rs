@@ -24,7 +24,9 @@ pub fn prometheus(req: &mut dyn RequestExt) -> EndpointResult {
     }
 
     let metrics = match req.params()["kind"].as_str() {
-        "service" => app.service_metrics.gather(&*req.db_read_only()?)?,
+        "service" => app
+            .service_metrics
+            .gather(&*req.db_read_only()?, &app.config)?,
         "instance" => app.instance_metrics.gather(app)?,
         _ => return Err(not_found()),
     };
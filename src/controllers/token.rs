@@ -1,6 +1,6 @@
 use super::frontend_prelude::*;
 
-use crate::models::ApiToken;
+use crate::models::{ApiToken, FeedToken};
 use crate::schema::api_tokens;
 use crate::util::read_fill;
 use crate::views::EncodableApiTokenWithToken;
@@ -108,3 +108,30 @@ pub fn revoke(req: &mut dyn RequestExt) -> EndpointResult {
     struct R {}
     Ok(req.json(&R {}))
 }
+
+/// Handles the `PUT /me/feed_token` route.
+///
+/// Creates the token authenticating the feed of the crates followed by the user, replacing the
+/// previous one. The token doesn't give access to the rest of the API.
+pub fn regenerate_feed_token(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = req.authenticate()?.forbid_api_token_auth()?;
+    let conn = req.db_conn()?;
+    let feed_token = FeedToken::regenerate(&conn, authenticated_user.user_id())?;
+
+    #[derive(Serialize)]
+    struct R {
+        feed_token: String,
+    }
+    Ok(req.json(&R { feed_token }))
+}
+
+/// Handles the `DELETE /me/feed_token` route.
+pub fn revoke_feed_token(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = req.authenticate()?.forbid_api_token_auth()?;
+    let conn = req.db_conn()?;
+    FeedToken::revoke(&conn, authenticated_user.user_id())?;
+
+    #[derive(Serialize)]
+    struct R {}
+    Ok(req.json(&R {}))
+}
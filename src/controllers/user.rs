@@ -1,3 +1,4 @@
 pub mod me;
+pub mod notifications;
 pub mod other;
 pub mod session;
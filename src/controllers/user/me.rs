@@ -1,12 +1,13 @@
+use chrono::NaiveDateTime;
 use std::collections::HashMap;
 
 use crate::controllers::frontend_prelude::*;
 
 use crate::controllers::helpers::*;
 
-use crate::controllers::helpers::pagination::Paginated;
+use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
 use crate::models::{
-    CrateOwner, Email, Follow, NewEmail, OwnerKind, User, Version, VersionOwnerAction,
+    Advisory, CrateOwner, Email, Follow, NewEmail, OwnerKind, User, Version, VersionOwnerAction,
 };
 use crate::schema::{crate_owners, crates, emails, follows, users, versions};
 use crate::views::{EncodableMe, EncodablePrivateUser, EncodableVersion, OwnedCrate};
@@ -57,32 +58,49 @@ pub fn updates(req: &mut dyn RequestExt) -> EndpointResult {
     let authenticated_user = req.authenticate()?.forbid_api_token_auth()?;
     let user = authenticated_user.user();
 
+    let pagination = PaginationOptions::with_seek(req)?;
     let followed_crates = Follow::belonging_to(&user).select(follows::crate_id);
-    let query = versions::table
+    let mut query = versions::table
         .inner_join(crates::table)
         .left_outer_join(users::table)
         .filter(crates::id.eq(any(followed_crates)))
-        .order(versions::created_at.desc())
+        .order((versions::created_at.desc(), versions::id.desc()))
         .select((
             versions::all_columns,
             crates::name,
             users::all_columns.nullable(),
         ))
-        .paginate(req)?;
+        .into_boxed();
+    if let Some(seek) = pagination.seek() {
+        if let Some((created_at, id)) = seek.decode::<(NaiveDateTime, i32)>()? {
+            query = query.filter(
+                versions::created_at
+                    .lt(created_at)
+                    .or(versions::created_at.eq(created_at).and(versions::id.lt(id))),
+            );
+        }
+    }
+
     let conn = req.db_conn()?;
-    let data: Paginated<(Version, String, Option<User>)> = query.load(&*conn)?;
-    let more = data.next_page_params().is_some();
+    let data: Paginated<(Version, String, Option<User>)> =
+        query.paginate_with(pagination).load(&*conn)?;
+    let next_page = data
+        .next_seek_params(|(version, _, _)| (version.created_at, version.id))?
+        .or_else(|| data.next_page_params())
+        .map(|p| req.query_with_params(p));
+    let more = next_page.is_some();
     let versions = data.iter().map(|(v, _, _)| v).cloned().collect::<Vec<_>>();
     let data = data
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, cn, pb), voas)| (v, cn, pb, voas))
+        .zip(Advisory::for_versions(&conn, &versions)?.into_iter())
+        .map(|(((v, cn, pb), voas), advisories)| (v, cn, pb, voas, advisories))
         .collect::<Vec<_>>();
 
     let versions = data
         .into_iter()
-        .map(|(version, crate_name, published_by, actions)| {
-            EncodableVersion::from(version, &crate_name, published_by, actions)
+        .map(|(version, crate_name, published_by, actions, advisories)| {
+            EncodableVersion::from(version, &crate_name, published_by, actions, advisories)
         })
         .collect();
 
@@ -94,10 +112,11 @@ pub fn updates(req: &mut dyn RequestExt) -> EndpointResult {
     #[derive(Serialize)]
     struct Meta {
         more: bool,
+        next_page: Option<String>,
     }
     Ok(req.json(&R {
         versions,
-        meta: Meta { more },
+        meta: Meta { more, next_page },
     }))
 }
 
@@ -164,7 +183,7 @@ pub fn update_user(req: &mut dyn RequestExt) -> EndpointResult {
         let _ = req
             .app()
             .emails
-            .send_user_confirm(user_email, &user.gh_login, &token);
+            .send_user_confirm(&conn, user_email, &user.gh_login, &token);
 
         Ok(())
     })?;
@@ -215,7 +234,7 @@ pub fn regenerate_token_and_send(req: &mut dyn RequestExt) -> EndpointResult {
 
         req.app()
             .emails
-            .send_user_confirm(&email.email, &user.gh_login, &email.token)
+            .send_user_confirm(&conn, &email.email, &user.gh_login, &email.token)
     })?;
 
     ok_true()
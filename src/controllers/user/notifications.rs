@@ -0,0 +1,189 @@
+//! Endpoints managing which categories of notification emails users receive.
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{
+    Crate, CrateNotificationPreferencesChanges, NotificationCategory, NotificationPreferences,
+    NotificationPreferencesChanges, OwnerKind,
+};
+use crate::schema::{crate_owners, crates, notification_preferences};
+use crate::util::errors::not_found;
+use crate::views::{EncodableCrateNotificationPreferences, EncodableNotificationPreferences};
+use conduit::{Body, Response};
+use htmlescape::encode_minimal;
+
+#[derive(Deserialize)]
+struct ChangesRequest<T> {
+    notification_preferences: T,
+}
+
+fn parse_changes<T: serde::de::DeserializeOwned>(req: &mut dyn RequestExt) -> AppResult<T> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: ChangesRequest<T> =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    Ok(request.notification_preferences)
+}
+
+/// Handles the `GET /me/notification_preferences` route.
+pub fn preferences(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_read_only()?;
+
+    let preferences = notification_preferences::table
+        .find(user_id)
+        .first::<NotificationPreferences>(&*conn)
+        .optional()?
+        .map(EncodableNotificationPreferences::from)
+        .unwrap_or_default();
+
+    let crates = crate_owners::table
+        .inner_join(crates::table)
+        .filter(crate_owners::owner_id.eq(user_id))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+        .filter(crate_owners::deleted.eq(false))
+        .select((
+            crates::id,
+            crates::name,
+            crate_owners::email_notifications,
+            crate_owners::publish_notifications,
+            crate_owners::yank_notifications,
+            crate_owners::ownership_notifications,
+            crate_owners::advisory_notifications,
+        ))
+        .order(crates::name)
+        .load(&*conn)?
+        .into_iter()
+        .map(
+            |(id, name, email_notifications, publish, yank, ownership, advisories)| {
+                EncodableCrateNotificationPreferences {
+                    id,
+                    name,
+                    email_notifications,
+                    publish,
+                    yank,
+                    ownership,
+                    advisories,
+                }
+            },
+        )
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        notification_preferences: EncodableNotificationPreferences,
+        crates: Vec<EncodableCrateNotificationPreferences>,
+    }
+    Ok(req.json(&R {
+        notification_preferences: preferences,
+        crates,
+    }))
+}
+
+/// Handles the `PUT /me/notification_preferences` route.
+pub fn update_preferences(req: &mut dyn RequestExt) -> EndpointResult {
+    let changes: NotificationPreferencesChanges = parse_changes(req)?;
+    if changes.is_empty() {
+        return Err(bad_request("no notification preferences to change"));
+    }
+
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let preferences = NotificationPreferences::update(&conn, user_id, &changes)?;
+
+    #[derive(Serialize)]
+    struct R {
+        notification_preferences: EncodableNotificationPreferences,
+    }
+    Ok(req.json(&R {
+        notification_preferences: preferences.into(),
+    }))
+}
+
+/// Handles the `PUT /crates/:crate_id/notification_preferences` route.
+pub fn update_crate_preferences(req: &mut dyn RequestExt) -> EndpointResult {
+    let changes: CrateNotificationPreferencesChanges = parse_changes(req)?;
+    if changes.is_empty() {
+        return Err(bad_request("no notification preferences to change"));
+    }
+
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(&req.params()["crate_id"]).first(&*conn)?;
+
+    let updated = diesel::update(
+        crate_owners::table
+            .filter(crate_owners::crate_id.eq(krate.id))
+            .filter(crate_owners::owner_id.eq(user_id))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+            .filter(crate_owners::deleted.eq(false)),
+    )
+    .set(&changes)
+    .execute(&*conn)?;
+    if updated == 0 {
+        return Err(cargo_err(
+            "only owners can change their notification preferences for a crate",
+        ));
+    }
+
+    ok_true()
+}
+
+/// Handles the `GET /unsubscribe/:token/:category` route.
+///
+/// Shows a page asking users who follow the unsubscribe link of an email to confirm, as links
+/// can also be opened by link scanners and previews. The confirmation `POST`s to `unsubscribe`.
+pub fn confirm_unsubscribe(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::exists;
+
+    let category: NotificationCategory = req.params()["category"]
+        .parse()
+        .map_err(|e: String| bad_request(&e))?;
+    let conn = req.db_read_only()?;
+
+    let token = &req.params()["token"];
+    let token_exists: bool = diesel::select(exists(
+        notification_preferences::table
+            .filter(notification_preferences::unsubscribe_token.eq(token)),
+    ))
+    .get_result(&*conn)?;
+    if !token_exists {
+        return Err(not_found());
+    }
+
+    let body = format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head><meta charset=\"utf-8\"><title>Unsubscribe</title></head>\n\
+         <body>\n\
+         <p>Do you want to stop receiving the <code>{}</code> notification emails of {}?</p>\n\
+         <form method=\"post\"><button type=\"submit\">Unsubscribe</button></form>\n\
+         </body>\n\
+         </html>\n",
+        category.as_str(),
+        encode_minimal(&req.app().config.domain_name),
+    );
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .header(header::CONTENT_LENGTH, body.len())
+        // The URL contains the token, which mustn't leak to other sites
+        .header(header::REFERRER_POLICY, "no-referrer")
+        .body(Body::from_vec(body.into_bytes()))?)
+}
+
+/// Handles the `POST /unsubscribe/:token/:category` route.
+///
+/// This is the one-click unsubscribe URL of the `List-Unsubscribe` header of notification
+/// emails, which email clients request without the user being logged in.
+pub fn unsubscribe(req: &mut dyn RequestExt) -> EndpointResult {
+    let category: NotificationCategory = req.params()["category"]
+        .parse()
+        .map_err(|e: String| bad_request(&e))?;
+    let conn = req.db_conn()?;
+
+    if !NotificationPreferences::unsubscribe(&conn, &req.params()["token"], category)? {
+        return Err(not_found());
+    }
+
+    ok_true()
+}
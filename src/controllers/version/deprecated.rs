@@ -7,7 +7,7 @@
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{Crate, User, Version, VersionOwnerAction};
+use crate::models::{Advisory, Crate, User, Version, VersionOwnerAction};
 use crate::schema::*;
 use crate::views::EncodableVersion;
 
@@ -40,8 +40,9 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
     let versions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((version, crate_name, published_by), actions)| {
-            EncodableVersion::from(version, &crate_name, published_by, actions)
+        .zip(Advisory::for_versions(&conn, &versions)?.into_iter())
+        .map(|(((v, crate_name, pb), aas), advisories)| {
+            EncodableVersion::from(v, &crate_name, pb, aas, advisories)
         })
         .collect();
 
@@ -70,12 +71,19 @@ pub fn show_by_id(req: &mut dyn RequestExt) -> EndpointResult {
         ))
         .first(&*conn)?;
     let audit_actions = VersionOwnerAction::by_version(&conn, &version)?;
+    let advisories = Advisory::for_version(&conn, &version)?;
 
     #[derive(Serialize)]
     struct R {
         version: EncodableVersion,
     }
     Ok(req.json(&R {
-        version: EncodableVersion::from(version, &krate.name, published_by, audit_actions),
+        version: EncodableVersion::from(
+            version,
+            &krate.name,
+            published_by,
+            audit_actions,
+            advisories,
+        ),
     }))
 }
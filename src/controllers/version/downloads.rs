@@ -3,12 +3,14 @@
 //! Crate level functionality is located in `krate::downloads`.
 
 use super::{extract_crate_name_and_semver, version_and_crate};
+use crate::controllers::helpers::DownloadsRange;
 use crate::controllers::prelude::*;
 use crate::db::PoolError;
-use crate::models::{Crate, VersionDownload};
+use crate::models::{ClientClass, Crate, Granularity, VersionDownload, VersionDownloadRollup};
 use crate::schema::*;
+use crate::util::request_header;
 use crate::views::EncodableVersionDownload;
-use chrono::{Duration, NaiveDate, Utc};
+use chrono::NaiveDate;
 
 /// Handles the `GET /crates/:crate_id/:version/download` route.
 /// This returns a URL to the location where the crate is stored.
@@ -18,6 +20,9 @@ pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
 
     let mut crate_name = req.params()["crate_id"].clone();
     let version = req.params()["version"].as_str();
+    let user_agent = request_header(req, header::USER_AGENT);
+    let client = ClientClass::from_user_agent(user_agent);
+    let ip = client_ip(req);
 
     let mut log_metadata = None;
     match recorder.record("get_conn", || req.db_conn()) {
@@ -43,9 +48,22 @@ pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
             }
             crate_name = canonical_crate_name;
 
-            // The increment does not happen instantly, but it's deferred to be executed in a batch
-            // along with other downloads. See crate::downloads_counter for the implementation.
-            app.downloads_counter.increment(version_id);
+            match app.downloads_filter.check(&ip, version_id, user_agent) {
+                None => {
+                    // The increment does not happen instantly, but it's deferred to be executed in
+                    // a batch along with other downloads. See crate::downloads_counter for the
+                    // implementation.
+                    app.downloads_counter.increment(version_id);
+                    app.downloads_counter.increment_client(version_id, client);
+                }
+                Some(reason) => {
+                    // The crate is still served, but the download is not counted.
+                    app.instance_metrics
+                        .downloads_discarded_total
+                        .with_label_values(&[reason.as_str()])
+                        .inc();
+                }
+            }
         }
         Err(PoolError::UnhealthyPool) => {
             // The download endpoint is the most critical route in the whole crates.io application,
@@ -61,13 +79,15 @@ pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
             // checking whether the crate exists or the rigth name is used. Non-Cargo clients might
             // get a 404 response instead of a 500, but that's worth it.
             //
-            // Without a working database we also can't count downloads, but that's also less
-            // critical than keeping Cargo downloads operational.
+            // Without a working database we also can't count downloads right away. They're
+            // written to the spill log instead, and counted once the database is back.
 
             app.instance_metrics
                 .downloads_unconditional_redirects_total
                 .inc();
             log_metadata = Some(("unconditional_redirect", "true"));
+
+            app.downloads_counter.spill_unresolved(&crate_name, version);
         }
         Err(err) => return Err(err.into()),
     }
@@ -93,6 +113,14 @@ pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
     }
 }
 
+/// Returns the IP address of the client, as reported by the proxy in front of the application.
+fn client_ip(req: &dyn RequestExt) -> String {
+    match request_header(req, "x-real-ip") {
+        "" => req.remote_addr().ip().to_string(),
+        ip => ip.to_string(),
+    }
+}
+
 /// Handles the `GET /crates/:crate_id/:version/downloads` route.
 pub fn downloads(req: &mut dyn RequestExt) -> EndpointResult {
     let (crate_name, semver) = extract_crate_name_and_semver(req)?;
@@ -100,20 +128,44 @@ pub fn downloads(req: &mut dyn RequestExt) -> EndpointResult {
     let conn = req.db_read_only()?;
     let (version, _) = version_and_crate(&conn, crate_name, semver)?;
 
-    let cutoff_end_date = req
+    // `before_date` is the legacy name of the `until` parameter
+    let before_date = req
         .query()
         .get("before_date")
-        .and_then(|d| NaiveDate::parse_from_str(d, "%F").ok())
-        .unwrap_or_else(|| Utc::today().naive_utc());
-    let cutoff_start_date = cutoff_end_date - Duration::days(89);
-
-    let downloads = VersionDownload::belonging_to(&version)
-        .filter(version_downloads::date.between(cutoff_start_date, cutoff_end_date))
-        .order(version_downloads::date)
-        .load(&*conn)?
-        .into_iter()
-        .map(VersionDownload::into)
-        .collect();
+        .and_then(|d| NaiveDate::parse_from_str(d, "%F").ok());
+    let range = DownloadsRange::from_request(req, before_date)?;
+
+    let downloads = match range.granularity {
+        Granularity::Day => {
+            let mut query = VersionDownload::belonging_to(&version)
+                .filter(version_downloads::date.le(range.until))
+                .order(version_downloads::date)
+                .into_boxed();
+            if let Some(since) = range.since {
+                query = query.filter(version_downloads::date.ge(since));
+            }
+            query
+                .load(&*conn)?
+                .into_iter()
+                .map(VersionDownload::into)
+                .collect()
+        }
+        granularity => {
+            let mut query = VersionDownloadRollup::belonging_to(&version)
+                .filter(version_downloads_rollups::granularity.eq(granularity.as_str()))
+                .filter(version_downloads_rollups::period.le(range.until))
+                .order(version_downloads_rollups::period)
+                .into_boxed();
+            if let Some(since) = range.since {
+                query = query.filter(version_downloads_rollups::period.ge(since));
+            }
+            query
+                .load(&*conn)?
+                .into_iter()
+                .map(VersionDownloadRollup::into)
+                .collect()
+        }
+    };
 
     #[derive(Serialize)]
     struct R {
@@ -6,8 +6,11 @@
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::VersionOwnerAction;
-use crate::views::{EncodableDependency, EncodablePublicUser, EncodableVersion};
+use crate::dependency_tree::{self, ResolveOptions};
+use crate::models::{Advisory, VersionOwnerAction};
+use crate::views::{
+    EncodableDependency, EncodableDependencyTreeNode, EncodablePublicUser, EncodableVersion,
+};
 
 use super::{extract_crate_name_and_semver, version_and_crate};
 
@@ -35,6 +38,82 @@ pub fn dependencies(req: &mut dyn RequestExt) -> EndpointResult {
     Ok(req.json(&R { dependencies: deps }))
 }
 
+/// The depth of the dependency tree if the `depth` parameter is missing.
+const DEFAULT_TREE_DEPTH: usize = 5;
+/// The maximum value of the `depth` parameter.
+const MAX_TREE_DEPTH: usize = 10;
+/// The maximum number of versions in a dependency tree.
+const MAX_TREE_NODES: usize = 300;
+
+/// Handles the `GET /crates/:crate_id/:version/dependency_tree` route.
+///
+/// Resolves the transitive dependencies of the version to the highest non-yanked versions
+/// matching their requirements. The `features` (comma separated), `default_features` (`yes` or
+/// `no`) and `target` parameters work like the equivalent cargo options.
+///
+/// The first node is the requested version, and the `node` of each dependency is the `id` of
+/// the node it resolved to, or `null` if no version matches.
+pub fn dependency_tree(req: &mut dyn RequestExt) -> EndpointResult {
+    let (crate_name, semver) = extract_crate_name_and_semver(req)?;
+    let params = req.query();
+
+    let max_depth = match params.get("depth") {
+        Some(depth) => match depth.parse::<usize>() {
+            Ok(depth) if depth <= MAX_TREE_DEPTH => depth,
+            _ => {
+                return Err(bad_request(&format_args!(
+                    "`depth` must be a number between 0 and {}",
+                    MAX_TREE_DEPTH
+                )))
+            }
+        },
+        None => DEFAULT_TREE_DEPTH,
+    };
+    let features = params
+        .get("features")
+        .map(|features| {
+            features
+                .split(',')
+                .map(str::trim)
+                .filter(|feature| !feature.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    let default_features = match params.get("default_features").map(|s| &**s) {
+        Some("yes") | None => true,
+        Some("no") => false,
+        Some(_) => return Err(bad_request("`default_features` must be `yes` or `no`")),
+    };
+    let options = ResolveOptions {
+        max_depth,
+        max_nodes: MAX_TREE_NODES,
+        features,
+        default_features,
+        target: params.get("target").cloned(),
+    };
+
+    let conn = req.db_read_only()?;
+    let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
+    let tree = dependency_tree::resolve(&conn, &version, &krate.name, &options)?;
+
+    #[derive(Serialize)]
+    struct R {
+        nodes: Vec<EncodableDependencyTreeNode>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        truncated: bool,
+    }
+    Ok(req.json(&R {
+        nodes: tree.nodes,
+        meta: Meta {
+            truncated: tree.truncated,
+        },
+    }))
+}
+
 /// Handles the `GET /crates/:crate_id/:version/authors` route.
 pub fn authors(req: &mut dyn RequestExt) -> EndpointResult {
     // Currently we return the empty list.
@@ -64,12 +143,13 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
     let published_by = version.published_by(&conn);
     let actions = VersionOwnerAction::by_version(&conn, &version)?;
+    let advisories = Advisory::for_version(&conn, &version)?;
 
     #[derive(Serialize)]
     struct R {
         version: EncodableVersion,
     }
     Ok(req.json(&R {
-        version: EncodableVersion::from(version, &krate.name, published_by, actions),
+        version: EncodableVersion::from(version, &krate.name, published_by, actions, advisories),
     }))
 }
@@ -37,9 +37,10 @@ fn modify_yank(req: &mut dyn RequestExt, yanked: bool) -> EndpointResult {
     let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
     let api_token_id = authenticated_user.api_token_id();
     let user = authenticated_user.user();
-    let owners = krate.owners(&conn)?;
+    let owners = krate.owners_with_rights(&conn)?;
 
-    if user.rights(req.app(), &owners)? < Rights::Publish {
+    let rights = user.rights(req.app(), &conn, &owners)?;
+    if !rights.contains(Rights::YANK) {
         return Err(cargo_err("must already be an owner to yank or unyank"));
     }
     let action = if yanked {
@@ -0,0 +1,253 @@
+//! Endpoints managing the webhooks of a crate, and the webhooks of a user for all their crates.
+//!
+//! The events delivered to the webhooks are triggered when version and crate owner actions are
+//! recorded, see `Webhook::trigger_version_event` and `Webhook::trigger_owner_event`.
+
+use super::frontend_prelude::*;
+
+use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
+use crate::controllers::helpers::Paginate;
+use crate::models::{
+    resolve_webhook_url, Crate, NewWebhook, Rights, Webhook, WebhookDelivery, WEBHOOK_EVENTS,
+};
+use crate::schema::{webhook_deliveries, webhooks};
+use crate::util::read_fill;
+use crate::views::{EncodableWebhook, EncodableWebhookDelivery, EncodableWebhookWithSecret};
+
+/// The maximum number of webhooks of a crate or a user.
+const MAX_WEBHOOKS: i64 = 10;
+
+/// Handles the `GET /crates/:crate_id/webhooks` route.
+pub fn list_crate_webhooks(req: &mut dyn RequestExt) -> EndpointResult {
+    list(req, true)
+}
+
+/// Handles the `GET /me/webhooks` route.
+pub fn list_user_webhooks(req: &mut dyn RequestExt) -> EndpointResult {
+    list(req, false)
+}
+
+/// Handles the `PUT /crates/:crate_id/webhooks` route.
+pub fn create_crate_webhook(req: &mut dyn RequestExt) -> EndpointResult {
+    create(req, true)
+}
+
+/// Handles the `PUT /me/webhooks` route.
+pub fn create_user_webhook(req: &mut dyn RequestExt) -> EndpointResult {
+    create(req, false)
+}
+
+/// Handles the `DELETE /crates/:crate_id/webhooks/:id` route.
+pub fn delete_crate_webhook(req: &mut dyn RequestExt) -> EndpointResult {
+    delete(req, true)
+}
+
+/// Handles the `DELETE /me/webhooks/:id` route.
+pub fn delete_user_webhook(req: &mut dyn RequestExt) -> EndpointResult {
+    delete(req, false)
+}
+
+/// Handles the `GET /crates/:crate_id/webhooks/:id/deliveries` route.
+pub fn crate_webhook_deliveries(req: &mut dyn RequestExt) -> EndpointResult {
+    deliveries(req, true)
+}
+
+/// Handles the `GET /me/webhooks/:id/deliveries` route.
+pub fn user_webhook_deliveries(req: &mut dyn RequestExt) -> EndpointResult {
+    deliveries(req, false)
+}
+
+/// The owner of the webhooks managed by a request: either the crate of the `crate_id`
+/// parameter, which requires full rights on it, or the authenticated user.
+struct Scope {
+    user_id: i32,
+    krate: Option<Crate>,
+}
+
+impl Scope {
+    fn new(req: &mut dyn RequestExt, for_crate: bool) -> AppResult<Self> {
+        let authenticated_user = req.authenticate()?;
+        let user = authenticated_user.user();
+        if !for_crate {
+            return Ok(Self {
+                user_id: user.id,
+                krate: None,
+            });
+        }
+
+        let conn = req.db_read_only()?;
+        let krate: Crate = Crate::by_name(&req.params()["crate_id"]).first(&*conn)?;
+        let owners = krate.owners_with_rights(&conn)?;
+        if user.rights(req.app(), &conn, &owners)? != Rights::FULL {
+            return Err(cargo_err(
+                "only owners have permission to manage the webhooks of a crate",
+            ));
+        }
+        Ok(Self {
+            user_id: user.id,
+            krate: Some(krate),
+        })
+    }
+
+    fn crate_id(&self) -> Option<i32> {
+        self.krate.as_ref().map(|krate| krate.id)
+    }
+
+    fn crate_name(&self) -> Option<&str> {
+        self.krate.as_ref().map(|krate| krate.name.as_str())
+    }
+
+    fn webhooks(&self) -> webhooks::BoxedQuery<'static, diesel::pg::Pg> {
+        match self.crate_id() {
+            Some(crate_id) => webhooks::table
+                .filter(webhooks::crate_id.eq(crate_id))
+                .into_boxed(),
+            None => webhooks::table
+                .filter(webhooks::user_id.eq(self.user_id))
+                .into_boxed(),
+        }
+    }
+
+    fn find(&self, req: &dyn RequestExt, conn: &PgConnection) -> AppResult<Webhook> {
+        let id = req.params()["id"]
+            .parse::<i32>()
+            .map_err(|e| bad_request(&format!("invalid webhook id: {:?}", e)))?;
+        Ok(self.webhooks().filter(webhooks::id.eq(id)).first(conn)?)
+    }
+}
+
+fn list(req: &mut dyn RequestExt, for_crate: bool) -> EndpointResult {
+    let scope = Scope::new(req, for_crate)?;
+    let conn = req.db_read_only()?;
+    let webhooks = scope
+        .webhooks()
+        .order(webhooks::id)
+        .load::<Webhook>(&*conn)?
+        .into_iter()
+        .map(|webhook| EncodableWebhook::from(webhook, scope.crate_name()))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        webhooks: Vec<EncodableWebhook>,
+    }
+    Ok(req.json(&R { webhooks }))
+}
+
+fn create(req: &mut dyn RequestExt, for_crate: bool) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct NewWebhookRequest {
+        webhook: NewWebhookFields,
+    }
+
+    #[derive(Deserialize)]
+    struct NewWebhookFields {
+        url: String,
+        events: Option<Vec<String>>,
+    }
+
+    let max_size = 2000;
+    let length = req
+        .content_length()
+        .chain_error(|| bad_request("missing header: Content-Length"))?;
+    if length > max_size {
+        return Err(bad_request(&format!("max content length is: {}", max_size)));
+    }
+    let mut body = vec![0; length as usize];
+    read_fill(req.body(), &mut body)?;
+    let request: NewWebhookRequest = serde_json::from_slice(&body)
+        .map_err(|e| bad_request(&format!("invalid new webhook request: {:?}", e)))?;
+    let NewWebhookFields { url, events } = request.webhook;
+
+    let allow_private_urls = req.app().config.webhooks_allow_private_urls;
+    match url::Url::parse(&url) {
+        Ok(url) if url.scheme() == "https" => {}
+        Ok(url) if url.scheme() == "http" && allow_private_urls => {}
+        _ => return Err(bad_request("the webhook URL must be an HTTPS URL")),
+    }
+    let events = events.unwrap_or_else(|| WEBHOOK_EVENTS.iter().map(|&e| e.into()).collect());
+    if events.is_empty() {
+        return Err(bad_request(
+            "the webhook must be subscribed to at least one event",
+        ));
+    }
+    if let Some(event) = events
+        .iter()
+        .find(|e| !WEBHOOK_EVENTS.contains(&e.as_str()))
+    {
+        return Err(bad_request(&format!(
+            "unknown event `{}`, expected one of: {}",
+            event,
+            WEBHOOK_EVENTS.join(", ")
+        )));
+    }
+
+    let scope = Scope::new(req, for_crate)?;
+    // The host is only resolved once the user is authorized, as it may need a DNS lookup. The
+    // deliveries check it again, in case the DNS records change.
+    resolve_webhook_url(&url, allow_private_urls).map_err(|message| bad_request(&message))?;
+
+    let conn = req.db_conn()?;
+    let count: i64 = scope.webhooks().count().get_result(&*conn)?;
+    if count >= MAX_WEBHOOKS {
+        return Err(bad_request(&format!(
+            "maximum number of webhooks reached: {}",
+            MAX_WEBHOOKS
+        )));
+    }
+
+    let webhook = NewWebhook::new(scope.crate_id(), scope.user_id, &url, &events).create(&conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        webhook: EncodableWebhookWithSecret,
+    }
+    Ok(req.json(&R {
+        webhook: EncodableWebhookWithSecret::from(webhook, scope.crate_name()),
+    }))
+}
+
+fn delete(req: &mut dyn RequestExt, for_crate: bool) -> EndpointResult {
+    let scope = Scope::new(req, for_crate)?;
+    let conn = req.db_conn()?;
+    let webhook = scope.find(req, &conn)?;
+    diesel::delete(&webhook).execute(&*conn)?;
+
+    ok_true()
+}
+
+/// Lists the deliveries of a webhook, most recent first, so that owners can check whether and
+/// why they failed.
+fn deliveries(req: &mut dyn RequestExt, for_crate: bool) -> EndpointResult {
+    let scope = Scope::new(req, for_crate)?;
+    let pagination = PaginationOptions::new(req)?;
+    let conn = req.db_read_only()?;
+    let webhook = scope.find(req, &conn)?;
+
+    let data: Paginated<WebhookDelivery> = WebhookDelivery::belonging_to(&webhook)
+        .order(webhook_deliveries::id.desc())
+        .paginate_with(pagination)
+        .load(&*conn)?;
+    let total = data.total();
+    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+    let deliveries = data
+        .into_iter()
+        .map(EncodableWebhookDelivery::from)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        deliveries: Vec<EncodableWebhookDelivery>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: Option<i64>,
+        next_page: Option<String>,
+    }
+
+    Ok(req.json(&R {
+        deliveries,
+        meta: Meta { total, next_page },
+    }))
+}
@@ -16,10 +16,7 @@ pub enum DieselPool {
 }
 
 impl DieselPool {
-    pub(crate) fn new(
-        url: &str,
-        config: r2d2::Builder<ConnectionManager<PgConnection>>,
-    ) -> DieselPool {
+    pub fn new(url: &str, config: r2d2::Builder<ConnectionManager<PgConnection>>) -> DieselPool {
         let manager = ConnectionManager::new(connection_url(url));
         DieselPool::Pool(config.build(manager).unwrap())
     }
@@ -0,0 +1,445 @@
+//! Resolution of the transitive dependencies of a crate version.
+//!
+//! This approximates what cargo would resolve without a lockfile: every dependency resolves to
+//! the highest non-yanked version matching its requirement. Unlike cargo, semver-compatible
+//! versions of a crate are not unified, so the tree only shows one possible resolution.
+//!
+//! Optional dependencies are only followed when they are enabled by a feature, and the
+//! features enabled through the tree are unified per version. Dev-dependencies are ignored, and
+//! target-specific dependencies are only left out when their target is known not to match.
+
+mod cfg;
+
+use std::collections::{BTreeSet, HashMap};
+
+use diesel::prelude::*;
+
+use self::cfg::{Cfg, Target};
+use crate::models::{Dependency, DependencyKind, Version};
+use crate::schema::{crates, dependencies, versions};
+use crate::views::{EncodableDependencyTreeEdge, EncodableDependencyTreeNode};
+
+/// The options of `resolve`.
+#[derive(Debug)]
+pub struct ResolveOptions {
+    /// Dependencies deeper than this are not resolved.
+    pub max_depth: usize,
+    /// Once the tree has this many nodes, new dependencies are not resolved anymore.
+    pub max_nodes: usize,
+    /// The features enabled on the root version.
+    pub features: Vec<String>,
+    /// Whether the `default` feature of the root version is enabled.
+    pub default_features: bool,
+    /// Leave out the dependencies which are known not to be used for this target triple.
+    pub target: Option<String>,
+}
+
+/// A resolved dependency tree. The first node is the root version.
+#[derive(Debug)]
+pub struct DependencyTree {
+    pub nodes: Vec<EncodableDependencyTreeNode>,
+    /// Whether some dependencies are missing because of the depth or node count limits.
+    pub truncated: bool,
+}
+
+/// A version which can be picked by the resolution.
+#[derive(Debug, Clone)]
+struct Candidate {
+    id: i32,
+    num: semver::Version,
+    features: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug)]
+struct Node {
+    crate_id: i32,
+    version: Candidate,
+    /// The features requested by the root and the dependents, unified.
+    requested_features: BTreeSet<String>,
+    /// The features of the version enabled by the requested features.
+    features: BTreeSet<String>,
+    depth: usize,
+    edges: Vec<EncodableDependencyTreeEdge>,
+    truncated: bool,
+}
+
+struct Resolver<'a> {
+    conn: &'a PgConnection,
+    options: &'a ResolveOptions,
+    target: Option<Target>,
+    crate_names: HashMap<i32, String>,
+    candidates: HashMap<i32, Vec<Candidate>>,
+    dependencies: HashMap<i32, Vec<Dependency>>,
+    nodes: Vec<Node>,
+    node_ids: HashMap<i32, usize>,
+    truncated: bool,
+}
+
+/// Resolves the dependency tree of a version.
+pub fn resolve(
+    conn: &PgConnection,
+    version: &Version,
+    crate_name: &str,
+    options: &ResolveOptions,
+) -> QueryResult<DependencyTree> {
+    let mut features = options.features.iter().cloned().collect::<BTreeSet<_>>();
+    if options.default_features {
+        features.insert("default".into());
+    }
+
+    let mut resolver = Resolver {
+        conn,
+        options,
+        target: options.target.as_deref().map(Target::from_triple),
+        crate_names: HashMap::new(),
+        candidates: HashMap::new(),
+        dependencies: HashMap::new(),
+        nodes: Vec::new(),
+        node_ids: HashMap::new(),
+        truncated: false,
+    };
+    resolver
+        .crate_names
+        .insert(version.crate_id, crate_name.into());
+    let root = Candidate {
+        id: version.id,
+        num: version.num.clone(),
+        features: parse_features(&version.features),
+    };
+    resolver.add_node(version.crate_id, root, features, 0);
+
+    let mut pending = vec![0];
+    while !pending.is_empty() {
+        pending = resolver.expand(&pending)?;
+    }
+
+    Ok(resolver.into_tree())
+}
+
+impl<'a> Resolver<'a> {
+    fn add_node(
+        &mut self,
+        crate_id: i32,
+        version: Candidate,
+        requested_features: BTreeSet<String>,
+        depth: usize,
+    ) -> usize {
+        let id = self.nodes.len();
+        self.node_ids.insert(version.id, id);
+        self.nodes.push(Node {
+            crate_id,
+            version,
+            requested_features,
+            features: BTreeSet::new(),
+            depth,
+            edges: Vec::new(),
+            truncated: false,
+        });
+        id
+    }
+
+    /// Resolves the dependencies of the given nodes, and returns the nodes that need to be
+    /// resolved next, because they are new or have new features enabled.
+    fn expand(&mut self, ids: &[usize]) -> QueryResult<Vec<usize>> {
+        self.load_dependencies(ids)?;
+
+        let mut pending = BTreeSet::new();
+        for &id in ids {
+            self.nodes[id].truncated = false;
+            let node = &self.nodes[id];
+            let dependencies = self.dependencies[&node.version.id]
+                .iter()
+                .filter(|dep| dep.kind != DependencyKind::Dev)
+                .filter(|dep| self.matches_target(dep.target.as_deref()))
+                .collect::<Vec<_>>();
+            let activated = activate_features(
+                &node.requested_features,
+                &node.version.features,
+                |dep_name| {
+                    dependencies
+                        .iter()
+                        .any(|dep| dep.optional && self.crate_names[&dep.crate_id] == dep_name)
+                },
+            );
+            let dependencies = dependencies
+                .into_iter()
+                .filter(|dep| {
+                    !dep.optional
+                        || activated
+                            .optional_dependencies
+                            .contains(&self.crate_names[&dep.crate_id])
+                })
+                .collect::<Vec<_>>();
+
+            if node.depth >= self.options.max_depth {
+                let truncated = !dependencies.is_empty();
+                self.nodes[id].features = activated.features;
+                self.nodes[id].truncated = truncated;
+                self.truncated |= truncated;
+                continue;
+            }
+
+            // Figure out what to resolve first, as resolving borrows the resolver mutably
+            let mut resolutions = Vec::new();
+            for dep in dependencies {
+                let name = self.crate_names[&dep.crate_id].clone();
+                let mut features = dep.features.iter().cloned().collect::<BTreeSet<_>>();
+                if let Some(enabled) = activated.dependency_features.get(&name) {
+                    features.extend(enabled.iter().cloned());
+                }
+                if dep.default_features {
+                    features.insert("default".into());
+                }
+                let edge = EncodableDependencyTreeEdge {
+                    node: None,
+                    crate_id: name,
+                    req: dep.req.clone(),
+                    optional: dep.optional,
+                    default_features: dep.default_features,
+                    features: dep.features.clone(),
+                    target: dep.target.clone(),
+                    kind: dep.kind,
+                };
+                resolutions.push((dep.crate_id, self.best_candidate(dep), features, edge));
+            }
+
+            self.nodes[id].features = activated.features;
+            let depth = self.nodes[id].depth + 1;
+            let mut edges = Vec::new();
+            for (crate_id, candidate, features, mut edge) in resolutions {
+                edge.node = match candidate {
+                    Some(version) => match self.node_ids.get(&version.id) {
+                        Some(&child) => {
+                            let node = &mut self.nodes[child];
+                            let previous = (node.requested_features.len(), node.depth);
+                            node.requested_features.extend(features);
+                            node.depth = node.depth.min(depth);
+                            if (node.requested_features.len(), node.depth) != previous {
+                                pending.insert(child);
+                            }
+                            Some(child)
+                        }
+                        None if self.nodes.len() >= self.options.max_nodes => {
+                            self.nodes[id].truncated = true;
+                            self.truncated = true;
+                            continue;
+                        }
+                        None => {
+                            let child = self.add_node(crate_id, version, features, depth);
+                            pending.insert(child);
+                            Some(child)
+                        }
+                    },
+                    None => None,
+                };
+                edges.push(edge);
+            }
+            self.nodes[id].edges = edges;
+        }
+
+        Ok(pending.into_iter().collect())
+    }
+
+    /// Loads the dependencies of the given nodes, and the versions and names of the crates
+    /// they depend on.
+    fn load_dependencies(&mut self, ids: &[usize]) -> QueryResult<()> {
+        let version_ids = ids
+            .iter()
+            .map(|&id| self.nodes[id].version.id)
+            .filter(|version_id| !self.dependencies.contains_key(version_id))
+            .collect::<Vec<_>>();
+        for version_id in &version_ids {
+            self.dependencies.insert(*version_id, Vec::new());
+        }
+        let loaded: Vec<Dependency> = dependencies::table
+            .filter(dependencies::version_id.eq_any(&version_ids))
+            .order(dependencies::id)
+            .load(self.conn)?;
+
+        let mut crate_ids = loaded
+            .iter()
+            .map(|dep| dep.crate_id)
+            .filter(|crate_id| !self.candidates.contains_key(crate_id))
+            .collect::<Vec<_>>();
+        crate_ids.sort_unstable();
+        crate_ids.dedup();
+        for dep in loaded {
+            self.dependencies
+                .entry(dep.version_id)
+                .or_default()
+                .push(dep);
+        }
+        if crate_ids.is_empty() {
+            return Ok(());
+        }
+
+        let names: Vec<(i32, String)> = crates::table
+            .filter(crates::id.eq_any(&crate_ids))
+            .select((crates::id, crates::name))
+            .load(self.conn)?;
+        self.crate_names.extend(names);
+
+        let candidates: Vec<(i32, i32, String, serde_json::Value)> = versions::table
+            .filter(versions::crate_id.eq_any(&crate_ids))
+            .filter(versions::yanked.eq(false))
+            .select((
+                versions::id,
+                versions::crate_id,
+                versions::num,
+                versions::features,
+            ))
+            .load(self.conn)?;
+        for crate_id in crate_ids {
+            self.candidates.insert(crate_id, Vec::new());
+        }
+        for (id, crate_id, num, features) in candidates {
+            if let Ok(num) = semver::Version::parse(&num) {
+                self.candidates
+                    .entry(crate_id)
+                    .or_default()
+                    .push(Candidate {
+                        id,
+                        num,
+                        features: parse_features(&features),
+                    });
+            }
+        }
+        Ok(())
+    }
+
+    /// The highest non-yanked version matching the requirement of the dependency.
+    fn best_candidate(&self, dep: &Dependency) -> Option<Candidate> {
+        let req = semver::VersionReq::parse(&dep.req).ok()?;
+        self.candidates
+            .get(&dep.crate_id)?
+            .iter()
+            .filter(|candidate| req.matches(&candidate.num))
+            .max_by(|a, b| a.num.cmp(&b.num))
+            .cloned()
+    }
+
+    /// Whether a dependency may be used on the requested target. Dependencies with a `cfg`
+    /// which can't be evaluated are kept.
+    fn matches_target(&self, dep_target: Option<&str>) -> bool {
+        match (&self.target, dep_target) {
+            (Some(target), Some(dep_target)) => match Cfg::parse(dep_target) {
+                Some(cfg) => cfg.eval(target) != Some(false),
+                None => Some(dep_target) == self.options.target.as_deref(),
+            },
+            _ => true,
+        }
+    }
+
+    fn into_tree(self) -> DependencyTree {
+        let crate_names = self.crate_names;
+        let nodes = self
+            .nodes
+            .into_iter()
+            .enumerate()
+            .map(|(id, node)| EncodableDependencyTreeNode {
+                id,
+                krate: crate_names[&node.crate_id].clone(),
+                num: node.version.num.to_string(),
+                features: node.features.into_iter().collect(),
+                depth: node.depth,
+                dependencies: node.edges,
+                truncated: node.truncated,
+            })
+            .collect();
+
+        DependencyTree {
+            nodes,
+            truncated: self.truncated,
+        }
+    }
+}
+
+/// The dependencies enabled by the features of a version.
+#[derive(Debug, Default)]
+struct ActivatedFeatures {
+    /// The enabled features of the `[features]` table.
+    features: BTreeSet<String>,
+    /// The names of the enabled optional dependencies.
+    optional_dependencies: BTreeSet<String>,
+    /// The features enabled on dependencies through `dep/feature` values.
+    dependency_features: HashMap<String, BTreeSet<String>>,
+}
+
+/// Follows the `[features]` table of a version, starting from the requested features.
+/// Optional dependencies are implicit features, enabled by their name.
+fn activate_features(
+    requested: &BTreeSet<String>,
+    table: &HashMap<String, Vec<String>>,
+    is_optional_dependency: impl Fn(&str) -> bool,
+) -> ActivatedFeatures {
+    let mut activated = ActivatedFeatures::default();
+    let mut enabled = BTreeSet::new();
+    let mut stack = requested.iter().cloned().collect::<Vec<_>>();
+    while let Some(feature) = stack.pop() {
+        if !enabled.insert(feature.clone()) {
+            continue;
+        }
+        if let Some(idx) = feature.find('/') {
+            let (dep, dep_feature) = (&feature[..idx], &feature[idx + 1..]);
+            activated.optional_dependencies.insert(dep.into());
+            activated
+                .dependency_features
+                .entry(dep.into())
+                .or_default()
+                .insert(dep_feature.into());
+        } else if let Some(values) = table.get(&feature) {
+            stack.extend(values.iter().cloned());
+            activated.features.insert(feature);
+        } else if is_optional_dependency(&feature) {
+            activated.optional_dependencies.insert(feature);
+        }
+    }
+    activated
+}
+
+fn parse_features(features: &serde_json::Value) -> HashMap<String, Vec<String>> {
+    serde_json::from_value(features.clone()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|&value| value.into()).collect()
+    }
+
+    #[test]
+    fn features_enable_optional_dependencies() {
+        let mut table = HashMap::new();
+        table.insert("default".into(), strings(&["std"]));
+        table.insert("std".into(), strings(&["serde/std", "alloc"]));
+        table.insert("alloc".into(), strings(&[]));
+        table.insert("full".into(), strings(&["rayon", "std"]));
+
+        let is_optional = |name: &str| name == "rayon" || name == "serde";
+        let requested = |features: &[&str]| features.iter().map(|&f| f.into()).collect();
+
+        let activated = activate_features(&requested(&["default"]), &table, is_optional);
+        assert_eq!(activated.features, requested(&["alloc", "default", "std"]));
+        assert_eq!(
+            activated.optional_dependencies,
+            requested(&["serde"]),
+            "`dep/feature` enables the dependency"
+        );
+        assert_eq!(activated.dependency_features["serde"], requested(&["std"]));
+
+        let activated = activate_features(&requested(&["full"]), &table, is_optional);
+        assert_eq!(
+            activated.optional_dependencies,
+            requested(&["rayon", "serde"])
+        );
+
+        let activated = activate_features(&requested(&["rayon"]), &table, is_optional);
+        assert_eq!(activated.optional_dependencies, requested(&["rayon"]));
+        assert!(activated.dependency_features.is_empty());
+
+        let activated = activate_features(&requested(&["alloc", "unknown"]), &table, is_optional);
+        assert!(activated.optional_dependencies.is_empty());
+    }
+}
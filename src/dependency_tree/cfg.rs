@@ -0,0 +1,414 @@
+//! A small evaluator for the `cfg(...)` expressions of target-specific dependencies, like
+//! `[target.'cfg(unix)'.dependencies]`.
+//!
+//! The configuration of a target is guessed from its triple, so only the common keys are
+//! known. Expressions using other keys (e.g. `target_feature`) evaluate to `None`.
+
+/// The longest `target` that is parsed. Targets come from published crates, so their length and
+/// nesting are capped to bound the work done to parse and evaluate them.
+const MAX_TARGET_LENGTH: usize = 1024;
+/// The deepest nesting of `all`, `any` and `not` predicates that is parsed.
+const MAX_DEPTH: usize = 16;
+
+/// A parsed `cfg(...)` predicate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cfg {
+    /// A name like `unix`.
+    Name(String),
+    /// A key-value pair like `target_os = "linux"`.
+    KeyPair(String, String),
+    All(Vec<Cfg>),
+    Any(Vec<Cfg>),
+    Not(Box<Cfg>),
+}
+
+impl Cfg {
+    /// Parses the `target` of a dependency, if it is a `cfg(...)` expression that isn't too long
+    /// or deeply nested.
+    pub fn parse(target: &str) -> Option<Self> {
+        if target.len() > MAX_TARGET_LENGTH {
+            return None;
+        }
+        let mut tokens = tokenize(target)?.into_iter().peekable();
+        if tokens.next()? != Token::Ident("cfg".into()) || tokens.next()? != Token::Open {
+            return None;
+        }
+        let cfg = parse_predicate(&mut tokens, 0)?;
+        if tokens.next()? != Token::Close || tokens.next().is_some() {
+            return None;
+        }
+        Some(cfg)
+    }
+
+    /// Evaluates the predicate for the target, or returns `None` if it can't be known.
+    pub fn eval(&self, target: &Target) -> Option<bool> {
+        match self {
+            Cfg::Name(name) => match &**name {
+                "unix" | "windows" => Some(target.family.iter().any(|family| family == name)),
+                // Dependencies are never built for tests
+                "test" => Some(false),
+                _ => None,
+            },
+            Cfg::KeyPair(key, value) => {
+                let actual = match &**key {
+                    "target_arch" => &target.arch,
+                    "target_os" => &target.os,
+                    "target_env" => &target.env,
+                    "target_vendor" => &target.vendor,
+                    "target_pointer_width" => &target.pointer_width,
+                    "target_endian" => &target.endian,
+                    "target_family" => {
+                        return Some(target.family.iter().any(|family| family == value))
+                    }
+                    _ => return None,
+                };
+                Some(actual == value)
+            }
+            Cfg::All(cfgs) => {
+                let mut result = Some(true);
+                for cfg in cfgs {
+                    match cfg.eval(target) {
+                        Some(false) => return Some(false),
+                        Some(true) => {}
+                        None => result = None,
+                    }
+                }
+                result
+            }
+            Cfg::Any(cfgs) => {
+                let mut result = Some(false);
+                for cfg in cfgs {
+                    match cfg.eval(target) {
+                        Some(true) => return Some(true),
+                        Some(false) => {}
+                        None => result = None,
+                    }
+                }
+                result
+            }
+            Cfg::Not(cfg) => cfg.eval(target).map(|result| !result),
+        }
+    }
+}
+
+/// The configuration of a target, guessed from its triple.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub arch: String,
+    pub vendor: String,
+    pub os: String,
+    pub env: String,
+    pub family: Vec<String>,
+    pub pointer_width: String,
+    pub endian: String,
+}
+
+const UNIX_OSES: &[&str] = &[
+    "linux",
+    "android",
+    "macos",
+    "ios",
+    "freebsd",
+    "netbsd",
+    "openbsd",
+    "dragonfly",
+    "solaris",
+    "illumos",
+    "fuchsia",
+    "redox",
+    "haiku",
+    "emscripten",
+];
+
+impl Target {
+    /// Guesses the configuration of a target like `x86_64-unknown-linux-gnu`.
+    pub fn from_triple(triple: &str) -> Self {
+        let parts = triple.split('-').collect::<Vec<_>>();
+        let arch = parts[0].to_string();
+        let vendor = match parts.len() {
+            1 | 2 => "unknown",
+            _ => parts[1],
+        }
+        .to_string();
+
+        let os = if parts.contains(&"android") || parts.contains(&"androideabi") {
+            "android"
+        } else if parts.contains(&"darwin") {
+            "macos"
+        } else if let Some(&os) = parts.iter().skip(1).find(|&&part| {
+            UNIX_OSES.contains(&part) || ["windows", "wasi", "cuda", "uefi"].contains(&part)
+        }) {
+            os
+        } else {
+            "none"
+        };
+        let os = match (os, &*arch) {
+            ("none", "wasm32") | ("none", "wasm64") => "unknown",
+            (os, _) => os,
+        }
+        .to_string();
+
+        let env = parts
+            .last()
+            .filter(|_| parts.len() > 3 || os == "windows")
+            .map(|env| {
+                ["gnu", "musl", "msvc", "sgx", "uclibc"]
+                    .iter()
+                    .find(|known| env.starts_with(*known))
+                    .copied()
+                    .unwrap_or("")
+            })
+            .unwrap_or("")
+            .to_string();
+
+        let mut family = Vec::new();
+        if os == "windows" {
+            family.push("windows".to_string());
+        } else if UNIX_OSES.contains(&&*os) {
+            family.push("unix".to_string());
+        }
+        if arch.starts_with("wasm") {
+            family.push("wasm".to_string());
+        }
+
+        let pointer_width = if arch.contains("64") || arch == "s390x" {
+            "64"
+        } else if arch == "avr" || arch == "msp430" {
+            "16"
+        } else {
+            "32"
+        }
+        .to_string();
+
+        let big_endian = arch.ends_with("be")
+            || [
+                "powerpc",
+                "powerpc64",
+                "s390x",
+                "mips",
+                "mips64",
+                "sparc",
+                "sparc64",
+            ]
+            .contains(&&*arch);
+        let endian = if big_endian { "big" } else { "little" }.to_string();
+
+        Self {
+            arch,
+            vendor,
+            os,
+            env,
+            family,
+            pointer_width,
+            endian,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Open,
+    Close,
+    Comma,
+    Equals,
+}
+
+fn tokenize(input: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '=' => Token::Equals,
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        c => value.push(c),
+                    }
+                }
+                Token::Str(value)
+            }
+            c if c.is_whitespace() => continue,
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_alphanumeric() && c != '_' {
+                        break;
+                    }
+                    ident.push(c);
+                    chars.next();
+                }
+                Token::Ident(ident)
+            }
+            _ => return None,
+        };
+        tokens.push(token);
+    }
+    Some(tokens)
+}
+
+fn parse_predicate<I: Iterator<Item = Token>>(
+    tokens: &mut std::iter::Peekable<I>,
+    depth: usize,
+) -> Option<Cfg> {
+    let name = match tokens.next()? {
+        Token::Ident(name) => name,
+        _ => return None,
+    };
+    match tokens.peek() {
+        Some(Token::Equals) => {
+            tokens.next();
+            match tokens.next()? {
+                Token::Str(value) => Some(Cfg::KeyPair(name, value)),
+                _ => None,
+            }
+        }
+        Some(Token::Open) => {
+            if depth >= MAX_DEPTH {
+                return None;
+            }
+            tokens.next();
+            let mut cfgs = Vec::new();
+            loop {
+                if tokens.peek() == Some(&Token::Close) {
+                    tokens.next();
+                    break;
+                }
+                cfgs.push(parse_predicate(tokens, depth + 1)?);
+                match tokens.next()? {
+                    Token::Comma => {}
+                    Token::Close => break,
+                    _ => return None,
+                }
+            }
+            match &*name {
+                "all" => Some(Cfg::All(cfgs)),
+                "any" => Some(Cfg::Any(cfgs)),
+                "not" if cfgs.len() == 1 => Some(Cfg::Not(Box::new(cfgs.remove(0)))),
+                _ => None,
+            }
+        }
+        _ => Some(Cfg::Name(name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(cfg: &str, triple: &str) -> Option<bool> {
+        Cfg::parse(cfg).unwrap().eval(&Target::from_triple(triple))
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Cfg::parse("cfg(unix)"), Some(Cfg::Name("unix".into())));
+        assert_eq!(
+            Cfg::parse(r#"cfg(all(target_os = "linux", not(target_env = "musl")))"#),
+            Some(Cfg::All(vec![
+                Cfg::KeyPair("target_os".into(), "linux".into()),
+                Cfg::Not(Box::new(Cfg::KeyPair("target_env".into(), "musl".into()))),
+            ]))
+        );
+        assert_eq!(Cfg::parse("x86_64-pc-windows-msvc"), None);
+        assert_eq!(Cfg::parse("cfg(unix"), None);
+        assert_eq!(Cfg::parse("cfg(not(unix, windows))"), None);
+
+        let nested = |depth| format!("cfg({}unix{})", "not(".repeat(depth), ")".repeat(depth));
+        assert!(Cfg::parse(&nested(MAX_DEPTH)).is_some());
+        assert_eq!(Cfg::parse(&nested(MAX_DEPTH + 1)), None);
+        assert_eq!(Cfg::parse(&nested(100_000)), None);
+        let long = format!("cfg(any({}unix))", "windows, ".repeat(MAX_TARGET_LENGTH));
+        assert_eq!(Cfg::parse(&long), None);
+    }
+
+    #[test]
+    fn targets() {
+        let linux = Target::from_triple("x86_64-unknown-linux-gnu");
+        assert_eq!(linux.arch, "x86_64");
+        assert_eq!(linux.vendor, "unknown");
+        assert_eq!(linux.os, "linux");
+        assert_eq!(linux.env, "gnu");
+        assert_eq!(linux.family, ["unix"]);
+        assert_eq!(linux.pointer_width, "64");
+        assert_eq!(linux.endian, "little");
+
+        let windows = Target::from_triple("i686-pc-windows-msvc");
+        assert_eq!(windows.os, "windows");
+        assert_eq!(windows.env, "msvc");
+        assert_eq!(windows.family, ["windows"]);
+        assert_eq!(windows.pointer_width, "32");
+
+        let macos = Target::from_triple("aarch64-apple-darwin");
+        assert_eq!(macos.os, "macos");
+        assert_eq!(macos.env, "");
+        assert_eq!(macos.family, ["unix"]);
+
+        let wasm = Target::from_triple("wasm32-unknown-unknown");
+        assert_eq!(wasm.os, "unknown");
+        assert_eq!(wasm.family, ["wasm"]);
+    }
+
+    #[test]
+    fn evaluation() {
+        assert_eq!(eval("cfg(unix)", "x86_64-unknown-linux-gnu"), Some(true));
+        assert_eq!(
+            eval("cfg(windows)", "x86_64-unknown-linux-gnu"),
+            Some(false)
+        );
+        assert_eq!(
+            eval(r#"cfg(target_os = "macos")"#, "x86_64-apple-darwin"),
+            Some(true)
+        );
+        assert_eq!(
+            eval(
+                r#"cfg(any(target_os = "macos", target_os = "ios"))"#,
+                "x86_64-pc-windows-gnu"
+            ),
+            Some(false)
+        );
+        assert_eq!(
+            eval(
+                r#"cfg(not(target_arch = "wasm32"))"#,
+                "wasm32-unknown-unknown"
+            ),
+            Some(false)
+        );
+
+        // Unknown keys can't be evaluated, unless the result doesn't depend on them
+        assert_eq!(
+            eval(
+                r#"cfg(target_feature = "sse2")"#,
+                "x86_64-unknown-linux-gnu"
+            ),
+            None
+        );
+        assert_eq!(
+            eval(
+                r#"cfg(all(windows, target_feature = "sse2"))"#,
+                "x86_64-unknown-linux-gnu"
+            ),
+            Some(false)
+        );
+        assert_eq!(
+            eval(
+                r#"cfg(any(unix, target_feature = "sse2"))"#,
+                "x86_64-unknown-linux-gnu"
+            ),
+            Some(true)
+        );
+        assert_eq!(
+            eval(
+                r#"cfg(any(windows, target_feature = "sse2"))"#,
+                "x86_64-unknown-linux-gnu"
+            ),
+            None
+        );
+    }
+}
@@ -1,10 +1,18 @@
+use crate::models::ClientClass;
 use crate::App;
 use anyhow::Error;
 use dashmap::{DashMap, SharedValue};
 use diesel::{pg::upsert::excluded, prelude::*};
 use std::collections::{HashMap, HashSet};
+use std::io;
 use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
 
+pub use self::filter::DownloadsFilter;
+pub use self::spill::SpillLog;
+
+mod filter;
+mod spill;
+
 /// crates.io receives a lot of download requests, and we can't execute a write query to the
 /// database during each connection for performance reasons. To reduce the write load, this struct
 /// collects the pending updates from the current process and writes in batch.
@@ -22,23 +30,40 @@ use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
 /// persisted, so it's possible to lose some of them if the process exits ungracefully. While
 /// that's far from ideal, the advantage of batching database updates far outweights potentially
 /// losing some download counts.
+///
+/// When a `SpillLog` is configured, the downloads that can't be persisted because of database
+/// errors (along with their breakdown by client) are written to it instead of being lost, and
+/// replayed later by `replay_spilled`.
 #[derive(Debug)]
 pub struct DownloadsCounter {
     /// Inner storage for the download counts.
     inner: DashMap<i32, AtomicUsize>,
+    /// Inner storage for the download counts broken down by the kind of client.
+    clients: DashMap<(i32, ClientClass), AtomicUsize>,
     /// Index of the next shard that should be persisted by `persist_next_shard`.
     shard_idx: AtomicUsize,
     /// Number of downloads that are not yet persisted on the database. This is just used as a
     /// metric included in log lines, and it's not guaranteed to be accurate.
     pending_count: AtomicI64,
+    /// Log on disk storing the downloads that couldn't be persisted in the database.
+    spill_log: Option<SpillLog>,
 }
 
 impl DownloadsCounter {
     pub(crate) fn new() -> Self {
         Self {
             inner: DashMap::new(),
+            clients: DashMap::new(),
             shard_idx: AtomicUsize::new(0),
             pending_count: AtomicI64::new(0),
+            spill_log: None,
+        }
+    }
+
+    pub(crate) fn with_spill_log(spill_log: SpillLog) -> Self {
+        Self {
+            spill_log: Some(spill_log),
+            ..Self::new()
         }
     }
 
@@ -64,9 +89,53 @@ impl DownloadsCounter {
         }
     }
 
+    /// Records which kind of client downloaded the version. This is tracked separately from the
+    /// download counts, which are still incremented by the `increment` method.
+    pub(crate) fn increment_client(&self, version_id: i32, client: ClientClass) {
+        if let Some(counter) = self.clients.get(&(version_id, client)) {
+            counter.value().fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.clients
+                .entry((version_id, client))
+                .and_modify(|counter| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .or_insert_with(|| AtomicUsize::new(1));
+        }
+    }
+
+    /// Records a download served while the database was unavailable, which can't be counted
+    /// until the crate name and version number are resolved to a version. The download is
+    /// buffered in memory until the next `flush_spill_log`, and dropped if no `SpillLog` is
+    /// configured.
+    pub(crate) fn spill_unresolved(&self, crate_name: &str, version: &str) {
+        if let Some(spill_log) = &self.spill_log {
+            spill_log.spill_unresolved(crate_name, version);
+        }
+    }
+
+    /// Writes the downloads buffered by `spill_unresolved` to the `SpillLog`.
+    pub fn flush_spill_log(&self) -> io::Result<()> {
+        match &self.spill_log {
+            Some(spill_log) => spill_log.flush(),
+            None => Ok(()),
+        }
+    }
+
     pub fn persist_all_shards(&self, app: &App) -> Result<PersistStats, Error> {
-        let conn = app.primary_database.get()?;
-        self.persist_all_shards_with_conn(&conn)
+        // This is called when the process is shutting down, so the buffered downloads need to be
+        // written to the log to be replayed by the next process.
+        self.flush_spill_log()?;
+
+        match app.primary_database.get() {
+            Ok(conn) => self.persist_all_shards_with_conn(&conn),
+            Err(err) => {
+                // This is called when the process is shutting down, so there will be no other
+                // chance to persist the downloads.
+                self.spill_all_shards()?;
+                Err(err.into())
+            }
+        }
     }
 
     pub fn persist_next_shard(&self, app: &App) -> Result<PersistStats, Error> {
@@ -74,11 +143,86 @@ impl DownloadsCounter {
         self.persist_next_shard_with_conn(&conn)
     }
 
+    /// Persists the downloads in the `SpillLog` into the database, returning how many of them were
+    /// replayed.
+    pub fn replay_spilled(&self, app: &App) -> Result<usize, Error> {
+        match &self.spill_log {
+            Some(spill_log) => spill_log.replay(&*app.primary_database.get()?),
+            None => Ok(0),
+        }
+    }
+
+    fn spill_all_shards(&self) -> Result<(), Error> {
+        if self.spill_log.is_some() {
+            for (shard, clients_shard) in self.inner.shards().iter().zip(self.clients.shards()) {
+                let shard = std::mem::take(&mut *shard.write());
+                let to_spill = shard
+                    .iter()
+                    .map(|(id, atomic)| (*id, atomic.get().load(Ordering::SeqCst)))
+                    .collect::<Vec<_>>();
+                self.spill(&to_spill)?;
+
+                let clients_shard = std::mem::take(&mut *clients_shard.write());
+                let clients_to_spill = clients_shard
+                    .iter()
+                    .map(|((id, client), atomic)| {
+                        (*id, client.to_string(), atomic.get().load(Ordering::SeqCst))
+                    })
+                    .collect::<Vec<_>>();
+                self.spill_clients(&clients_to_spill)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Called when persisting `to_insert` failed with `err`, so that the downloads (and their
+    /// breakdown by client) are not lost.
+    fn spill_unpersisted(
+        &self,
+        to_insert: &[(i32, usize)],
+        clients_to_insert: &[(i32, String, usize)],
+        err: diesel::result::Error,
+    ) -> Error {
+        if let Err(spill_err) = self.spill(to_insert) {
+            println!("downloads_counter spill error: {}", spill_err);
+        }
+        self.spill_unpersisted_clients(clients_to_insert, err)
+    }
+
+    fn spill_unpersisted_clients(
+        &self,
+        clients_to_insert: &[(i32, String, usize)],
+        err: diesel::result::Error,
+    ) -> Error {
+        if let Err(spill_err) = self.spill_clients(clients_to_insert) {
+            println!("downloads_counter spill error: {}", spill_err);
+        }
+        err.into()
+    }
+
+    fn spill_clients(&self, clients: &[(i32, String, usize)]) -> Result<(), Error> {
+        if let Some(spill_log) = &self.spill_log {
+            spill_log.spill_clients(clients)?;
+        }
+        Ok(())
+    }
+
+    fn spill(&self, downloads: &[(i32, usize)]) -> Result<(), Error> {
+        if let Some(spill_log) = &self.spill_log {
+            spill_log.spill_versions(downloads)?;
+            let spilled: usize = downloads.iter().map(|(_, count)| count).sum();
+            self.pending_count
+                .fetch_sub(spilled as i64, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
     fn persist_all_shards_with_conn(&self, conn: &PgConnection) -> Result<PersistStats, Error> {
         let mut stats = PersistStats::default();
-        for shard in self.inner.shards() {
+        for (shard, clients_shard) in self.inner.shards().iter().zip(self.clients.shards()) {
             let shard = std::mem::take(&mut *shard.write());
-            stats = stats.merge(self.persist_shard(&conn, shard)?);
+            let clients_shard = std::mem::take(&mut *clients_shard.write());
+            stats = stats.merge(self.persist_shard(&conn, shard, clients_shard)?);
         }
 
         Ok(stats)
@@ -91,8 +235,10 @@ impl DownloadsCounter {
         let shards = self.inner.shards();
         let idx = self.shard_idx.fetch_add(1, Ordering::SeqCst) % shards.len();
         let shard = std::mem::take(&mut *shards[idx].write());
+        // Both maps are created with the same amount of shards.
+        let clients_shard = std::mem::take(&mut *self.clients.shards()[idx].write());
 
-        let mut stats = self.persist_shard(&conn, shard)?;
+        let mut stats = self.persist_shard(&conn, shard, clients_shard)?;
         stats.shard = Some(idx);
         Ok(stats)
     }
@@ -101,8 +247,9 @@ impl DownloadsCounter {
         &self,
         conn: &PgConnection,
         shard: HashMap<i32, SharedValue<AtomicUsize>>,
+        clients_shard: HashMap<(i32, ClientClass), SharedValue<AtomicUsize>>,
     ) -> Result<PersistStats, Error> {
-        use crate::schema::{version_downloads, versions};
+        use crate::schema::{version_downloads, version_downloads_by_client, versions};
 
         let mut discarded_downloads = 0;
         let mut counted_downloads = 0;
@@ -113,7 +260,14 @@ impl DownloadsCounter {
             .map(|(id, atomic)| (*id, atomic.get().load(Ordering::SeqCst)))
             .collect::<Vec<_>>();
 
-        if !to_insert.is_empty() {
+        let mut clients_to_insert = clients_shard
+            .iter()
+            .map(|((id, client), atomic)| {
+                (*id, client.to_string(), atomic.get().load(Ordering::SeqCst))
+            })
+            .collect::<Vec<_>>();
+
+        if !to_insert.is_empty() || !clients_to_insert.is_empty() {
             // The rows we're about to insert need to be sorted to avoid deadlocks when multiple
             // instances of crates.io are running at the same time.
             //
@@ -129,6 +283,7 @@ impl DownloadsCounter {
             //     https://www.postgresql.org/docs/11/explicit-locking.html#LOCKING-DEADLOCKS
             //
             to_insert.sort_by_key(|(key, _)| *key);
+            clients_to_insert.sort();
 
             // Our database schema enforces that every row in the `version_downloads` table points
             // to a valid version in the `versions` table with a foreign key. This doesn't cause
@@ -143,7 +298,11 @@ impl DownloadsCounter {
             // the shard we were about to persist. To avoid that from happening this snippet does a
             // `SELECT` query on the version table before persisting to check whether every version
             // still exists in the database. Missing versions are removed from the following query.
-            let version_ids = to_insert.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+            let version_ids = to_insert
+                .iter()
+                .map(|(id, _)| *id)
+                .chain(clients_to_insert.iter().map(|(id, _, _)| *id))
+                .collect::<Vec<_>>();
             let existing_version_ids: HashSet<i32> = versions::table
                 .select(versions::id)
                 // `FOR SHARE` prevents updates or deletions on the selected rows in the `versions`
@@ -154,7 +313,8 @@ impl DownloadsCounter {
                 // multiple `SELECT` transactions, to allow for concurrent downloads persisting.
                 .for_share()
                 .filter(versions::id.eq_any(version_ids))
-                .load(conn)?
+                .load(conn)
+                .map_err(|err| self.spill_unpersisted(&to_insert, &clients_to_insert, err))?
                 .into_iter()
                 .collect();
 
@@ -180,7 +340,40 @@ impl DownloadsCounter {
                     version_downloads::downloads
                         .eq(version_downloads::downloads + excluded(version_downloads::downloads)),
                 )
-                .execute(conn)?;
+                .execute(conn)
+                .map_err(|err| self.spill_unpersisted(&to_insert, &clients_to_insert, err))?;
+
+            // The breakdown by client of missing versions is discarded too. It's not included in
+            // the stats, as those downloads were already counted above. For the same reason
+            // failing to persist it only spills the breakdown, as spilling the downloads would
+            // count them twice.
+            let values = clients_to_insert
+                .iter()
+                .filter(|(id, _, _)| existing_version_ids.contains(id))
+                .map(|(id, client, count)| {
+                    (
+                        version_downloads_by_client::version_id.eq(*id),
+                        version_downloads_by_client::client.eq(client),
+                        version_downloads_by_client::downloads.eq(*count as i32),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            diesel::insert_into(version_downloads_by_client::table)
+                .values(&values)
+                .on_conflict((
+                    version_downloads_by_client::version_id,
+                    version_downloads_by_client::date,
+                    version_downloads_by_client::client,
+                ))
+                .do_update()
+                .set(
+                    version_downloads_by_client::downloads
+                        .eq(version_downloads_by_client::downloads
+                            + excluded(version_downloads_by_client::downloads)),
+                )
+                .execute(conn)
+                .map_err(|err| self.spill_unpersisted_clients(&clients_to_insert, err))?;
         }
 
         let old_pending = self.pending_count.fetch_sub(
@@ -243,6 +436,7 @@ mod tests {
     use crate::email::Emails;
     use crate::models::{Crate, NewCrate, NewUser, NewVersion, User};
     use diesel::PgConnection;
+    use prometheus::IntCounter;
     use semver::Version;
 
     #[test]
@@ -414,6 +608,124 @@ mod tests {
         state.assert_downloads_count(&conn, v2, 0);
     }
 
+    #[test]
+    fn test_increment_client_and_persist_all() {
+        let counter = DownloadsCounter::new();
+        let conn = crate::db::test_conn();
+        let mut state = State::new(&conn);
+
+        let v1 = state.new_version(&conn);
+        let missing = v1 + 1;
+
+        for _ in 0..3 {
+            counter.increment(v1);
+            counter.increment_client(v1, ClientClass::Cargo(1, 50));
+        }
+        counter.increment(v1);
+        counter.increment_client(v1, ClientClass::Tool("curl"));
+        counter.increment(missing);
+        counter.increment_client(missing, ClientClass::Cargo(1, 50));
+
+        counter
+            .persist_all_shards_with_conn(&conn)
+            .expect("failed to persist download counts");
+
+        state.assert_downloads_count(&conn, v1, 4);
+        state.assert_client_downloads(&conn, v1, &[("cargo 1.50", 3), ("curl", 1)]);
+        state.assert_client_downloads(&conn, missing, &[]);
+    }
+
+    #[test]
+    fn test_spilled_downloads_are_replayed() {
+        let dir = tempfile::tempdir().unwrap();
+        let spilled = IntCounter::new("spilled", "spilled").unwrap();
+        let replayed = IntCounter::new("replayed", "replayed").unwrap();
+        let spill_log = SpillLog::open(
+            &dir.path().join("spill.log"),
+            spilled.clone(),
+            replayed.clone(),
+        )
+        .unwrap();
+        let counter = DownloadsCounter::with_spill_log(spill_log);
+        let conn = crate::db::test_conn();
+        let mut state = State::new(&conn);
+
+        let v1 = state.new_version(&conn);
+        let missing = v1 + 1;
+
+        counter.spill(&[(v1, 2), (missing, 1)]).unwrap();
+        counter
+            .spill_clients(&[(v1, "cargo 1.50".into(), 2), (missing, "curl".into(), 1)])
+            .unwrap();
+        // Downloads of unknown crates and versions are spilled, but discarded when replaying.
+        counter.spill_unresolved("FOO", "1.0.0");
+        counter.spill_unresolved("FOO", "1.0.0");
+        counter.spill_unresolved("foo", "9.9.9");
+        // Unresolved downloads are only written to the log when it's flushed
+        assert_eq!(spilled.get(), 3);
+        counter.flush_spill_log().unwrap();
+        assert_eq!(spilled.get(), 6);
+
+        let spill_log = counter.spill_log.as_ref().unwrap();
+        assert_eq!(spill_log.replay(&conn).unwrap(), 4);
+        assert_eq!(replayed.get(), 4);
+        state.assert_downloads_count(&conn, v1, 4);
+        state.assert_client_downloads(&conn, v1, &[("cargo 1.50", 2)]);
+
+        // The log is emptied after replaying it, and downloads spilled in the meantime are
+        // replayed the next time.
+        assert_eq!(spill_log.replay(&conn).unwrap(), 0);
+        counter.spill_unresolved("foo", "1.0.0");
+        assert_eq!(spill_log.replay(&conn).unwrap(), 1);
+        state.assert_downloads_count(&conn, v1, 5);
+    }
+
+    #[test]
+    fn test_large_spill_logs_are_replayed() {
+        use crate::schema::version_downloads_by_client;
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spill.log");
+        let conn = crate::db::test_conn();
+        let mut state = State::new(&conn);
+        let v1 = state.new_version(&conn);
+
+        // Both the downloads of each day and the downloads of each client need more rows than
+        // the bind parameters of a single query allow.
+        let days = 25_000;
+        let mut log = std::fs::File::create(&path).unwrap();
+        let first_day = chrono::NaiveDate::from_ymd(1950, 1, 1);
+        for day in 0..days {
+            let date = first_day + chrono::Duration::days(day);
+            writeln!(
+                log,
+                r#"{{"kind":"version","date":"{}","version_id":{},"downloads":1}}"#,
+                date, v1
+            )
+            .unwrap();
+        }
+        drop(log);
+
+        let spilled = IntCounter::new("spilled", "spilled").unwrap();
+        let replayed = IntCounter::new("replayed", "replayed").unwrap();
+        let spill_log = SpillLog::open(&path, spilled, replayed).unwrap();
+        let clients = (0..20_000)
+            .map(|n| (v1, format!("client {}", n), 1))
+            .collect::<Vec<_>>();
+        spill_log.spill_clients(&clients).unwrap();
+
+        assert_eq!(spill_log.replay(&conn).unwrap(), days as usize);
+        state.assert_downloads_count(&conn, v1, days);
+
+        let client_rows: i64 = version_downloads_by_client::table
+            .filter(version_downloads_by_client::version_id.eq(v1))
+            .count()
+            .get_result(&conn)
+            .unwrap();
+        assert_eq!(client_rows, 20_000);
+    }
+
     struct State {
         user: User,
         krate: Crate,
@@ -453,6 +765,7 @@ mod tests {
                 None,
                 0,
                 self.user.id,
+                None,
             )
             .expect("failed to create version")
             .save(conn, &[], "ghost@example.com")
@@ -462,6 +775,27 @@ mod tests {
             version.id
         }
 
+        fn assert_client_downloads(
+            &self,
+            conn: &PgConnection,
+            version: i32,
+            expected: &[(&str, i32)],
+        ) {
+            use crate::schema::version_downloads_by_client::dsl::*;
+
+            let actual: Vec<(String, i32)> = version_downloads_by_client
+                .select((client, downloads))
+                .filter(version_id.eq(version))
+                .order(client)
+                .load(conn)
+                .unwrap();
+            let expected: Vec<(String, i32)> = expected
+                .iter()
+                .map(|(name, count)| (name.to_string(), *count))
+                .collect();
+            assert_eq!(actual, expected);
+        }
+
         fn assert_downloads_count(&self, conn: &PgConnection, version: i32, expected: i64) {
             use crate::schema::version_downloads::dsl::*;
             use diesel::dsl::*;
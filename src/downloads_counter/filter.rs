@@ -0,0 +1,186 @@
+use crate::Config;
+use dashmap::{mapref::entry::Entry, DashMap};
+use std::time::{Duration, Instant};
+
+/// Why a download was not counted. This is used as the label of the `downloads_discarded_total`
+/// instance metric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscardReason {
+    /// The same IP address already downloaded the version within the deduplication window.
+    Duplicate,
+    /// The user agent is blocklisted in `Config::blocked_traffic`.
+    BlockedUserAgent,
+}
+
+impl DiscardReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiscardReason::Duplicate => "duplicate",
+            DiscardReason::BlockedUserAgent => "blocked_user_agent",
+        }
+    }
+}
+
+/// Heuristics deciding whether a download request should be counted, applied before calling
+/// `DownloadsCounter::increment`. Discarded downloads are still served, they just don't inflate
+/// the download counts.
+///
+/// Downloads of the same version from the same IP address are counted at most once per
+/// deduplication window, which prevents misbehaving CI loops from adding millions of downloads.
+/// Downloads from the user agents blocked by the `BLOCKED_TRAFFIC` configuration are never
+/// counted, even if the request reaches the endpoint.
+#[derive(Debug)]
+pub struct DownloadsFilter {
+    /// Deduplication is disabled if the window is zero.
+    dedup_window: Duration,
+    blocked_user_agents: Vec<String>,
+    /// When a download was last counted for each IP address and version.
+    last_counted: DashMap<(String, i32), Instant>,
+}
+
+impl DownloadsFilter {
+    pub fn new(dedup_window: Duration, blocked_user_agents: Vec<String>) -> Self {
+        Self {
+            dedup_window,
+            blocked_user_agents,
+            last_counted: DashMap::new(),
+        }
+    }
+
+    pub(crate) fn from_config(config: &Config) -> Self {
+        let blocked_user_agents = config
+            .blocked_traffic
+            .iter()
+            .filter(|(header, _)| header.eq_ignore_ascii_case("User-Agent"))
+            .flat_map(|(_, values)| values.iter().cloned())
+            .collect();
+
+        Self::new(
+            Duration::from_secs(config.downloads_dedup_window_secs),
+            blocked_user_agents,
+        )
+    }
+
+    /// Returns why the download shouldn't be counted, or `None` if it should.
+    pub(crate) fn check(
+        &self,
+        ip: &str,
+        version_id: i32,
+        user_agent: &str,
+    ) -> Option<DiscardReason> {
+        self.check_at(ip, version_id, user_agent, Instant::now())
+    }
+
+    /// Forgets the downloads that are past the deduplication window, so that the memory used by
+    /// the filter doesn't grow unbounded.
+    pub fn prune_expired(&self) {
+        self.prune_expired_at(Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        ip: &str,
+        version_id: i32,
+        user_agent: &str,
+        now: Instant,
+    ) -> Option<DiscardReason> {
+        if self.blocked_user_agents.iter().any(|ua| ua == user_agent) {
+            return Some(DiscardReason::BlockedUserAgent);
+        }
+        if self.dedup_window == Duration::default() {
+            return None;
+        }
+
+        match self.last_counted.entry((ip.into(), version_id)) {
+            Entry::Occupied(mut entry) => {
+                if now.saturating_duration_since(*entry.get()) < self.dedup_window {
+                    return Some(DiscardReason::Duplicate);
+                }
+                entry.insert(now);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+            }
+        }
+        None
+    }
+
+    fn prune_expired_at(&self, now: Instant) {
+        let dedup_window = self.dedup_window;
+        self.last_counted
+            .retain(|_, last_counted| now.saturating_duration_since(*last_counted) < dedup_window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO: &str = "cargo 1.50.0 (f04e7fab7 2021-02-04)";
+
+    #[test]
+    fn duplicate_downloads_are_discarded_within_the_window() {
+        let filter = DownloadsFilter::new(Duration::from_secs(60), vec![]);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(filter.check_at("127.0.0.1", 1, CARGO, at(0)), None);
+        assert_eq!(
+            filter.check_at("127.0.0.1", 1, CARGO, at(30)),
+            Some(DiscardReason::Duplicate)
+        );
+        // Other versions and other IP addresses are counted separately
+        assert_eq!(filter.check_at("127.0.0.1", 2, CARGO, at(30)), None);
+        assert_eq!(filter.check_at("127.0.0.2", 1, CARGO, at(30)), None);
+
+        // The window starts from the last counted download, not the last discarded one
+        assert_eq!(
+            filter.check_at("127.0.0.1", 1, CARGO, at(59)),
+            Some(DiscardReason::Duplicate)
+        );
+        assert_eq!(filter.check_at("127.0.0.1", 1, CARGO, at(60)), None);
+        assert_eq!(
+            filter.check_at("127.0.0.1", 1, CARGO, at(119)),
+            Some(DiscardReason::Duplicate)
+        );
+    }
+
+    #[test]
+    fn zero_window_disables_deduplication() {
+        let filter = DownloadsFilter::new(Duration::default(), vec![]);
+        let now = Instant::now();
+
+        assert_eq!(filter.check_at("127.0.0.1", 1, CARGO, now), None);
+        assert_eq!(filter.check_at("127.0.0.1", 1, CARGO, now), None);
+        assert!(filter.last_counted.is_empty());
+    }
+
+    #[test]
+    fn blocked_user_agents_are_discarded() {
+        let filter = DownloadsFilter::new(Duration::from_secs(60), vec!["curl/7.54.0".into()]);
+        let now = Instant::now();
+
+        assert_eq!(
+            filter.check_at("127.0.0.1", 1, "curl/7.54.0", now),
+            Some(DiscardReason::BlockedUserAgent)
+        );
+        // Blocked downloads don't start a deduplication window
+        assert_eq!(filter.check_at("127.0.0.1", 1, CARGO, now), None);
+        assert_eq!(filter.check_at("127.0.0.1", 1, "curl/7.64.1", now), None);
+    }
+
+    #[test]
+    fn expired_downloads_are_pruned() {
+        let filter = DownloadsFilter::new(Duration::from_secs(60), vec![]);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        filter.check_at("127.0.0.1", 1, CARGO, at(0));
+        filter.check_at("127.0.0.1", 2, CARGO, at(30));
+
+        filter.prune_expired_at(at(60));
+        assert_eq!(filter.last_counted.len(), 1);
+        filter.prune_expired_at(at(90));
+        assert!(filter.last_counted.is_empty());
+    }
+}
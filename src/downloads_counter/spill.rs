@@ -0,0 +1,381 @@
+use crate::models::Crate;
+use crate::schema::{crates, version_downloads, version_downloads_by_client, versions};
+use anyhow::Error;
+use chrono::{NaiveDate, Utc};
+use dashmap::DashMap;
+use diesel::{pg::upsert::excluded, prelude::*};
+use prometheus::IntCounter;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The number of rows inserted by each query when replaying a log. A long outage can spill more
+/// rows than fit in the 65535 bind parameters of a single query.
+const INSERT_CHUNK_SIZE: usize = 5000;
+
+/// Append-only log on disk storing the downloads that couldn't be persisted in the database, for
+/// example during a database outage. The log is replayed into the `version_downloads` table once
+/// the database is healthy again.
+///
+/// Each line of the log is a JSON object. Lines are only ever appended, so if the process crashes
+/// while writing one the rest of the log is still readable: the truncated line is skipped during
+/// the replay. The log is only deleted once the replayed downloads are committed, so a crash in
+/// between could count them twice, which is preferable to losing them.
+///
+/// Downloads served while the database is unavailable are buffered in memory and only written to
+/// the log by `flush`, so that serving them doesn't require a write to the disk.
+#[derive(Debug)]
+pub struct SpillLog {
+    path: PathBuf,
+    file: Mutex<File>,
+    unresolved: DashMap<(NaiveDate, String, String), AtomicUsize>,
+    spilled: IntCounter,
+    replayed: IntCounter,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum SpilledDownloads {
+    /// Downloads of a version that was already looked up in the database.
+    Version {
+        date: NaiveDate,
+        version_id: i32,
+        downloads: usize,
+    },
+    /// Downloads served while the database was unavailable, so the crate name and version number
+    /// still need to be resolved to a version.
+    Unresolved {
+        date: NaiveDate,
+        crate_name: String,
+        version: String,
+        downloads: usize,
+    },
+    /// The breakdown by client of downloads of a version. These downloads are already included in
+    /// a `Version` entry, so they're not counted again.
+    Client {
+        date: NaiveDate,
+        version_id: i32,
+        client: String,
+        downloads: usize,
+    },
+}
+
+impl SpilledDownloads {
+    fn downloads(&self) -> usize {
+        match self {
+            SpilledDownloads::Version { downloads, .. } => *downloads,
+            SpilledDownloads::Unresolved { downloads, .. } => *downloads,
+            SpilledDownloads::Client { .. } => 0,
+        }
+    }
+}
+
+impl SpillLog {
+    /// Opens the log at `path`, creating it if it doesn't exist yet. The counters are incremented
+    /// with the number of downloads written to and replayed from the log.
+    pub fn open(path: &Path, spilled: IntCounter, replayed: IntCounter) -> io::Result<Self> {
+        Ok(Self {
+            path: path.into(),
+            file: Mutex::new(open_log(path)?),
+            unresolved: DashMap::new(),
+            spilled,
+            replayed,
+        })
+    }
+
+    pub(super) fn spill_versions(&self, downloads: &[(i32, usize)]) -> io::Result<()> {
+        let date = Utc::today().naive_utc();
+        self.append(
+            downloads
+                .iter()
+                .map(|&(version_id, downloads)| SpilledDownloads::Version {
+                    date,
+                    version_id,
+                    downloads,
+                }),
+        )
+    }
+
+    pub(super) fn spill_clients(&self, downloads: &[(i32, String, usize)]) -> io::Result<()> {
+        let date = Utc::today().naive_utc();
+        self.append(downloads.iter().map(|(version_id, client, downloads)| {
+            SpilledDownloads::Client {
+                date,
+                version_id: *version_id,
+                client: client.clone(),
+                downloads: *downloads,
+            }
+        }))
+    }
+
+    /// Buffers a download served while the database was unavailable, until the next `flush`.
+    pub(super) fn spill_unresolved(&self, crate_name: &str, version: &str) {
+        let key = (Utc::today().naive_utc(), crate_name.into(), version.into());
+        self.buffer_unresolved(key, 1);
+    }
+
+    fn buffer_unresolved(&self, key: (NaiveDate, String, String), downloads: usize) {
+        if let Some(counter) = self.unresolved.get(&key) {
+            counter.value().fetch_add(downloads, Ordering::SeqCst);
+        } else {
+            self.unresolved
+                .entry(key)
+                .and_modify(|counter| {
+                    counter.fetch_add(downloads, Ordering::SeqCst);
+                })
+                .or_insert_with(|| AtomicUsize::new(downloads));
+        }
+    }
+
+    /// Writes the downloads buffered by `spill_unresolved` to the log.
+    pub(super) fn flush(&self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        for shard in self.unresolved.shards() {
+            let shard = std::mem::take(&mut *shard.write());
+            entries.extend(
+                shard
+                    .into_iter()
+                    .map(
+                        |((date, crate_name, version), atomic)| SpilledDownloads::Unresolved {
+                            date,
+                            crate_name,
+                            version,
+                            downloads: atomic.get().load(Ordering::SeqCst),
+                        },
+                    ),
+            );
+        }
+
+        if let Err(err) = self.append(entries.iter().cloned()) {
+            // Put the downloads back, so that the next flush can try again.
+            for entry in entries {
+                if let SpilledDownloads::Unresolved {
+                    date,
+                    crate_name,
+                    version,
+                    downloads,
+                } = entry
+                {
+                    self.buffer_unresolved((date, crate_name, version), downloads);
+                }
+            }
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    fn append(&self, entries: impl Iterator<Item = SpilledDownloads>) -> io::Result<()> {
+        let mut buffer = Vec::new();
+        let mut downloads = 0;
+        for entry in entries {
+            downloads += entry.downloads();
+            serde_json::to_writer(&mut buffer, &entry)?;
+            buffer.push(b'\n');
+        }
+        if buffer.is_empty() {
+            return Ok(());
+        }
+
+        let mut file = self.lock();
+        file.write_all(&buffer)?;
+        file.sync_data()?;
+        self.spilled.inc_by(downloads as u64);
+
+        Ok(())
+    }
+
+    /// Persists all the downloads in the log into the database and deletes them, returning the
+    /// number of replayed downloads. Downloads of versions that don't exist are discarded.
+    ///
+    /// The log is first moved aside, so that new downloads can be appended to a fresh log while
+    /// the old one is replayed. If replaying fails the moved log is kept, and replayed again on
+    /// the next call.
+    pub(super) fn replay(&self, conn: &PgConnection) -> Result<usize, Error> {
+        self.flush()?;
+
+        let mut replaying = self.path.clone().into_os_string();
+        replaying.push(".replaying");
+        let replaying = PathBuf::from(replaying);
+
+        if !replaying.exists() {
+            let mut file = self.lock();
+            if file.metadata()?.len() == 0 {
+                return Ok(0);
+            }
+            fs::rename(&self.path, &replaying)?;
+            *file = open_log(&self.path)?;
+        }
+
+        let totals = SpilledTotals::read(BufReader::new(File::open(&replaying)?))?;
+        let replayed = conn.transaction(|| totals.persist(conn))?;
+
+        fs::remove_file(&replaying)?;
+        self.replayed.inc_by(replayed as u64);
+
+        Ok(replayed)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, File> {
+        self.file.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn open_log(path: &Path) -> io::Result<File> {
+    OpenOptions::new().append(true).create(true).open(path)
+}
+
+/// The downloads in a log, summed by version (or crate name and version number) and date, so
+/// that they can be persisted with as few queries as possible.
+///
+/// Sorted by version and date, for the same reason `DownloadsCounter::persist_shard` sorts the
+/// rows it inserts.
+#[derive(Debug, Default)]
+struct SpilledTotals {
+    versions: BTreeMap<(i32, NaiveDate), usize>,
+    unresolved: BTreeMap<(String, String, NaiveDate), usize>,
+    clients: BTreeMap<(i32, NaiveDate, String), usize>,
+}
+
+impl SpilledTotals {
+    fn read(log: impl BufRead) -> io::Result<Self> {
+        let mut totals = Self::default();
+        for line in log.lines() {
+            let entry = match serde_json::from_str::<SpilledDownloads>(&line?) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+            match entry {
+                SpilledDownloads::Version {
+                    date,
+                    version_id,
+                    downloads,
+                } => *totals.versions.entry((version_id, date)).or_insert(0) += downloads,
+                SpilledDownloads::Unresolved {
+                    date,
+                    crate_name,
+                    version,
+                    downloads,
+                } => {
+                    *totals
+                        .unresolved
+                        .entry((crate_name, version, date))
+                        .or_insert(0) += downloads
+                }
+                SpilledDownloads::Client {
+                    date,
+                    version_id,
+                    client,
+                    downloads,
+                } => {
+                    *totals
+                        .clients
+                        .entry((version_id, date, client))
+                        .or_insert(0) += downloads
+                }
+            }
+        }
+        Ok(totals)
+    }
+
+    fn persist(&self, conn: &PgConnection) -> QueryResult<usize> {
+        let mut downloads = self.versions.clone();
+        let mut resolved = HashMap::new();
+        for ((crate_name, version, date), count) in &self.unresolved {
+            let version_id = match resolved.get(&(crate_name, version)) {
+                Some(version_id) => *version_id,
+                None => {
+                    let version_id = versions::table
+                        .inner_join(crates::table)
+                        .select(versions::id)
+                        .filter(Crate::with_name(crate_name))
+                        .filter(versions::num.eq(version))
+                        .first::<i32>(conn)
+                        .optional()?;
+                    resolved.insert((crate_name, version), version_id);
+                    version_id
+                }
+            };
+            if let Some(version_id) = version_id {
+                *downloads.entry((version_id, *date)).or_insert(0) += count;
+            }
+        }
+
+        let version_ids = downloads
+            .keys()
+            .map(|(id, _)| *id)
+            .chain(self.clients.keys().map(|(id, _, _)| *id))
+            .collect::<Vec<_>>();
+        let existing_version_ids: HashSet<i32> = versions::table
+            .select(versions::id)
+            .for_share()
+            .filter(versions::id.eq_any(version_ids))
+            .load(conn)?
+            .into_iter()
+            .collect();
+
+        let mut replayed = 0;
+        let mut values = Vec::new();
+        for ((id, date), count) in &downloads {
+            if existing_version_ids.contains(id) {
+                replayed += count;
+                values.push((
+                    version_downloads::version_id.eq(*id),
+                    version_downloads::date.eq(*date),
+                    version_downloads::downloads.eq(*count as i32),
+                ));
+            }
+        }
+
+        for chunk in values.chunks(INSERT_CHUNK_SIZE) {
+            diesel::insert_into(version_downloads::table)
+                .values(chunk)
+                .on_conflict((version_downloads::version_id, version_downloads::date))
+                .do_update()
+                .set((
+                    version_downloads::downloads
+                        .eq(version_downloads::downloads + excluded(version_downloads::downloads)),
+                    // Rows of past days might have already been processed by the
+                    // `update_downloads` task, which needs to pick up the replayed downloads too.
+                    version_downloads::processed.eq(false),
+                ))
+                .execute(conn)?;
+        }
+
+        let values = self
+            .clients
+            .iter()
+            .filter(|((id, _, _), _)| existing_version_ids.contains(id))
+            .map(|((id, date, client), count)| {
+                (
+                    version_downloads_by_client::version_id.eq(*id),
+                    version_downloads_by_client::date.eq(*date),
+                    version_downloads_by_client::client.eq(client),
+                    version_downloads_by_client::downloads.eq(*count as i32),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for chunk in values.chunks(INSERT_CHUNK_SIZE) {
+            diesel::insert_into(version_downloads_by_client::table)
+                .values(chunk)
+                .on_conflict((
+                    version_downloads_by_client::version_id,
+                    version_downloads_by_client::date,
+                    version_downloads_by_client::client,
+                ))
+                .do_update()
+                .set(
+                    version_downloads_by_client::downloads
+                        .eq(version_downloads_by_client::downloads
+                            + excluded(version_downloads_by_client::downloads)),
+                )
+                .execute(conn)?;
+        }
+
+        Ok(replayed)
+    }
+}
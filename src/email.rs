@@ -1,16 +1,58 @@
 use std::path::PathBuf;
 use std::sync::Mutex;
 
-use crate::util::errors::{server_error, AppResult};
+use crate::models::{Subscription, VersionAction};
+use crate::tasks;
+use crate::util::errors::AppResult;
 
+use diesel::PgConnection;
+use handlebars::Handlebars;
+use lettre::message::{Mailbox, MultiPart};
 use lettre::transport::file::FileTransport;
 use lettre::transport::smtp::authentication::{Credentials, Mechanism};
 use lettre::transport::smtp::SmtpTransport;
 use lettre::{Message, Transport};
+use swirl::{Job, PerformError};
+
+/// The email templates, by name. Each of them has a text part, `src/email/<name>.txt.hbs`, and
+/// an HTML part, `src/email/<name>.html.hbs`, which is wrapped in `layout.html.hbs`.
+const TEMPLATES: &[(&str, &str, &str)] = &[
+    (
+        "user_confirm",
+        include_str!("email/user_confirm.txt.hbs"),
+        include_str!("email/user_confirm.html.hbs"),
+    ),
+    (
+        "owner_invite",
+        include_str!("email/owner_invite.txt.hbs"),
+        include_str!("email/owner_invite.html.hbs"),
+    ),
+    (
+        "ownership_transfer",
+        include_str!("email/ownership_transfer.txt.hbs"),
+        include_str!("email/ownership_transfer.html.hbs"),
+    ),
+    (
+        "owner_invite_expired",
+        include_str!("email/owner_invite_expired.txt.hbs"),
+        include_str!("email/owner_invite_expired.html.hbs"),
+    ),
+    (
+        "advisory_notification",
+        include_str!("email/advisory_notification.txt.hbs"),
+        include_str!("email/advisory_notification.html.hbs"),
+    ),
+    (
+        "version_notification",
+        include_str!("email/version_notification.txt.hbs"),
+        include_str!("email/version_notification.html.hbs"),
+    ),
+];
 
 #[derive(Debug)]
 pub struct Emails {
     backend: EmailBackend,
+    templates: Templates,
 }
 
 impl Emails {
@@ -32,7 +74,10 @@ impl Emails {
             },
         };
 
-        Self { backend }
+        Self {
+            backend,
+            templates: Templates::new(),
+        }
     }
 
     /// Create a new test backend that stores all the outgoing emails in memory, allowing for tests
@@ -42,48 +87,169 @@ impl Emails {
             backend: EmailBackend::Memory {
                 mails: Mutex::new(Vec::new()),
             },
+            templates: Templates::new(),
+        }
+    }
+
+    /// Create a new backend storing the outgoing emails as files in `path`, like the one used
+    /// during development. Unlike the memory backend, the emails are sent by the `send_email`
+    /// background job.
+    pub fn new_in_directory(path: PathBuf) -> Self {
+        Self {
+            backend: EmailBackend::FileSystem { path },
+            templates: Templates::new(),
         }
     }
 
     /// Attempts to send a confirmation email.
-    pub fn send_user_confirm(&self, email: &str, user_name: &str, token: &str) -> AppResult<()> {
+    pub fn send_user_confirm(
+        &self,
+        conn: &PgConnection,
+        email: &str,
+        user_name: &str,
+        token: &str,
+    ) -> AppResult<()> {
         // Create a URL with token string as path to send to user
         // If user clicks on path, look email/user up in database,
         // make sure tokens match
 
         let subject = "Please confirm your email address";
-        let body = format!(
-            "Hello {}! Welcome to Crates.io. Please click the
-link below to verify your email address. Thank you!\n
-https://{}/confirm/{}",
-            user_name,
-            crate::config::domain_name(),
-            token
-        );
+        let context = json!({ "user_name": user_name, "token": token });
 
-        self.send(email, subject, &body)
+        self.send(conn, email, None, subject, "user_confirm", context)
     }
 
     /// Attempts to send an ownership invitation.
     pub fn send_owner_invite(
         &self,
+        conn: &PgConnection,
         email: &str,
+        subscription: &Subscription,
         user_name: &str,
         crate_name: &str,
         token: &str,
     ) -> AppResult<()> {
         let subject = "Crate ownership invitation";
-        let body = format!(
-            "{} has invited you to become an owner of the crate {}!\n
-Visit https://{domain}/accept-invite/{} to accept this invitation,
-or go to https://{domain}/me/pending-invites to manage all of your crate ownership invitations.",
-            user_name,
-            crate_name,
-            token,
-            domain = crate::config::domain_name()
-        );
+        let context = json!({ "user_name": user_name, "crate_name": crate_name, "token": token });
+
+        self.send(
+            conn,
+            email,
+            Some(subscription),
+            subject,
+            "owner_invite",
+            context,
+        )
+    }
+
+    /// Attempts to send a crate ownership transfer request.
+    pub fn send_ownership_transfer(
+        &self,
+        conn: &PgConnection,
+        email: &str,
+        user_name: &str,
+        crate_name: &str,
+        recipient: &str,
+        token: &str,
+    ) -> AppResult<()> {
+        let subject = "Crate ownership transfer request";
+        let context = json!({
+            "user_name": user_name,
+            "crate_name": crate_name,
+            "recipient": recipient,
+            "token": token,
+        });
+
+        self.send(conn, email, None, subject, "ownership_transfer", context)
+    }
+
+    /// Attempts to notify the user who sent an ownership invitation that it expired without being
+    /// accepted.
+    pub fn send_owner_invite_expired(
+        &self,
+        conn: &PgConnection,
+        email: &str,
+        subscription: &Subscription,
+        invited_user_name: &str,
+        crate_name: &str,
+    ) -> AppResult<()> {
+        let subject = "Crate ownership invitation expired";
+        let context = json!({ "invited_user_name": invited_user_name, "crate_name": crate_name });
+
+        self.send(
+            conn,
+            email,
+            Some(subscription),
+            subject,
+            "owner_invite_expired",
+            context,
+        )
+    }
+
+    /// Attempts to notify an owner of a crate that a security advisory was published for it.
+    pub fn send_advisory_notification(
+        &self,
+        conn: &PgConnection,
+        email: &str,
+        subscription: &Subscription,
+        crate_name: &str,
+        advisory_id: &str,
+        title: &str,
+    ) -> AppResult<()> {
+        let subject = format!("Security advisory {} for {}", advisory_id, crate_name);
+        let context = json!({
+            "crate_name": crate_name,
+            "advisory_id": advisory_id,
+            "title": title,
+        });
+
+        self.send(
+            conn,
+            email,
+            Some(subscription),
+            &subject,
+            "advisory_notification",
+            context,
+        )
+    }
+
+    /// Attempts to notify an owner of a crate that one of its versions was published or yanked by
+    /// someone else, so that a compromised account or API token is noticed quickly.
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_version_notification(
+        &self,
+        conn: &PgConnection,
+        email: &str,
+        subscription: &Subscription,
+        crate_name: &str,
+        version: &str,
+        action: VersionAction,
+        user_name: &str,
+        token_name: Option<&str>,
+    ) -> AppResult<()> {
+        let action = match action {
+            VersionAction::Publish => "published",
+            VersionAction::Yank => "yanked",
+            VersionAction::Unyank => "unyanked",
+        };
+
+        let subject = format!("{} {} was {}", crate_name, version, action);
+        let context = json!({
+            "crate_name": crate_name,
+            "version": version,
+            "action": action,
+            "user_name": user_name,
+            "token_name": token_name,
+        });
 
-        self.send(email, subject, &body)
+        self.send(
+            conn,
+            email,
+            Some(subscription),
+            &subject,
+            "version_notification",
+            context,
+        )
     }
 
     /// This is supposed to be used only during tests, to retrieve the messages stored in the
@@ -96,41 +262,108 @@ or go to https://{domain}/me/pending-invites to manage all of your crate ownersh
         }
     }
 
-    fn send(&self, recipient: &str, subject: &str, body: &str) -> AppResult<()> {
-        let email = Message::builder()
-            .to(recipient.parse()?)
-            .from(self.sender_address().parse()?)
-            .subject(subject)
-            .body(body.to_string())?;
+    /// Renders an email and queues it to be sent by the `send_email` background job, so that a
+    /// slow SMTP server doesn't hold up the request. The memory backend captures the rendered
+    /// email right away instead, so that tests don't have to run the job.
+    ///
+    /// Emails sent to a subscription to a category of notifications have a `List-Unsubscribe`
+    /// header, allowing users to unsubscribe in one click from their email client.
+    fn send(
+        &self,
+        conn: &PgConnection,
+        recipient: &str,
+        subscription: Option<&Subscription>,
+        subject: &str,
+        template: &str,
+        context: serde_json::Value,
+    ) -> AppResult<()> {
+        // Invalid addresses are rejected upfront, rather than failing the job on every retry
+        let _: Mailbox = recipient.parse()?;
+        let (body, html_body) = self.templates.render(template, context)?;
+        let unsubscribe_url = subscription.map(Subscription::unsubscribe_url);
+
+        match &self.backend {
+            EmailBackend::Memory { mails } => mails.lock().unwrap().push(StoredEmail {
+                to: recipient.into(),
+                subject: subject.into(),
+                body,
+                html_body,
+                unsubscribe_url,
+            }),
+            _ => {
+                let job = tasks::send_email(
+                    recipient.into(),
+                    subject.into(),
+                    body,
+                    html_body,
+                    unsubscribe_url,
+                );
+                job.enqueue(conn)?;
+            }
+        }
 
+        Ok(())
+    }
+
+    /// Sends a rendered email with the text and HTML parts as alternatives. This is called by the
+    /// `send_email` background job, which is retried if it fails.
+    pub(crate) fn deliver(
+        &self,
+        recipient: &str,
+        subject: &str,
+        body: String,
+        html_body: String,
+        unsubscribe_url: Option<String>,
+    ) -> Result<(), PerformError> {
         match &self.backend {
             EmailBackend::Smtp {
                 server,
                 login,
                 password,
             } => {
+                let email = self.message(recipient, subject, body, html_body)?;
                 SmtpTransport::relay(&server)?
                     .credentials(Credentials::new(login.clone(), password.clone()))
                     .authentication(vec![Mechanism::Plain])
                     .build()
-                    .send(&email)
-                    .map_err(|_| server_error("Error in sending email"))?;
+                    .send_raw(
+                        email.envelope(),
+                        &formatted(&email, unsubscribe_url.as_deref()),
+                    )?;
             }
             EmailBackend::FileSystem { path } => {
-                FileTransport::new(&path)
-                    .send(&email)
-                    .map_err(|_| server_error("Email file could not be generated"))?;
+                let email = self.message(recipient, subject, body, html_body)?;
+                FileTransport::new(&path).send_raw(
+                    email.envelope(),
+                    &formatted(&email, unsubscribe_url.as_deref()),
+                )?;
             }
             EmailBackend::Memory { mails } => mails.lock().unwrap().push(StoredEmail {
                 to: recipient.into(),
                 subject: subject.into(),
-                body: body.into(),
+                body,
+                html_body,
+                unsubscribe_url,
             }),
         }
 
         Ok(())
     }
 
+    fn message(
+        &self,
+        recipient: &str,
+        subject: &str,
+        body: String,
+        html_body: String,
+    ) -> Result<Message, PerformError> {
+        Ok(Message::builder()
+            .to(recipient.parse()?)
+            .from(self.sender_address().parse()?)
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(body, html_body))?)
+    }
+
     fn sender_address(&self) -> &str {
         match &self.backend {
             EmailBackend::Smtp { login, .. } => login,
@@ -140,6 +373,23 @@ or go to https://{domain}/me/pending-invites to manage all of your crate ownersh
     }
 }
 
+/// Formats a message, with the one-click `List-Unsubscribe` headers of RFC 8058 if it has an
+/// unsubscribe URL. lettre has no types for these headers, so they're prepended to the others.
+fn formatted(email: &Message, unsubscribe_url: Option<&str>) -> Vec<u8> {
+    let mut formatted = Vec::new();
+    if let Some(url) = unsubscribe_url {
+        formatted.extend_from_slice(
+            format!(
+                "List-Unsubscribe: <{}>\r\nList-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n",
+                url
+            )
+            .as_bytes(),
+        );
+    }
+    formatted.extend(email.formatted());
+    formatted
+}
+
 enum EmailBackend {
     /// Backend used in production to send mails using SMTP.
     Smtp {
@@ -173,22 +423,73 @@ impl std::fmt::Debug for EmailBackend {
     }
 }
 
+/// The registries rendering the email templates. They are separate since only the HTML parts
+/// need their values to be escaped.
+struct Templates {
+    text: Handlebars<'static>,
+    html: Handlebars<'static>,
+}
+
+impl Templates {
+    fn new() -> Self {
+        let mut text = Handlebars::new();
+        text.set_strict_mode(true);
+        text.register_escape_fn(handlebars::no_escape);
+        let mut html = Handlebars::new();
+        html.set_strict_mode(true);
+        html.register_partial("layout", include_str!("email/layout.html.hbs"))
+            .expect("Invalid email layout");
+
+        for (name, text_template, html_template) in TEMPLATES {
+            text.register_template_string(name, text_template)
+                .expect("Invalid email template");
+            html.register_template_string(name, html_template)
+                .expect("Invalid email template");
+        }
+
+        Self { text, html }
+    }
+
+    /// Renders the text and HTML parts of an email.
+    fn render(&self, name: &str, mut context: serde_json::Value) -> AppResult<(String, String)> {
+        context["domain"] = crate::config::domain_name().into();
+        Ok((
+            self.text.render(name, &context)?,
+            self.html.render(name, &context)?,
+        ))
+    }
+}
+
+impl std::fmt::Debug for Templates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Templates")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct StoredEmail {
     pub to: String,
     pub subject: String,
+    /// The text part of the email.
     pub body: String,
+    pub html_body: String,
+    /// The URL of the `List-Unsubscribe` header.
+    pub unsubscribe_url: Option<String>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::NotificationCategory;
+    use crate::test_util::pg_connection;
 
     #[test]
     fn sending_to_invalid_email_fails() {
+        let conn = pg_connection();
         let emails = Emails::new_in_memory();
 
-        assert_err!(emails.send(
+        assert_err!(emails.send_user_confirm(
+            &conn,
             "String.Format(\"{0}.{1}@live.com\", FirstName, LastName)",
             "test",
             "test",
@@ -197,8 +498,34 @@ mod tests {
 
     #[test]
     fn sending_to_valid_email_succeeds() {
+        let conn = pg_connection();
         let emails = Emails::new_in_memory();
 
-        assert_ok!(emails.send("someone@example.com", "test", "test"));
+        assert_ok!(emails.send_user_confirm(&conn, "someone@example.com", "test", "test"));
+    }
+
+    #[test]
+    fn only_the_html_part_is_escaped() {
+        let conn = pg_connection();
+        let emails = Emails::new_in_memory();
+        let subscription = Subscription {
+            category: NotificationCategory::Advisories,
+            unsubscribe_token: "token".into(),
+        };
+
+        assert_ok!(emails.send_advisory_notification(
+            &conn,
+            "someone@example.com",
+            &subscription,
+            "foo",
+            "RUSTSEC-2021-0001",
+            "<Flaw> & co",
+        ));
+        let mails = emails.mails_in_memory().unwrap();
+        assert!(mails[0].body.contains("RUSTSEC-2021-0001: <Flaw> & co"));
+        assert!(mails[0].html_body.contains("&lt;Flaw&gt; &amp; co"));
+        assert!(mails[0].html_body.ends_with("</html>\n"));
+        let unsubscribe_url = mails[0].unsubscribe_url.as_ref().unwrap();
+        assert!(unsubscribe_url.ends_with("/api/v1/unsubscribe/token/advisories"));
     }
 }
@@ -23,7 +23,9 @@ extern crate serde_json;
 #[macro_use]
 extern crate tracing;
 
-pub use crate::config::{Config, DbPoolConfig};
+pub use crate::config::{
+    Config, DbPoolConfig, SearchRankingWeights, OWNERSHIP_INVITATIONS_EXPIRATION_DAYS,
+};
 pub use crate::{app::App, email::Emails, uploaders::Uploader};
 use std::sync::Arc;
 
@@ -39,6 +41,7 @@ pub mod background_jobs;
 pub mod boot;
 mod config;
 pub mod db;
+mod dependency_tree;
 mod downloads_counter;
 pub mod email;
 pub mod git;
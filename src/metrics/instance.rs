@@ -19,7 +19,7 @@
 
 use crate::util::errors::AppResult;
 use crate::{app::App, db::DieselPool};
-use prometheus::{proto::MetricFamily, IntCounter, IntGauge, IntGaugeVec};
+use prometheus::{proto::MetricFamily, IntCounter, IntCounterVec, IntGauge, IntGaugeVec};
 
 metrics! {
     pub struct InstanceMetrics {
@@ -37,6 +37,15 @@ metrics! {
         pub downloads_unconditional_redirects_total: IntCounter,
         /// Number of download requests with a non-canonical crate name.
         pub downloads_non_canonical_crate_name_total: IntCounter,
+        /// Number of downloads written to the spill log because they couldn't be persisted.
+        pub downloads_spilled_total: IntCounter,
+        /// Number of downloads replayed from the spill log into the database.
+        pub downloads_replayed_total: IntCounter,
+        /// Number of downloads served but not counted, by the reason they were discarded.
+        pub downloads_discarded_total: IntCounterVec["reason"],
+
+        /// Number of team membership lookups, by whether they were answered from the cache
+        pub team_membership_cache_lookups_total: IntCounterVec["result"],
     }
 
     // All instance metrics will be prefixed with this namespace.
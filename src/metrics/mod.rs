@@ -10,3 +10,4 @@ mod service;
 load_metric_type!(IntGauge as single);
 load_metric_type!(IntCounter as single);
 load_metric_type!(IntGaugeVec as vec);
+load_metric_type!(IntCounterVec as vec);
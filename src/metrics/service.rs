@@ -10,10 +10,12 @@
 //! As a rule of thumb, if the metric is not straight up fetched from the database it's probably an
 //! instance-level metric, and you should add it to `src/metrics/instance.rs`.
 
-use crate::schema::{crates, versions};
+use crate::config::Config;
+use crate::schema::{crate_owner_invitations, crates, versions};
 use crate::util::errors::AppResult;
-use diesel::{dsl::count_star, prelude::*, PgConnection};
-use prometheus::{proto::MetricFamily, IntGauge};
+use diesel::dsl::{count_star, now, IntervalDsl};
+use diesel::{prelude::*, PgConnection};
+use prometheus::{proto::MetricFamily, IntGauge, IntGaugeVec};
 
 metrics! {
     pub struct ServiceMetrics {
@@ -21,6 +23,8 @@ metrics! {
         crates_total: IntGauge,
         /// Number of versions ever published
         versions_total: IntGauge,
+        /// Number of crate owner invitations waiting to be accepted or to be cleaned up
+        crate_owner_invitations_total: IntGaugeVec["state"],
     }
 
     // All service metrics will be prefixed with this namespace.
@@ -28,12 +32,31 @@ metrics! {
 }
 
 impl ServiceMetrics {
-    pub(crate) fn gather(&self, conn: &PgConnection) -> AppResult<Vec<MetricFamily>> {
+    pub(crate) fn gather(
+        &self,
+        conn: &PgConnection,
+        config: &Config,
+    ) -> AppResult<Vec<MetricFamily>> {
         self.crates_total
             .set(crates::table.select(count_star()).first(conn)?);
         self.versions_total
             .set(versions::table.select(count_star()).first(conn)?);
 
+        let expiration_days = config.ownership_invitations_expiration_days as i32;
+        let expired: i64 = crate_owner_invitations::table
+            .select(count_star())
+            .filter(crate_owner_invitations::created_at.le(now - expiration_days.days()))
+            .first(conn)?;
+        let total: i64 = crate_owner_invitations::table
+            .select(count_star())
+            .first(conn)?;
+        self.crate_owner_invitations_total
+            .with_label_values(&["pending"])
+            .set(total - expired);
+        self.crate_owner_invitations_total
+            .with_label_values(&["expired"])
+            .set(expired);
+
         Ok(self.registry.gather())
     }
 }
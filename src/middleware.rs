@@ -42,6 +42,7 @@ pub fn build_middleware(app: Arc<App>, endpoints: RouteBuilder) -> MiddlewareBui
     let mut m = MiddlewareBuilder::new(endpoints);
     let env = app.config.env;
     let blocked_traffic = app.config.blocked_traffic.clone();
+    let blocked_traffic_exempt_downloads = app.config.blocked_traffic_exempt_downloads;
 
     if env != Env::Test {
         m.add(ensure_well_formed_500::EnsureWellFormed500);
@@ -112,7 +113,11 @@ pub fn build_middleware(app: Arc<App>, endpoints: RouteBuilder) -> MiddlewareBui
     m.around(Head::default());
 
     for (header, blocked_values) in blocked_traffic {
-        m.around(block_traffic::BlockTraffic::new(header, blocked_values));
+        m.around(block_traffic::BlockTraffic::new(
+            header,
+            blocked_values,
+            blocked_traffic_exempt_downloads,
+        ));
     }
 
     m.around(require_user_agent::RequireUserAgent::default());
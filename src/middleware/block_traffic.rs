@@ -7,6 +7,10 @@
 //! (c4fcfb725 2019-05-15)`, and `BLOCKED_IPS` to `192.168.0.1,127.0.0.1` to block requests from
 //! the versions of curl or Cargo specified or from either of the IPs (values are nonsensical
 //! examples). Values of the headers must match exactly.
+//!
+//! If `BLOCKED_TRAFFIC_EXEMPT_DOWNLOADS` is set, crate downloads are not blocked based on the
+//! `User-Agent` header, for when breaking the builds of those clients is worse than serving them.
+//! Their downloads are not counted instead, see `DownloadsFilter`.
 
 use super::prelude::*;
 use crate::App;
@@ -18,19 +22,30 @@ use std::sync::Arc;
 pub struct BlockTraffic {
     header_name: String,
     blocked_values: Vec<String>,
+    exempt_downloads: bool,
     handler: Option<Box<dyn Handler>>,
 }
 
 impl BlockTraffic {
-    pub fn new(header_name: String, blocked_values: Vec<String>) -> Self {
+    pub fn new(header_name: String, blocked_values: Vec<String>, exempt_downloads: bool) -> Self {
+        let exempt_downloads = exempt_downloads && header_name.eq_ignore_ascii_case("User-Agent");
         Self {
             header_name,
             blocked_values,
+            exempt_downloads,
             handler: None,
         }
     }
 }
 
+/// Whether the request is for the `/api/v1/crates/:crate_id/:version/download` route.
+fn is_download(path: &str) -> bool {
+    matches!(
+        path.split('/').collect::<Vec<_>>()[..],
+        ["", "api", "v1", "crates", _, _, "download"]
+    )
+}
+
 impl AroundMiddleware for BlockTraffic {
     fn with_handler(&mut self, handler: Box<dyn Handler>) {
         self.handler = Some(handler);
@@ -42,6 +57,10 @@ impl Handler for BlockTraffic {
         let app = req.extensions().find::<Arc<App>>().expect("Missing app");
         let domain_name = app.config.domain_name.clone();
 
+        if self.exempt_downloads && is_download(req.path()) {
+            return self.handler.as_ref().unwrap().call(req);
+        }
+
         let has_blocked_value = req
             .headers()
             .get_all(&self.header_name)
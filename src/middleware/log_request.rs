@@ -18,6 +18,9 @@ const SLOW_REQUEST_THRESHOLD_MS: u64 = 1000;
 
 const FILTERED_HEADERS: &[&str] = &["Authorization", "Cookie", "X-Real-Ip", "X-Forwarded-For"];
 
+/// Query parameters holding credentials, like the `token` of `GET /feeds/me/updates`.
+const FILTERED_QUERY_PARAMS: &[&str] = &["token"];
+
 #[derive(Default)]
 pub(super) struct LogRequests();
 
@@ -283,7 +286,27 @@ impl<'a> Display for FullPath<'a> {
             self.0.extensions().find::<OriginalPath>().unwrap().0
         )?;
         if let Some(q_string) = self.0.query_string() {
-            write!(f, "?{}", q_string)?;
+            write!(f, "?{}", FilteredQueryString(q_string))?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays a query string with the values of `FILTERED_QUERY_PARAMS` redacted.
+struct FilteredQueryString<'a>(&'a str);
+
+impl Display for FilteredQueryString<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, pair) in self.0.split('&').enumerate() {
+            if i > 0 {
+                f.write_str("&")?;
+            }
+            let key = pair.split('=').next().unwrap_or_default();
+            if FILTERED_QUERY_PARAMS.contains(&key) {
+                write!(f, "{}=[FILTERED]", key)?;
+            } else {
+                f.write_str(pair)?;
+            }
         }
         Ok(())
     }
@@ -346,3 +369,18 @@ impl<'f, 'g> LogLine<'f, 'g> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::FilteredQueryString;
+
+    #[test]
+    fn credentials_are_filtered_from_query_strings() {
+        let filter = |q_string| FilteredQueryString(q_string).to_string();
+        assert_eq!(filter("page=2&per_page=10"), "page=2&per_page=10");
+        assert_eq!(filter("token=secret"), "token=[FILTERED]");
+        assert_eq!(filter("a=1&token=secret&b"), "a=1&token=[FILTERED]&b");
+        assert_eq!(filter("token"), "token=[FILTERED]");
+        assert_eq!(filter("tokens=1"), "tokens=1");
+    }
+}
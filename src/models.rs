@@ -1,35 +1,55 @@
-pub use self::action::{insert_version_owner_action, VersionAction, VersionOwnerAction};
+pub use self::action::{
+    insert_crate_owner_action, insert_version_owner_action, CrateAction, CrateOwnerAction,
+    VersionAction, VersionOwnerAction,
+};
+pub use self::advisory::{Advisory, NewAdvisory};
 pub use self::badge::{Badge, CrateBadge, MaintenanceStatus};
 pub use self::category::{Category, CrateCategory, NewCategory};
 pub use self::crate_owner_invitation::{CrateOwnerInvitation, NewCrateOwnerInvitationOutcome};
-pub use self::dependency::{Dependency, DependencyKind, ReverseDependency};
-pub use self::download::VersionDownload;
+pub use self::crate_ownership_transfer::CrateOwnershipTransfer;
+pub use self::dependency::{Dependency, DependencyKind, Dependent, ReverseDependency};
+pub use self::download::{
+    ClientClass, Granularity, VersionDownload, VersionDownloadByClient, VersionDownloadRollup,
+};
 pub use self::email::{Email, NewEmail};
+pub use self::feed_token::FeedToken;
 pub use self::follow::Follow;
 pub use self::keyword::{CrateKeyword, Keyword};
 pub use self::krate::{Crate, CrateVersions, NewCrate, RecentCrateDownloads};
+pub use self::notification_preferences::{
+    CrateNotificationPreferencesChanges, NotificationCategory, NotificationPreferences,
+    NotificationPreferencesChanges, Subscription,
+};
 pub use self::owner::{CrateOwner, Owner, OwnerKind};
 pub use self::rights::Rights;
-pub use self::team::{NewTeam, Team};
+pub use self::team::{NewTeam, Team, TeamMembership};
 pub use self::token::{ApiToken, CreatedApiToken};
 pub use self::user::{NewUser, User};
-pub use self::version::{NewVersion, TopVersions, Version};
+pub use self::version::{valid_rust_version, NewVersion, TopVersions, Version};
+pub use self::webhook::{
+    resolve_webhook_url, NewWebhook, Webhook, WebhookDelivery, WEBHOOK_EVENTS,
+};
 
 pub mod helpers;
 
 mod action;
+mod advisory;
 mod badge;
 pub mod category;
 mod crate_owner_invitation;
+mod crate_ownership_transfer;
 pub mod dependency;
 mod download;
 mod email;
+mod feed_token;
 mod follow;
 mod keyword;
 pub mod krate;
+mod notification_preferences;
 mod owner;
 mod rights;
 mod team;
 mod token;
 pub mod user;
 mod version;
+mod webhook;
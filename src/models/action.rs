@@ -6,10 +6,14 @@ use diesel::{
     serialize::{self, Output, ToSql},
     sql_types::Integer,
 };
+use std::collections::HashMap;
 use std::io::Write;
+use swirl::Job;
 
-use crate::models::{ApiToken, User, Version};
+use crate::models::{ApiToken, Crate, Owner, OwnerKind, Rights, Team, User, Version, Webhook};
 use crate::schema::*;
+use crate::tasks;
+use crate::util::errors::AppResult;
 
 #[derive(Debug, Clone, Copy, PartialEq, FromSqlRow, AsExpression)]
 #[repr(i32)]
@@ -102,15 +106,183 @@ pub fn insert_version_owner_action(
     user_id_: i32,
     api_token_id_: Option<i32>,
     action_: VersionAction,
-) -> QueryResult<VersionOwnerAction> {
+) -> AppResult<VersionOwnerAction> {
     use version_owner_actions::dsl::{action, api_token_id, user_id, version_id};
 
-    diesel::insert_into(version_owner_actions::table)
+    let inserted: VersionOwnerAction = diesel::insert_into(version_owner_actions::table)
         .values((
             version_id.eq(version_id_),
             user_id.eq(user_id_),
             api_token_id.eq(api_token_id_),
             action.eq(action_),
         ))
-        .get_result(conn)
+        .get_result(conn)?;
+
+    Webhook::trigger_version_event(conn, version_id_, user_id_, action_)?;
+    if action_ != VersionAction::Unyank {
+        tasks::send_version_notifications(inserted.id).enqueue(conn)?;
+    }
+
+    Ok(inserted)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, FromSqlRow, AsExpression)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+pub enum CrateAction {
+    TransferRequest = 0,
+    TransferCancel = 1,
+    TransferAccept = 2,
+    Invite = 3,
+    InviteAccept = 4,
+    InviteDecline = 5,
+    Remove = 6,
+    TeamAdd = 7,
+    TeamRightsChange = 8,
+}
+
+impl From<CrateAction> for &'static str {
+    fn from(action: CrateAction) -> Self {
+        match action {
+            CrateAction::TransferRequest => "transfer_request",
+            CrateAction::TransferCancel => "transfer_cancel",
+            CrateAction::TransferAccept => "transfer_accept",
+            CrateAction::Invite => "invite",
+            CrateAction::InviteAccept => "invite_accept",
+            CrateAction::InviteDecline => "invite_decline",
+            CrateAction::Remove => "remove",
+            CrateAction::TeamAdd => "team_add",
+            CrateAction::TeamRightsChange => "team_rights_change",
+        }
+    }
+}
+
+impl From<CrateAction> for String {
+    fn from(action: CrateAction) -> Self {
+        let string: &'static str = action.into();
+
+        string.into()
+    }
+}
+
+impl FromSql<Integer, Pg> for CrateAction {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(CrateAction::TransferRequest),
+            1 => Ok(CrateAction::TransferCancel),
+            2 => Ok(CrateAction::TransferAccept),
+            3 => Ok(CrateAction::Invite),
+            4 => Ok(CrateAction::InviteAccept),
+            5 => Ok(CrateAction::InviteDecline),
+            6 => Ok(CrateAction::Remove),
+            7 => Ok(CrateAction::TeamAdd),
+            8 => Ok(CrateAction::TeamRightsChange),
+            n => Err(format!("unknown crate action: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for CrateAction {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[belongs_to(Crate)]
+#[belongs_to(User, foreign_key = "user_id")]
+#[belongs_to(ApiToken, foreign_key = "api_token_id")]
+#[table_name = "crate_owner_actions"]
+pub struct CrateOwnerAction {
+    pub id: i32,
+    pub crate_id: i32,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    pub action: CrateAction,
+    pub time: NaiveDateTime,
+    /// The user or team who was invited, added or removed, if it isn't the acting user.
+    pub target_id: Option<i32>,
+    pub target_kind: Option<i32>,
+    /// The rights given to the team which was added or whose rights were changed.
+    pub rights: Option<Rights>,
+}
+
+impl CrateOwnerAction {
+    /// Returns the actions on the owners of a crate, along with the user who performed each of
+    /// them and the owner it applied to, if any.
+    pub fn by_crate(
+        conn: &PgConnection,
+        krate: &Crate,
+    ) -> QueryResult<Vec<(Self, User, Option<Owner>)>> {
+        let actions: Vec<(Self, User)> = Self::belonging_to(krate)
+            .inner_join(users::table)
+            .order(crate_owner_actions::dsl::id)
+            .load(conn)?;
+
+        let target_ids = |kind: OwnerKind| {
+            actions
+                .iter()
+                .filter(|(action, _)| action.target_kind == Some(kind as i32))
+                .filter_map(|(action, _)| action.target_id)
+                .collect::<Vec<_>>()
+        };
+        let target_users: HashMap<i32, User> = users::table
+            .filter(users::id.eq_any(target_ids(OwnerKind::User)))
+            .load::<User>(conn)?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect();
+        let target_teams: HashMap<i32, Team> = teams::table
+            .filter(teams::id.eq_any(target_ids(OwnerKind::Team)))
+            .load::<Team>(conn)?
+            .into_iter()
+            .map(|team| (team.id, team))
+            .collect();
+
+        Ok(actions
+            .into_iter()
+            .map(|(action, user)| {
+                let target = match (action.target_kind, action.target_id) {
+                    (Some(kind), Some(id)) if kind == OwnerKind::User as i32 => {
+                        target_users.get(&id).cloned().map(Owner::User)
+                    }
+                    (Some(kind), Some(id)) if kind == OwnerKind::Team as i32 => {
+                        target_teams.get(&id).cloned().map(Owner::Team)
+                    }
+                    _ => None,
+                };
+                (action, user, target)
+            })
+            .collect())
+    }
+}
+
+pub fn insert_crate_owner_action(
+    conn: &PgConnection,
+    crate_id_: i32,
+    user_id_: i32,
+    api_token_id_: Option<i32>,
+    action_: CrateAction,
+    target: Option<&Owner>,
+    rights_: Option<Rights>,
+) -> AppResult<CrateOwnerAction> {
+    use crate_owner_actions::dsl::{
+        action, api_token_id, crate_id, rights, target_id, target_kind, user_id,
+    };
+
+    let inserted = diesel::insert_into(crate_owner_actions::table)
+        .values((
+            crate_id.eq(crate_id_),
+            user_id.eq(user_id_),
+            api_token_id.eq(api_token_id_),
+            action.eq(action_),
+            target_id.eq(target.map(Owner::id)),
+            target_kind.eq(target.map(Owner::kind)),
+            rights.eq(rights_),
+        ))
+        .get_result(conn)?;
+
+    Webhook::trigger_owner_event(conn, crate_id_, user_id_, action_)?;
+
+    Ok(inserted)
 }
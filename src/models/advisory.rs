@@ -0,0 +1,154 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+
+use crate::models::{Crate, Version};
+use crate::schema::advisories;
+
+/// A security advisory imported from a RustSec advisory database by the `sync_advisories`
+/// background job.
+#[derive(Clone, Identifiable, Queryable, Associations, Debug)]
+#[belongs_to(Crate)]
+#[table_name = "advisories"]
+pub struct Advisory {
+    pub id: String,
+    pub crate_id: i32,
+    pub title: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub date: NaiveDate,
+    /// The kind of informational advisory (e.g. `unmaintained`), or `None` for vulnerabilities.
+    pub informational: Option<String>,
+    pub aliases: Vec<String>,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+    pub withdrawn: Option<NaiveDate>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Debug, Clone, PartialEq)]
+#[table_name = "advisories"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewAdvisory {
+    pub id: String,
+    pub crate_id: i32,
+    pub title: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub date: NaiveDate,
+    pub informational: Option<String>,
+    pub aliases: Vec<String>,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+    pub withdrawn: Option<NaiveDate>,
+}
+
+impl Advisory {
+    /// Returns the advisories of a crate which weren't withdrawn, most recent first.
+    pub fn by_crate(conn: &PgConnection, krate: &Crate) -> QueryResult<Vec<Self>> {
+        Self::belonging_to(krate)
+            .filter(advisories::withdrawn.is_null())
+            .order((advisories::date.desc(), advisories::id.desc()))
+            .load(conn)
+    }
+
+    /// Returns the IDs of the advisories affecting a version.
+    pub fn for_version(conn: &PgConnection, version: &Version) -> QueryResult<Vec<String>> {
+        Ok(Self::for_versions(conn, std::slice::from_ref(version))?
+            .pop()
+            .unwrap_or_default())
+    }
+
+    /// Returns the IDs of the advisories affecting each version.
+    pub fn for_versions(
+        conn: &PgConnection,
+        versions: &[Version],
+    ) -> QueryResult<Vec<Vec<String>>> {
+        let mut crate_ids = versions.iter().map(|v| v.crate_id).collect::<Vec<_>>();
+        crate_ids.sort_unstable();
+        crate_ids.dedup();
+
+        let crate_advisories: Vec<Self> = advisories::table
+            .filter(advisories::crate_id.eq_any(crate_ids))
+            .filter(advisories::withdrawn.is_null())
+            .order(advisories::id)
+            .load(conn)?;
+
+        Ok(versions
+            .iter()
+            .map(|version| {
+                crate_advisories
+                    .iter()
+                    .filter(|advisory| advisory.crate_id == version.crate_id)
+                    .filter(|advisory| advisory.affects(&version.num))
+                    .map(|advisory| advisory.id.clone())
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Whether the version is affected, meaning that it matches neither the `patched` nor the
+    /// `unaffected` requirements. Withdrawn advisories don't affect any version.
+    pub fn affects(&self, version: &semver::Version) -> bool {
+        self.withdrawn.is_none()
+            && !self
+                .patched
+                .iter()
+                .chain(&self.unaffected)
+                .filter_map(|req| semver::VersionReq::parse(req).ok())
+                .any(|req| req.matches(version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advisory(patched: &[&str], unaffected: &[&str]) -> Advisory {
+        let date = NaiveDate::from_ymd(2021, 1, 1);
+        Advisory {
+            id: "RUSTSEC-2021-0001".into(),
+            crate_id: 1,
+            title: "Memory corruption".into(),
+            description: String::new(),
+            url: None,
+            date,
+            informational: None,
+            aliases: Vec::new(),
+            patched: patched.iter().map(|&req| req.into()).collect(),
+            unaffected: unaffected.iter().map(|&req| req.into()).collect(),
+            withdrawn: None,
+            created_at: date.and_hms(0, 0, 0),
+            updated_at: date.and_hms(0, 0, 0),
+        }
+    }
+
+    fn affects(advisory: &Advisory, version: &str) -> bool {
+        advisory.affects(&semver::Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn versions_matching_patched_or_unaffected_are_not_affected() {
+        let advisory = advisory(&[">= 1.2.3, < 2.0.0", ">= 2.0.1"], &["< 1.0.0"]);
+        assert!(!affects(&advisory, "0.9.0"));
+        assert!(affects(&advisory, "1.0.0"));
+        assert!(affects(&advisory, "1.2.2"));
+        assert!(!affects(&advisory, "1.2.3"));
+        assert!(affects(&advisory, "2.0.0"));
+        assert!(!affects(&advisory, "2.0.1"));
+    }
+
+    #[test]
+    fn advisories_without_patches_affect_all_versions() {
+        let advisory = advisory(&[], &[]);
+        assert!(affects(&advisory, "0.1.0"));
+        assert!(affects(&advisory, "3.0.0"));
+    }
+
+    #[test]
+    fn withdrawn_advisories_affect_no_version() {
+        let mut advisory = advisory(&[], &[]);
+        advisory.withdrawn = Some(NaiveDate::from_ymd(2021, 2, 1));
+        assert!(!affects(&advisory, "1.0.0"));
+    }
+}
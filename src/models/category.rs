@@ -100,18 +100,45 @@ impl Category {
         limit: i64,
         offset: i64,
     ) -> QueryResult<Vec<Category>> {
-        use diesel::sql_types::Int8;
+        Self::toplevel_query(conn, sort, limit, offset, None)
+    }
+
+    /// Returns the top-level categories sorted after `seek`, which holds the crates count and
+    /// name of the last category of the previous page.
+    pub fn toplevel_after(
+        conn: &PgConnection,
+        sort: &str,
+        limit: i64,
+        seek: (i32, &str),
+    ) -> QueryResult<Vec<Category>> {
+        Self::toplevel_query(conn, sort, limit, 0, Some(seek))
+    }
 
-        let sort_sql = match sort {
-            "crates" => "ORDER BY crates_cnt DESC",
-            _ => "ORDER BY category ASC",
+    fn toplevel_query(
+        conn: &PgConnection,
+        sort: &str,
+        limit: i64,
+        offset: i64,
+        seek: Option<(i32, &str)>,
+    ) -> QueryResult<Vec<Category>> {
+        use diesel::sql_types::{Int4, Int8, Nullable, Text};
+
+        let (sort_sql, seek_sql) = match sort {
+            "crates" => (
+                "ORDER BY crates_cnt DESC, category ASC",
+                "WHERE crates_cnt < $3 OR (crates_cnt = $3 AND category > $4)",
+            ),
+            _ => ("ORDER BY category ASC", "WHERE category > $4"),
         };
+        let seek_sql = if seek.is_some() { seek_sql } else { "" };
 
         // Collect all the top-level categories and sum up the crates_cnt of
         // the crates in all subcategories
-        sql_query(format!(include_str!("toplevel.sql"), sort_sql))
+        sql_query(format!(include_str!("toplevel.sql"), seek_sql, sort_sql))
             .bind::<Int8, _>(limit)
             .bind::<Int8, _>(offset)
+            .bind::<Nullable<Int4>, _>(seek.map(|(crates_cnt, _)| crates_cnt))
+            .bind::<Nullable<Text>, _>(seek.map(|(_, category)| category))
             .load(conn)
     }
 
@@ -251,6 +278,30 @@ mod tests {
         assert_eq!(expected, cats);
     }
 
+    #[test]
+    fn category_toplevel_seeks_after_the_last_category() {
+        use self::categories::dsl::*;
+        let conn = pg_connection();
+        insert_into(categories)
+            .values(&vec![
+                (category.eq("Cat 1"), slug.eq("cat1"), crates_cnt.eq(1)),
+                (category.eq("Cat 2"), slug.eq("cat2"), crates_cnt.eq(2)),
+                (category.eq("Cat 3"), slug.eq("cat3"), crates_cnt.eq(1)),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let names = |cats: Vec<Category>| cats.into_iter().map(|c| c.category).collect::<Vec<_>>();
+
+        let cats = Category::toplevel_after(&conn, "", 10, (0, "Cat 1")).unwrap();
+        assert_eq!(names(cats), vec!["Cat 2", "Cat 3"]);
+
+        let cats = Category::toplevel_after(&conn, "crates", 10, (2, "Cat 2")).unwrap();
+        assert_eq!(names(cats), vec!["Cat 1", "Cat 3"]);
+        let cats = Category::toplevel_after(&conn, "crates", 10, (1, "Cat 1")).unwrap();
+        assert_eq!(names(cats), vec!["Cat 3"]);
+    }
+
     #[test]
     fn category_toplevel_includes_subcategories_in_crate_cnt() {
         use self::categories::dsl::*;
@@ -1,8 +1,9 @@
 use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::{now, IntervalDsl};
 use diesel::prelude::*;
 
 use crate::config::Config;
-use crate::models::{CrateOwner, OwnerKind};
+use crate::models::{insert_crate_owner_action, CrateAction, CrateOwner, OwnerKind, Rights};
 use crate::schema::{crate_owner_invitations, crate_owners, crates};
 use crate::util::errors::{AppResult, OwnershipInvitationExpired};
 
@@ -93,7 +94,20 @@ impl CrateOwnerInvitation {
             .first::<Self>(&*conn)?)
     }
 
-    pub fn accept(self, conn: &PgConnection, config: &Config) -> AppResult<()> {
+    /// Returns all the invitations created more than `expiration_days` ago, which can't be accepted
+    /// anymore.
+    pub fn find_expired(conn: &PgConnection, expiration_days: i32) -> QueryResult<Vec<Self>> {
+        crate_owner_invitations::table
+            .filter(crate_owner_invitations::created_at.le(now - expiration_days.days()))
+            .load(conn)
+    }
+
+    pub fn accept(
+        self,
+        conn: &PgConnection,
+        config: &Config,
+        api_token_id: Option<i32>,
+    ) -> AppResult<()> {
         if self.is_expired(config) {
             let crate_name = crates::table
                 .find(self.crate_id)
@@ -110,6 +124,7 @@ impl CrateOwnerInvitation {
                     created_by: self.invited_by_user_id,
                     owner_kind: OwnerKind::User as i32,
                     email_notifications: true,
+                    rights: Rights::FULL,
                 })
                 .on_conflict(crate_owners::table.primary_key())
                 .do_update()
@@ -118,17 +133,40 @@ impl CrateOwnerInvitation {
 
             diesel::delete(&self).execute(conn)?;
 
+            insert_crate_owner_action(
+                conn,
+                self.crate_id,
+                self.invited_user_id,
+                api_token_id,
+                CrateAction::InviteAccept,
+                None,
+                None,
+            )?;
+
             Ok(())
         })
     }
 
-    pub fn decline(self, conn: &PgConnection) -> AppResult<()> {
+    pub fn decline(self, conn: &PgConnection, api_token_id: Option<i32>) -> AppResult<()> {
         // The check to prevent declining expired invitations is *explicitly* missing. We do not
         // care if an expired invitation is declined, as that just removes the invitation from the
         // database.
 
-        diesel::delete(&self).execute(conn)?;
-        Ok(())
+        conn.transaction(|| {
+            diesel::delete(&self).execute(conn)?;
+
+            insert_crate_owner_action(
+                conn,
+                self.crate_id,
+                self.invited_user_id,
+                api_token_id,
+                CrateAction::InviteDecline,
+                None,
+                None,
+            )?;
+
+            Ok(())
+        })
     }
 
     pub fn is_expired(&self, config: &Config) -> bool {
@@ -0,0 +1,238 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use crate::app::App;
+use crate::config::Config;
+use crate::models::{
+    insert_crate_owner_action, CrateAction, CrateOwner, Owner, OwnerKind, Rights, Team, User,
+};
+use crate::schema::{crate_owners, crate_ownership_transfers, crates, teams};
+use crate::util::errors::{bad_request, AppResult, OwnershipTransferExpired};
+
+/// The model representing a row in the `crate_ownership_transfers` database table.
+///
+/// A crate has at most one pending transfer. Once the recipient accepts it, they become the
+/// owner of the crate and all previous owners are removed.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+#[primary_key(crate_id)]
+pub struct CrateOwnershipTransfer {
+    pub crate_id: i32,
+    pub requested_by_user_id: i32,
+    pub recipient_id: i32,
+    pub recipient_kind: i32,
+    pub created_at: NaiveDateTime,
+    pub token: String,
+}
+
+impl CrateOwnershipTransfer {
+    /// Requests the transfer of a crate to `recipient`, replacing any pending transfer of the
+    /// same crate. Returns the plaintext token needed to accept the transfer.
+    pub fn create(
+        crate_id: i32,
+        requested_by: &User,
+        api_token_id: Option<i32>,
+        recipient: &Owner,
+        conn: &PgConnection,
+    ) -> AppResult<String> {
+        #[derive(Insertable, Clone, Copy, Debug)]
+        #[table_name = "crate_ownership_transfers"]
+        struct NewRecord {
+            crate_id: i32,
+            requested_by_user_id: i32,
+            recipient_id: i32,
+            recipient_kind: i32,
+        }
+
+        conn.transaction(|| {
+            diesel::delete(crate_ownership_transfers::table.find(crate_id)).execute(conn)?;
+
+            let token = diesel::insert_into(crate_ownership_transfers::table)
+                .values(&NewRecord {
+                    crate_id,
+                    requested_by_user_id: requested_by.id,
+                    recipient_id: recipient.id(),
+                    recipient_kind: recipient.kind(),
+                })
+                .returning(crate_ownership_transfers::token)
+                .get_result(conn)?;
+
+            insert_crate_owner_action(
+                conn,
+                crate_id,
+                requested_by.id,
+                api_token_id,
+                CrateAction::TransferRequest,
+                Some(recipient),
+                None,
+            )?;
+
+            Ok(token)
+        })
+    }
+
+    pub fn find_by_crate_id(crate_id: i32, conn: &PgConnection) -> AppResult<Self> {
+        Ok(crate_ownership_transfers::table
+            .find(crate_id)
+            .first::<Self>(&*conn)?)
+    }
+
+    pub fn find_by_token(token: &str, conn: &PgConnection) -> AppResult<Self> {
+        Ok(crate_ownership_transfers::table
+            .filter(crate_ownership_transfers::token.eq(token))
+            .first::<Self>(&*conn)?)
+    }
+
+    /// Accepts the transfer on behalf of `user`, who must either be the recipient or, if the
+    /// crate is transferred to a team, a member of that team.
+    ///
+    /// All previous owners are removed in the same transaction. A team can't manage the owners of
+    /// a crate, so the accepting team member is added as an individual owner as well.
+    pub fn accept(
+        self,
+        app: &App,
+        conn: &PgConnection,
+        user: &User,
+        api_token_id: Option<i32>,
+    ) -> AppResult<()> {
+        if self.is_expired(&app.config) {
+            let crate_name = crates::table
+                .find(self.crate_id)
+                .select(crates::name)
+                .first(conn)?;
+            return Err(Box::new(OwnershipTransferExpired { crate_name }));
+        }
+
+        let is_recipient = match self.recipient_kind() {
+            OwnerKind::User => self.recipient_id == user.id,
+            OwnerKind::Team => {
+                let team: Team = teams::table.find(self.recipient_id).first(conn)?;
+                team.contains_user(app, conn, user)?
+            }
+        };
+        if !is_recipient {
+            return Err(bad_request(
+                "this ownership transfer is addressed to another user or team",
+            ));
+        }
+
+        conn.transaction(|| {
+            // The transfer is only valid as long as the requesting user still owns the crate
+            let requester_is_owner = crate_owners::table
+                .find((
+                    self.crate_id,
+                    self.requested_by_user_id,
+                    OwnerKind::User as i32,
+                ))
+                .filter(crate_owners::deleted.eq(false))
+                .select(crate_owners::owner_id)
+                .first::<i32>(conn)
+                .optional()?
+                .is_some();
+            if !requester_is_owner {
+                return Err(bad_request(
+                    "the user who requested this transfer is no longer an owner of the crate",
+                ));
+            }
+
+            diesel::update(crate_owners::table.filter(crate_owners::crate_id.eq(self.crate_id)))
+                .set(crate_owners::deleted.eq(true))
+                .execute(conn)?;
+
+            match self.recipient_kind() {
+                OwnerKind::User => {
+                    self.add_owner(self.recipient_id, OwnerKind::User, Rights::FULL, conn)?;
+                }
+                OwnerKind::Team => {
+                    self.add_owner(
+                        self.recipient_id,
+                        OwnerKind::Team,
+                        Rights::PUBLISH | Rights::YANK,
+                        conn,
+                    )?;
+                    self.add_owner(user.id, OwnerKind::User, Rights::FULL, conn)?;
+                }
+            }
+
+            diesel::delete(&self).execute(conn)?;
+
+            insert_crate_owner_action(
+                conn,
+                self.crate_id,
+                user.id,
+                api_token_id,
+                CrateAction::TransferAccept,
+                None,
+                None,
+            )?;
+
+            Ok(())
+        })
+    }
+
+    pub fn cancel(
+        self,
+        conn: &PgConnection,
+        user_id: i32,
+        api_token_id: Option<i32>,
+    ) -> AppResult<()> {
+        conn.transaction(|| {
+            diesel::delete(&self).execute(conn)?;
+
+            insert_crate_owner_action(
+                conn,
+                self.crate_id,
+                user_id,
+                api_token_id,
+                CrateAction::TransferCancel,
+                None,
+                None,
+            )?;
+
+            Ok(())
+        })
+    }
+
+    pub fn is_expired(&self, config: &Config) -> bool {
+        self.expires_at(config) <= Utc::now().naive_utc()
+    }
+
+    pub fn expires_at(&self, config: &Config) -> NaiveDateTime {
+        let days = chrono::Duration::days(config.ownership_invitations_expiration_days as i64);
+        self.created_at + days
+    }
+
+    fn recipient_kind(&self) -> OwnerKind {
+        if self.recipient_kind == OwnerKind::Team as i32 {
+            OwnerKind::Team
+        } else {
+            OwnerKind::User
+        }
+    }
+
+    fn add_owner(
+        &self,
+        owner_id: i32,
+        owner_kind: OwnerKind,
+        rights: Rights,
+        conn: &PgConnection,
+    ) -> QueryResult<()> {
+        diesel::insert_into(crate_owners::table)
+            .values(&CrateOwner {
+                crate_id: self.crate_id,
+                owner_id,
+                created_by: self.requested_by_user_id,
+                owner_kind: owner_kind as i32,
+                email_notifications: true,
+                rights,
+            })
+            .on_conflict(crate_owners::table.primary_key())
+            .do_update()
+            .set((
+                crate_owners::deleted.eq(false),
+                crate_owners::rights.eq(rights),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -32,7 +32,21 @@ pub struct ReverseDependency {
     pub name: String,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug, FromSqlRow)]
+/// How a version of another crate depends on a crate.
+#[derive(Debug, QueryableByName)]
+pub struct Dependent {
+    /// The crate of the dependent version.
+    #[sql_type = "::diesel::sql_types::Integer"]
+    pub crate_id: i32,
+    #[sql_type = "::diesel::sql_types::Text"]
+    pub req: String,
+    #[sql_type = "::diesel::sql_types::Integer"]
+    pub kind: DependencyKind,
+    #[sql_type = "::diesel::sql_types::Bool"]
+    pub optional: bool,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, FromSqlRow)]
 #[serde(rename_all = "lowercase")]
 #[repr(u32)]
 pub enum DependencyKind {
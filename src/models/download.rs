@@ -1,6 +1,8 @@
 use crate::models::Version;
-use crate::schema::version_downloads;
-use chrono::NaiveDate;
+use crate::schema::{version_downloads, version_downloads_by_client, version_downloads_rollups};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Queryable, Identifiable, Associations, Debug, Clone, Copy)]
 #[belongs_to(Version)]
@@ -12,3 +14,189 @@ pub struct VersionDownload {
     pub date: NaiveDate,
     pub processed: bool,
 }
+
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Version)]
+#[primary_key(version_id, date, client)]
+#[table_name = "version_downloads_by_client"]
+pub struct VersionDownloadByClient {
+    pub version_id: i32,
+    pub date: NaiveDate,
+    pub client: String,
+    pub downloads: i32,
+}
+
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Version)]
+#[primary_key(version_id, granularity, period)]
+pub struct VersionDownloadRollup {
+    pub version_id: i32,
+    pub granularity: String,
+    /// First day of the week or month.
+    pub period: NaiveDate,
+    pub downloads: i32,
+}
+
+/// The periods download counts can be grouped by. Daily counts are stored in `version_downloads`,
+/// while weekly and monthly ones are rolled up in `version_downloads_rollups` by the
+/// `update_downloads` task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+}
+
+impl Granularity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+        }
+    }
+
+    /// Returns the first day of the period containing `date`. Weeks start on Monday, like in
+    /// PostgreSQL's `date_trunc`.
+    pub fn period_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => date,
+            Granularity::Week => {
+                date - Duration::days(date.weekday().num_days_from_monday().into())
+            }
+            Granularity::Month => NaiveDate::from_ymd(date.year(), date.month(), 1),
+        }
+    }
+}
+
+impl FromStr for Granularity {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Granularity::Day),
+            "week" => Ok(Granularity::Week),
+            "month" => Ok(Granularity::Month),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Known tools other than cargo, identified by a prefix of their `User-Agent` header.
+const KNOWN_TOOLS: &[(&str, &str)] = &[
+    ("curl/", "curl"),
+    ("Wget/", "wget"),
+    ("python-requests/", "python-requests"),
+    ("Go-http-client/", "go-http-client"),
+    ("Mozilla/", "browser"),
+];
+
+/// The kind of client that downloaded a crate, as reported by its `User-Agent` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ClientClass {
+    /// Cargo, along with its major and minor version.
+    Cargo(u64, u64),
+    /// One of the `KNOWN_TOOLS`.
+    Tool(&'static str),
+    /// Crawlers, or any client we don't recognize, including Cargo versions which don't exist.
+    Other,
+}
+
+/// The highest minor version of Cargo 1.x which is recorded as such. Clients claiming to be a
+/// later (or another major) version are recorded as `ClientClass::Other`, so that made up
+/// `User-Agent` headers can't add an unbounded number of clients to `version_downloads_by_client`.
+const MAX_CARGO_MINOR: u64 = 99;
+
+impl ClientClass {
+    pub fn from_user_agent(user_agent: &str) -> Self {
+        let lowercase = user_agent.to_lowercase();
+        if ["bot", "crawler", "spider"]
+            .iter()
+            .any(|bot| lowercase.contains(bot))
+        {
+            return ClientClass::Other;
+        }
+
+        // Cargo identifies itself as `cargo 1.50.0 (f04e7fab7 2021-02-04)`
+        if let Some(version) = user_agent.strip_prefix("cargo ") {
+            let mut parts = version.split(|c: char| !c.is_ascii_digit());
+            return match (parts.next(), parts.next()) {
+                (Some(major), Some(minor)) => match (major.parse(), minor.parse()) {
+                    (Ok(1), Ok(minor)) if minor <= MAX_CARGO_MINOR => ClientClass::Cargo(1, minor),
+                    _ => ClientClass::Other,
+                },
+                _ => ClientClass::Other,
+            };
+        }
+
+        KNOWN_TOOLS
+            .iter()
+            .find(|(prefix, _)| user_agent.starts_with(prefix))
+            .map(|(_, name)| ClientClass::Tool(name))
+            .unwrap_or(ClientClass::Other)
+    }
+}
+
+/// The representation stored in the `client` column of `version_downloads_by_client`.
+impl fmt::Display for ClientClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientClass::Cargo(major, minor) => write!(f, "cargo {}.{}", major, minor),
+            ClientClass::Tool(name) => f.write_str(name),
+            ClientClass::Other => f.write_str("other"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientClass, Granularity};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn period_start() {
+        // 2021-03-10 is a Wednesday
+        let date = NaiveDate::from_ymd(2021, 3, 10);
+        assert_eq!(Granularity::Day.period_start(date), date);
+        assert_eq!(
+            Granularity::Week.period_start(date),
+            NaiveDate::from_ymd(2021, 3, 8)
+        );
+        assert_eq!(
+            Granularity::Week.period_start(NaiveDate::from_ymd(2021, 3, 1)),
+            NaiveDate::from_ymd(2021, 3, 1)
+        );
+        assert_eq!(
+            Granularity::Month.period_start(date),
+            NaiveDate::from_ymd(2021, 3, 1)
+        );
+    }
+
+    #[test]
+    fn classify_user_agents() {
+        let classify = |user_agent| ClientClass::from_user_agent(user_agent).to_string();
+
+        assert_eq!(
+            classify("cargo 1.50.0 (f04e7fab7 2021-02-04)"),
+            "cargo 1.50"
+        );
+        assert_eq!(
+            classify("cargo 1.52.0-nightly (a73e5b7d5 2021-03-05)"),
+            "cargo 1.52"
+        );
+        assert_eq!(classify("cargo"), "other");
+        assert_eq!(classify("cargo 1.100.0"), "other");
+        assert_eq!(classify("cargo 2.0.0"), "other");
+        assert_eq!(classify("cargo 18446744073709551615.1"), "other");
+        assert_eq!(classify("curl/7.64.1"), "curl");
+        assert_eq!(
+            classify("Mozilla/5.0 (X11; Linux x86_64; rv:86.0) Gecko/20100101 Firefox/86.0"),
+            "browser"
+        );
+        assert_eq!(
+            classify("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"),
+            "other"
+        );
+        assert_eq!(classify(""), "other");
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::{feed_tokens, users};
+use crate::util::token::{SecureToken, SecureTokenKind};
+
+/// A token which only gives access to the Atom feed of the crates followed by a user, so that it
+/// can be given to feed readers. Each user has at most one feed token.
+#[derive(Debug, Identifiable, Queryable, Associations)]
+#[belongs_to(User)]
+#[primary_key(user_id)]
+pub struct FeedToken {
+    pub user_id: i32,
+    token: SecureToken,
+    pub created_at: NaiveDateTime,
+}
+
+impl FeedToken {
+    /// Generates a new feed token for a user, replacing the previous one. Returns the plaintext
+    /// of the token, which is not stored.
+    pub fn regenerate(conn: &PgConnection, user_id: i32) -> QueryResult<String> {
+        let token = SecureToken::generate(SecureTokenKind::Feed);
+        diesel::insert_into(feed_tokens::table)
+            .values((
+                feed_tokens::user_id.eq(user_id),
+                feed_tokens::token.eq(&*token),
+            ))
+            .on_conflict(feed_tokens::user_id)
+            .do_update()
+            .set((
+                feed_tokens::token.eq(&*token),
+                feed_tokens::created_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+        Ok(token.plaintext().into())
+    }
+
+    /// Revokes the feed token of a user, if any.
+    pub fn revoke(conn: &PgConnection, user_id: i32) -> QueryResult<()> {
+        diesel::delete(feed_tokens::table.find(user_id)).execute(conn)?;
+        Ok(())
+    }
+
+    /// Finds the user owning a feed token.
+    pub fn find_user(conn: &PgConnection, plaintext: &str) -> QueryResult<Option<User>> {
+        let token = match SecureToken::parse(SecureTokenKind::Feed, plaintext) {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        feed_tokens::table
+            .inner_join(users::table)
+            .filter(feed_tokens::token.eq(token))
+            .select(users::all_columns)
+            .first(conn)
+            .optional()
+    }
+}
@@ -9,8 +9,9 @@ use crate::app::App;
 use crate::controllers::helpers::pagination::*;
 use crate::models::version::TopVersions;
 use crate::models::{
-    Badge, CrateOwner, CrateOwnerInvitation, NewCrateOwnerInvitationOutcome, Owner, OwnerKind,
-    ReverseDependency, User, Version,
+    insert_crate_owner_action, Badge, CrateAction, CrateOwner, CrateOwnerInvitation,
+    DependencyKind, Dependent, NewCrateOwnerInvitationOutcome, NotificationCategory,
+    NotificationPreferences, Owner, OwnerKind, ReverseDependency, Rights, Team, User, Version,
 };
 use crate::util::errors::{cargo_err, AppResult};
 
@@ -186,6 +187,7 @@ impl<'a> NewCrate<'a> {
                     created_by: user_id,
                     owner_kind: OwnerKind::User as i32,
                     email_notifications: true,
+                    rights: Rights::FULL,
                 };
                 diesel::insert_into(crate_owners::table)
                     .values(&owner)
@@ -220,6 +222,53 @@ impl Crate {
         }
     }
 
+    /// SQL filter matching the crates whose name is similar to the given string, based on the
+    /// trigrams they have in common. This tolerates typos like `tokoi` for `tokio`.
+    pub fn fuzzy_matches_name<QS>(
+        name: &str,
+    ) -> Box<dyn BoxableExpression<QS, Pg, SqlType = Bool> + '_>
+    where
+        crates::name: SelectableExpression<QS>,
+    {
+        Box::new(IsSimilar::new(
+            canon_crate_name(crates::name),
+            canon_crate_name(name),
+        ))
+    }
+
+    /// Returns the name of the crate most similar to the given string, excluding the crate with
+    /// that exact name. This is used to suggest a crate when a search has few results.
+    pub fn most_similar_name(conn: &PgConnection, name: &str) -> QueryResult<Option<String>> {
+        crates::table
+            .select(crates::name)
+            .filter(Self::fuzzy_matches_name(name))
+            .filter(canon_crate_name(crates::name).ne(canon_crate_name(name)))
+            .order((
+                similarity(canon_crate_name(crates::name), canon_crate_name(name)).desc(),
+                crates::downloads.desc(),
+            ))
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns up to `limit` crate names to autocomplete a search for `query`.
+    pub fn suggestions(conn: &PgConnection, query: &str, limit: i64) -> QueryResult<Vec<String>> {
+        use diesel::sql_query;
+        use diesel::sql_types::BigInt;
+
+        #[derive(QueryableByName)]
+        struct Suggestion {
+            #[sql_type = "Text"]
+            name: String,
+        }
+
+        let suggestions: Vec<Suggestion> = sql_query(include_str!("krate_suggestions.sql"))
+            .bind::<Text, _>(query)
+            .bind::<BigInt, _>(limit)
+            .load(conn)?;
+        Ok(suggestions.into_iter().map(|s| s.name).collect())
+    }
+
     /// SQL filter with the = binary operator
     pub fn with_name(name: &str) -> WithName<'_> {
         canon_crate_name(crates::name).eq(canon_crate_name(name))
@@ -301,30 +350,84 @@ impl Crate {
     }
 
     pub fn owners(&self, conn: &PgConnection) -> QueryResult<Vec<Owner>> {
+        Ok(self
+            .owners_with_rights(conn)?
+            .into_iter()
+            .map(|(owner, _)| owner)
+            .collect())
+    }
+
+    /// Returns all owners of the crate together with the rights of each of them
+    pub fn owners_with_rights(&self, conn: &PgConnection) -> QueryResult<Vec<(Owner, Rights)>> {
         let users = CrateOwner::by_owner_kind(OwnerKind::User)
             .filter(crate_owners::crate_id.eq(self.id))
             .inner_join(users::table)
-            .select(users::all_columns)
-            .load(conn)?
+            .select((users::all_columns, crate_owners::rights))
+            .load::<(User, Rights)>(conn)?
             .into_iter()
-            .map(Owner::User);
+            .map(|(user, rights)| (Owner::User(user), rights));
         let teams = CrateOwner::by_owner_kind(OwnerKind::Team)
             .filter(crate_owners::crate_id.eq(self.id))
             .inner_join(teams::table)
-            .select(teams::all_columns)
-            .load(conn)?
+            .select((teams::all_columns, crate_owners::rights))
+            .load::<(Team, Rights)>(conn)?
             .into_iter()
-            .map(Owner::Team);
+            .map(|(team, rights)| (Owner::Team(team), rights));
 
         Ok(users.chain(teams).collect())
     }
 
+    /// Changes the rights of an existing team owner of the crate
+    pub fn set_owner_rights(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        req_user: &User,
+        login: &str,
+        rights: Rights,
+        api_token_id: Option<i32>,
+    ) -> AppResult<()> {
+        let owner = Owner::find_or_create_by_login(app, conn, req_user, login)?;
+        if let Owner::User(_) = owner {
+            return Err(cargo_err("only the rights of team owners can be changed"));
+        }
+        ensure_team_rights(rights)?;
+
+        let target = crate_owners::table
+            .find((self.id(), owner.id(), owner.kind()))
+            .filter(crate_owners::deleted.eq(false));
+        let updated = diesel::update(target)
+            .set(crate_owners::rights.eq(rights))
+            .execute(conn)?;
+        if updated == 0 {
+            return Err(cargo_err(&format_args!(
+                "`{}` is not an owner of crate `{}`",
+                owner.login(),
+                self.name
+            )));
+        }
+
+        insert_crate_owner_action(
+            conn,
+            self.id,
+            req_user.id,
+            api_token_id,
+            CrateAction::TeamRightsChange,
+            Some(&owner),
+            Some(rights),
+        )?;
+
+        Ok(())
+    }
+
     pub fn owner_add(
         &self,
         app: &App,
         conn: &PgConnection,
         req_user: &User,
         login: &str,
+        rights: Option<Rights>,
+        api_token_id: Option<i32>,
     ) -> AppResult<String> {
         use diesel::insert_into;
 
@@ -332,16 +435,40 @@ impl Crate {
 
         match owner {
             // Users are invited and must accept before being added
-            Owner::User(user) => {
+            Owner::User(ref user) => {
+                if rights.map_or(false, |rights| rights != Rights::FULL) {
+                    return Err(cargo_err("individual owners always have full rights"));
+                }
+
                 let config = &app.config;
                 match CrateOwnerInvitation::create(user.id, req_user.id, self.id, conn, config)? {
                     NewCrateOwnerInvitationOutcome::InviteCreated { plaintext_token } => {
-                        if let Ok(Some(email)) = user.verified_email(&conn) {
+                        insert_crate_owner_action(
+                            conn,
+                            self.id,
+                            req_user.id,
+                            api_token_id,
+                            CrateAction::Invite,
+                            Some(&owner),
+                            None,
+                        )?;
+
+                        let subscription = NotificationPreferences::subscription(
+                            conn,
+                            user.id,
+                            NotificationCategory::Ownership,
+                            None,
+                        )?;
+                        if let (Ok(Some(email)), Some(subscription)) =
+                            (user.verified_email(&conn), subscription)
+                        {
                             // Swallow any error. Whether or not the email is sent, the invitation
                             // entry will be created in the database and the user will see the
                             // invitation when they visit https://crates.io/me/pending-invites/.
                             let _ = app.emails.send_owner_invite(
+                                conn,
                                 &email,
+                                &subscription,
                                 &req_user.gh_login,
                                 &self.name,
                                 &plaintext_token,
@@ -361,6 +488,8 @@ impl Crate {
             }
             // Teams are added as owners immediately
             owner @ Owner::Team(_) => {
+                let rights = rights.unwrap_or(Rights::PUBLISH | Rights::YANK);
+                ensure_team_rights(rights)?;
                 insert_into(crate_owners::table)
                     .values(&CrateOwner {
                         crate_id: self.id,
@@ -368,12 +497,26 @@ impl Crate {
                         created_by: req_user.id,
                         owner_kind: OwnerKind::Team as i32,
                         email_notifications: true,
+                        rights,
                     })
                     .on_conflict(crate_owners::table.primary_key())
                     .do_update()
-                    .set(crate_owners::deleted.eq(false))
+                    .set((
+                        crate_owners::deleted.eq(false),
+                        crate_owners::rights.eq(rights),
+                    ))
                     .execute(conn)?;
 
+                insert_crate_owner_action(
+                    conn,
+                    self.id,
+                    req_user.id,
+                    api_token_id,
+                    CrateAction::TeamAdd,
+                    Some(&owner),
+                    Some(rights),
+                )?;
+
                 Ok(format!(
                     "team {} has been added as an owner of crate {}",
                     owner.login(),
@@ -389,13 +532,30 @@ impl Crate {
         conn: &PgConnection,
         req_user: &User,
         login: &str,
+        api_token_id: Option<i32>,
     ) -> AppResult<()> {
         let owner = Owner::find_or_create_by_login(app, conn, req_user, login)?;
 
-        let target = crate_owners::table.find((self.id(), owner.id(), owner.kind() as i32));
-        diesel::update(target)
+        let target = crate_owners::table
+            .find((self.id(), owner.id(), owner.kind() as i32))
+            .filter(crate_owners::deleted.eq(false));
+        let removed = diesel::update(target)
             .set(crate_owners::deleted.eq(true))
             .execute(conn)?;
+
+        // Only record the removal of owners who weren't already removed
+        if removed > 0 {
+            insert_crate_owner_action(
+                conn,
+                self.id,
+                req_user.id,
+                api_token_id,
+                CrateAction::Remove,
+                Some(&owner),
+                None,
+            )?;
+        }
+
         Ok(())
     }
 
@@ -406,29 +566,86 @@ impl Crate {
     }
 
     /// Returns (dependency, dependent crate name, dependent crate downloads)
+    ///
+    /// When using seek-based pagination, the cursor holds the downloads and name of the last
+    /// dependent crate of the previous page.
     pub(crate) fn reverse_dependencies(
         &self,
         conn: &PgConnection,
         options: PaginationOptions,
     ) -> AppResult<(Vec<ReverseDependency>, i64)> {
         use diesel::sql_query;
-        use diesel::sql_types::{BigInt, Integer};
+        use diesel::sql_types::{BigInt, Integer, Nullable, Text};
 
         let offset = options.offset().unwrap_or_default();
-        let rows: Vec<WithCount<ReverseDependency>> =
-            sql_query(include_str!("krate_reverse_dependencies.sql"))
-                .bind::<Integer, _>(self.id)
-                .bind::<BigInt, _>(i64::from(offset))
-                .bind::<BigInt, _>(i64::from(options.per_page))
-                .load(conn)?;
+        let seek = match options.seek() {
+            Some(seek) => seek.decode::<(i32, String)>()?,
+            None => None,
+        };
+        let (seek_downloads, seek_name) = match seek {
+            Some((downloads, name)) => (Some(downloads), Some(name)),
+            None => (None, None),
+        };
+        // Counting all the dependents would defeat the purpose of seeking, so the total is only
+        // computed when using numeric pagination.
+        let query = if options.seek().is_some() {
+            format!(
+                "SELECT *, 0::bigint AS total FROM ({}) t LIMIT $3",
+                include_str!("krate_reverse_dependencies.sql")
+            )
+        } else {
+            format!(
+                "SELECT *, COUNT(*) OVER () AS total FROM ({}) t OFFSET $2 LIMIT $3",
+                include_str!("krate_reverse_dependencies.sql")
+            )
+        };
+        let rows: Vec<WithCount<ReverseDependency>> = sql_query(query)
+            .bind::<Integer, _>(self.id)
+            .bind::<BigInt, _>(i64::from(offset))
+            .bind::<BigInt, _>(i64::from(options.per_page))
+            .bind::<Nullable<Integer>, _>(seek_downloads)
+            .bind::<Nullable<Text>, _>(seek_name)
+            .load(conn)?;
 
         Ok(rows.records_and_total())
     }
+
+    /// Returns how the latest non-yanked version of each reverse dependency depends on this
+    /// crate, optionally only with the given kind or optionality. A crate can be listed more than
+    /// once, e.g. as both a normal and a dev dependency.
+    pub(crate) fn latest_dependents(
+        &self,
+        conn: &PgConnection,
+        kind: Option<DependencyKind>,
+        optional: Option<bool>,
+    ) -> QueryResult<Vec<Dependent>> {
+        use diesel::sql_query;
+        use diesel::sql_types::{Integer, Nullable};
+
+        sql_query(include_str!("krate_latest_dependents.sql"))
+            .bind::<Integer, _>(self.id)
+            .bind::<Nullable<Integer>, _>(kind.map(|kind| kind as i32))
+            .bind::<Nullable<Bool>, _>(optional)
+            .load(conn)
+    }
+}
+
+/// Team members can't modify the owners of a crate, so teams can only be given the rights to
+/// publish and yank versions.
+fn ensure_team_rights(rights: Rights) -> AppResult<()> {
+    if !(Rights::PUBLISH | Rights::YANK).contains(rights) {
+        return Err(cargo_err(
+            "teams can only be given the `publish` and `yank` rights",
+        ));
+    }
+    Ok(())
 }
 
-use diesel::sql_types::{Date, Text};
+use diesel::sql_types::{Date, Float, Text};
 sql_function!(fn canon_crate_name(x: Text) -> Text);
 sql_function!(fn to_char(a: Date, b: Text) -> Text);
+sql_function!(fn similarity(a: Text, b: Text) -> Float);
+diesel_infix_operator!(IsSimilar, " % ");
 
 #[cfg(test)]
 mod tests {
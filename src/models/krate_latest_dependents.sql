@@ -0,0 +1,32 @@
+-- The dependencies on a crate of the *max* non-yanked version of every crate depending on it,
+-- picking the versions like `krate_reverse_dependencies.sql`. The dependencies can be narrowed
+-- down by kind and optionality, which are ignored when NULL.
+SELECT
+versions.crate_id,
+dependencies.req,
+dependencies.kind,
+dependencies.optional
+FROM dependencies
+INNER JOIN (
+    SELECT versions.id, versions.crate_id,
+    row_number() OVER (
+        PARTITION BY crate_id
+        ORDER BY to_semver_no_prerelease(num) DESC NULLS LAST
+    ) rn
+    FROM versions
+    WHERE NOT yanked
+    -- This is completely redundant, but it's faster to filter the versions
+    -- early even if this subselect is done via an index scan.
+    AND crate_id = ANY(
+        SELECT versions.crate_id
+        FROM versions
+        INNER JOIN dependencies
+        ON dependencies.version_id = versions.id
+        WHERE dependencies.crate_id = $1
+    )
+) versions
+  ON versions.id = dependencies.version_id
+WHERE dependencies.crate_id = $1
+  AND rn = 1
+  AND ($2::integer IS NULL OR dependencies.kind = $2)
+  AND ($3::boolean IS NULL OR dependencies.optional = $3)
@@ -0,0 +1,26 @@
+-- Crates whose name starts with the query come first, followed by the crates with a similar
+-- name. Both are weighted by their recent downloads.
+WITH query AS (
+    SELECT
+    canon_crate_name($1) AS name,
+    -- The wildcards of `LIKE` are escaped, `_` being part of canonical crate names
+    replace(replace(replace(canon_crate_name($1), '\', '\\'), '%', '\%'), '_', '\_') || '%' AS prefix
+)
+SELECT name FROM (
+    SELECT
+    crates.name,
+    canon_crate_name(crates.name) LIKE query.prefix AS is_prefix,
+    similarity(canon_crate_name(crates.name), query.name) AS similarity,
+    COALESCE(recent_crate_downloads.downloads, 0) AS recent_downloads
+    FROM crates
+    CROSS JOIN query
+    LEFT JOIN recent_crate_downloads
+      ON recent_crate_downloads.crate_id = crates.id
+    WHERE canon_crate_name(crates.name) LIKE query.prefix
+       OR canon_crate_name(crates.name) % query.name
+) matches
+ORDER BY
+  is_prefix DESC,
+  CASE WHEN is_prefix THEN 1 ELSE similarity END * ln(recent_downloads + 2) DESC,
+  name ASC
+LIMIT $2
@@ -0,0 +1,241 @@
+use std::str::FromStr;
+
+use diesel::prelude::*;
+
+use crate::models::{OwnerKind, User};
+use crate::schema::{crate_owners, notification_preferences};
+
+/// The categories of notification emails, which users can unsubscribe from separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationCategory {
+    Publish,
+    Yank,
+    Ownership,
+    Advisories,
+    TokenExpiry,
+}
+
+impl NotificationCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationCategory::Publish => "publish",
+            NotificationCategory::Yank => "yank",
+            NotificationCategory::Ownership => "ownership",
+            NotificationCategory::Advisories => "advisories",
+            NotificationCategory::TokenExpiry => "token_expiry",
+        }
+    }
+}
+
+impl FromStr for NotificationCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "publish" => Ok(NotificationCategory::Publish),
+            "yank" => Ok(NotificationCategory::Yank),
+            "ownership" => Ok(NotificationCategory::Ownership),
+            "advisories" => Ok(NotificationCategory::Advisories),
+            "token_expiry" => Ok(NotificationCategory::TokenExpiry),
+            _ => Err(format!("unknown notification category `{}`", s)),
+        }
+    }
+}
+
+/// The categories of notification emails a user wants to receive. Users who never changed them
+/// nor received a notification have no row, and receive all of them.
+#[derive(Debug, Clone, Identifiable, Queryable, Associations)]
+#[belongs_to(User)]
+#[primary_key(user_id)]
+#[table_name = "notification_preferences"]
+pub struct NotificationPreferences {
+    pub user_id: i32,
+    pub publish: bool,
+    pub yank: bool,
+    pub ownership: bool,
+    pub advisories: bool,
+    pub token_expiry: bool,
+    /// Unsubscribes the user from a category of emails without logging in, through the
+    /// `List-Unsubscribe` header of the emails.
+    pub unsubscribe_token: String,
+}
+
+/// Changes to the preferences of a user, leaving the categories which are `None` as they are.
+#[derive(Debug, Default, Deserialize, AsChangeset)]
+#[table_name = "notification_preferences"]
+pub struct NotificationPreferencesChanges {
+    pub publish: Option<bool>,
+    pub yank: Option<bool>,
+    pub ownership: Option<bool>,
+    pub advisories: Option<bool>,
+    pub token_expiry: Option<bool>,
+}
+
+/// Changes to the preferences of an owner for one of their crates, which apply on top of their
+/// own preferences and of the `email_notifications` flag of the crate.
+#[derive(Debug, Default, Deserialize, AsChangeset)]
+#[table_name = "crate_owners"]
+pub struct CrateNotificationPreferencesChanges {
+    #[column_name = "publish_notifications"]
+    pub publish: Option<bool>,
+    #[column_name = "yank_notifications"]
+    pub yank: Option<bool>,
+    #[column_name = "ownership_notifications"]
+    pub ownership: Option<bool>,
+    #[column_name = "advisory_notifications"]
+    pub advisories: Option<bool>,
+}
+
+/// A category of emails which a user is subscribed to, allowing them to unsubscribe from it.
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    pub category: NotificationCategory,
+    pub unsubscribe_token: String,
+}
+
+impl Subscription {
+    pub fn unsubscribe_url(&self) -> String {
+        format!(
+            "https://{}/api/v1/unsubscribe/{}/{}",
+            crate::config::domain_name(),
+            self.unsubscribe_token,
+            self.category.as_str()
+        )
+    }
+}
+
+impl NotificationPreferencesChanges {
+    pub fn is_empty(&self) -> bool {
+        self.publish.is_none()
+            && self.yank.is_none()
+            && self.ownership.is_none()
+            && self.advisories.is_none()
+            && self.token_expiry.is_none()
+    }
+}
+
+impl CrateNotificationPreferencesChanges {
+    pub fn is_empty(&self) -> bool {
+        self.publish.is_none()
+            && self.yank.is_none()
+            && self.ownership.is_none()
+            && self.advisories.is_none()
+    }
+}
+
+impl NotificationPreferences {
+    /// Returns the preferences of a user, storing the default ones if they have none yet.
+    pub fn for_user(conn: &PgConnection, user_id: i32) -> QueryResult<Self> {
+        diesel::insert_into(notification_preferences::table)
+            .values(notification_preferences::user_id.eq(user_id))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        notification_preferences::table.find(user_id).first(conn)
+    }
+
+    pub fn update(
+        conn: &PgConnection,
+        user_id: i32,
+        changes: &NotificationPreferencesChanges,
+    ) -> QueryResult<Self> {
+        let preferences = Self::for_user(conn, user_id)?;
+        diesel::update(&preferences).set(changes).get_result(conn)
+    }
+
+    /// Unsubscribes the user owning a token from a category of emails. Returns `false` if no user
+    /// owns the token.
+    pub fn unsubscribe(
+        conn: &PgConnection,
+        token: &str,
+        category: NotificationCategory,
+    ) -> QueryResult<bool> {
+        let mut changes = NotificationPreferencesChanges::default();
+        match category {
+            NotificationCategory::Publish => changes.publish = Some(false),
+            NotificationCategory::Yank => changes.yank = Some(false),
+            NotificationCategory::Ownership => changes.ownership = Some(false),
+            NotificationCategory::Advisories => changes.advisories = Some(false),
+            NotificationCategory::TokenExpiry => changes.token_expiry = Some(false),
+        }
+        let updated = diesel::update(
+            notification_preferences::table
+                .filter(notification_preferences::unsubscribe_token.eq(token)),
+        )
+        .set(&changes)
+        .execute(conn)?;
+        Ok(updated > 0)
+    }
+
+    /// Returns the subscription of a user to a category of emails, or `None` if they don't want
+    /// to receive them. If the emails are about a crate the user owns, their preferences for the
+    /// crate are taken into account as well.
+    ///
+    /// Users without preferences are subscribed to everything. Their default preferences are only
+    /// stored when an email is about to be sent, as they hold the unsubscribe token of the email.
+    pub fn subscription(
+        conn: &PgConnection,
+        user_id: i32,
+        category: NotificationCategory,
+        crate_id: Option<i32>,
+    ) -> QueryResult<Option<Subscription>> {
+        let preferences = notification_preferences::table
+            .find(user_id)
+            .first::<Self>(conn)
+            .optional()?;
+        if let Some(preferences) = &preferences {
+            if !preferences.is_subscribed(category) {
+                return Ok(None);
+            }
+        }
+
+        if let Some(crate_id) = crate_id {
+            let crate_preferences: Option<(bool, bool, bool, bool, bool)> = crate_owners::table
+                .filter(crate_owners::crate_id.eq(crate_id))
+                .filter(crate_owners::owner_id.eq(user_id))
+                .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+                .filter(crate_owners::deleted.eq(false))
+                .select((
+                    crate_owners::email_notifications,
+                    crate_owners::publish_notifications,
+                    crate_owners::yank_notifications,
+                    crate_owners::ownership_notifications,
+                    crate_owners::advisory_notifications,
+                ))
+                .first(conn)
+                .optional()?;
+
+            // Users who don't own the crate, e.g. invited ones, only have their own preferences
+            if let Some((all, publish, yank, ownership, advisories)) = crate_preferences {
+                let subscribed = match category {
+                    NotificationCategory::Publish => publish,
+                    NotificationCategory::Yank => yank,
+                    NotificationCategory::Ownership => ownership,
+                    NotificationCategory::Advisories => advisories,
+                    NotificationCategory::TokenExpiry => true,
+                };
+                if !all || !subscribed {
+                    return Ok(None);
+                }
+            }
+        }
+
+        let preferences = match preferences {
+            Some(preferences) => preferences,
+            None => Self::for_user(conn, user_id)?,
+        };
+        Ok(Some(Subscription {
+            category,
+            unsubscribe_token: preferences.unsubscribe_token,
+        }))
+    }
+
+    pub fn is_subscribed(&self, category: NotificationCategory) -> bool {
+        match category {
+            NotificationCategory::Publish => self.publish,
+            NotificationCategory::Yank => self.yank,
+            NotificationCategory::Ownership => self.ownership,
+            NotificationCategory::Advisories => self.advisories,
+            NotificationCategory::TokenExpiry => self.token_expiry,
+        }
+    }
+}
@@ -4,7 +4,7 @@ use diesel::prelude::*;
 use crate::app::App;
 use crate::util::errors::{cargo_err, AppResult};
 
-use crate::models::{Crate, Team, User};
+use crate::models::{Crate, Rights, Team, User};
 use crate::schema::{crate_owners, users};
 
 #[derive(Insertable, Associations, Identifiable, Debug, Clone, Copy)]
@@ -19,6 +19,7 @@ pub struct CrateOwner {
     pub created_by: i32,
     pub owner_kind: i32,
     pub email_notifications: bool,
+    pub rights: Rights,
 }
 
 type BoxedQuery<'a> = crate_owners::BoxedQuery<'a, Pg, crate_owners::SqlType>;
@@ -1,8 +1,122 @@
-/// Access rights to the crate (publishing and ownership management)
-/// NOTE: The order of these variants matters!
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
-pub enum Rights {
-    None,
-    Publish,
-    Full,
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Integer,
+};
+use std::io::Write;
+use std::ops::BitOr;
+use std::str::FromStr;
+
+/// Access rights to the crate (yanking, publishing and ownership management)
+///
+/// These are flags, so that an owner may be allowed to yank versions without being allowed to
+/// publish them, or the other way around. Managing the owners of the crate is only part of
+/// `Rights::FULL`, which includes all the other rights and is never given to teams.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, FromSqlRow, AsExpression)]
+#[sql_type = "Integer"]
+pub struct Rights(i32);
+
+const MANAGE_OWNERS: i32 = 1 << 2;
+
+impl Rights {
+    pub const NONE: Rights = Rights(0);
+    pub const YANK: Rights = Rights(1);
+    pub const PUBLISH: Rights = Rights(1 << 1);
+    pub const FULL: Rights = Rights(Self::YANK.0 | Self::PUBLISH.0 | MANAGE_OWNERS);
+
+    /// Whether these rights include all of the `other` rights.
+    pub fn contains(self, other: Rights) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Rights {
+    type Output = Rights;
+
+    fn bitor(self, other: Rights) -> Rights {
+        Rights(self.0 | other.0)
+    }
+}
+
+impl From<Rights> for &'static str {
+    fn from(rights: Rights) -> Self {
+        match rights {
+            Rights::NONE => "none",
+            Rights::YANK => "yank",
+            Rights::PUBLISH => "publish",
+            Rights::FULL => "full",
+            _ => "publish,yank",
+        }
+    }
+}
+
+impl FromStr for Rights {
+    type Err = ();
+
+    /// Parses the rights that can be granted to an owner, which are either `full` or a comma
+    /// separated list of `publish` and `yank`. `none` is not one of them, since an owner
+    /// without any rights would just be removed instead.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "full" {
+            return Ok(Rights::FULL);
+        }
+        s.split(',')
+            .map(|right| match right.trim() {
+                "yank" => Ok(Rights::YANK),
+                "publish" => Ok(Rights::PUBLISH),
+                _ => Err(()),
+            })
+            .try_fold(Rights::NONE, |rights, right| Ok(rights | right?))
+    }
+}
+
+impl FromSql<Integer, Pg> for Rights {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            n @ 1..=3 | n @ 7 => Ok(Rights(n)),
+            n => Err(format!("unknown rights: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for Rights {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&self.0, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rights;
+
+    #[test]
+    fn rights_are_flags() {
+        let publish_and_yank = Rights::PUBLISH | Rights::YANK;
+        assert!(publish_and_yank.contains(Rights::YANK));
+        assert!(!Rights::PUBLISH.contains(Rights::YANK));
+        assert!(!Rights::YANK.contains(Rights::PUBLISH));
+        assert!(Rights::FULL.contains(publish_and_yank));
+        assert!(!publish_and_yank.contains(Rights::FULL));
+    }
+
+    #[test]
+    fn rights_are_parsed() {
+        assert_eq!("yank".parse(), Ok(Rights::YANK));
+        assert_eq!("publish".parse(), Ok(Rights::PUBLISH));
+        assert_eq!("publish,yank".parse(), Ok(Rights::PUBLISH | Rights::YANK));
+        assert_eq!("yank, publish".parse(), Ok(Rights::PUBLISH | Rights::YANK));
+        assert_eq!("full".parse(), Ok(Rights::FULL));
+        assert_eq!("none".parse::<Rights>(), Err(()));
+        assert_eq!("".parse::<Rights>(), Err(()));
+        assert_eq!("publish,full".parse::<Rights>(), Err(()));
+    }
+
+    #[test]
+    fn rights_are_displayed() {
+        let display = |rights: Rights| -> &'static str { rights.into() };
+        assert_eq!(display(Rights::PUBLISH | Rights::YANK), "publish,yank");
+        assert_eq!(display(Rights::YANK), "yank");
+        assert_eq!(display(Rights::FULL), "full");
+    }
 }
@@ -1,16 +1,21 @@
+use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
+use swirl::Job;
 
 use crate::app::App;
+use crate::config::Config;
+use crate::github::GitHubClient;
+use crate::tasks;
 use crate::util::errors::{cargo_err, AppResult, NotFound};
 
 use oauth2::AccessToken;
 
 use crate::models::{Crate, CrateOwner, Owner, OwnerKind, User};
-use crate::schema::{crate_owners, teams};
+use crate::schema::{crate_owners, team_memberships, teams};
 
 /// For now, just a Github Team. Can be upgraded to other teams
 /// later if desirable.
-#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug)]
+#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug, Clone)]
 pub struct Team {
     /// Unique table id
     pub id: i32,
@@ -151,7 +156,7 @@ impl Team {
 
         let org_id = team.organization.id;
 
-        if !team_with_gh_id_contains_user(app, org_id, team.id, req_user)? {
+        if !team_with_gh_id_contains_user(&app.github, org_id, team.id, req_user)? {
             return Err(cargo_err("only members of a team can add it as an owner"));
         }
 
@@ -168,13 +173,66 @@ impl Team {
         .map_err(Into::into)
     }
 
-    /// Phones home to Github to ask if this User is a member of the given team.
+    /// Determines whether this User is a member of the given team.
+    ///
+    /// Answers from Github are cached in the `team_memberships` table for
+    /// `Config::team_membership_cache_ttl_minutes`. If Github can't be reached
+    /// we fall back to the last known answer, as long as it isn't older than
+    /// `Config::team_membership_fallback_max_age_hours`, so that users removed
+    /// from a team don't keep their rights during long Github outages.
+    ///
     /// Note that we're assuming that the given user is the one interested in
     /// the answer. If this is not the case, then we could accidentally leak
     /// private membership information here.
-    pub fn contains_user(&self, app: &App, user: &User) -> AppResult<bool> {
+    pub fn contains_user(&self, app: &App, conn: &PgConnection, user: &User) -> AppResult<bool> {
+        let metric = &app.instance_metrics.team_membership_cache_lookups_total;
+
+        let cached = TeamMembership::find(conn, self.id, user.id)?;
+        if let Some(cached) = cached.filter(|cached| cached.is_fresh(&app.config)) {
+            metric.with_label_values(&["hit"]).inc();
+            return Ok(cached.is_member);
+        }
+
+        match self.contains_user_on_github(&app.github, user) {
+            Ok(is_member) => {
+                metric.with_label_values(&["miss"]).inc();
+                self.cache_membership(conn, user, is_member)?;
+                Ok(is_member)
+            }
+            Err(error) => match cached {
+                Some(cached) if cached.is_usable_as_fallback(&app.config) => {
+                    metric.with_label_values(&["fallback"]).inc();
+                    warn!(
+                        "Falling back to cached membership of {} in {}: {}",
+                        user.gh_login, self.login, error
+                    );
+                    Ok(cached.is_member)
+                }
+                _ => Err(error),
+            },
+        }
+    }
+
+    /// Caches the answer of Github about the membership of a user.
+    ///
+    /// The answer is recorded by a background job, so that checking rights neither needs a
+    /// second connection nor keeps the cache entry locked until the transaction of the caller
+    /// ends. If that transaction is rolled back the answer is lost, and Github is asked again
+    /// on the next check.
+    fn cache_membership(&self, conn: &PgConnection, user: &User, is_member: bool) -> AppResult<()> {
+        tasks::record_team_membership(self.id, user.id, is_member).enqueue(conn)?;
+        Ok(())
+    }
+
+    /// Phones home to Github to ask if this User is a member of the given team,
+    /// bypassing the membership cache.
+    pub(crate) fn contains_user_on_github(
+        &self,
+        github: &GitHubClient,
+        user: &User,
+    ) -> AppResult<bool> {
         match self.org_id {
-            Some(org_id) => team_with_gh_id_contains_user(app, org_id, self.github_id, user),
+            Some(org_id) => team_with_gh_id_contains_user(github, org_id, self.github_id, user),
             // This means we don't have an org_id on file for the `self` team. It much
             // probably was deleted from github by the time we backfilled the database.
             // Short-circuiting to false since a non-existent team cannot contain any
@@ -198,7 +256,7 @@ impl Team {
 }
 
 fn team_with_gh_id_contains_user(
-    app: &App,
+    github: &GitHubClient,
     github_org_id: i32,
     github_team_id: i32,
     user: &User,
@@ -208,10 +266,7 @@ fn team_with_gh_id_contains_user(
 
     let token = AccessToken::new(user.gh_access_token.clone());
     let membership =
-        match app
-            .github
-            .team_membership(github_org_id, github_team_id, &user.gh_login, &token)
-        {
+        match github.team_membership(github_org_id, github_team_id, &user.gh_login, &token) {
             // Officially how `false` is returned
             Err(ref e) if e.is::<NotFound>() => return Ok(false),
             x => x?,
@@ -221,3 +276,62 @@ fn team_with_gh_id_contains_user(
     // some feedback, but it's not obvious how that should work.
     Ok(membership.state == "active")
 }
+
+/// The model representing a row in the `team_memberships` database table, which caches
+/// the answers of Github to whether a user is a member of a team.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone, Copy)]
+#[belongs_to(Team)]
+#[belongs_to(User)]
+#[primary_key(team_id, user_id)]
+pub struct TeamMembership {
+    pub team_id: i32,
+    pub user_id: i32,
+    pub is_member: bool,
+    pub checked_at: NaiveDateTime,
+}
+
+impl TeamMembership {
+    pub fn find(conn: &PgConnection, team_id: i32, user_id: i32) -> QueryResult<Option<Self>> {
+        team_memberships::table
+            .find((team_id, user_id))
+            .first(conn)
+            .optional()
+    }
+
+    /// Stores the latest answer of Github, resetting the age of the cache entry.
+    pub fn record(
+        conn: &PgConnection,
+        team_id: i32,
+        user_id: i32,
+        is_member: bool,
+    ) -> QueryResult<()> {
+        use diesel::dsl::now;
+
+        diesel::insert_into(team_memberships::table)
+            .values((
+                team_memberships::team_id.eq(team_id),
+                team_memberships::user_id.eq(user_id),
+                team_memberships::is_member.eq(is_member),
+            ))
+            .on_conflict(team_memberships::table.primary_key())
+            .do_update()
+            .set((
+                team_memberships::is_member.eq(is_member),
+                team_memberships::checked_at.eq(now),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn is_fresh(&self, config: &Config) -> bool {
+        let ttl = chrono::Duration::minutes(config.team_membership_cache_ttl_minutes as i64);
+        self.checked_at + ttl > Utc::now().naive_utc()
+    }
+
+    /// Whether this answer is recent enough to be used when Github can't be reached.
+    pub fn is_usable_as_fallback(&self, config: &Config) -> bool {
+        let max_age = chrono::Duration::hours(config.team_membership_fallback_max_age_hours as i64);
+        self.checked_at + max_age > Utc::now().naive_utc()
+    }
+}
@@ -100,7 +100,7 @@ impl<'a> NewUser<'a> {
 
                 if let Some(token) = token {
                     // Swallows any error. Some users might insert an invalid email address here.
-                    let _ = emails.send_user_confirm(user_email, &user.gh_login, &token);
+                    let _ = emails.send_user_confirm(conn, user_email, &user.gh_login, &token);
                 }
             }
 
@@ -133,31 +133,41 @@ impl User {
         Ok(users.collect())
     }
 
-    /// Given this set of owners, determines the strongest rights the
-    /// user has.
+    /// Given this set of owners and their rights, determines all the rights
+    /// the user has, through any of the owners.
     ///
-    /// Shortcircuits on `Full` because you can't beat it. In practice we'll always
-    /// see `[user, user, user, ..., team, team, team]`, so we could shortcircuit on
-    /// `Publish` as well, but this is a non-obvious invariant so we don't bother.
-    /// Sweet free optimization if teams are proving burdensome to check.
+    /// Shortcircuits on `FULL` because you can't beat it. Teams that can't
+    /// add to the rights found so far are skipped, so we only ask GitHub
+    /// about the team memberships that actually matter.
     /// More than one team isn't really expected, though.
-    pub fn rights(&self, app: &App, owners: &[Owner]) -> AppResult<Rights> {
-        let mut best = Rights::None;
-        for owner in owners {
+    pub fn rights(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        owners: &[(Owner, Rights)],
+    ) -> AppResult<Rights> {
+        let mut all = Rights::NONE;
+        for (owner, rights) in owners {
+            if all.contains(*rights) {
+                continue;
+            }
             match *owner {
                 Owner::User(ref other_user) => {
                     if other_user.id == self.id {
-                        return Ok(Rights::Full);
+                        all = all | *rights;
                     }
                 }
                 Owner::Team(ref team) => {
-                    if team.contains_user(app, self)? {
-                        best = Rights::Publish;
+                    if team.contains_user(app, conn, self)? {
+                        all = all | *rights;
                     }
                 }
             }
+            if all == Rights::FULL {
+                return Ok(all);
+            }
         }
-        Ok(best)
+        Ok(all)
     }
 
     /// Queries the database for the verified emails
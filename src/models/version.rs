@@ -23,6 +23,7 @@ pub struct Version {
     pub license: Option<String>,
     pub crate_size: Option<i32>,
     pub published_by: Option<i32>,
+    pub rust_version: Option<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -34,6 +35,7 @@ pub struct NewVersion {
     license: Option<String>,
     crate_size: Option<i32>,
     published_by: i32,
+    rust_version: Option<String>,
 }
 
 /// The highest version (semver order) and the most recently updated version.
@@ -119,6 +121,7 @@ impl NewVersion {
         license_file: Option<&str>,
         crate_size: i32,
         published_by: i32,
+        rust_version: Option<&str>,
     ) -> AppResult<Self> {
         let features = serde_json::to_value(features)?;
 
@@ -129,9 +132,11 @@ impl NewVersion {
             license,
             crate_size: Some(crate_size),
             published_by,
+            rust_version: rust_version.map(String::from),
         };
 
         new_version.validate_license(license_file)?;
+        new_version.validate_rust_version()?;
 
         Ok(new_version)
     }
@@ -200,11 +205,34 @@ impl NewVersion {
         }
         Ok(())
     }
+
+    fn validate_rust_version(&self) -> AppResult<()> {
+        if let Some(ref rust_version) = self.rust_version {
+            if !valid_rust_version(rust_version) {
+                return Err(cargo_err(&format_args!(
+                    "invalid rust-version `{}`; it must be a bare version \
+                     number with two or three components, like `1.50`",
+                    rust_version
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `rust_version` is a version like `1.50` or `1.50.0`, the format allowed for the
+/// `rust-version` field of manifests.
+pub fn valid_rust_version(rust_version: &str) -> bool {
+    let parts = rust_version.split('.').collect::<Vec<_>>();
+    (2..=3).contains(&parts.len())
+        && parts.iter().all(|part| {
+            !part.is_empty() && part.len() <= 9 && part.bytes().all(|b| b.is_ascii_digit())
+        })
 }
 
 #[cfg(test)]
 mod tests {
-    use super::TopVersions;
+    use super::{valid_rust_version, TopVersions};
     use chrono::NaiveDateTime;
 
     #[track_caller]
@@ -273,4 +301,15 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn rust_versions() {
+        assert!(valid_rust_version("1.50"));
+        assert!(valid_rust_version("1.50.0"));
+        assert!(!valid_rust_version("1"));
+        assert!(!valid_rust_version("1.50.0.1"));
+        assert!(!valid_rust_version("1.50-beta"));
+        assert!(!valid_rust_version("^1.50"));
+        assert!(!valid_rust_version("1..0"));
+    }
 }
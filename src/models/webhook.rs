@@ -0,0 +1,335 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+use swirl::Job;
+use url::Url;
+
+use crate::models::{Crate, CrateAction, OwnerKind, VersionAction};
+use crate::schema::{crate_owners, crates, users, versions, webhook_deliveries, webhooks};
+use crate::tasks;
+use crate::util::errors::AppResult;
+use crate::util::token::generate_secure_alphanumeric_string;
+
+/// The events which can be delivered to webhooks.
+pub const WEBHOOK_EVENTS: &[&str] = &["publish", "yank", "unyank", "owner_change"];
+
+/// A URL to which events of a crate are delivered. Webhooks are either registered on a crate by
+/// its owners, or by a user for all the crates they own.
+#[derive(Clone, Debug, Identifiable, Queryable)]
+pub struct Webhook {
+    pub id: i32,
+    pub crate_id: Option<i32>,
+    pub user_id: Option<i32>,
+    pub url: String,
+    /// The key used to sign the payloads delivered to the webhook.
+    pub secret: String,
+    pub events: Vec<String>,
+    pub created_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/// Checks that events can be delivered to a webhook URL, and returns the address to connect to.
+///
+/// Webhooks can be registered by any user, so their URL must be an HTTPS URL whose host only
+/// resolves to public IP addresses, so that they can't be used to reach the internal network of
+/// the registry. The host is resolved again before each delivery, which must then connect to the
+/// returned address, so that the DNS records can't be changed in between to point elsewhere.
+///
+/// With `allow_private`, used during development and in tests, any HTTP(S) URL is accepted.
+pub fn resolve_webhook_url(url: &str, allow_private: bool) -> Result<(Url, SocketAddr), String> {
+    let url = Url::parse(url).map_err(|_| "the webhook URL is invalid".to_string())?;
+    match url.scheme() {
+        "https" => {}
+        "http" if allow_private => {}
+        _ => return Err("the webhook URL must be an HTTPS URL".into()),
+    }
+
+    let addrs = url
+        .socket_addrs(|| None)
+        .map_err(|_| "the webhook host could not be resolved".to_string())?;
+    if !allow_private && addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err("the webhook host must only resolve to public IP addresses".into());
+    }
+    let addr = addrs
+        .into_iter()
+        .next()
+        .ok_or_else(|| "the webhook host could not be resolved".to_string())?;
+    Ok((url, addr))
+}
+
+/// Returns whether an IP address is reachable over the internet, unlike the loopback, private,
+/// link-local and multicast addresses.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_multicast()
+                || ip.is_broadcast()
+                // The "this network" and shared address spaces
+                || first == 0
+                || (first == 100 && second & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => {
+            // IPv4-mapped and IPv4-compatible addresses
+            if let Some(ipv4) = ip.to_ipv4() {
+                return is_public_ip(IpAddr::V4(ipv4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local and link-local addresses
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "webhooks"]
+pub struct NewWebhook<'a> {
+    crate_id: Option<i32>,
+    user_id: Option<i32>,
+    url: &'a str,
+    secret: String,
+    events: &'a [String],
+    created_by: i32,
+}
+
+impl<'a> NewWebhook<'a> {
+    /// Prepares a webhook of a crate if `crate_id` is set, or of the user otherwise, with a new
+    /// secret.
+    pub fn new(crate_id: Option<i32>, user_id: i32, url: &'a str, events: &'a [String]) -> Self {
+        Self {
+            crate_id,
+            user_id: if crate_id.is_some() {
+                None
+            } else {
+                Some(user_id)
+            },
+            url,
+            secret: generate_secure_alphanumeric_string(32),
+            events,
+            created_by: user_id,
+        }
+    }
+
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<Webhook> {
+        diesel::insert_into(webhooks::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
+
+#[derive(Clone, Debug, Identifiable, Queryable, Associations)]
+#[belongs_to(Webhook)]
+#[table_name = "webhook_deliveries"]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    /// The HTTP status of the last attempt, if the webhook responded.
+    pub status_code: Option<i32>,
+    /// Why the last attempt failed.
+    pub error: Option<String>,
+    pub delivered_at: Option<NaiveDateTime>,
+    /// When the delivery will be attempted next, or `None` if it succeeded or was given up.
+    pub next_attempt_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Webhook {
+    /// Returns the HMAC-SHA256 signature of a payload, encoded in hexadecimal.
+    pub fn sign(&self, payload: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_varkey(self.secret.as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.update(payload);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Queues the delivery of a version event to the webhooks subscribed to it.
+    pub fn trigger_version_event(
+        conn: &PgConnection,
+        version_id: i32,
+        user_id: i32,
+        action: VersionAction,
+    ) -> AppResult<()> {
+        let event: &'static str = action.into();
+        let (crate_id, crate_name, num): (i32, String, String) = versions::table
+            .inner_join(crates::table)
+            .filter(versions::id.eq(version_id))
+            .select((crates::id, crates::name, versions::num))
+            .first(conn)?;
+        let login: String = users::table
+            .find(user_id)
+            .select(users::gh_login)
+            .first(conn)?;
+
+        let payload = json!({
+            "event": event,
+            "crate": crate_name,
+            "version": num,
+            "user": login,
+        });
+        Self::trigger(conn, crate_id, event, payload)
+    }
+
+    /// Queues the delivery of an `owner_change` event to the webhooks subscribed to it, if the
+    /// action changed the owners of the crate or their rights.
+    pub fn trigger_owner_event(
+        conn: &PgConnection,
+        crate_id: i32,
+        user_id: i32,
+        action: CrateAction,
+    ) -> AppResult<()> {
+        match action {
+            CrateAction::TransferAccept
+            | CrateAction::InviteAccept
+            | CrateAction::Remove
+            | CrateAction::TeamAdd
+            | CrateAction::TeamRightsChange => {}
+            _ => return Ok(()),
+        }
+
+        let krate: Crate = Crate::all().filter(crates::id.eq(crate_id)).first(conn)?;
+        let owners = krate
+            .owners(conn)?
+            .iter()
+            .map(|owner| owner.login().to_string())
+            .collect::<Vec<_>>();
+        let login: String = users::table
+            .find(user_id)
+            .select(users::gh_login)
+            .first(conn)?;
+        let action: &'static str = action.into();
+
+        let payload = json!({
+            "event": "owner_change",
+            "crate": krate.name,
+            "action": action,
+            "user": login,
+            "owners": owners,
+        });
+        Self::trigger(conn, crate_id, "owner_change", payload)
+    }
+
+    fn trigger(
+        conn: &PgConnection,
+        crate_id: i32,
+        event: &str,
+        payload: serde_json::Value,
+    ) -> AppResult<()> {
+        let owner_ids: Vec<i32> = crate_owners::table
+            .filter(crate_owners::crate_id.eq(crate_id))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+            .filter(crate_owners::deleted.eq(false))
+            .select(crate_owners::owner_id)
+            .load(conn)?;
+        let webhook_ids = webhooks::table
+            .filter(
+                webhooks::crate_id
+                    .eq(crate_id)
+                    .or(webhooks::user_id.eq_any(owner_ids)),
+            )
+            .load::<Webhook>(conn)?
+            .into_iter()
+            .filter(|webhook| webhook.events.iter().any(|e| e == event))
+            .map(|webhook| webhook.id)
+            .collect::<Vec<_>>();
+        if webhook_ids.is_empty() {
+            return Ok(());
+        }
+
+        let deliveries = webhook_ids
+            .into_iter()
+            .map(|webhook_id| {
+                (
+                    webhook_deliveries::webhook_id.eq(webhook_id),
+                    webhook_deliveries::event.eq(event),
+                    webhook_deliveries::payload.eq(payload.clone()),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(webhook_deliveries::table)
+            .values(&deliveries)
+            .execute(conn)?;
+
+        tasks::deliver_webhooks().enqueue(conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_ip_addresses_are_allowed() {
+        let is_public = |ip: &str| is_public_ip(ip.parse().unwrap());
+        assert!(is_public("203.0.113.1"));
+        assert!(is_public("2001:db8::1"));
+        for ip in &[
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "224.0.0.1",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "::ffff:127.0.0.1",
+            "fd00::1",
+            "fe80::1",
+            "ff02::1",
+        ] {
+            assert!(!is_public(ip), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn webhook_urls_must_be_public_https_urls() {
+        let resolve = |url| resolve_webhook_url(url, false).map(|(_, addr)| addr.to_string());
+        assert_eq!(
+            resolve("https://203.0.113.1/hook").unwrap(),
+            "203.0.113.1:443"
+        );
+        assert!(resolve("http://203.0.113.1/hook").is_err());
+        assert!(resolve("ftp://203.0.113.1/hook").is_err());
+        assert!(resolve("https://127.0.0.1:8080/hook").is_err());
+        assert!(resolve("https://[::1]/hook").is_err());
+        assert!(resolve("https://localhost/hook").is_err());
+
+        let (_, addr) = resolve_webhook_url("http://127.0.0.1:8080/hook", true).unwrap();
+        assert_eq!(addr.to_string(), "127.0.0.1:8080");
+    }
+
+    #[test]
+    fn payloads_are_signed_with_hmac_sha256() {
+        // Test case 2 of RFC 4231
+        let webhook = Webhook {
+            id: 1,
+            crate_id: Some(1),
+            user_id: None,
+            url: "https://example.com/hook".into(),
+            secret: "Jefe".into(),
+            events: vec!["publish".into()],
+            created_by: 1,
+            created_at: chrono::NaiveDate::from_ymd(2021, 1, 1).and_hms(0, 0, 0),
+        };
+        assert_eq!(
+            webhook.sign(b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
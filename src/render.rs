@@ -1,9 +1,10 @@
 //! Render README files to HTML.
 
 use ammonia::{Builder, UrlRelative, UrlRelativeEvaluate};
-use comrak::nodes::{AstNode, NodeValue};
-use htmlescape::encode_minimal;
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+use htmlescape::{encode_attribute, encode_minimal};
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::path::Path;
 use swirl::PerformError;
 use url::Url;
@@ -11,6 +12,10 @@ use url::Url;
 use crate::background_jobs::Environment;
 use crate::models::Version;
 
+mod asciidoc;
+mod highlight;
+mod rst;
+
 /// Context for markdown to HTML rendering.
 #[allow(missing_debug_implementations)]
 struct MarkdownRenderer<'a> {
@@ -23,39 +28,9 @@ impl<'a> MarkdownRenderer<'a> {
     /// Per `readme_to_html`, `base_url` is the base URL prepended to any
     /// relative links in the input document.  See that function for more detail.
     fn new(base_url: Option<&'a str>) -> MarkdownRenderer<'a> {
-        let allowed_classes = hashmap(&[(
-            "code",
-            hashset(&[
-                "language-bash",
-                "language-clike",
-                "language-glsl",
-                "language-go",
-                "language-ini",
-                "language-javascript",
-                "language-json",
-                "language-markup",
-                "language-protobuf",
-                "language-ruby",
-                "language-rust",
-                "language-scss",
-                "language-sql",
-                "language-toml",
-                "language-yaml",
-            ]),
-        )]);
-        let sanitize_url = UrlRelative::Custom(Box::new(SanitizeUrl::new(base_url)));
-
-        let mut html_sanitizer = Builder::default();
-        html_sanitizer
-            .add_tags(&["input"])
-            .link_rel(Some("nofollow noopener noreferrer"))
-            .add_generic_attributes(&["align"])
-            .add_tag_attributes("a", &["id", "target"])
-            .add_tag_attributes("input", &["checked", "disabled", "type"])
-            .allowed_classes(allowed_classes)
-            .url_relative(sanitize_url)
-            .id_prefix(Some("user-content-"));
-        MarkdownRenderer { html_sanitizer }
+        MarkdownRenderer {
+            html_sanitizer: html_sanitizer(base_url),
+        }
     }
 
     /// Renders the given markdown to HTML using the current settings.
@@ -85,8 +60,9 @@ impl<'a> MarkdownRenderer<'a> {
         let arena = Arena::new();
         let root = parse_document(&arena, text, &options);
 
-        // Tweak annotations of code blocks.
+        // Tweak annotations of code blocks, and highlight the ones with a language.
         iter_nodes(root, &|node| {
+            let mut highlighted = None;
             if let NodeValue::CodeBlock(ref mut ncb) = node.data.borrow_mut().value {
                 // If annot includes invalid UTF-8 char, do nothing.
                 if let Ok(mut orig_annot) = String::from_utf8(ncb.info.to_vec()) {
@@ -95,8 +71,18 @@ impl<'a> MarkdownRenderer<'a> {
                         let _ = orig_annot.drain(offset..orig_annot.len());
                         ncb.info = orig_annot.as_bytes().to_vec();
                     }
+                    if let Some(language) = orig_annot.split_whitespace().next() {
+                        let code = String::from_utf8_lossy(&ncb.literal);
+                        highlighted = Some(code_block(Some(language), &code));
+                    }
                 }
             }
+            if let Some(html) = highlighted {
+                node.data.borrow_mut().value = NodeValue::HtmlBlock(NodeHtmlBlock {
+                    block_type: 0,
+                    literal: html.into_bytes(),
+                });
+            }
         });
 
         let mut html = Vec::new();
@@ -106,6 +92,49 @@ impl<'a> MarkdownRenderer<'a> {
     }
 }
 
+/// Creates the `ammonia` settings shared by the renderers of all the readme formats.
+///
+/// Per `readme_to_html`, `base_url` is the base URL prepended to any
+/// relative links in the input document.  See that function for more detail.
+fn html_sanitizer<'a>(base_url: Option<&str>) -> Builder<'a> {
+    let allowed_classes = hashmap(&[
+        (
+            "code",
+            hashset(&[
+                "language-bash",
+                "language-clike",
+                "language-glsl",
+                "language-go",
+                "language-ini",
+                "language-javascript",
+                "language-json",
+                "language-markup",
+                "language-protobuf",
+                "language-ruby",
+                "language-rust",
+                "language-scss",
+                "language-sql",
+                "language-toml",
+                "language-yaml",
+            ]),
+        ),
+        ("span", hashset(&highlight::CLASSES)),
+    ]);
+    let sanitize_url = UrlRelative::Custom(Box::new(SanitizeUrl::new(base_url)));
+
+    let mut html_sanitizer = Builder::default();
+    html_sanitizer
+        .add_tags(&["input"])
+        .link_rel(Some("nofollow noopener noreferrer"))
+        .add_generic_attributes(&["align"])
+        .add_tag_attributes("a", &["id", "target"])
+        .add_tag_attributes("input", &["checked", "disabled", "type"])
+        .allowed_classes(allowed_classes)
+        .url_relative(sanitize_url)
+        .id_prefix(Some("user-content-"));
+    html_sanitizer
+}
+
 /// Iterate the nodes in the CommonMark AST, used in comrak.
 fn iter_nodes<'a, F>(node: &'a AstNode<'a>, f: &F)
 where
@@ -220,6 +249,20 @@ fn markdown_to_html(text: &str, base_url: Option<&str>) -> String {
     renderer.to_html(text)
 }
 
+/// Renders reStructuredText to sanitized HTML with a given `base_url`.
+/// See `readme_to_html` for the interpretation of `base_url`.
+fn rst_to_html(text: &str, base_url: Option<&str>) -> String {
+    let rendered = rst::to_html(text);
+    html_sanitizer(base_url).clean(&rendered).to_string()
+}
+
+/// Renders AsciiDoc text to sanitized HTML with a given `base_url`.
+/// See `readme_to_html` for the interpretation of `base_url`.
+fn asciidoc_to_html(text: &str, base_url: Option<&str>) -> String {
+    let rendered = asciidoc::to_html(text);
+    html_sanitizer(base_url).clean(&rendered).to_string()
+}
+
 /// Any readme with a filename ending in one of these extensions will be rendered as Markdown.
 /// Note we also render a readme as Markdown if _no_ extension is on the filename.
 static MARKDOWN_EXTENSIONS: [&str; 7] = [
@@ -232,6 +275,13 @@ static MARKDOWN_EXTENSIONS: [&str; 7] = [
     ".mkdown",
 ];
 
+/// Any readme with a filename ending in one of these extensions will be rendered as
+/// reStructuredText.
+static RST_EXTENSIONS: [&str; 2] = [".rst", ".rest"];
+
+/// Any readme with a filename ending in one of these extensions will be rendered as AsciiDoc.
+static ASCIIDOC_EXTENSIONS: [&str; 4] = [".adoc", ".asciidoc", ".asc", ".ad"];
+
 /// Renders a readme to sanitized HTML.  An appropriate rendering method is chosen depending
 /// on the extension of the supplied `filename`.
 ///
@@ -239,7 +289,7 @@ static MARKDOWN_EXTENSIONS: [&str; 7] = [
 /// onclick, onmouseover, etc.).
 ///
 /// The `base_url` parameter will be used as the base for any relative links found in the
-/// Markdown, as long as its host part is github.com, gitlab.com, or bitbucket.org.  The
+/// readme, as long as its host part is github.com, gitlab.com, or bitbucket.org.  The
 /// supplied URL will be used as a directory base whether or not the relative link is
 /// prefixed with '/'.  If `None` is passed, relative links will be omitted.
 ///
@@ -257,6 +307,12 @@ pub fn readme_to_html(text: &str, filename: &str, base_url: Option<&str>) -> Str
     if !filename.contains('.') || MARKDOWN_EXTENSIONS.iter().any(|e| filename.ends_with(e)) {
         return markdown_to_html(text, base_url);
     }
+    if RST_EXTENSIONS.iter().any(|e| filename.ends_with(e)) {
+        return rst_to_html(text, base_url);
+    }
+    if ASCIIDOC_EXTENSIONS.iter().any(|e| filename.ends_with(e)) {
+        return asciidoc_to_html(text, base_url);
+    }
 
     encode_minimal(text).replace("\n", "<br>\n")
 }
@@ -288,6 +344,161 @@ pub fn render_and_upload_readme(
     })
 }
 
+/// Generates unique ids for the anchors of headings, the same way comrak does for Markdown.
+#[derive(Default)]
+struct HeadingAnchors {
+    seen: HashMap<String, usize>,
+}
+
+impl HeadingAnchors {
+    /// Renders a heading of the given level, with an anchor based on its `text`.
+    fn heading(&mut self, level: usize, text: &str, html: &str) -> String {
+        let id: String = text
+            .trim()
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+                _ => None,
+            })
+            .collect();
+        let count = self.seen.entry(id.clone()).or_insert(0);
+        let anchor = match *count {
+            0 => id,
+            n => format!("{}-{}", id, n),
+        };
+        *count += 1;
+        format!(
+            "<h{0}><a href=\"#{1}\" id=\"{1}\"></a>{2}</h{0}>\n",
+            level, anchor, html
+        )
+    }
+}
+
+/// The deepest nesting of blocks, like list items and block quotes, rendered by the line-based
+/// renderers. Deeper blocks are rendered as plain text, to bound the recursion of the background
+/// job rendering the readmes of published crates.
+const MAX_DEPTH: usize = 16;
+
+/// Splits a readme into lines for the line-based renderers, expanding tabs and removing
+/// trailing whitespace.
+fn lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.replace('\t', "        ").trim_end().to_string())
+        .collect()
+}
+
+/// Returns the number of spaces a line is indented by.
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Removes the common indentation of a block of lines.
+fn dedent(lines: &[String]) -> Vec<String> {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| indentation(line))
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").to_string())
+        .collect()
+}
+
+/// Returns the index of the first line which is not blank, starting at `lines[i]`.
+fn skip_blank(lines: &[String], mut i: usize) -> usize {
+    while lines.get(i).map_or(false, |line| line.is_empty()) {
+        i += 1;
+    }
+    i
+}
+
+/// Escapes plain text, turning the bare URLs it contains into links.
+fn autolink(text: &str) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+    while let Some(start) = ["https://", "http://"]
+        .iter()
+        .filter_map(|scheme| rest.find(scheme))
+        .min()
+    {
+        let end = rest[start..]
+            .find(|c: char| c.is_whitespace() || c == '<' || c == '>' || c == '"')
+            .map_or(rest.len(), |end| start + end);
+        let url = rest[start..end].trim_end_matches(|c| ".,;:!?)'".contains(c));
+        html += &encode_minimal(&rest[..start]);
+        html += &link(url, &encode_minimal(url));
+        rest = &rest[start + url.len()..];
+    }
+    html + &encode_minimal(rest)
+}
+
+/// Renders a link around the given HTML.
+fn link(url: &str, html: &str) -> String {
+    format!("<a href=\"{}\">{}</a>", encode_attribute(url), html)
+}
+
+/// Renders an image, linking to `target` if there is one.
+fn image(url: &str, alt: &str, target: Option<&str>) -> String {
+    let image = format!(
+        "<img src=\"{}\" alt=\"{}\">",
+        encode_attribute(url),
+        encode_attribute(alt)
+    );
+    match target {
+        Some(target) => link(target, &image),
+        None => image,
+    }
+}
+
+/// Renders a code block the same way comrak does, with the code highlighted if its language
+/// is known, and as plain text otherwise.
+fn code_block(language: Option<&str>, code: &str) -> String {
+    let mut code = code.to_string();
+    if !code.ends_with('\n') {
+        code.push('\n');
+    }
+    match language.filter(|language| !language.is_empty()) {
+        Some(language) => format!(
+            "<pre><code class=\"language-{}\">{}</code></pre>\n",
+            encode_attribute(language),
+            highlight::highlight(language, &code).unwrap_or_else(|| encode_minimal(&code))
+        ),
+        None => format!("<pre><code>{}</code></pre>\n", encode_minimal(&code)),
+    }
+}
+
+/// Renders an admonition, such as a note or a warning, as a block quote.
+fn admonition(title: &str, html: &str) -> String {
+    format!(
+        "<blockquote>\n<p><strong>{}</strong></p>\n{}</blockquote>\n",
+        title, html
+    )
+}
+
+/// Renders list items which consist of a single paragraph without the paragraph, like comrak
+/// does for tight lists.
+fn tight_list_item(html: String) -> String {
+    match html
+        .strip_prefix("<p>")
+        .and_then(|html| html.strip_suffix("</p>\n"))
+    {
+        Some(text) if !text.contains("<p>") => text.to_string(),
+        _ => html,
+    }
+}
+
+/// Renders lines as a paragraph of plain text, for blocks nested deeper than `MAX_DEPTH`.
+fn plain_text(lines: &[String]) -> String {
+    match lines.join("\n").trim() {
+        "" => String::new(),
+        text => format!("<p>{}</p>\n", encode_minimal(text)),
+    }
+}
+
 /// Helper function to build a new `HashSet` from the items slice.
 fn hashset<T>(items: &[T]) -> std::collections::HashSet<T>
 where
@@ -380,6 +591,23 @@ mod tests {
         assert!(result.contains("<code class=\"language-rust\">"));
     }
 
+    #[test]
+    fn code_block_is_highlighted_with_classes() {
+        let code_block = "```rust\nfn main() {}\n```\n";
+        let result = markdown_to_html(code_block, None);
+        assert!(result.contains("<code class=\"language-rust\">"));
+        assert!(result.contains("<span class=\"hl-storage hl-type hl-function\">fn</span>"));
+        assert!(!result.contains("style="));
+    }
+
+    #[test]
+    fn code_block_with_unknown_language_is_not_highlighted() {
+        let code_block = "```brainfuck\n+<>\n```\n";
+        let result = markdown_to_html(code_block, None);
+        assert!(result.contains(">+&lt;&gt;\n</code></pre>"));
+        assert!(!result.contains("<span"));
+    }
+
     #[test]
     fn text_with_forbidden_class_attribute() {
         let text = "<p class='bad-class'>Hello World!</p>";
@@ -473,7 +701,7 @@ mod tests {
 
     #[test]
     fn readme_to_html_renders_other_things() {
-        for f in &["readme.exe", "readem.org", "blah.adoc"] {
+        for f in &["readme.exe", "readem.org", "blah.txt"] {
             assert_eq!(
                 readme_to_html("<script>lobster</script>\n\nis my friend\n", f, None),
                 "&lt;script&gt;lobster&lt;/script&gt;<br>\n<br>\nis my friend<br>\n"
@@ -481,6 +709,62 @@ mod tests {
         }
     }
 
+    #[test]
+    fn readme_to_html_renders_rst() {
+        for f in &["README.rst", "readme.REST"] {
+            assert_eq!(
+                readme_to_html("**lobster** is ``my`` friend", f, None),
+                "<p><strong>lobster</strong> is <code>my</code> friend</p>\n"
+            );
+        }
+    }
+
+    #[test]
+    fn readme_to_html_renders_asciidoc() {
+        for f in &["README.adoc", "readme.asciidoc", "README.asc"] {
+            assert_eq!(
+                readme_to_html("*lobster* is `my` friend", f, None),
+                "<p><strong>lobster</strong> is <code>my</code> friend</p>\n"
+            );
+        }
+    }
+
+    #[test]
+    fn rst_headings_and_code_blocks() {
+        let text = "My crate\n========\n\n.. code-block:: toml\n\n   x = 1\n\n.. raw:: html\n\n   <script>alert(1)</script>\n";
+        assert_eq!(
+            rst_to_html(text, None),
+            "<h1><a href=\"#my-crate\" id=\"user-content-my-crate\" rel=\"nofollow noopener noreferrer\"></a>My crate</h1>\n<pre><code class=\"language-toml\">x = 1\n</code></pre>\n\n"
+        );
+    }
+
+    #[test]
+    fn rst_relative_links() {
+        let text = "See the `guide <docs/guide.md>`_ and |logo|.\n\n.. |logo| image:: logo.png\n";
+        assert_eq!(
+            rst_to_html(text, Some("https://github.com/rust-lang/test")),
+            "<p>See the <a href=\"https://github.com/rust-lang/test/blob/HEAD/docs/guide.md\" rel=\"nofollow noopener noreferrer\">guide</a> and <img src=\"https://github.com/rust-lang/test/raw/HEAD/logo.png\" alt=\"logo\">.</p>\n"
+        );
+    }
+
+    #[test]
+    fn asciidoc_headings_and_code_blocks() {
+        let text = "= My crate\n\n[source,toml]\n----\nx = 1\n----\n\n++++\n<script>alert(1)</script>\n++++\n";
+        assert_eq!(
+            asciidoc_to_html(text, None),
+            "<h1><a href=\"#my-crate\" id=\"user-content-my-crate\" rel=\"nofollow noopener noreferrer\"></a>My crate</h1>\n<pre><code class=\"language-toml\">x = 1\n</code></pre>\n\n"
+        );
+    }
+
+    #[test]
+    fn asciidoc_relative_links() {
+        let text = "See the link:docs/guide.md[guide] and image:logo.png[Logo].\n";
+        assert_eq!(
+            asciidoc_to_html(text, Some("https://github.com/rust-lang/test")),
+            "<p>See the <a href=\"https://github.com/rust-lang/test/blob/HEAD/docs/guide.md\" rel=\"nofollow noopener noreferrer\">guide</a> and <img src=\"https://github.com/rust-lang/test/raw/HEAD/logo.png\" alt=\"Logo\">.</p>\n"
+        );
+    }
+
     #[test]
     fn header_has_tags() {
         let text = "# My crate\n\nHello, world!\n";
@@ -0,0 +1,797 @@
+//! Render AsciiDoc readmes to HTML.
+//!
+//! This supports the subset of AsciiDoc commonly used in readmes: section titles, paragraphs,
+//! lists, delimited blocks, tables, images, admonitions and document attributes.
+
+use htmlescape::encode_minimal;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::{
+    admonition, code_block, dedent, image, indentation, lines, link, plain_text, skip_blank,
+    tight_list_item, HeadingAnchors, MAX_DEPTH,
+};
+
+/// The labels of admonitions, e.g. `NOTE: ...` or `[NOTE]`, and their titles.
+const ADMONITIONS: [(&str, &str); 5] = [
+    ("NOTE", "Note"),
+    ("TIP", "Tip"),
+    ("IMPORTANT", "Important"),
+    ("WARNING", "Warning"),
+    ("CAUTION", "Caution"),
+];
+
+/// Renders AsciiDoc to HTML. The output still needs to be sanitized.
+pub(super) fn to_html(text: &str) -> String {
+    Renderer::default().blocks(&lines(text))
+}
+
+#[derive(Default)]
+struct Renderer {
+    /// The values of the document attributes, e.g. `:repository: https://github.com/...`.
+    attributes: HashMap<String, String>,
+    anchors: HeadingAnchors,
+    /// The nesting of the blocks being rendered.
+    depth: usize,
+}
+
+impl Renderer {
+    /// Renders a sequence of blocks, or plain text if they are nested too deeply.
+    fn blocks(&mut self, lines: &[String]) -> String {
+        if self.depth >= MAX_DEPTH {
+            return plain_text(lines);
+        }
+        self.depth += 1;
+
+        let mut html = String::new();
+        // The attributes of the next block, e.g. `[source,rust]`
+        let mut attributes = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i].as_str();
+            if line.is_empty() {
+                i += 1;
+                continue;
+            }
+            if let Some(anchor) = line.strip_prefix("[[").and_then(|a| a.strip_suffix("]]")) {
+                html += &format!("<a id=\"{}\"></a>\n", encode_minimal(anchor));
+                i += 1;
+                continue;
+            }
+            if let Some(list) = line.strip_prefix('[').and_then(|a| a.strip_suffix(']')) {
+                attributes = parse_attributes(list);
+                i += 1;
+                continue;
+            }
+            if let Some(title) = block_title(line) {
+                html += &format!("<p><strong>{}</strong></p>\n", self.inline(title));
+                i += 1;
+                continue;
+            }
+
+            if is_delimiter(line) {
+                let end = (i + 1..lines.len())
+                    .find(|&j| lines[j] == line)
+                    .unwrap_or(lines.len());
+                html += &self.delimited_block(line, &attributes, &lines[i + 1..end]);
+                i = end + 1;
+            } else if line.starts_with("//") {
+                // Comments
+                i += 1;
+            } else if let Some((name, value)) = attribute_entry(line) {
+                match name.strip_prefix('!').or_else(|| name.strip_suffix('!')) {
+                    Some(name) => self.attributes.remove(name),
+                    None => {
+                        let value = self.substitute(value);
+                        self.attributes.insert(name.to_string(), value)
+                    }
+                };
+                i += 1;
+            } else if let Some((level, title)) = section_title(line) {
+                let html_title = self.inline(title);
+                html += &self.anchors.heading(level, title, &html_title);
+                i += 1;
+            } else if let Some(language) = line.strip_prefix("```") {
+                let end = (i + 1..lines.len())
+                    .find(|&j| lines[j] == "```")
+                    .unwrap_or(lines.len());
+                html += &code_block(Some(language.trim()), &lines[i + 1..end].join("\n"));
+                i = end + 1;
+            } else if ["'''", "---", "- - -", "***", "* * *"].contains(&line) {
+                html.push_str("<hr>\n");
+                i += 1;
+            } else if line == "<<<" {
+                // Page breaks
+                i += 1;
+            } else if let Some(image) = line.strip_prefix("image::") {
+                let image = self.substitute(image);
+                if let Some(start) = image.find('[') {
+                    let attributes = image[start + 1..].trim_end_matches(']');
+                    html += &format!("<p>{}</p>\n", image_macro(&image[..start], attributes));
+                }
+                i += 1;
+            } else if list_item(line).is_some() {
+                let (list, end) = self.list(lines, i);
+                html += &list;
+                i = end;
+            } else if description(line).is_some() {
+                let (list, end) = self.description_list(lines, i);
+                html += &list;
+                i = end;
+            } else if indentation(line) > 0 {
+                // Literal paragraphs
+                let end = (i..lines.len())
+                    .find(|&j| lines[j].is_empty())
+                    .unwrap_or(lines.len());
+                html += &code_block(None, &dedent(&lines[i..end]).join("\n"));
+                i = end;
+            } else {
+                let mut end = i + 1;
+                while lines.get(end).map_or(false, |line| {
+                    !line.is_empty() && !is_delimiter(line) && list_item(line).is_none()
+                }) {
+                    end += 1;
+                }
+                html += &self.paragraph(&lines[i..end].join("\n"), &attributes);
+                i = end;
+            }
+            attributes.clear();
+        }
+        self.depth -= 1;
+        html
+    }
+
+    /// Renders a paragraph, whose style may be changed by its block attributes.
+    fn paragraph(&self, text: &str, attributes: &[String]) -> String {
+        let style = attributes.first().map_or("", String::as_str);
+        let labeled = ADMONITIONS.iter().find_map(|(label, title)| {
+            let text = text.strip_prefix(label)?.strip_prefix(": ")?;
+            Some((*title, text))
+        });
+        if let Some((title, text)) = labeled {
+            return admonition(title, &format!("<p>{}</p>\n", self.inline(text)));
+        }
+
+        let html = format!("<p>{}</p>\n", self.inline(text));
+        match style {
+            "source" | "listing" => code_block(self.source_language(attributes), text),
+            "literal" => code_block(None, text),
+            "quote" | "verse" => format!("<blockquote>\n{}</blockquote>\n", html),
+            _ => match admonition_title(style) {
+                Some(title) => admonition(title, &html),
+                None => html,
+            },
+        }
+    }
+
+    /// Renders a delimited block, e.g. a listing block delimited by `----` lines.
+    fn delimited_block(
+        &mut self,
+        delimiter: &str,
+        attributes: &[String],
+        lines: &[String],
+    ) -> String {
+        let style = attributes.first().map_or("", String::as_str);
+        match delimiter.chars().next() {
+            Some('-') if delimiter.len() > 2 => {
+                code_block(self.source_language(attributes), &lines.join("\n"))
+            }
+            Some('.') => code_block(None, &lines.join("\n")),
+            // Passthrough blocks are sanitized like the raw HTML of Markdown readmes
+            Some('+') => lines.join("\n") + "\n",
+            Some('/') => String::new(),
+            Some('_') => format!("<blockquote>\n{}</blockquote>\n", self.blocks(lines)),
+            Some('|') => self.table(attributes, lines),
+            // Example, sidebar and open blocks
+            _ => match admonition_title(style) {
+                Some(title) => admonition(title, &self.blocks(lines)),
+                None => self.blocks(lines),
+            },
+        }
+    }
+
+    /// Returns the language of a source block, given its block attributes.
+    fn source_language<'a>(&'a self, attributes: &'a [String]) -> Option<&'a str> {
+        match attributes {
+            [style, language, ..] if style == "source" || style.is_empty() => {
+                Some(language.as_str())
+            }
+            [style, ..] if style == "source" => {
+                self.attributes.get("source-language").map(String::as_str)
+            }
+            _ => None,
+        }
+    }
+
+    /// Renders a table, whose cells are separated by `|`.
+    fn table(&self, attributes: &[String], lines: &[String]) -> String {
+        let mut cells = Vec::new();
+        let mut first_row_length = None;
+        let mut has_header = attributes
+            .iter()
+            .any(|attribute| attribute.contains("header"));
+        for (i, line) in lines.iter().enumerate() {
+            let row: Vec<&str> = line.split('|').skip(1).map(str::trim).collect();
+            if row.is_empty() {
+                continue;
+            }
+            // The first line is the header if it is followed by a blank line
+            if first_row_length.is_none() {
+                first_row_length = Some(row.len());
+                has_header |= lines.get(i + 1).map_or(false, |next| next.is_empty());
+            }
+            cells.extend(row);
+        }
+
+        let columns = attributes
+            .iter()
+            .find_map(|attribute| attribute.strip_prefix("cols="))
+            .map(column_count)
+            .or(first_row_length)
+            .unwrap_or(1)
+            .max(1);
+        let mut rows = cells.chunks(columns);
+        let mut html = String::from("<table>\n");
+        if has_header {
+            if let Some(row) = rows.next() {
+                html += &format!("<thead>\n{}</thead>\n", self.table_row(row, "th"));
+            }
+        }
+        html.push_str("<tbody>\n");
+        for row in rows {
+            html += &self.table_row(row, "td");
+        }
+        html.push_str("</tbody>\n</table>\n");
+        html
+    }
+
+    fn table_row(&self, cells: &[&str], tag: &str) -> String {
+        let cells: String = cells
+            .iter()
+            .map(|cell| format!("<{0}>{1}</{0}>", tag, self.inline(cell)))
+            .collect();
+        format!("<tr>{}</tr>\n", cells)
+    }
+
+    /// Renders the list starting at `lines[i]`. Returns the index of the line following it.
+    fn list(&mut self, lines: &[String], mut i: usize) -> (String, usize) {
+        // The items are collected first, as the nesting of lists depends on their markers
+        let mut items: Vec<(String, bool, Vec<String>)> = Vec::new();
+        while let Some(line) = lines.get(i) {
+            if let Some((marker, ordered, text)) = list_item(line) {
+                items.push((marker, ordered, vec![text.to_string()]));
+                i += 1;
+                continue;
+            }
+            let body = match items.last_mut() {
+                Some((_, _, body)) => body,
+                None => break,
+            };
+            if line.is_empty() {
+                // Items may be separated by blank lines
+                let next = skip_blank(lines, i);
+                if lines
+                    .get(next)
+                    .map_or(true, |next| list_item(next).is_none())
+                {
+                    break;
+                }
+                i = next;
+            } else if line == "+" {
+                // List continuations attach a block to the item
+                let end = attached_block_end(lines, i + 1);
+                body.push(String::new());
+                body.extend_from_slice(&lines[i + 1..end]);
+                i = end;
+            } else if is_delimiter(line) || section_title(line).is_some() {
+                break;
+            } else {
+                body.push(line.trim_start().to_string());
+                i += 1;
+            }
+        }
+
+        let mut html = String::new();
+        let mut open: Vec<(String, bool)> = Vec::new();
+        for (marker, ordered, body) in items {
+            match open
+                .iter()
+                .position(|(open_marker, _)| *open_marker == marker)
+            {
+                Some(depth) => {
+                    for (_, ordered) in open.drain(depth + 1..).rev() {
+                        html += &format!("</li>\n{}", list_tag(ordered, true));
+                    }
+                    html.push_str("</li>\n");
+                }
+                None => {
+                    html += list_tag(ordered, false);
+                    open.push((marker, ordered));
+                }
+            }
+            html += &format!("<li>{}", self.item_content(body));
+        }
+        while let Some((_, ordered)) = open.pop() {
+            html += &format!("</li>\n{}", list_tag(ordered, true));
+        }
+        (html, i)
+    }
+
+    /// Renders the content of a list item, which may be a checklist item.
+    fn item_content(&mut self, mut body: Vec<String>) -> String {
+        let checkbox = match body[0].get(..4) {
+            Some("[x] ") | Some("[*] ") => "<input type=\"checkbox\" checked disabled> ",
+            Some("[ ] ") => "<input type=\"checkbox\" disabled> ",
+            _ => "",
+        };
+        if !checkbox.is_empty() {
+            body[0].replace_range(..4, "");
+        }
+        format!("{}{}", checkbox, tight_list_item(self.blocks(&body)))
+    }
+
+    /// Renders the description list starting at `lines[i]`, e.g. `term:: definition`.
+    fn description_list(&mut self, lines: &[String], mut i: usize) -> (String, usize) {
+        let mut html = String::from("<dl>\n");
+        while let Some((term, definition)) = lines.get(i).and_then(|line| description(line)) {
+            let mut end = i + 1;
+            while lines.get(end).map_or(false, |line| {
+                !line.is_empty() && description(line).is_none()
+            }) {
+                end += 1;
+            }
+
+            let mut body = vec![definition.to_string()];
+            body.extend(
+                lines[i + 1..end]
+                    .iter()
+                    .map(|line| line.trim_start().to_string()),
+            );
+            let term = self.inline(term);
+            let definition = tight_list_item(self.blocks(&body));
+            html += &format!("<dt>{}</dt>\n<dd>{}</dd>\n", term, definition);
+            i = skip_blank(lines, end);
+        }
+        html.push_str("</dl>\n");
+        (html, i)
+    }
+
+    /// Replaces the references to document attributes, e.g. `{repository}`, with their values.
+    fn substitute(&self, text: &str) -> String {
+        let mut substituted = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
+            };
+            substituted.push_str(&rest[..start]);
+            match self.attributes.get(&rest[start + 1..end]) {
+                Some(value) => substituted.push_str(value),
+                None => substituted.push_str(&rest[start..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        substituted + rest
+    }
+
+    /// Renders inline markup: formatted text, links, images and cross references.
+    fn inline(&self, text: &str) -> String {
+        let chars: Vec<char> = self.substitute(text).chars().collect();
+        let mut html = String::new();
+        let mut plain = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '\\'
+                && chars
+                    .get(i + 1)
+                    .map_or(false, |&next| "*_`#+\\{<".contains(next))
+            {
+                plain.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+
+            let at_boundary = i == 0 || !chars[i - 1].is_alphanumeric();
+            let markup = match c {
+                '*' | '_' | '`' | '#' => self.quoted_text(&chars, i),
+                '+' if i > 0
+                    && chars[i - 1] == ' '
+                    && chars.get(i + 1).map_or(true, |&next| next == '\n') =>
+                {
+                    // Hard line breaks
+                    Some(("<br>".to_string(), i + 1))
+                }
+                '+' if at_boundary => passthrough(&chars, i),
+                '<' if chars.get(i + 1) == Some(&'<') => cross_reference(&chars, i),
+                _ if at_boundary => self.inline_macro(&chars, i),
+                _ => None,
+            };
+            match markup {
+                Some((markup, next)) => {
+                    html += &encode_minimal(&plain);
+                    plain.clear();
+                    html += &markup;
+                    i = next;
+                }
+                None => {
+                    plain.push(c);
+                    i += 1;
+                }
+            }
+        }
+        html + &encode_minimal(&plain)
+    }
+
+    /// Renders the text enclosed by a pair of `*`, `_`, `` ` `` or `#` starting at `chars[i]`.
+    fn quoted_text(&self, chars: &[char], i: usize) -> Option<(String, usize)> {
+        let mark = chars[i];
+        let (start, end, length) = if chars.get(i + 1) == Some(&mark) {
+            // Unconstrained pairs, e.g. **bold**text, may be used anywhere
+            let end = (i + 3..chars.len().saturating_sub(1))
+                .find(|&j| chars[j] == mark && chars[j + 1] == mark)?;
+            (i + 2, end, 2)
+        } else {
+            // Constrained pairs must be surrounded by word boundaries
+            if i > 0 && chars[i - 1].is_alphanumeric() {
+                return None;
+            }
+            if chars.get(i + 1).map_or(true, |next| next.is_whitespace()) {
+                return None;
+            }
+            let end = (i + 2..chars.len()).find(|&j| {
+                chars[j] == mark
+                    && !chars[j - 1].is_whitespace()
+                    && chars
+                        .get(j + 1)
+                        .map_or(true, |next| !next.is_alphanumeric())
+            })?;
+            (i + 1, end, 1)
+        };
+
+        let text: String = chars[start..end].iter().collect();
+        let html = match mark {
+            '`' => {
+                let text = text
+                    .strip_prefix('+')
+                    .and_then(|text| text.strip_suffix('+'))
+                    .unwrap_or(&text);
+                format!("<code>{}</code>", encode_minimal(text))
+            }
+            '*' => format!("<strong>{}</strong>", self.inline(&text)),
+            '_' => format!("<em>{}</em>", self.inline(&text)),
+            _ => format!("<mark>{}</mark>", self.inline(&text)),
+        };
+        Some((html, end + length))
+    }
+
+    /// Renders the inline macro starting at `chars[i]`, e.g. `link:CONTRIBUTING.adoc[guide]`,
+    /// `image:logo.png[Logo]` or a URL optionally followed by its text in brackets.
+    fn inline_macro(&self, chars: &[char], i: usize) -> Option<(String, usize)> {
+        let prefix: String = chars[i..].iter().take(8).collect();
+        let name = ["https://", "http://", "mailto:", "link:", "image:", "xref:"]
+            .iter()
+            .find(|name| prefix.starts_with(*name))?;
+        // The target of URLs includes their scheme
+        let start = if name.ends_with('/') || *name == "mailto:" {
+            i
+        } else {
+            i + name.len()
+        };
+        let target_end = (start..chars.len())
+            .find(|&j| chars[j].is_whitespace() || "[<>\"".contains(chars[j]))
+            .unwrap_or(chars.len());
+        let target: String = chars[start..target_end].iter().collect();
+
+        if chars.get(target_end) == Some(&'[') {
+            let end = (target_end + 1..chars.len()).find(|&j| chars[j] == ']')?;
+            let text: String = chars[target_end + 1..end].iter().collect();
+            let html = match *name {
+                "image:" => image_macro(&target, &text),
+                "xref:" => {
+                    let text = if text.is_empty() { &target } else { &text };
+                    link(&format!("#{}", target), &encode_minimal(text))
+                }
+                _ => {
+                    // Trailing attributes, e.g. `text,window=_blank` or `text^`, are ignored
+                    let text = match text.find(',') {
+                        Some(comma) if text[comma..].contains('=') => &text[..comma],
+                        _ => text.trim_end_matches('^'),
+                    };
+                    let text = match text {
+                        "" => encode_minimal(&target),
+                        text => self.inline(text),
+                    };
+                    link(&target, &text)
+                }
+            };
+            return Some((html, end + 1));
+        }
+
+        // Bare URLs don't need any text
+        if start != i || target.len() <= name.len() {
+            return None;
+        }
+        let url = target.trim_end_matches(|c| ".,;:!?)'".contains(c));
+        Some((link(url, &encode_minimal(url)), i + url.chars().count()))
+    }
+}
+
+/// Returns whether a line opens or closes a delimited block, e.g. `----` or `|===`.
+fn is_delimiter(line: &str) -> bool {
+    let mut chars = line.chars();
+    let first = match chars.next() {
+        Some(first) => first,
+        None => return false,
+    };
+    line == "--"
+        || line == "|==="
+        || (line.len() >= 4 && "-._=*+/".contains(first) && chars.all(|c| c == first))
+}
+
+/// Returns the end of the block following a list continuation, i.e. a `+` line.
+fn attached_block_end(lines: &[String], start: usize) -> usize {
+    let mut i = start;
+    // The block attributes and title of the attached block
+    while lines.get(i).map_or(false, |line| {
+        (line.starts_with('[') && line.ends_with(']')) || block_title(line).is_some()
+    }) {
+        i += 1;
+    }
+    match lines.get(i) {
+        Some(line) if is_delimiter(line) => (i + 1..lines.len())
+            .find(|&j| lines[j] == *line)
+            .map_or(lines.len(), |end| end + 1),
+        _ => (i..lines.len())
+            .find(|&j| lines[j].is_empty())
+            .unwrap_or(lines.len()),
+    }
+}
+
+/// Parses the attributes of a block or macro, which are separated by commas.
+fn parse_attributes(list: &str) -> Vec<String> {
+    let mut attributes = vec![String::new()];
+    let mut quoted = false;
+    for c in list.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => attributes.push(String::new()),
+            c => {
+                if let Some(attribute) = attributes.last_mut() {
+                    attribute.push(c);
+                }
+            }
+        }
+    }
+    attributes
+        .into_iter()
+        .map(|attribute| attribute.trim().to_string())
+        .collect()
+}
+
+/// Returns the number of columns of a table from its `cols` attribute, e.g. `1,2` or `3*`.
+fn column_count(cols: &str) -> usize {
+    match cols.split('*').next().and_then(|count| count.parse().ok()) {
+        Some(count) if cols.contains('*') => count,
+        _ => cols.split(',').count(),
+    }
+}
+
+/// Parses a document attribute entry, e.g. `:repository: https://github.com/...`.
+fn attribute_entry(line: &str) -> Option<(&str, &str)> {
+    let entry = line.strip_prefix(':')?;
+    let end = entry.find(':')?;
+    let (name, value) = (&entry[..end], &entry[end + 1..]);
+    let is_entry = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '!')
+        && (value.is_empty() || value.starts_with(' '));
+    Some((name, value.trim())).filter(|_| is_entry)
+}
+
+/// Returns the level and text of a section title, e.g. `== Usage`.
+fn section_title(line: &str) -> Option<(usize, &str)> {
+    let marker = line.chars().next().filter(|&c| c == '=' || c == '#')?;
+    let level = line.chars().take_while(|&c| c == marker).count();
+    let title = line[level..].strip_prefix(' ')?.trim();
+    Some((level, title)).filter(|_| level <= 6 && !title.is_empty())
+}
+
+/// Returns the text of a block title, e.g. `.Example`.
+fn block_title(line: &str) -> Option<&str> {
+    let title = line.strip_prefix('.')?;
+    let is_title = title
+        .chars()
+        .next()
+        .map_or(false, |c| !c.is_whitespace() && c != '.');
+    Some(title).filter(|_| is_title)
+}
+
+/// Returns the marker of a list item, whether the list is ordered, and the text of the item.
+fn list_item(line: &str) -> Option<(String, bool, &str)> {
+    let line = line.trim_start();
+    let first = line.chars().next()?;
+    let marker_length = match first {
+        '*' | '.' => line.chars().take_while(|&c| c == first).count(),
+        '-' => 1,
+        c if c.is_ascii_digit() => {
+            let digits = line.chars().take_while(char::is_ascii_digit).count();
+            line[digits..].strip_prefix('.')?;
+            digits + 1
+        }
+        _ => return None,
+    };
+    let text = line[marker_length..].strip_prefix(' ')?.trim_start();
+    let marker = match first {
+        c if c.is_ascii_digit() => "1.".to_string(),
+        _ => line[..marker_length].to_string(),
+    };
+    Some((marker, first != '*' && first != '-', text))
+}
+
+/// Returns the term and definition of a description list item, e.g. `term:: definition`.
+fn description(line: &str) -> Option<(&str, &str)> {
+    let end = line.find("::")?;
+    let (term, definition) = (&line[..end], &line[end + 2..]);
+    let is_description = !term.trim().is_empty()
+        && !line.starts_with(' ')
+        && (definition.is_empty() || definition.starts_with(' '));
+    Some((term.trim(), definition.trim())).filter(|_| is_description)
+}
+
+/// Returns the title of an admonition style, e.g. `NOTE`.
+fn admonition_title(style: &str) -> Option<&'static str> {
+    ADMONITIONS
+        .iter()
+        .find(|(label, _)| *label == style)
+        .map(|(_, title)| *title)
+}
+
+/// Returns the opening or closing tag of a list.
+fn list_tag(ordered: bool, closing: bool) -> &'static str {
+    match (ordered, closing) {
+        (false, false) => "<ul>\n",
+        (false, true) => "</ul>\n",
+        (true, false) => "<ol>\n",
+        (true, true) => "</ol>\n",
+    }
+}
+
+/// Renders an image macro, e.g. `image:logo.png[Logo,link=https://crates.io]`.
+fn image_macro(target: &str, attributes: &str) -> String {
+    let attributes = parse_attributes(attributes);
+    let alt = match attributes
+        .first()
+        .filter(|alt| !alt.is_empty() && !alt.contains('='))
+    {
+        Some(alt) => alt.clone(),
+        // The default alternative text is the name of the file
+        None => Path::new(target).file_stem().map_or(String::new(), |stem| {
+            stem.to_string_lossy().replace(&['-', '_'][..], " ")
+        }),
+    };
+    let link_target = attributes
+        .iter()
+        .find_map(|attribute| attribute.strip_prefix("link="));
+    image(target, &alt, link_target)
+}
+
+/// Renders a passthrough starting at `chars[i]`, e.g. `+{not-an-attribute}+`, as plain text.
+fn passthrough(chars: &[char], i: usize) -> Option<(String, usize)> {
+    if chars.get(i + 1).map_or(true, |next| next.is_whitespace()) {
+        return None;
+    }
+    let end = (i + 2..chars.len()).find(|&j| chars[j] == '+' && !chars[j - 1].is_whitespace())?;
+    let text: String = chars[i + 1..end].iter().collect();
+    Some((encode_minimal(&text), end + 1))
+}
+
+/// Renders a cross reference starting at `chars[i]`, e.g. `<<usage,Usage>>`.
+fn cross_reference(chars: &[char], i: usize) -> Option<(String, usize)> {
+    let end =
+        (i + 2..chars.len().saturating_sub(1)).find(|&j| chars[j] == '>' && chars[j + 1] == '>')?;
+    let reference: String = chars[i + 2..end].iter().collect();
+    let (id, text) = match reference.find(',') {
+        Some(comma) => (reference[..comma].trim(), reference[comma + 1..].trim()),
+        None => (reference.trim(), reference.trim()),
+    };
+    Some((link(&format!("#{}", id), &encode_minimal(text)), end + 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            to_html("* one\n** nested\n* two\n"),
+            "<ul>\n<li>one<ul>\n<li>nested</li>\n</ul>\n</li>\n<li>two</li>\n</ul>\n"
+        );
+        assert_eq!(
+            to_html(". first\n. second\n"),
+            "<ol>\n<li>first</li>\n<li>second</li>\n</ol>\n"
+        );
+        assert_eq!(
+            to_html("* [x] done\n* [ ] todo\n"),
+            "<ul>\n<li><input type=\"checkbox\" checked disabled> done</li>\n\
+             <li><input type=\"checkbox\" disabled> todo</li>\n</ul>\n"
+        );
+        assert_eq!(
+            to_html("* one\n+\n----\ncode\n----\n* two\n"),
+            "<ul>\n<li><p>one</p>\n<pre><code>code\n</code></pre>\n</li>\n<li>two</li>\n</ul>\n"
+        );
+        assert_eq!(
+            to_html("term:: definition\n"),
+            "<dl>\n<dt>term</dt>\n<dd>definition</dd>\n</dl>\n"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        assert_eq!(
+            to_html("[cols=\"2*\"]\n|===\n|A |B\n\n|1 |2\n|===\n"),
+            "<table>\n<thead>\n<tr><th>A</th><th>B</th></tr>\n</thead>\n\
+             <tbody>\n<tr><td>1</td><td>2</td></tr>\n</tbody>\n</table>\n"
+        );
+    }
+
+    #[test]
+    fn delimited_blocks() {
+        assert_eq!(
+            to_html("____\nquoted\n____\n"),
+            "<blockquote>\n<p>quoted</p>\n</blockquote>\n"
+        );
+        assert_eq!(
+            to_html("[NOTE]\n====\nNoted.\n====\n\nTIP: Tipped.\n"),
+            "<blockquote>\n<p><strong>Note</strong></p>\n<p>Noted.</p>\n</blockquote>\n\
+             <blockquote>\n<p><strong>Tip</strong></p>\n<p>Tipped.</p>\n</blockquote>\n"
+        );
+        assert_eq!(
+            to_html("[source,rust]\n----\nfn main() {}\n----\n"),
+            "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn attributes_and_links() {
+        assert_eq!(
+            to_html(":name: world\n\nHello {name}! See https://crates.io[crates.io] and <<usage,Usage>>.\n"),
+            "<p>Hello world! See <a href=\"https&#x3A;&#x2F;&#x2F;crates&#x2E;io\">crates.io</a> \
+             and <a href=\"&#x23;usage\">Usage</a>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn deeply_nested_blocks_are_rendered_as_plain_text() {
+        let quote = |depth| "<blockquote>\n".repeat(depth);
+        let nested = |depth: usize| {
+            let delimiters: Vec<String> = (0..depth).map(|i| "_".repeat(4 + i)).collect();
+            let mut lines = delimiters.clone();
+            lines.push("x".to_string());
+            lines.extend(delimiters.into_iter().rev());
+            lines.join("\n")
+        };
+        assert_eq!(
+            to_html(&nested(MAX_DEPTH)),
+            format!(
+                "{}<p>x</p>\n{}",
+                quote(MAX_DEPTH),
+                "</blockquote>\n".repeat(MAX_DEPTH)
+            )
+        );
+        let delimiter = "_".repeat(MAX_DEPTH + 4);
+        assert!(to_html(&nested(MAX_DEPTH + 1)).contains(&format!(
+            "{}<p>{}\nx\n{}</p>",
+            quote(MAX_DEPTH),
+            delimiter,
+            delimiter
+        )));
+        assert!(to_html(&nested(1_000)).starts_with(&quote(MAX_DEPTH)));
+
+        let list = format!("{}x\n", "- ".repeat(100_000));
+        assert!(to_html(&list).starts_with(&"<ul>\n<li>".repeat(MAX_DEPTH)));
+    }
+}
@@ -0,0 +1,87 @@
+//! Syntax highlighting of the code blocks found in readmes.
+
+use once_cell::sync::Lazy;
+use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+
+/// The prefix of the classes of highlighted spans, one per atom of their scope, so that
+/// `keyword.control.rust` is rendered as `<span class="hl-keyword hl-control hl-rust">`.
+const CLASS_PREFIX: &str = "hl-";
+
+/// The classes of the scope atoms that stylesheets may rely on, out of the ones emitted by
+/// the default syntaxes.  These are the only classes kept on spans by the sanitizer.
+pub(super) static CLASSES: [&str; 50] = [
+    "hl-accessor",
+    "hl-annotation",
+    "hl-attribute-name",
+    "hl-block",
+    "hl-bold",
+    "hl-character",
+    "hl-class",
+    "hl-comment",
+    "hl-constant",
+    "hl-control",
+    "hl-declaration",
+    "hl-definition",
+    "hl-deprecated",
+    "hl-documentation",
+    "hl-double",
+    "hl-entity",
+    "hl-escape",
+    "hl-function",
+    "hl-heading",
+    "hl-illegal",
+    "hl-inherited-class",
+    "hl-interpolated",
+    "hl-invalid",
+    "hl-italic",
+    "hl-keyword",
+    "hl-language",
+    "hl-line",
+    "hl-link",
+    "hl-markup",
+    "hl-modifier",
+    "hl-name",
+    "hl-numeric",
+    "hl-operator",
+    "hl-other",
+    "hl-parameter",
+    "hl-punctuation",
+    "hl-quoted",
+    "hl-raw",
+    "hl-regexp",
+    "hl-section",
+    "hl-separator",
+    "hl-single",
+    "hl-storage",
+    "hl-string",
+    "hl-support",
+    "hl-tag",
+    "hl-terminator",
+    "hl-triple",
+    "hl-type",
+    "hl-variable",
+];
+
+/// Highlights `code` written in `language`, which is either the name of the language or
+/// one of its file extensions, such as `rust` or `rs`.
+///
+/// Returns `None` if the language is unknown, in which case the code should be rendered as
+/// plain text.
+pub(super) fn highlight(language: &str, code: &str) -> Option<String> {
+    let syntax = SYNTAX_SET.find_syntax_by_token(&language.to_lowercase())?;
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(
+        syntax,
+        &SYNTAX_SET,
+        ClassStyle::SpacedPrefixed {
+            prefix: CLASS_PREFIX,
+        },
+    );
+    for line in LinesWithEndings::from(code) {
+        generator.parse_html_for_line_which_includes_newline(line);
+    }
+    Some(generator.finalize())
+}
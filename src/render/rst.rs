@@ -0,0 +1,761 @@
+//! Render reStructuredText readmes to HTML.
+//!
+//! This supports the subset of reStructuredText commonly used in readmes: section titles,
+//! paragraphs, lists, literal and code blocks, images, admonitions, hyperlink targets and
+//! substitutions. Tables are rendered as preformatted text.
+
+use htmlescape::encode_minimal;
+use std::collections::HashMap;
+
+use super::{
+    admonition, autolink, code_block, dedent, image, indentation, lines, link, plain_text,
+    skip_blank, tight_list_item, HeadingAnchors, MAX_DEPTH,
+};
+
+/// Renders reStructuredText to HTML. The output still needs to be sanitized.
+pub(super) fn to_html(text: &str) -> String {
+    let lines = lines(text);
+    let mut renderer = Renderer::default();
+    renderer.collect_targets(&lines);
+    renderer.collect_substitutions(&lines);
+    renderer.blocks(&lines)
+}
+
+#[derive(Default)]
+struct Renderer {
+    /// The URLs of the named hyperlink targets, e.g. `.. _Rust: https://www.rust-lang.org`.
+    targets: HashMap<String, String>,
+    /// The HTML of the substitution definitions, e.g. `.. |build| image:: https://...`.
+    substitutions: HashMap<String, String>,
+    /// The adornment styles of section titles, in the order of their levels.
+    title_styles: Vec<(char, bool)>,
+    anchors: HeadingAnchors,
+    /// The nesting of the body elements being rendered.
+    depth: usize,
+}
+
+impl Renderer {
+    /// Collects the hyperlink targets, which may be referenced before they are defined.
+    fn collect_targets(&mut self, lines: &[String]) {
+        for (i, line) in lines.iter().enumerate() {
+            let definition = match line.trim_start().strip_prefix(".. _") {
+                Some(definition) => definition,
+                None => continue,
+            };
+            let (name, url) = match definition.strip_prefix('`') {
+                Some(quoted) => match quoted.find("`:") {
+                    Some(end) => (&quoted[..end], &quoted[end + 2..]),
+                    None => continue,
+                },
+                None => match definition.find(':') {
+                    Some(end) => (&definition[..end], &definition[end + 1..]),
+                    None => continue,
+                },
+            };
+            // Anonymous targets are not supported
+            if name.is_empty() || name == "_" {
+                continue;
+            }
+
+            // Long URLs may be continued on the following lines
+            let mut url = url.trim().to_string();
+            let continuation = lines[i + 1..]
+                .iter()
+                .take_while(|next| indentation(next) > indentation(line));
+            for next in continuation {
+                url.push_str(next.trim());
+            }
+            self.targets.insert(normalize(name), url);
+        }
+    }
+
+    /// Collects the substitution definitions of images and text.
+    fn collect_substitutions(&mut self, lines: &[String]) {
+        for (i, line) in lines.iter().enumerate() {
+            let definition = match line.trim_start().strip_prefix(".. |") {
+                Some(definition) => definition,
+                None => continue,
+            };
+            let end = match definition.find('|') {
+                Some(end) => end,
+                None => continue,
+            };
+            let name = normalize(&definition[..end]);
+            let directive = definition[end + 1..].trim();
+
+            let body_end = block_end(lines, i + 1, indentation(line) + 1);
+            let body = dedent(&lines[i + 1..body_end]);
+            let (options, _) = directive_options(&body);
+
+            let html = if let Some(url) = directive.strip_prefix("image::") {
+                let alt = options.get("alt").unwrap_or(&name);
+                let target = options.get("target").map(|target| self.target_url(target));
+                image(url.trim(), alt, target.as_deref())
+            } else if let Some(text) = directive.strip_prefix("replace::") {
+                self.inline(text.trim())
+            } else {
+                continue;
+            };
+            self.substitutions.insert(name, html);
+        }
+    }
+
+    /// Returns the URL of a named hyperlink target, following indirect targets.
+    fn target(&self, name: &str) -> Option<&str> {
+        let url = self.targets.get(&normalize(name))?;
+        let indirect = url
+            .strip_suffix('_')
+            .filter(|name| !name.contains(' '))
+            .and_then(|name| self.targets.get(&normalize(name.trim_matches('`'))));
+        Some(indirect.unwrap_or(url).as_str())
+    }
+
+    /// Returns the URL of a `:target:` option, which may reference a hyperlink target.
+    fn target_url(&self, target: &str) -> String {
+        let url = target
+            .strip_suffix('_')
+            .and_then(|name| self.target(name.trim_matches('`')));
+        url.unwrap_or(target).to_string()
+    }
+
+    /// Renders a sequence of body elements, or plain text if they are nested too deeply.
+    fn blocks(&mut self, lines: &[String]) -> String {
+        if self.depth >= MAX_DEPTH {
+            return plain_text(lines);
+        }
+        self.depth += 1;
+
+        let mut html = String::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let line = &lines[i];
+            if line.is_empty() {
+                i += 1;
+            } else if indentation(line) > 0 {
+                let end = block_end(lines, i, 1);
+                html.push_str("<blockquote>\n");
+                html += &self.blocks(&dedent(&lines[i..end]));
+                html.push_str("</blockquote>\n");
+                i = end;
+            } else if line == ".." || line.starts_with(".. ") {
+                let end = block_end(lines, i + 1, 1);
+                html += &self.explicit_markup(line[2..].trim(), &dedent(&lines[i + 1..end]));
+                i = end;
+            } else if let Some((title, style, consumed)) = section_title(lines, i) {
+                html += &self.section(title, style);
+                i += consumed;
+            } else if adornment(line).is_some() && line.len() >= 4 {
+                html.push_str("<hr>\n");
+                i += 1;
+            } else if line.starts_with("+-") || line.starts_with("+=") || line.starts_with(">>>") {
+                // Grid tables and doctest blocks
+                let end = (i..lines.len())
+                    .find(|&j| lines[j].is_empty())
+                    .unwrap_or(lines.len());
+                html += &code_block(None, &lines[i..end].join("\n"));
+                i = end;
+            } else if is_table_border(line) {
+                let end = simple_table_end(lines, i);
+                html += &code_block(None, &lines[i..end].join("\n"));
+                i = end;
+            } else if bullet(line).is_some() {
+                let (list, end) = self.list(lines, i, bullet, "ul");
+                html += &list;
+                i = end;
+            } else if enumerator(line).is_some() {
+                let (list, end) = self.list(lines, i, enumerator, "ol");
+                html += &list;
+                i = end;
+            } else if is_definition(lines, i) {
+                let (list, end) = self.definition_list(lines, i);
+                html += &list;
+                i = end;
+            } else {
+                let (paragraph, end) = self.paragraph(lines, i);
+                html += &paragraph;
+                i = end;
+            }
+        }
+        self.depth -= 1;
+        html
+    }
+
+    /// Renders a section title, whose level depends on the order in which the adornment
+    /// styles are first used.
+    fn section(&mut self, title: &str, style: (char, bool)) -> String {
+        let level = match self.title_styles.iter().position(|s| *s == style) {
+            Some(position) => position + 1,
+            None => {
+                self.title_styles.push(style);
+                self.title_styles.len()
+            }
+        };
+        let html = self.inline(title);
+        self.anchors.heading(level.min(6), title, &html)
+    }
+
+    /// Renders a paragraph, and the literal block following it if it ends with `::`.
+    fn paragraph(&mut self, lines: &[String], i: usize) -> (String, usize) {
+        let mut end = i + 1;
+        while lines.get(end).map_or(false, |line| {
+            !line.is_empty() && indentation(line) == 0 && bullet(line).is_none()
+        }) {
+            end += 1;
+        }
+
+        let mut text = lines[i..end].join("\n");
+        let literal = text.ends_with("::");
+        if literal {
+            let marker = if text == "::" || text.ends_with(" ::") {
+                3
+            } else {
+                1
+            };
+            text.truncate(text.len().saturating_sub(marker));
+        }
+
+        let mut html = String::new();
+        if !text.is_empty() {
+            html += &format!("<p>{}</p>\n", self.inline(&text));
+        }
+
+        let start = skip_blank(lines, end);
+        if literal && lines.get(start).map_or(false, |line| indentation(line) > 0) {
+            end = block_end(lines, start, 1);
+            html += &code_block(None, &dedent(&lines[start..end]).join("\n"));
+        }
+        (html, end)
+    }
+
+    /// Renders the list starting at `lines[i]`, whose items start with the markers recognized
+    /// by `marker`. Returns the index of the line following the list.
+    fn list(
+        &mut self,
+        lines: &[String],
+        mut i: usize,
+        marker: fn(&str) -> Option<usize>,
+        tag: &str,
+    ) -> (String, usize) {
+        let mut html = format!("<{}>\n", tag);
+        while let Some(offset) = lines.get(i).and_then(|line| marker(line)) {
+            let end = block_end(lines, i + 1, offset);
+            let mut item = vec![lines[i][offset..].to_string()];
+            item.extend(
+                lines[i + 1..end]
+                    .iter()
+                    .map(|line| line.get(offset..).unwrap_or("").to_string()),
+            );
+            html += &format!("<li>{}</li>\n", tight_list_item(self.blocks(&item)));
+
+            // Items may be separated by blank lines
+            i = end;
+            let next = skip_blank(lines, i);
+            if lines.get(next).and_then(|line| marker(line)).is_some() {
+                i = next;
+            }
+        }
+        html += &format!("</{}>\n", tag);
+        (html, i)
+    }
+
+    /// Renders the definition list starting at `lines[i]`.
+    fn definition_list(&mut self, lines: &[String], mut i: usize) -> (String, usize) {
+        let mut html = String::from("<dl>\n");
+        loop {
+            // Classifiers of the term, separated by ` : `, are not rendered
+            let term = lines[i].split(" : ").next().unwrap_or("");
+            let end = block_end(lines, i + 1, 1);
+            let term = self.inline(term);
+            let definition = tight_list_item(self.blocks(&dedent(&lines[i + 1..end])));
+            html += &format!("<dt>{}</dt>\n<dd>{}</dd>\n", term, definition);
+
+            i = end;
+            let next = skip_blank(lines, i);
+            if !is_definition(lines, next) {
+                break;
+            }
+            i = next;
+        }
+        html.push_str("</dl>\n");
+        (html, i)
+    }
+
+    /// Renders directives, such as images and code blocks. Comments, hyperlink targets,
+    /// substitution definitions and unsupported directives render nothing.
+    fn explicit_markup(&mut self, head: &str, body: &[String]) -> String {
+        let (directive, argument) = match head.find("::") {
+            Some(end) if !head.starts_with('_') && !head.starts_with('|') => {
+                (head[..end].trim(), head[end + 2..].trim())
+            }
+            _ => return String::new(),
+        };
+        let (options, content) = directive_options(body);
+
+        match directive {
+            "code" | "code-block" | "sourcecode" => code_block(Some(argument), &content.join("\n")),
+            "image" | "figure" => {
+                let alt = options.get("alt").map_or("", String::as_str);
+                let target = options.get("target").map(|target| self.target_url(target));
+                let mut html = format!("<p>{}</p>\n", image(argument, alt, target.as_deref()));
+                // The content of figures is their caption
+                if directive == "figure" {
+                    html += &self.blocks(content);
+                }
+                html
+            }
+            // Raw HTML is sanitized like the raw HTML of Markdown readmes
+            "raw" if argument == "html" => content.join("\n") + "\n",
+            "admonition" => {
+                let title = self.inline(argument);
+                admonition(&title, &self.blocks(content))
+            }
+            "attention" | "caution" | "danger" | "error" | "hint" | "important" | "note"
+            | "tip" | "warning" => {
+                let mut title = directive.to_string();
+                title[..1].make_ascii_uppercase();
+                let mut content = body.to_vec();
+                if !argument.is_empty() {
+                    content.insert(0, argument.to_string());
+                }
+                admonition(&title, &self.blocks(&content))
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Renders inline markup: emphasis, literals, hyperlinks and substitutions.
+    fn inline(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut ends = EndStrings::default();
+        let mut html = String::new();
+        let mut plain = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '\\' {
+                plain.extend(chars.get(i + 1));
+                i += 2;
+                continue;
+            }
+
+            let starts_markup = "`*|:".contains(c)
+                && (i == 0 || is_markup_boundary(chars[i - 1]))
+                && chars.get(i + 1).map_or(false, |next| !next.is_whitespace());
+            if starts_markup {
+                if let Some((markup, next)) = self.markup(&chars, i, &mut ends) {
+                    html += &autolink(&plain);
+                    plain.clear();
+                    html += &markup;
+                    i = next;
+                    continue;
+                }
+            }
+
+            // Simple references to hyperlink targets, e.g. `Rust_`
+            let ends_reference = c == '_'
+                && i > 0
+                && chars[i - 1].is_alphanumeric()
+                && chars
+                    .get(i + 1)
+                    .map_or(true, |next| !next.is_alphanumeric());
+            if ends_reference {
+                let start = plain
+                    .trim_end_matches(|c: char| c.is_alphanumeric() || c == '-')
+                    .len();
+                if let Some(url) = self.target(&plain[start..]) {
+                    let reference = link(url, &encode_minimal(&plain[start..]));
+                    plain.truncate(start);
+                    html += &autolink(&plain);
+                    plain.clear();
+                    html += &reference;
+                    i += if chars.get(i + 1) == Some(&'_') { 2 } else { 1 };
+                    continue;
+                }
+            }
+
+            plain.push(c);
+            i += 1;
+        }
+        html + &autolink(&plain)
+    }
+
+    /// Renders the inline markup starting at `chars[i]`, returning the index of the character
+    /// following it. Returns `None` if the markup isn't terminated.
+    fn markup(&self, chars: &[char], i: usize, ends: &mut EndStrings) -> Option<(String, usize)> {
+        let rest = &chars[i..];
+        if rest.starts_with(&['`', '`']) {
+            let end = ends.find(chars, i + 2, "``")?;
+            let code: String = chars[i + 2..end].iter().collect();
+            return Some((format!("<code>{}</code>", encode_minimal(&code)), end + 2));
+        }
+        if rest.starts_with(&['*', '*']) {
+            let end = ends.find(chars, i + 2, "**")?;
+            let text: String = chars[i + 2..end].iter().collect();
+            return Some((
+                format!("<strong>{}</strong>", encode_minimal(&text)),
+                end + 2,
+            ));
+        }
+
+        match chars[i] {
+            '*' => {
+                let end = ends.find(chars, i + 1, "*")?;
+                let text: String = chars[i + 1..end].iter().collect();
+                Some((format!("<em>{}</em>", encode_minimal(&text)), end + 1))
+            }
+            '`' => {
+                let end = ends.find(chars, i + 1, "`")?;
+                let text: String = chars[i + 1..end].iter().collect();
+                let (is_reference, next) = reference_suffix(chars, end + 1);
+                Some((self.interpreted_text(&text, is_reference), next))
+            }
+            '|' => {
+                let end = ends.find(chars, i + 1, "|")?;
+                let name: String = chars[i + 1..end].iter().collect();
+                let substitution = self.substitutions.get(&normalize(&name))?;
+                let (is_reference, next) = reference_suffix(chars, end + 1);
+                let html = match self.target(&name).filter(|_| is_reference) {
+                    Some(url) => link(url, substitution),
+                    None => substitution.clone(),
+                };
+                Some((html, next))
+            }
+            ':' => {
+                // Roles, e.g. :code:`Vec<T>`
+                let role_end = (i + 1..chars.len())
+                    .find(|&j| !(chars[j].is_alphanumeric() || "-_.+".contains(chars[j])))?;
+                if role_end == i + 1 || !chars[role_end..].starts_with(&[':', '`']) {
+                    return None;
+                }
+                let end = ends.find(chars, role_end + 2, "`")?;
+                let role: String = chars[i + 1..role_end].iter().collect();
+                let text: String = chars[role_end + 2..end].iter().collect();
+                Some((role_html(&role, &text), end + 1))
+            }
+            _ => None,
+        }
+    }
+
+    /// Renders interpreted text, which is either a hyperlink reference or a title reference.
+    fn interpreted_text(&self, text: &str, is_reference: bool) -> String {
+        // Embedded URIs, e.g. `Rust <https://www.rust-lang.org>`_
+        if let Some(start) = text.rfind('<').filter(|_| text.ends_with('>')) {
+            let url = &text[start + 1..text.len() - 1];
+            let label = match text[..start].trim() {
+                "" => url,
+                label => label,
+            };
+            let url = self.target_url(url);
+            return link(&url, &encode_minimal(label));
+        }
+
+        match self.target(text).filter(|_| is_reference) {
+            Some(url) => link(url, &encode_minimal(text)),
+            None if is_reference => encode_minimal(text),
+            None => format!("<cite>{}</cite>", encode_minimal(text)),
+        }
+    }
+}
+
+/// Normalizes the name of a hyperlink target or substitution, which are case-insensitive.
+fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Returns the end of the block starting at `lines[start]` whose lines are indented by at least
+/// `indent`, not including its trailing blank lines.
+fn block_end(lines: &[String], start: usize, indent: usize) -> usize {
+    let mut end = start;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        if line.is_empty() {
+            continue;
+        }
+        if indentation(line) < indent {
+            break;
+        }
+        end = i + 1;
+    }
+    end
+}
+
+/// Returns the adornment character of a line, if it is a section title adornment or a
+/// transition.
+fn adornment(line: &str) -> Option<char> {
+    let c = line.chars().next()?;
+    let is_adornment =
+        line.len() >= 2 && c.is_ascii_punctuation() && line.chars().all(|other| other == c);
+    Some(c).filter(|_| is_adornment)
+}
+
+/// Returns the section title starting at `lines[i]`, its adornment style and the number of
+/// lines it spans.
+fn section_title(lines: &[String], i: usize) -> Option<(&str, (char, bool), usize)> {
+    let line = &lines[i];
+    if let Some(c) = adornment(line) {
+        let title = lines.get(i + 1)?;
+        let is_overlined = !title.trim().is_empty()
+            && lines
+                .get(i + 2)
+                .map_or(false, |underline| adornment(underline) == Some(c));
+        return Some((title.trim(), (c, true), 3)).filter(|_| is_overlined);
+    }
+
+    let underline = lines.get(i + 1)?;
+    let c = adornment(underline)?;
+    // Titles with short underlines are only accepted if they are obviously underlines
+    let length = underline.chars().count();
+    if length < line.chars().count() && length < 4 {
+        return None;
+    }
+    Some((line.trim(), (c, false), 2))
+}
+
+/// Returns the offset of the text of a bullet list item.
+fn bullet(line: &str) -> Option<usize> {
+    let mut chars = line.chars();
+    match (chars.next()?, chars.next()) {
+        ('-', Some(' ')) | ('*', Some(' ')) | ('+', Some(' ')) => Some(2),
+        _ => None,
+    }
+}
+
+/// Returns the offset of the text of an enumerated list item, e.g. `1.`, `(2)` or `#.`.
+fn enumerator(line: &str) -> Option<usize> {
+    let number = line.strip_prefix('(').unwrap_or(line);
+    let digits = number
+        .find(|c: char| !c.is_ascii_digit() && c != '#')
+        .filter(|&digits| digits > 0)?;
+    let suffix = &number[digits..];
+    let is_enumerator = if number.len() < line.len() {
+        suffix.starts_with(") ")
+    } else {
+        suffix.starts_with(". ") || suffix.starts_with(") ")
+    };
+    Some(line.len() - suffix.len() + 2).filter(|_| is_enumerator)
+}
+
+/// Returns whether `lines[i]` is the term of a definition list item, i.e. it is followed by an
+/// indented definition.
+fn is_definition(lines: &[String], i: usize) -> bool {
+    let is_term = lines.get(i).map_or(false, |line| {
+        !line.is_empty() && indentation(line) == 0 && !line.ends_with("::")
+    });
+    is_term
+        && lines
+            .get(i + 1)
+            .map_or(false, |next| !next.is_empty() && indentation(next) > 0)
+}
+
+/// Returns whether a line is the border of a simple table, e.g. `=====  =====`.
+fn is_table_border(line: &str) -> bool {
+    let mut columns = line.split_whitespace();
+    columns.clone().count() > 1 && columns.all(|column| column.chars().all(|c| c == '='))
+}
+
+/// Returns the end of the simple table starting at `lines[i]`, whose last border is followed by
+/// a blank line.
+fn simple_table_end(lines: &[String], i: usize) -> usize {
+    (i + 1..lines.len())
+        .find(|&j| {
+            is_table_border(&lines[j]) && lines.get(j + 1).map_or(true, |next| next.is_empty())
+        })
+        .map_or(lines.len(), |end| end + 1)
+}
+
+/// Parses the options at the start of the content of a directive, e.g. `:alt: Build status`.
+/// Returns the options and the rest of the content.
+fn directive_options(body: &[String]) -> (HashMap<String, String>, &[String]) {
+    let mut options = HashMap::new();
+    let mut i = 0;
+    while let Some(option) = body.get(i).and_then(|line| line.strip_prefix(':')) {
+        let end = match option.find(':') {
+            Some(end) => end,
+            None => break,
+        };
+        let value = option[end + 1..].trim().to_string();
+        options.insert(option[..end].to_string(), value);
+        i += 1;
+    }
+    (options, &body[skip_blank(body, i)..])
+}
+
+/// Returns whether inline markup may start after the given character.
+fn is_markup_boundary(previous: char) -> bool {
+    previous.is_whitespace() || "-:/'\"<([{".contains(previous)
+}
+
+/// Remembers where the end-strings of inline markup were found, so that unterminated markup,
+/// e.g. `*a *a *a`, doesn't search the rest of the text again for each start-string.
+#[derive(Default)]
+struct EndStrings {
+    /// The position each end-string was last searched from, and where it was found.
+    found: HashMap<&'static str, (usize, Option<usize>)>,
+}
+
+impl EndStrings {
+    /// Finds the end-string of inline markup like `find_end`. The end-string found from a
+    /// position is also the one found from any later position before it, and none is found from
+    /// any later position if none was found.
+    fn find(&mut self, chars: &[char], start: usize, end: &'static str) -> Option<usize> {
+        if let Some(&(searched, found)) = self.found.get(end) {
+            if searched <= start && found.map_or(true, |found| found > start) {
+                return found;
+            }
+        }
+        let found = find_end(chars, start, end);
+        self.found.insert(end, (start, found));
+        found
+    }
+}
+
+/// Finds the end-string of inline markup, which must follow some text which doesn't end with
+/// whitespace, and mustn't be followed by an alphanumeric character.
+fn find_end(chars: &[char], start: usize, end: &str) -> Option<usize> {
+    let end: Vec<char> = end.chars().collect();
+    let last = chars.len().checked_sub(end.len())?;
+    (start + 1..=last).find(|&j| {
+        chars[j..].starts_with(&end)
+            && !chars[j - 1].is_whitespace()
+            && chars
+                .get(j + end.len())
+                .map_or(true, |next| !next.is_alphanumeric())
+    })
+}
+
+/// Returns whether interpreted text or a substitution reference ending at `chars[i]` is a
+/// hyperlink reference, i.e. is followed by `_` or `__`, and the index following it.
+fn reference_suffix(chars: &[char], i: usize) -> (bool, usize) {
+    match (chars.get(i), chars.get(i + 1)) {
+        (Some('_'), Some('_')) => (true, i + 2),
+        (Some('_'), _) => (true, i + 1),
+        _ => (false, i),
+    }
+}
+
+/// Renders the text of a role, e.g. :code:`Vec<T>`.
+fn role_html(role: &str, text: &str) -> String {
+    let text = encode_minimal(text);
+    match role {
+        "emphasis" => format!("<em>{}</em>", text),
+        "strong" => format!("<strong>{}</strong>", text),
+        "sub" | "subscript" => format!("<sub>{}</sub>", text),
+        "sup" | "superscript" => format!("<sup>{}</sup>", text),
+        "title-reference" | "title" | "t" => format!("<cite>{}</cite>", text),
+        _ => format!("<code>{}</code>", text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists() {
+        let text = "- one\n- two\n\n  * nested\n\n3. three\n#. four\n";
+        assert_eq!(
+            to_html(text),
+            "<ul>\n<li>one</li>\n<li><p>two</p>\n<ul>\n<li>nested</li>\n</ul>\n</li>\n</ul>\n\
+             <ol>\n<li>three</li>\n<li>four</li>\n</ol>\n"
+        );
+        assert_eq!(
+            to_html("(1) first\n(2) second\n"),
+            "<ol>\n<li>first</li>\n<li>second</li>\n</ol>\n"
+        );
+        assert_eq!(
+            to_html("term\n    definition\n"),
+            "<dl>\n<dt>term</dt>\n<dd>definition</dd>\n</dl>\n"
+        );
+    }
+
+    #[test]
+    fn block_quotes_and_sections() {
+        assert_eq!(
+            to_html("Title\n=====\n\nquote:\n\n    indented\n\n---------\n"),
+            "<h1><a href=\"#title\" id=\"title\"></a>Title</h1>\n<p>quote:</p>\n\
+             <blockquote>\n<p>indented</p>\n</blockquote>\n<hr>\n"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let text = "=====  =====\nA      B\n=====  =====\n1      2\n=====  =====\n";
+        assert_eq!(to_html(text), format!("<pre><code>{}</code></pre>\n", text));
+        assert_eq!(
+            to_html("+---+\n| a |\n+---+\n"),
+            "<pre><code>+---+\n| a |\n+---+\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn directives() {
+        assert_eq!(
+            to_html(".. code-block:: rust\n\n   fn main() {}\n"),
+            "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n"
+        );
+        assert_eq!(
+            to_html(".. note:: Be careful.\n\n.. admonition:: *Custom*\n\n   Text.\n"),
+            "<blockquote>\n<p><strong>Note</strong></p>\n<p>Be careful.</p>\n</blockquote>\n\
+             <blockquote>\n<p><strong><em>Custom</em></strong></p>\n<p>Text.</p>\n</blockquote>\n"
+        );
+        assert_eq!(to_html(".. unknown:: x\n\n   hidden\n"), "");
+        assert_eq!(
+            to_html("Hello |name|!\n\n.. |name| replace:: *world*\n"),
+            "<p>Hello <em>world</em>!</p>\n"
+        );
+    }
+
+    #[test]
+    fn roles() {
+        assert_eq!(
+            to_html(":code:`Vec<T>`, :sup:`2`, :emphasis:`e`, :title:`T` and :unknown:`u`\n"),
+            "<p><code>Vec&lt;T&gt;</code>, <sup>2</sup>, <em>e</em>, <cite>T</cite> and \
+             <code>u</code></p>\n"
+        );
+    }
+
+    #[test]
+    fn targets() {
+        let text = "See Rust_, `the book`_ and `docs <https://docs.rs>`_.\n\n\
+                    .. image:: logo.png\n   :alt: Logo\n   :target: Rust_\n\n\
+                    .. _Rust: https://www.rust-lang.org\n\
+                    .. _the book: Rust_\n";
+        let rust = "https&#x3A;&#x2F;&#x2F;www&#x2E;rust&#x2D;lang&#x2E;org";
+        assert_eq!(
+            to_html(text),
+            format!(
+                "<p>See <a href=\"{0}\">Rust</a>, <a href=\"{0}\">the book</a> and \
+                 <a href=\"https&#x3A;&#x2F;&#x2F;docs&#x2E;rs\">docs</a>.</p>\n\
+                 <p><a href=\"{0}\"><img src=\"logo&#x2E;png\" alt=\"Logo\"></a></p>\n",
+                rust
+            )
+        );
+    }
+
+    #[test]
+    fn deeply_nested_blocks_are_rendered_as_plain_text() {
+        let nested = |depth| format!("{}x\n", "- ".repeat(depth));
+        let list = |depth| "<ul>\n<li>".repeat(depth);
+        assert_eq!(
+            to_html(&nested(MAX_DEPTH)),
+            format!("{}x{}", list(MAX_DEPTH), "</li>\n</ul>\n".repeat(MAX_DEPTH))
+        );
+        assert!(to_html(&nested(MAX_DEPTH + 1)).contains(&format!("{}- x</li>", list(MAX_DEPTH))));
+        assert!(to_html(&nested(100_000)).starts_with(&list(MAX_DEPTH)));
+
+        let indented: String = (1..1_000)
+            .map(|i| format!("{}x\n", " ".repeat(i)))
+            .collect();
+        assert!(to_html(&indented).ends_with("</blockquote>\n"));
+    }
+
+    #[test]
+    fn unterminated_inline_markup() {
+        for markup in &["*", "**", "`", "``", "|", ":code:`"] {
+            let text = format!("{}a ", markup).repeat(20_000);
+            assert_eq!(to_html(&text), format!("<p>{}</p>\n", text.trim_end()));
+        }
+    }
+}
@@ -13,12 +13,18 @@ pub fn build_router(app: &App) -> RouteBuilder {
 
     // Route used by both `cargo search` and the frontend
     api_router.get("/crates", C(krate::search::search));
+    // Route used by the frontend to autocomplete searches
+    api_router.get("/crates/suggest", C(krate::suggest::suggest));
 
     // Routes used by `cargo`
     api_router.put("/crates/new", C(krate::publish::publish));
     api_router.get("/crates/:crate_id/owners", C(krate::owners::owners));
     api_router.put("/crates/:crate_id/owners", C(krate::owners::add_owners));
     api_router.delete("/crates/:crate_id/owners", C(krate::owners::remove_owners));
+    api_router.put(
+        "/crates/:crate_id/owner_rights",
+        C(krate::owners::update_owner_rights),
+    );
     api_router.delete("/crates/:crate_id/:version/yank", C(version::yank::yank));
     api_router.put(
         "/crates/:crate_id/:version/unyank",
@@ -44,6 +50,10 @@ pub fn build_router(app: &App) -> RouteBuilder {
         "/crates/:crate_id/:version/dependencies",
         C(version::metadata::dependencies),
     );
+    api_router.get(
+        "/crates/:crate_id/:version/dependency_tree",
+        C(version::metadata::dependency_tree),
+    );
     api_router.get(
         "/crates/:crate_id/:version/downloads",
         C(version::downloads::downloads),
@@ -62,10 +72,48 @@ pub fn build_router(app: &App) -> RouteBuilder {
     api_router.get("/crates/:crate_id/following", C(krate::follow::following));
     api_router.get("/crates/:crate_id/owner_team", C(krate::owners::owner_team));
     api_router.get("/crates/:crate_id/owner_user", C(krate::owners::owner_user));
+    api_router.get(
+        "/crates/:crate_id/owner_actions",
+        C(krate::owners::owner_actions),
+    );
     api_router.get(
         "/crates/:crate_id/reverse_dependencies",
         C(krate::metadata::reverse_dependencies),
     );
+    api_router.put(
+        "/crates/:crate_id/transfer",
+        C(krate::transfer::request_transfer),
+    );
+    api_router.delete(
+        "/crates/:crate_id/transfer",
+        C(krate::transfer::cancel_transfer),
+    );
+    api_router.get(
+        "/crates/:crate_id/webhooks",
+        C(webhook::list_crate_webhooks),
+    );
+    api_router.put(
+        "/crates/:crate_id/webhooks",
+        C(webhook::create_crate_webhook),
+    );
+    api_router.delete(
+        "/crates/:crate_id/webhooks/:id",
+        C(webhook::delete_crate_webhook),
+    );
+    api_router.get(
+        "/crates/:crate_id/webhooks/:id/deliveries",
+        C(webhook::crate_webhook_deliveries),
+    );
+    api_router.put(
+        "/crates/:crate_id/notification_preferences",
+        C(user::notifications::update_crate_preferences),
+    );
+    api_router.get("/advisories", C(advisory::index));
+    api_router.get("/feeds/crates", C(feed::new_crates));
+    api_router.get("/feeds/crates/:crate_id", C(feed::crate_versions));
+    api_router.get("/feeds/categories/:category_id", C(feed::category_versions));
+    api_router.get("/feeds/keywords/:keyword_id", C(feed::keyword_versions));
+    api_router.get("/feeds/me/updates", C(feed::followed_versions));
     api_router.get("/keywords", C(keyword::index));
     api_router.get("/keywords/:keyword_id", C(keyword::show));
     api_router.get("/categories", C(category::index));
@@ -80,6 +128,15 @@ pub fn build_router(app: &App) -> RouteBuilder {
     api_router.get("/me/tokens", C(token::list));
     api_router.put("/me/tokens", C(token::new));
     api_router.delete("/me/tokens/:id", C(token::revoke));
+    api_router.put("/me/feed_token", C(token::regenerate_feed_token));
+    api_router.delete("/me/feed_token", C(token::revoke_feed_token));
+    api_router.get("/me/webhooks", C(webhook::list_user_webhooks));
+    api_router.put("/me/webhooks", C(webhook::create_user_webhook));
+    api_router.delete("/me/webhooks/:id", C(webhook::delete_user_webhook));
+    api_router.get(
+        "/me/webhooks/:id/deliveries",
+        C(webhook::user_webhook_deliveries),
+    );
     api_router.get(
         "/me/crate_owner_invitations",
         C(crate_owner_invitation::list),
@@ -92,10 +149,30 @@ pub fn build_router(app: &App) -> RouteBuilder {
         "/me/crate_owner_invitations/accept/:token",
         C(crate_owner_invitation::handle_invite_with_token),
     );
+    api_router.put(
+        "/me/crate_ownership_transfers/accept/:token",
+        C(krate::transfer::accept_transfer),
+    );
     api_router.put(
         "/me/email_notifications",
         C(user::me::update_email_notifications),
     );
+    api_router.get(
+        "/me/notification_preferences",
+        C(user::notifications::preferences),
+    );
+    api_router.put(
+        "/me/notification_preferences",
+        C(user::notifications::update_preferences),
+    );
+    api_router.get(
+        "/unsubscribe/:token/:category",
+        C(user::notifications::confirm_unsubscribe),
+    );
+    api_router.post(
+        "/unsubscribe/:token/:category",
+        C(user::notifications::unsubscribe),
+    );
     api_router.get("/summary", C(krate::metadata::summary));
     api_router.put("/confirm/:email_token", C(user::me::confirm_user_email));
     api_router.put(
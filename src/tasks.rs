@@ -1,7 +1,25 @@
+pub mod archive_version_downloads;
 mod daily_db_maintenance;
+mod deliver_webhooks;
 pub mod dump_db;
+mod expire_owner_invitations;
+mod record_team_membership;
+mod refresh_crate_rankings;
+mod refresh_team_memberships;
+mod send_email;
+mod send_version_notifications;
+mod sync_advisories;
 mod update_downloads;
 
+pub use archive_version_downloads::archive_version_downloads;
 pub use daily_db_maintenance::daily_db_maintenance;
+pub use deliver_webhooks::deliver_webhooks;
 pub use dump_db::dump_db;
+pub use expire_owner_invitations::expire_owner_invitations;
+pub use record_team_membership::record_team_membership;
+pub use refresh_crate_rankings::refresh_crate_rankings;
+pub use refresh_team_memberships::refresh_team_memberships;
+pub use send_email::send_email;
+pub use send_version_notifications::send_version_notifications;
+pub use sync_advisories::sync_advisories;
 pub use update_downloads::update_downloads;
@@ -0,0 +1,205 @@
+use crate::background_jobs::Environment;
+use crate::schema::{version_downloads, version_downloads_archives};
+use crate::uploaders::Uploader;
+
+use anyhow::anyhow;
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::Date;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use reqwest::{blocking::Client, header};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use swirl::PerformError;
+
+/// A single row of an archive, matching a row of the `version_downloads` table.
+#[derive(Queryable, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchivedDownload {
+    pub version_id: i32,
+    pub date: NaiveDate,
+    pub downloads: i32,
+}
+
+/// Moves the daily downloads older than `retention_days` out of the `version_downloads` table,
+/// uploading them as compressed CSV files (one for each month) and recording them in the
+/// `version_downloads_archives` table.
+///
+/// Only whole months are archived, so the rows of a month are kept in the database until all of
+/// its days are past the retention window.
+#[swirl::background_job]
+pub fn archive_version_downloads(
+    env: &Environment,
+    conn: &PgConnection,
+    retention_days: i64,
+) -> Result<(), PerformError> {
+    let cutoff = first_day_of_month(Utc::today().naive_utc() - Duration::days(retention_days));
+
+    let mut months: Vec<NaiveDate> = version_downloads::table
+        .filter(version_downloads::date.lt(cutoff))
+        .select(sql::<Date>("date_trunc('month', date)::date"))
+        .distinct()
+        .load(conn)?;
+    months.sort();
+
+    println!("Archiving the downloads of {} months", months.len());
+    for month in months {
+        archive_month(env, conn, month)?;
+    }
+    println!("Finished archiving downloads");
+
+    Ok(())
+}
+
+fn archive_month(
+    env: &Environment,
+    conn: &PgConnection,
+    month: NaiveDate,
+) -> Result<(), PerformError> {
+    use self::version_downloads::dsl::*;
+
+    let already_archived = version_downloads_archives::table
+        .find(month)
+        .select(version_downloads_archives::month)
+        .first::<NaiveDate>(conn)
+        .optional()?;
+    if already_archived.is_some() {
+        // Uploading the month again would overwrite the existing archive, losing its contents.
+        println!("Downloads of {} were already archived, skipping", month);
+        return Ok(());
+    }
+
+    let next_month = first_day_of_month(month + Duration::days(31));
+    let rows: Vec<ArchivedDownload> = version_downloads
+        .filter(date.ge(month))
+        .filter(date.lt(next_month))
+        .select((version_id, date, downloads))
+        .order((date, version_id))
+        .load(conn)?;
+    let total: i64 = rows.iter().map(|row| i64::from(row.downloads)).sum();
+
+    let path = Uploader::version_downloads_archive_path(month);
+    let body = encode_archive(&rows)?;
+    let content_length = body.len() as u64;
+    env.uploader.upload(
+        env.http_client(),
+        &path,
+        Cursor::new(body),
+        content_length,
+        "application/gzip",
+        header::HeaderMap::new(),
+    )?;
+    println!("Uploaded {} rows of {} to {}", rows.len(), month, path);
+
+    conn.transaction(|| {
+        diesel::insert_into(version_downloads_archives::table)
+            .values((
+                version_downloads_archives::month.eq(month),
+                version_downloads_archives::path.eq(&path),
+                version_downloads_archives::downloads.eq(total),
+            ))
+            .execute(conn)?;
+        diesel::delete(
+            version_downloads
+                .filter(date.ge(month))
+                .filter(date.lt(next_month)),
+        )
+        .execute(conn)
+    })?;
+
+    Ok(())
+}
+
+fn first_day_of_month(date: NaiveDate) -> NaiveDate {
+    NaiveDate::from_ymd(date.year(), date.month(), 1)
+}
+
+/// Serializes the rows of an archive as a gzipped CSV file.
+pub fn encode_archive(rows: &[ArchivedDownload]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    writeln!(encoder, "version_id,date,downloads")?;
+    for row in rows {
+        writeln!(encoder, "{},{},{}", row.version_id, row.date, row.downloads)?;
+    }
+    encoder.finish()
+}
+
+/// Parses an archive previously created by `encode_archive`.
+pub fn decode_archive<R: Read>(archive: R) -> anyhow::Result<Vec<ArchivedDownload>> {
+    let mut lines = BufReader::new(GzDecoder::new(archive)).lines();
+
+    // Skip the header
+    lines.next().transpose()?;
+
+    lines
+        .map(|line| {
+            let line = line?;
+            match line.split(',').collect::<Vec<_>>()[..] {
+                [version_id, date, downloads] => Ok(ArchivedDownload {
+                    version_id: version_id.parse()?,
+                    date: date.parse()?,
+                    downloads: downloads.parse()?,
+                }),
+                _ => Err(anyhow!("invalid line in the archive: {}", line)),
+            }
+        })
+        .collect()
+}
+
+/// Fetches and parses the archive of the downloads of a month.
+pub fn fetch_archive(
+    uploader: &Uploader,
+    client: &Client,
+    month: NaiveDate,
+) -> anyhow::Result<Vec<ArchivedDownload>> {
+    let path = Uploader::version_downloads_archive_path(month);
+    match uploader {
+        // Local uploads are only served by the application under a relative URL, so the file
+        // is read directly instead.
+        Uploader::Local => decode_archive(File::open(Uploader::local_uploads_path(&path))?),
+        Uploader::S3 { .. } => {
+            let location = uploader.version_downloads_archive_location(month);
+            decode_archive(client.get(&location).send()?.error_for_status()?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archives_roundtrip() {
+        let rows = vec![
+            ArchivedDownload {
+                version_id: 1,
+                date: NaiveDate::from_ymd(2017, 1, 1),
+                downloads: 10,
+            },
+            ArchivedDownload {
+                version_id: 2,
+                date: NaiveDate::from_ymd(2017, 1, 31),
+                downloads: 0,
+            },
+        ];
+
+        let archive = encode_archive(&rows).unwrap();
+        assert_eq!(decode_archive(&archive[..]).unwrap(), rows);
+        assert_eq!(
+            decode_archive(&encode_archive(&[]).unwrap()[..]).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn months_are_archived_whole() {
+        assert_eq!(
+            first_day_of_month(NaiveDate::from_ymd(2020, 2, 29)),
+            NaiveDate::from_ymd(2020, 2, 1)
+        );
+        assert_eq!(
+            first_day_of_month(NaiveDate::from_ymd(2020, 12, 1) + Duration::days(31)),
+            NaiveDate::from_ymd(2021, 1, 1)
+        );
+    }
+}
@@ -4,9 +4,9 @@
 /// Because the `version_downloads` table includes years of historical data, we can accumulate
 /// a *lot* of garbage before an auto-vacuum is run.
 ///
-/// We only need to keep 90 days of entries in `version_downloads`. Once we have a mechanism to
-/// archive daily download counts and drop historical data, we can drop this task and rely on
-/// auto-vacuum again.
+/// We only need to keep 90 days of entries in `version_downloads`, older entries are moved out of
+/// the table by the `archive_version_downloads` task. Once that task has caught up with the
+/// historical data, we can drop this task and rely on auto-vacuum again.
 use diesel::{sql_query, RunQueryDsl};
 use swirl::PerformError;
 
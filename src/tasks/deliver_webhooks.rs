@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::models::{resolve_webhook_url, Webhook, WebhookDelivery};
+use crate::schema::{webhook_deliveries, webhooks};
+
+/// The number of attempts after which the delivery of an event is given up.
+const MAX_ATTEMPTS: i32 = 8;
+
+/// The maximum number of deliveries attempted by each run of the job.
+const BATCH_SIZE: i64 = 100;
+
+/// How long to wait for a webhook to respond.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the deliveries claimed by a run of the job are left to it, before they can be claimed
+/// again by another run if it didn't record their results, e.g. because it crashed. This is
+/// longer than a whole batch of timed out deliveries.
+const CLAIM_MINUTES: i64 = 30;
+
+/// Delivers the pending webhook events whose next attempt is due.
+///
+/// Each delivery is a `POST` of the JSON payload, signed with the secret of the webhook in the
+/// `X-Crates-Io-Signature` header. Deliveries which fail are retried with an exponential
+/// backoff, starting at one minute, by later runs of this job. It is enqueued whenever events
+/// are triggered, and should also be enqueued periodically so that retries happen.
+///
+/// The job runs in a transaction, so the deliveries are claimed and their results recorded on a
+/// separate connection, without holding any lock while the webhooks are called.
+#[swirl::background_job]
+pub fn deliver_webhooks(env: &Environment) -> Result<(), PerformError> {
+    let conn = env.connection()?;
+
+    let deliveries: Vec<(WebhookDelivery, Webhook)> = conn.transaction(|| {
+        let ids = webhook_deliveries::table
+            .select(webhook_deliveries::id)
+            .filter(webhook_deliveries::next_attempt_at.le(diesel::dsl::now))
+            .order(webhook_deliveries::id)
+            .limit(BATCH_SIZE)
+            .for_update()
+            .skip_locked()
+            .load::<i32>(&*conn)?;
+        let claimed_until = Utc::now().naive_utc() + chrono::Duration::minutes(CLAIM_MINUTES);
+        diesel::update(webhook_deliveries::table.filter(webhook_deliveries::id.eq_any(&ids)))
+            .set(webhook_deliveries::next_attempt_at.eq(claimed_until))
+            .execute(&*conn)?;
+
+        webhook_deliveries::table
+            .inner_join(webhooks::table)
+            .filter(webhook_deliveries::id.eq_any(&ids))
+            .order(webhook_deliveries::id)
+            .load(&*conn)
+    })?;
+
+    println!("Delivering {} webhook events", deliveries.len());
+    for (delivery, webhook) in deliveries {
+        let result = deliver(&delivery, &webhook, env.webhooks_allow_private_urls);
+
+        let attempts = delivery.attempts + 1;
+        let now = Utc::now().naive_utc();
+        let (delivered_at, next_attempt_at, status_code, error) = match result {
+            Ok(status_code) => (Some(now), None, Some(status_code), None),
+            Err(error) => {
+                let next_attempt_at = if attempts < MAX_ATTEMPTS {
+                    Some(now + chrono::Duration::minutes(1 << (attempts - 1)))
+                } else {
+                    None
+                };
+                (
+                    None,
+                    next_attempt_at,
+                    error.status_code,
+                    Some(error.message),
+                )
+            }
+        };
+
+        diesel::update(&delivery)
+            .set((
+                webhook_deliveries::attempts.eq(attempts),
+                webhook_deliveries::status_code.eq(status_code),
+                webhook_deliveries::error.eq(error),
+                webhook_deliveries::delivered_at.eq(delivered_at),
+                webhook_deliveries::next_attempt_at.eq(next_attempt_at),
+            ))
+            .execute(&*conn)?;
+    }
+
+    Ok(())
+}
+
+struct DeliveryError {
+    status_code: Option<i32>,
+    message: String,
+}
+
+impl DeliveryError {
+    fn new(message: impl ToString) -> Self {
+        Self {
+            status_code: None,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Sends a delivery to its webhook, returning the status code of the response.
+fn deliver(
+    delivery: &WebhookDelivery,
+    webhook: &Webhook,
+    allow_private_urls: bool,
+) -> Result<i32, DeliveryError> {
+    // The host is checked again, as its DNS records might have changed since the webhook was
+    // registered, and the request is sent to the checked address rather than resolving it again
+    let (url, addr) =
+        resolve_webhook_url(&webhook.url, allow_private_urls).map_err(DeliveryError::new)?;
+    // Redirects aren't followed, so that the payloads are only sent to the registered URLs
+    let mut client = Client::builder()
+        .timeout(TIMEOUT)
+        .redirect(Policy::none())
+        .no_proxy();
+    if let Some(domain) = url.domain() {
+        client = client.resolve(domain, addr);
+    }
+    let client = client.build().map_err(DeliveryError::new)?;
+
+    let body = delivery.payload.to_string();
+    let response = client
+        .post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .header("X-Crates-Io-Event", &delivery.event)
+        .header("X-Crates-Io-Delivery", delivery.id)
+        .header(
+            "X-Crates-Io-Signature",
+            format!("sha256={}", webhook.sign(body.as_bytes())),
+        )
+        .body(body)
+        .send()
+        .map_err(DeliveryError::new)?;
+
+    let status = response.status();
+    if status.is_success() {
+        Ok(status.as_u16().into())
+    } else {
+        Err(DeliveryError {
+            status_code: Some(status.as_u16().into()),
+            message: format!("the webhook responded with {}", status),
+        })
+    }
+}
@@ -0,0 +1,62 @@
+use crate::background_jobs::Environment;
+use crate::models::{CrateOwnerInvitation, NotificationCategory, NotificationPreferences, User};
+use crate::schema::{crates, users};
+
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// Deletes all the crate owner invitations created more than `expiration_days` ago, and lets the
+/// users who sent them know that they lapsed.
+#[swirl::background_job]
+pub fn expire_owner_invitations(
+    env: &Environment,
+    conn: &PgConnection,
+    expiration_days: i32,
+) -> Result<(), PerformError> {
+    let expired = CrateOwnerInvitation::find_expired(conn, expiration_days)?;
+
+    println!("Expiring {} crate owner invitations", expired.len());
+    for invitation in expired {
+        // The invitation might have been accepted or declined in the meantime, in which case
+        // there is nothing to notify the inviter about.
+        if diesel::delete(&invitation).execute(conn)? == 0 {
+            continue;
+        }
+
+        let crate_name: String = crates::table
+            .find(invitation.crate_id)
+            .select(crates::name)
+            .first(conn)?;
+        let invited_login: String = users::table
+            .find(invitation.invited_user_id)
+            .select(users::gh_login)
+            .first(conn)?;
+        let inviter: User = users::table
+            .find(invitation.invited_by_user_id)
+            .first(conn)?;
+
+        let subscription = NotificationPreferences::subscription(
+            conn,
+            inviter.id,
+            NotificationCategory::Ownership,
+            Some(invitation.crate_id),
+        )?;
+        if let (Some(email), Some(subscription)) = (inviter.verified_email(conn)?, subscription) {
+            if let Err(error) = env.emails().send_owner_invite_expired(
+                conn,
+                &email,
+                &subscription,
+                &invited_login,
+                &crate_name,
+            ) {
+                println!(
+                    "Could not notify {} about the expired invitation for {}: {}",
+                    inviter.gh_login, crate_name, error
+                );
+            }
+        }
+    }
+    println!("Finished expiring crate owner invitations");
+
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use crate::models::TeamMembership;
+
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// Caches the answer of Github about the membership of a user in a team, outside of the
+/// transaction of the request that asked for it.
+#[swirl::background_job]
+pub fn record_team_membership(
+    conn: &PgConnection,
+    team_id: i32,
+    user_id: i32,
+    is_member: bool,
+) -> Result<(), PerformError> {
+    TeamMembership::record(conn, team_id, user_id, is_member)?;
+    Ok(())
+}
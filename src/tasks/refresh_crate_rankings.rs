@@ -0,0 +1,17 @@
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// Refreshes the `crate_rankings` materialized view, holding the popularity and recency signals
+/// used to order search results by relevance.
+///
+/// Reverse dependencies and release dates change slowly, so running this job a few times a day
+/// is enough.
+#[swirl::background_job]
+pub fn refresh_crate_rankings(conn: &PgConnection) -> Result<(), PerformError> {
+    use diesel::select;
+
+    no_arg_sql_function!(refresh_crate_rankings, ());
+    select(refresh_crate_rankings).execute(conn)?;
+    println!("Finished running refresh_crate_rankings");
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use crate::background_jobs::Environment;
+use crate::models::{Team, TeamMembership, User};
+use crate::schema::{team_memberships, teams, users};
+
+use diesel::dsl::{now, IntervalDsl};
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// Asks Github again about all cached team memberships that were last checked more than
+/// `older_than_minutes` ago, so that rights checks rarely have to wait for Github.
+#[swirl::background_job]
+pub fn refresh_team_memberships(
+    env: &Environment,
+    conn: &PgConnection,
+    older_than_minutes: i32,
+) -> Result<(), PerformError> {
+    let stale: Vec<(TeamMembership, Team, User)> = team_memberships::table
+        .inner_join(teams::table)
+        .inner_join(users::table)
+        .filter(team_memberships::checked_at.lt(now - older_than_minutes.minutes()))
+        .load(conn)?;
+
+    println!("Refreshing {} team memberships", stale.len());
+    for (membership, team, user) in stale {
+        match team.contains_user_on_github(env.github(), &user) {
+            Ok(is_member) => TeamMembership::record(conn, membership.team_id, user.id, is_member)?,
+            // Keep the previous answer around, it is still used as a fallback
+            Err(error) => println!(
+                "Could not refresh membership of {} in {}: {}",
+                user.gh_login, team.login, error
+            ),
+        }
+    }
+    println!("Finished refreshing team memberships");
+
+    Ok(())
+}
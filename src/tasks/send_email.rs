@@ -0,0 +1,17 @@
+use crate::background_jobs::Environment;
+
+use swirl::PerformError;
+
+/// Sends an email rendered by `Emails`. If sending fails, the job fails and is retried later.
+#[swirl::background_job]
+pub fn send_email(
+    env: &Environment,
+    recipient: String,
+    subject: String,
+    body: String,
+    html_body: String,
+    unsubscribe_url: Option<String>,
+) -> Result<(), PerformError> {
+    env.emails()
+        .deliver(&recipient, &subject, body, html_body, unsubscribe_url)
+}
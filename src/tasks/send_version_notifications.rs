@@ -0,0 +1,79 @@
+use crate::background_jobs::Environment;
+use crate::models::{
+    NotificationCategory, NotificationPreferences, OwnerKind, User, VersionAction,
+    VersionOwnerAction,
+};
+use crate::schema::{api_tokens, crate_owners, crates, users, version_owner_actions, versions};
+
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// Lets the owners of a crate who are subscribed to publish or yank notifications know that one of
+/// its versions was published or yanked by another owner, and with which API token.
+#[swirl::background_job]
+pub fn send_version_notifications(
+    env: &Environment,
+    conn: &PgConnection,
+    action_id: i32,
+) -> Result<(), PerformError> {
+    let action: VersionOwnerAction = version_owner_actions::table.find(action_id).first(conn)?;
+    let (crate_id, crate_name, num): (i32, String, String) = versions::table
+        .inner_join(crates::table)
+        .filter(versions::id.eq(action.version_id))
+        .select((crates::id, crates::name, versions::num))
+        .first(conn)?;
+    let login: String = users::table
+        .find(action.user_id)
+        .select(users::gh_login)
+        .first(conn)?;
+    let token_name: Option<String> = match action.api_token_id {
+        Some(token_id) => Some(
+            api_tokens::table
+                .find(token_id)
+                .select(api_tokens::name)
+                .first(conn)?,
+        ),
+        None => None,
+    };
+
+    let recipients: Vec<User> = crate_owners::table
+        .inner_join(users::table)
+        .filter(crate_owners::crate_id.eq(crate_id))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+        .filter(crate_owners::deleted.eq(false))
+        .filter(crate_owners::owner_id.ne(action.user_id))
+        .select(users::all_columns)
+        .load(conn)?;
+
+    let category = match action.action {
+        VersionAction::Publish => NotificationCategory::Publish,
+        VersionAction::Yank | VersionAction::Unyank => NotificationCategory::Yank,
+    };
+    for recipient in recipients {
+        let subscription =
+            NotificationPreferences::subscription(conn, recipient.id, category, Some(crate_id))?;
+        let subscription = match subscription {
+            Some(subscription) => subscription,
+            None => continue,
+        };
+        if let Some(email) = recipient.verified_email(conn)? {
+            if let Err(error) = env.emails().send_version_notification(
+                conn,
+                &email,
+                &subscription,
+                &crate_name,
+                &num,
+                action.action,
+                &login,
+                token_name.as_deref(),
+            ) {
+                println!(
+                    "Could not notify {} about {} {}: {}",
+                    recipient.gh_login, crate_name, num, error
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
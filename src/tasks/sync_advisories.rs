@@ -0,0 +1,335 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::models::{Crate, NewAdvisory, NotificationCategory, NotificationPreferences, Owner};
+use crate::schema::{advisories, advisory_db_syncs};
+
+/// Imports the security advisories of a RustSec advisory database, checked out at `path`.
+///
+/// The advisories are read from the `crates/<crate name>/RUSTSEC-*.md` files of the database.
+/// Advisories of crates which aren't published on this registry are skipped, and advisories
+/// which are no longer in the database are deleted. Files which can't be parsed are skipped too,
+/// but the advisory they contained is kept until they're fixed. The owners of a crate are notified
+/// by email when an advisory is added for it, except during the first import of the database and
+/// unless they unsubscribed from advisory notifications.
+#[swirl::background_job]
+pub fn sync_advisories(
+    env: &Environment,
+    conn: &PgConnection,
+    path: String,
+) -> Result<(), PerformError> {
+    let (advisories, invalid) = read_advisory_db(Path::new(&path))?;
+    println!(
+        "Found {} advisories in {}, {} invalid",
+        advisories.len(),
+        path,
+        invalid.len()
+    );
+
+    let first_import = advisory_db_syncs::table.count().get_result::<i64>(conn)? == 0;
+    diesel::insert_into(advisory_db_syncs::table)
+        .default_values()
+        .execute(conn)?;
+
+    let existing = advisories::table
+        .select(advisories::id)
+        .load::<String>(conn)?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    let mut imported = Vec::new();
+    let mut added = Vec::new();
+    for advisory in advisories {
+        let krate = match Crate::by_exact_name(&advisory.package)
+            .first::<Crate>(conn)
+            .optional()?
+        {
+            Some(krate) => krate,
+            None => continue,
+        };
+
+        let new_advisory = advisory.into_new_advisory(krate.id);
+        diesel::insert_into(advisories::table)
+            .values(&new_advisory)
+            .on_conflict(advisories::id)
+            .do_update()
+            .set((&new_advisory, advisories::updated_at.eq(diesel::dsl::now)))
+            .execute(conn)?;
+
+        imported.push(new_advisory.id.clone());
+        if !existing.contains(&new_advisory.id) && new_advisory.withdrawn.is_none() {
+            added.push((krate, new_advisory));
+        }
+    }
+
+    let deleted = diesel::delete(
+        advisories::table
+            .filter(advisories::id.ne_all(&imported))
+            .filter(advisories::id.ne_all(&invalid)),
+    )
+    .execute(conn)?;
+    println!(
+        "Imported {} advisories, {} new, {} deleted",
+        imported.len(),
+        added.len(),
+        deleted
+    );
+
+    if first_import {
+        return Ok(());
+    }
+    for (krate, advisory) in added {
+        for owner in krate.owners(conn)? {
+            let user = match owner {
+                Owner::User(user) => user,
+                Owner::Team(_) => continue,
+            };
+            let subscription = NotificationPreferences::subscription(
+                conn,
+                user.id,
+                NotificationCategory::Advisories,
+                Some(krate.id),
+            )?;
+            let subscription = match subscription {
+                Some(subscription) => subscription,
+                None => continue,
+            };
+            if let Some(email) = user.verified_email(conn)? {
+                if let Err(error) = env.emails().send_advisory_notification(
+                    conn,
+                    &email,
+                    &subscription,
+                    &krate.name,
+                    &advisory.id,
+                    &advisory.title,
+                ) {
+                    println!(
+                        "Could not notify {} about {}: {}",
+                        user.gh_login, advisory.id, error
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// An advisory parsed from a file of the advisory database.
+#[derive(Debug, PartialEq)]
+struct AdvisoryFile {
+    package: String,
+    id: String,
+    title: String,
+    description: String,
+    url: Option<String>,
+    date: NaiveDate,
+    informational: Option<String>,
+    aliases: Vec<String>,
+    patched: Vec<String>,
+    unaffected: Vec<String>,
+    withdrawn: Option<NaiveDate>,
+}
+
+impl AdvisoryFile {
+    fn into_new_advisory(self, crate_id: i32) -> NewAdvisory {
+        NewAdvisory {
+            id: self.id,
+            crate_id,
+            title: self.title,
+            description: self.description,
+            url: self.url,
+            date: self.date,
+            informational: self.informational,
+            aliases: self.aliases,
+            patched: self.patched,
+            unaffected: self.unaffected,
+            withdrawn: self.withdrawn,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FrontMatter {
+    advisory: FrontMatterAdvisory,
+    #[serde(default)]
+    versions: FrontMatterVersions,
+}
+
+#[derive(Deserialize)]
+struct FrontMatterAdvisory {
+    id: String,
+    package: String,
+    date: String,
+    url: Option<String>,
+    /// Only present in the older format, where the markdown body doesn't exist.
+    title: Option<String>,
+    description: Option<String>,
+    informational: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    withdrawn: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct FrontMatterVersions {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+/// Returns the advisories of the database, and the IDs of the ones which couldn't be parsed.
+fn read_advisory_db(path: &Path) -> Result<(Vec<AdvisoryFile>, Vec<String>), PerformError> {
+    let mut advisories = Vec::new();
+    let mut invalid = Vec::new();
+    for crate_dir in fs::read_dir(path.join("crates"))? {
+        let crate_dir = crate_dir?.path();
+        if !crate_dir.is_dir() {
+            continue;
+        }
+        for file in fs::read_dir(&crate_dir)? {
+            let file = file?.path();
+            let is_advisory = file
+                .extension()
+                .map_or(false, |ext| ext == "md" || ext == "toml");
+            if !is_advisory {
+                continue;
+            }
+            // A single invalid advisory mustn't prevent the others from being imported
+            let advisory = fs::read_to_string(&file)
+                .map_err(PerformError::from)
+                .and_then(|contents| parse_advisory(&contents));
+            match advisory {
+                Ok(advisory) => advisories.push(advisory),
+                Err(error) => {
+                    println!("Skipping invalid advisory {}: {}", file.display(), error);
+                    // The files are named after the ID of their advisory
+                    if let Some(id) = file.file_stem().and_then(|stem| stem.to_str()) {
+                        invalid.push(id.to_string());
+                    }
+                }
+            }
+        }
+    }
+    advisories.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok((advisories, invalid))
+}
+
+/// Parses an advisory, either in the markdown format with a TOML front matter, or in the older
+/// TOML-only format.
+fn parse_advisory(contents: &str) -> Result<AdvisoryFile, PerformError> {
+    let (front_matter, body) = match contents.strip_prefix("```toml") {
+        Some(rest) => {
+            let end = rest
+                .find("\n```")
+                .ok_or("the TOML front matter is not terminated")?;
+            (&rest[..end], rest[end + 4..].trim())
+        }
+        None => (contents, ""),
+    };
+    let FrontMatter { advisory, versions } = toml::from_str(front_matter)?;
+
+    // The markdown body starts with a `# Title` line, followed by the description
+    let (title, description) = match body.strip_prefix('#') {
+        Some(body) => {
+            let (title, description) = body.split_at(body.find('\n').unwrap_or(body.len()));
+            (title.trim().to_string(), description.trim().to_string())
+        }
+        None => (
+            advisory.title.ok_or("the advisory has no title")?,
+            advisory.description.unwrap_or_default(),
+        ),
+    };
+
+    let parse_date = |date: &str| NaiveDate::parse_from_str(date, "%F");
+    Ok(AdvisoryFile {
+        package: advisory.package,
+        id: advisory.id,
+        title,
+        description,
+        url: advisory.url,
+        date: parse_date(&advisory.date)?,
+        informational: advisory.informational,
+        aliases: advisory.aliases,
+        patched: versions.patched,
+        unaffected: versions.unaffected,
+        withdrawn: advisory.withdrawn.as_deref().map(parse_date).transpose()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_markdown_advisory() {
+        let contents = r#"```toml
+[advisory]
+id = "RUSTSEC-2021-0001"
+package = "foo"
+date = "2021-01-05"
+url = "https://github.com/foo/foo/issues/1"
+aliases = ["CVE-2021-1234"]
+
+[versions]
+patched = [">= 1.2.3"]
+unaffected = ["< 1.0.0"]
+```
+
+# Use after free in `Foo::bar`
+
+`Foo::bar` can free its buffer twice.
+
+Upgrade to 1.2.3.
+"#;
+        assert_eq!(
+            parse_advisory(contents).unwrap(),
+            AdvisoryFile {
+                package: "foo".into(),
+                id: "RUSTSEC-2021-0001".into(),
+                title: "Use after free in `Foo::bar`".into(),
+                description: "`Foo::bar` can free its buffer twice.\n\nUpgrade to 1.2.3.".into(),
+                url: Some("https://github.com/foo/foo/issues/1".into()),
+                date: NaiveDate::from_ymd(2021, 1, 5),
+                informational: None,
+                aliases: vec!["CVE-2021-1234".into()],
+                patched: vec![">= 1.2.3".into()],
+                unaffected: vec!["< 1.0.0".into()],
+                withdrawn: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_toml_advisory() {
+        let contents = r#"
+[advisory]
+id = "RUSTSEC-2019-0002"
+package = "bar"
+date = "2019-03-01"
+title = "bar is unmaintained"
+description = "Use baz instead."
+informational = "unmaintained"
+withdrawn = "2019-04-01"
+"#;
+        let advisory = parse_advisory(contents).unwrap();
+        assert_eq!(advisory.title, "bar is unmaintained");
+        assert_eq!(advisory.description, "Use baz instead.");
+        assert_eq!(advisory.informational.as_deref(), Some("unmaintained"));
+        assert_eq!(advisory.withdrawn, Some(NaiveDate::from_ymd(2019, 4, 1)));
+        assert!(advisory.patched.is_empty());
+    }
+
+    #[test]
+    fn invalid_advisories_are_rejected() {
+        assert!(parse_advisory("```toml\n[advisory]\nid = \"RUSTSEC-2021-0001\"\n").is_err());
+        assert!(parse_advisory("[advisory]\nid = \"RUSTSEC-2021-0001\"\n").is_err());
+    }
+}
@@ -1,9 +1,9 @@
 use crate::{
-    models::VersionDownload,
-    schema::{crates, metadata, version_downloads, versions},
+    models::{Granularity, VersionDownload},
+    schema::{crates, metadata, version_downloads, version_downloads_rollups, versions},
 };
 
-use diesel::prelude::*;
+use diesel::{pg::upsert::excluded, prelude::*};
 use swirl::PerformError;
 
 #[swirl::background_job]
@@ -66,6 +66,30 @@ fn collect(conn: &PgConnection, rows: &[VersionDownload]) -> QueryResult<()> {
                 .set(metadata::total_downloads.eq(metadata::total_downloads + i64::from(amt)))
                 .execute(conn)?;
 
+            // Update the weekly and monthly rollups
+            for granularity in &[Granularity::Week, Granularity::Month] {
+                diesel::insert_into(version_downloads_rollups::table)
+                    .values((
+                        version_downloads_rollups::version_id.eq(download.version_id),
+                        version_downloads_rollups::granularity.eq(granularity.as_str()),
+                        version_downloads_rollups::period
+                            .eq(granularity.period_start(download.date)),
+                        version_downloads_rollups::downloads.eq(amt),
+                    ))
+                    .on_conflict((
+                        version_downloads_rollups::version_id,
+                        version_downloads_rollups::granularity,
+                        version_downloads_rollups::period,
+                    ))
+                    .do_update()
+                    .set(
+                        version_downloads_rollups::downloads
+                            .eq(version_downloads_rollups::downloads
+                                + excluded(version_downloads_rollups::downloads)),
+                    )
+                    .execute(conn)?;
+            }
+
             // Record that these downloads have been propagated to the other tables.  This is done
             // last, immediately before the transaction is committed, to minimize lock contention
             // with counting new downloads.
@@ -108,6 +132,7 @@ mod test {
             None,
             0,
             user_id,
+            None,
         )
         .unwrap();
         let version = version.save(conn, &[], "someone@example.com").unwrap();
@@ -256,6 +281,74 @@ mod test {
         assert_eq!(version3.downloads, 2);
     }
 
+    #[test]
+    fn update_rollups() {
+        use chrono::NaiveDate;
+        use diesel::dsl::*;
+
+        let conn = crate::db::test_conn();
+        let user = user(&conn);
+        let (_, version) = crate_and_version(&conn, user.id);
+        let day = |day| NaiveDate::from_ymd(2021, 3, day);
+        let insert_downloads = |date, downloads| {
+            insert_into(version_downloads::table)
+                .values((
+                    version_downloads::version_id.eq(version.id),
+                    version_downloads::date.eq(date),
+                    version_downloads::downloads.eq(downloads),
+                ))
+                .on_conflict((version_downloads::version_id, version_downloads::date))
+                .do_update()
+                .set((
+                    version_downloads::downloads.eq(excluded(version_downloads::downloads)),
+                    version_downloads::processed.eq(false),
+                ))
+                .execute(&conn)
+                .unwrap();
+        };
+        let rollups = || {
+            version_downloads_rollups::table
+                .filter(version_downloads_rollups::version_id.eq(version.id))
+                .select((
+                    version_downloads_rollups::granularity,
+                    version_downloads_rollups::period,
+                    version_downloads_rollups::downloads,
+                ))
+                .order((
+                    version_downloads_rollups::granularity,
+                    version_downloads_rollups::period,
+                ))
+                .load::<(String, NaiveDate, i32)>(&conn)
+                .unwrap()
+        };
+
+        // 2021-03-01 is a Monday
+        insert_downloads(day(1), 2);
+        insert_downloads(day(7), 3);
+        insert_downloads(day(8), 1);
+        super::update(&conn).unwrap();
+        assert_eq!(
+            rollups(),
+            vec![
+                ("month".into(), day(1), 6),
+                ("week".into(), day(1), 5),
+                ("week".into(), day(8), 1),
+            ]
+        );
+
+        // Only the new downloads are added to the rollups
+        insert_downloads(day(8), 5);
+        super::update(&conn).unwrap();
+        assert_eq!(
+            rollups(),
+            vec![
+                ("month".into(), day(1), 10),
+                ("week".into(), day(1), 5),
+                ("week".into(), day(8), 5),
+            ]
+        );
+    }
+
     #[test]
     fn set_processed_no_set_updated_at() {
         use diesel::dsl::*;
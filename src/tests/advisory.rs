@@ -0,0 +1,231 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::tasks;
+use cargo_registry::views::EncodableAdvisory;
+use conduit::StatusCode;
+use std::fs;
+use std::path::Path;
+use swirl::Job;
+
+#[derive(Deserialize)]
+struct AdvisoryList {
+    advisories: Vec<EncodableAdvisory>,
+    meta: Meta,
+}
+
+#[derive(Deserialize)]
+struct Meta {
+    total: Option<i64>,
+}
+
+fn write_advisory(db: &Path, crate_name: &str, id: &str, patched: &str) {
+    let dir = db.join("crates").join(crate_name);
+    fs::create_dir_all(&dir).unwrap();
+    let contents = format!(
+        "```toml\n[advisory]\nid = \"{}\"\npackage = \"{}\"\ndate = \"2021-03-01\"\n\n\
+         [versions]\npatched = [\"{}\"]\n```\n\n# Flaw in {}\n\nUpgrade.\n",
+        id, crate_name, patched, crate_name
+    );
+    fs::write(dir.join(format!("{}.md", id)), contents).unwrap();
+}
+
+fn sync_advisories(app: &TestApp, db: &Path) {
+    let path = db.to_str().unwrap().to_string();
+    app.db(|conn| tasks::sync_advisories(path).enqueue(conn).unwrap());
+    app.run_pending_background_jobs();
+}
+
+#[test]
+fn advisories_are_imported_and_matched_against_versions() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("vulnerable", user.id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .version("1.2.0")
+            .expect_build(conn);
+        CrateBuilder::new("safe", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let db = tempfile::tempdir().unwrap();
+    write_advisory(db.path(), "vulnerable", "RUSTSEC-2021-0001", ">= 1.2.0");
+    // Advisories of crates which aren't published on this registry are skipped
+    write_advisory(db.path(), "unknown", "RUSTSEC-2021-0002", ">= 1.0.0");
+    sync_advisories(&app, db.path());
+
+    let json: AdvisoryList = anon.get("/api/v1/advisories").good();
+    assert_eq!(json.meta.total, Some(1));
+    let advisory = &json.advisories[0];
+    assert_eq!(advisory.id, "RUSTSEC-2021-0001");
+    assert_eq!(advisory.krate, "vulnerable");
+    assert_eq!(advisory.title, "Flaw in vulnerable");
+    assert_eq!(advisory.description, "Upgrade.");
+    assert_eq!(advisory.affected_versions, ["1.0.0", "1.1.0"]);
+
+    let json = anon.show_crate("vulnerable");
+    let advisories = json.krate.advisories.unwrap();
+    assert_eq!(advisories.len(), 1);
+    assert_eq!(advisories[0].id, "RUSTSEC-2021-0001");
+    for version in json.versions {
+        let expected: &[&str] = match &*version.num {
+            "1.2.0" => &[],
+            _ => &["RUSTSEC-2021-0001"],
+        };
+        assert_eq!(version.advisories, expected);
+    }
+    let json = anon.show_version("vulnerable", "1.1.0");
+    assert_eq!(json.version.advisories, ["RUSTSEC-2021-0001"]);
+    let json = anon.show_version("safe", "1.0.0");
+    assert!(json.version.advisories.is_empty());
+
+    // Nobody is notified about the advisories of the first import
+    assert!(app.as_inner().emails.mails_in_memory().unwrap().is_empty());
+
+    write_advisory(db.path(), "vulnerable", "RUSTSEC-2021-0003", ">= 1.1.0");
+    sync_advisories(&app, db.path());
+
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to, "something@example.com");
+    assert_eq!(
+        emails[0].subject,
+        "Security advisory RUSTSEC-2021-0003 for vulnerable"
+    );
+    assert!(emails[0].body.contains("Flaw in vulnerable"));
+
+    // Advisories removed from the database are deleted
+    fs::remove_file(db.path().join("crates/vulnerable/RUSTSEC-2021-0001.md")).unwrap();
+    sync_advisories(&app, db.path());
+
+    let json: AdvisoryList = anon
+        .get_with_query("/api/v1/advisories", "crate=vulnerable")
+        .good();
+    assert_eq!(json.advisories.len(), 1);
+    assert_eq!(json.advisories[0].id, "RUSTSEC-2021-0003");
+    assert_eq!(json.advisories[0].affected_versions, ["1.0.0"]);
+    let json: AdvisoryList = anon
+        .get_with_query("/api/v1/advisories", "crate=safe")
+        .good();
+    assert!(json.advisories.is_empty());
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 1);
+}
+
+#[test]
+fn sync_updates_advisories_and_skips_invalid_files() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("vulnerable", user.id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+    });
+    let affected_versions = || {
+        let json: AdvisoryList = anon
+            .get_with_query("/api/v1/advisories", "crate=vulnerable")
+            .good();
+        json.advisories
+            .into_iter()
+            .map(|advisory| (advisory.id, advisory.affected_versions))
+            .collect::<Vec<_>>()
+    };
+
+    let db = tempfile::tempdir().unwrap();
+    write_advisory(db.path(), "vulnerable", "RUSTSEC-2021-0001", ">= 1.1.0");
+    write_advisory(db.path(), "vulnerable", "RUSTSEC-2021-0002", ">= 1.1.0");
+    sync_advisories(&app, db.path());
+    assert_eq!(affected_versions().len(), 2);
+
+    // An invalid file is skipped without preventing the others from being imported, and the
+    // advisory it contained is kept
+    let invalid = db.path().join("crates/vulnerable/RUSTSEC-2021-0002.md");
+    fs::write(
+        &invalid,
+        "```toml\n[advisory]\nid = \"RUSTSEC-2021-0002\"\n",
+    )
+    .unwrap();
+    write_advisory(db.path(), "vulnerable", "RUSTSEC-2021-0001", ">= 2.0.0");
+    write_advisory(db.path(), "vulnerable", "RUSTSEC-2021-0003", ">= 1.1.0");
+    sync_advisories(&app, db.path());
+
+    assert_eq!(
+        affected_versions(),
+        vec![
+            ("RUSTSEC-2021-0003".to_string(), vec!["1.0.0".to_string()]),
+            ("RUSTSEC-2021-0002".to_string(), vec!["1.0.0".to_string()]),
+            (
+                "RUSTSEC-2021-0001".to_string(),
+                vec!["1.0.0".to_string(), "1.1.0".to_string()]
+            ),
+        ]
+    );
+    // Only the owners of the crate with a new advisory are notified, not about updated ones
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(
+        emails[0].subject,
+        "Security advisory RUSTSEC-2021-0003 for vulnerable"
+    );
+
+    // Once the file is deleted, so is the advisory
+    fs::remove_file(&invalid).unwrap();
+    sync_advisories(&app, db.path());
+    let ids = affected_versions()
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, ["RUSTSEC-2021-0003", "RUSTSEC-2021-0001"]);
+}
+
+#[test]
+fn owners_are_notified_of_advisories_after_the_first_import() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("late_vulnerable", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    // The first import doesn't match any published crate
+    let db = tempfile::tempdir().unwrap();
+    write_advisory(db.path(), "unknown", "RUSTSEC-2021-0001", ">= 1.0.0");
+    sync_advisories(&app, db.path());
+    assert!(app.as_inner().emails.mails_in_memory().unwrap().is_empty());
+
+    write_advisory(
+        db.path(),
+        "late_vulnerable",
+        "RUSTSEC-2021-0002",
+        ">= 2.0.0",
+    );
+    sync_advisories(&app, db.path());
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(
+        emails[0].subject,
+        "Security advisory RUSTSEC-2021-0002 for late_vulnerable"
+    );
+
+    // Crate names are matched like in the other endpoints
+    let json: AdvisoryList = anon
+        .get_with_query("/api/v1/advisories", "crate=Late-Vulnerable")
+        .good();
+    assert_eq!(json.advisories.len(), 1);
+    assert_eq!(json.advisories[0].id, "RUSTSEC-2021-0002");
+}
+
+#[test]
+fn advisories_reject_seek_pagination() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get_with_query::<()>("/api/v1/advisories", "seek=");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "this endpoint doesn't support the `seek` parameter" }] })
+    );
+}
@@ -15,7 +15,7 @@ extern crate tracing;
 
 use crate::util::{RequestHelper, TestApp};
 use cargo_registry::{
-    models::{Crate, CrateOwner, NewCategory, NewTeam, NewUser, Team, User},
+    models::{Crate, CrateOwner, NewCategory, NewTeam, NewUser, Rights, Team, User},
     schema::crate_owners,
     views::{
         EncodableCategory, EncodableCategoryWithSubcategories, EncodableCrate, EncodableKeyword,
@@ -30,16 +30,20 @@ use std::{
 use diesel::prelude::*;
 
 mod account_lock;
+mod archived_downloads;
+mod advisory;
 mod authentication;
 mod badge;
 mod builders;
 mod categories;
 mod category;
 mod dump_db;
+mod feed;
 mod git;
 mod keyword;
 mod krate;
 mod metrics;
+mod notification_preferences;
 mod owners;
 mod read_only_mode;
 mod record;
@@ -51,6 +55,7 @@ mod unhealthy_database;
 mod user;
 mod util;
 mod version;
+mod webhook;
 
 #[derive(Deserialize)]
 pub struct CrateList {
@@ -142,6 +147,7 @@ fn add_team_to_crate(t: &Team, krate: &Crate, u: &User, conn: &PgConnection) ->
         created_by: u.id,
         owner_kind: 1, // Team owner kind is 1 according to owner.rs
         email_notifications: true,
+        rights: Rights::PUBLISH | Rights::YANK,
     };
 
     diesel::insert_into(crate_owners::table)
@@ -0,0 +1,101 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::TestApp;
+use cargo_registry::schema::{version_downloads, version_downloads_archives};
+use cargo_registry::tasks::{self, archive_version_downloads::fetch_archive};
+use cargo_registry::Uploader;
+use chrono::{NaiveDate, Utc};
+use diesel::prelude::*;
+use std::fs;
+use swirl::Job;
+
+fn insert_downloads(app: &TestApp, version_id: i32, date: NaiveDate, downloads: i32) {
+    app.db(|conn| {
+        diesel::insert_into(version_downloads::table)
+            .values((
+                version_downloads::version_id.eq(version_id),
+                version_downloads::date.eq(date),
+                version_downloads::downloads.eq(downloads),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+fn archive_version_downloads(app: &TestApp) {
+    app.db(|conn| tasks::archive_version_downloads(90).enqueue(conn).unwrap());
+    app.run_pending_background_jobs();
+}
+
+#[test]
+fn old_downloads_are_archived_once() {
+    let (app, _, user) = TestApp::init()
+        .with_config(|config| config.uploader = Uploader::Local)
+        .with_git_index()
+        .with_job_runner()
+        .with_user();
+    let version_id = app.db(|conn| {
+        let user_id = user.as_model().id;
+        let krate = CrateBuilder::new("archived", user_id).expect_build(conn);
+        VersionBuilder::new("1.0.0")
+            .expect_build(krate.id, user_id, conn)
+            .id
+    });
+
+    let month = NaiveDate::from_ymd(2017, 1, 1);
+    let path = Uploader::local_uploads_path(&Uploader::version_downloads_archive_path(month));
+    let today = Utc::today().naive_utc();
+    insert_downloads(&app, version_id, NaiveDate::from_ymd(2017, 1, 10), 5);
+    insert_downloads(&app, version_id, NaiveDate::from_ymd(2017, 1, 20), 3);
+    insert_downloads(&app, version_id, today, 7);
+
+    archive_version_downloads(&app);
+
+    // The downloads of the old month are moved to an archive, the recent ones are kept
+    let archived: Vec<(NaiveDate, i64)> = app.db(|conn| {
+        version_downloads_archives::table
+            .select((
+                version_downloads_archives::month,
+                version_downloads_archives::downloads,
+            ))
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(archived, vec![(month, 8)]);
+    let remaining: Vec<NaiveDate> = app.db(|conn| {
+        version_downloads::table
+            .select(version_downloads::date)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(remaining, vec![today]);
+
+    let rows = fetch_archive(&Uploader::Local, app.as_inner().http_client(), month).unwrap();
+    let rows: Vec<_> = rows
+        .into_iter()
+        .map(|row| (row.version_id, row.date, row.downloads))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (version_id, NaiveDate::from_ymd(2017, 1, 10), 5),
+            (version_id, NaiveDate::from_ymd(2017, 1, 20), 3),
+        ]
+    );
+
+    // Rows of a month which was already archived are skipped instead of overwriting the archive
+    insert_downloads(&app, version_id, NaiveDate::from_ymd(2017, 1, 25), 1);
+    archive_version_downloads(&app);
+
+    let remaining: i64 = app.db(|conn| {
+        version_downloads::table
+            .filter(version_downloads::date.lt(today))
+            .count()
+            .get_result(conn)
+            .unwrap()
+    });
+    assert_eq!(remaining, 1);
+    let rows = fetch_archive(&Uploader::Local, app.as_inner().http_client(), month).unwrap();
+    assert_eq!(rows.len(), 2);
+
+    fs::remove_file(path).unwrap();
+}
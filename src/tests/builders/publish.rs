@@ -35,6 +35,7 @@ pub struct PublishBuilder {
     license: Option<String>,
     license_file: Option<String>,
     readme: Option<String>,
+    rust_version: Option<String>,
     tarball: Vec<u8>,
     version: semver::Version,
 }
@@ -55,6 +56,7 @@ impl PublishBuilder {
             license: Some("MIT".to_string()),
             license_file: None,
             readme: None,
+            rust_version: None,
             tarball: EMPTY_TARBALL_BYTES.to_vec(),
             version: semver::Version::parse("1.0.0").unwrap(),
         }
@@ -162,6 +164,12 @@ impl PublishBuilder {
         self
     }
 
+    /// Set the minimum supported Rust version of this crate
+    pub fn rust_version(mut self, rust_version: &str) -> Self {
+        self.rust_version = Some(rust_version.into());
+        self
+    }
+
     /// Set the license file for this crate
     pub fn license_file(mut self, license_file: &str) -> Self {
         self.license_file = Some(license_file.into());
@@ -206,6 +214,7 @@ impl PublishBuilder {
             repository: None,
             badges: Some(self.badges),
             links: None,
+            rust_version: self.rust_version,
         };
 
         (serde_json::to_string(&new_crate).unwrap(), self.tarball)
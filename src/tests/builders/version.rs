@@ -1,5 +1,5 @@
 use cargo_registry::{
-    models::{Crate, NewVersion, Version},
+    models::{Crate, DependencyKind, NewVersion, Version},
     schema::{dependencies, versions},
     util::errors::AppResult,
 };
@@ -11,11 +11,18 @@ use diesel::prelude::*;
 /// A builder to create version records for the purpose of inserting directly into the database.
 pub struct VersionBuilder<'a> {
     created_at: Option<NaiveDateTime>,
-    dependencies: Vec<(i32, Option<&'static str>)>,
+    dependencies: Vec<(
+        i32,
+        Option<&'static str>,
+        &'static str,
+        DependencyKind,
+        bool,
+    )>,
     features: HashMap<String, Vec<String>>,
     license: Option<&'a str>,
     license_file: Option<&'a str>,
     num: semver::Version,
+    rust_version: Option<&'a str>,
     size: i32,
     yanked: bool,
 }
@@ -39,6 +46,7 @@ impl<'a> VersionBuilder<'a> {
             license: None,
             license_file: None,
             num,
+            rust_version: None,
             size: 0,
             yanked: false,
         }
@@ -56,9 +64,38 @@ impl<'a> VersionBuilder<'a> {
         self
     }
 
+    /// Sets the version's `rust_version` value.
+    pub fn rust_version(mut self, rust_version: &'a str) -> Self {
+        self.rust_version = Some(rust_version);
+        self
+    }
+
     /// Adds a dependency to this version.
     pub fn dependency(mut self, dependency: &Crate, target: Option<&'static str>) -> Self {
-        self.dependencies.push((dependency.id, target));
+        self.dependencies
+            .push((dependency.id, target, ">= 0", DependencyKind::Normal, false));
+        self
+    }
+
+    /// Adds a dependency to this version, with the given requirement, kind and optionality.
+    pub fn dependency_with(
+        mut self,
+        dependency: &Crate,
+        req: &'static str,
+        kind: DependencyKind,
+        optional: bool,
+    ) -> Self {
+        self.dependencies
+            .push((dependency.id, None, req, kind, optional));
+        self
+    }
+
+    /// Adds a feature to this version.
+    pub fn feature(mut self, name: &str, values: &[&str]) -> Self {
+        self.features.insert(
+            name.into(),
+            values.iter().map(|&value| value.into()).collect(),
+        );
         self
     }
 
@@ -91,6 +128,7 @@ impl<'a> VersionBuilder<'a> {
             self.license_file,
             self.size,
             published_by,
+            self.rust_version,
         )?
         .save(connection, &[], "someone@example.com")?;
 
@@ -109,13 +147,14 @@ impl<'a> VersionBuilder<'a> {
         let new_deps = self
             .dependencies
             .into_iter()
-            .map(|(crate_id, target)| {
+            .map(|(crate_id, target, req, kind, optional)| {
                 (
                     dependencies::version_id.eq(vers.id),
-                    dependencies::req.eq(">= 0"),
+                    dependencies::req.eq(req),
                     dependencies::crate_id.eq(crate_id),
                     dependencies::target.eq(target),
-                    dependencies::optional.eq(false),
+                    dependencies::kind.eq(kind as i32),
+                    dependencies::optional.eq(optional),
                     dependencies::default_features.eq(false),
                     dependencies::features.eq(Vec::<String>::new()),
                 )
@@ -0,0 +1,157 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crate::{new_category, OkBool};
+use cargo_registry::models::Keyword;
+use chrono::NaiveDate;
+use conduit::{header, StatusCode};
+
+#[derive(Deserialize)]
+struct FeedToken {
+    feed_token: String,
+}
+
+#[test]
+fn crate_versions_feed() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let day = |d| NaiveDate::from_ymd(2021, 3, d).and_hms(12, 0, 0);
+    app.db(|conn| {
+        CrateBuilder::new("foo_feed", user.id)
+            .version(VersionBuilder::new("1.0.0").created_at(day(1)))
+            .version(VersionBuilder::new("1.1.0").created_at(day(2)).yanked(true))
+            .version(VersionBuilder::new("2.0.0").created_at(day(3)))
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/api/v1/feeds/crates/foo_feed");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "application/atom+xml; charset=utf-8"
+    );
+    let last_modified = response.headers()[header::LAST_MODIFIED].clone();
+    let xml = response.text();
+    assert!(xml.contains("<title>New versions of foo_feed</title>"));
+    assert!(xml.contains("<id>https://crates.io/crates/foo_feed/1.0.0</id>"));
+    assert!(xml.contains("<published>2021-03-01T12:00:00Z</published>"));
+    assert!(xml.contains("<author><name>foo</name></author>"));
+    assert!(xml.contains("<summary>foo_feed 1.1.0 has been yanked.</summary>"));
+    // The most recent version comes first
+    let newest = xml.find("<title>foo_feed 2.0.0</title>").unwrap();
+    let oldest = xml.find("<title>foo_feed 1.0.0</title>").unwrap();
+    assert!(newest < oldest);
+
+    // Feed readers polling an unchanged feed get a `304 Not Modified`
+    let mut request = anon.get_request("/api/v1/feeds/crates/foo_feed");
+    request.header(header::IF_MODIFIED_SINCE, last_modified.to_str().unwrap());
+    assert_eq!(anon.run::<()>(request).status(), StatusCode::NOT_MODIFIED);
+    let mut request = anon.get_request("/api/v1/feeds/crates/foo_feed");
+    request.header(header::IF_MODIFIED_SINCE, "Mon, 01 Mar 2021 00:00:00 GMT");
+    assert_eq!(anon.run::<()>(request).status(), StatusCode::OK);
+
+    anon.get::<()>("/api/v1/feeds/crates/missing")
+        .assert_not_found();
+}
+
+#[test]
+fn new_crates_feed() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_new", user.id)
+            .description("A & B")
+            .expect_build(conn);
+    });
+
+    let xml = anon.get::<()>("/api/v1/feeds/crates").text();
+    assert!(xml.contains("<title>New crates on crates.io</title>"));
+    assert!(xml.contains("<id>https://crates.io/crates/foo_new</id>"));
+    assert!(xml.contains("<summary>A &amp; B</summary>"));
+}
+
+#[test]
+fn category_and_keyword_feeds() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        new_category("Category 1", "cat1", "Category 1 crates")
+            .create_or_update(conn)
+            .unwrap();
+        CrateBuilder::new("foo_categorized", user.id)
+            .category("cat1")
+            .keyword("kw1")
+            .expect_build(conn);
+        CrateBuilder::new("foo_other", user.id).expect_build(conn);
+        Keyword::find_or_create_all(conn, &["kw2"]).unwrap();
+    });
+
+    for path in &[
+        "/api/v1/feeds/categories/cat1",
+        "/api/v1/feeds/keywords/kw1",
+    ] {
+        let xml = anon.get::<()>(path).text();
+        assert!(xml.contains("<title>foo_categorized 1.0.0</title>"));
+        assert!(!xml.contains("foo_other"));
+    }
+
+    // An empty feed is still valid, but has no `Last-Modified` header
+    let response = anon.get::<()>("/api/v1/feeds/keywords/kw2");
+    assert!(response.headers().get(header::LAST_MODIFIED).is_none());
+    assert!(!response.text().contains("<entry>"));
+
+    anon.get::<()>("/api/v1/feeds/categories/missing")
+        .assert_not_found();
+    anon.get::<()>("/api/v1/feeds/keywords/missing")
+        .assert_not_found();
+}
+
+#[test]
+fn followed_crates_feed_requires_a_feed_token() {
+    let (app, anon, cookie, token) = TestApp::init().with_token();
+    let user = cookie.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_followed", user.id).expect_build(conn);
+        CrateBuilder::new("foo_unfollowed", user.id).expect_build(conn);
+    });
+    cookie
+        .put::<OkBool>("/api/v1/crates/foo_followed/follow", b"")
+        .good();
+
+    anon.get::<()>("/api/v1/feeds/me/updates")
+        .assert_forbidden();
+    anon.get_with_query::<()>("/api/v1/feeds/me/updates", "token=cfdinvalid")
+        .assert_forbidden();
+
+    // Feed tokens can't be created with API tokens
+    token
+        .put::<()>("/api/v1/me/feed_token", b"")
+        .assert_forbidden();
+    let json: FeedToken = cookie.put("/api/v1/me/feed_token", b"").good();
+    assert!(json.feed_token.starts_with("cfd"));
+
+    let query = format!("token={}", json.feed_token);
+    let xml = anon
+        .get_with_query::<()>("/api/v1/feeds/me/updates", &query)
+        .text();
+    assert!(xml.contains("<title>New versions of the crates followed by foo</title>"));
+    assert!(xml.contains("<title>foo_followed 1.0.0</title>"));
+    assert!(!xml.contains("foo_unfollowed"));
+
+    // Feed tokens aren't API tokens
+    let mut request = anon.get_request("/api/v1/me/updates");
+    request.header(header::AUTHORIZATION, &json.feed_token);
+    anon.run::<()>(request).assert_forbidden();
+
+    // Regenerating the token revokes the previous one
+    let regenerated: FeedToken = cookie.put("/api/v1/me/feed_token", b"").good();
+    anon.get_with_query::<()>("/api/v1/feeds/me/updates", &query)
+        .assert_forbidden();
+    let query = format!("token={}", regenerated.feed_token);
+    let response = anon.get_with_query::<()>("/api/v1/feeds/me/updates", &query);
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = cookie.delete::<()>("/api/v1/me/feed_token");
+    assert_eq!(response.status(), StatusCode::OK);
+    anon.get_with_query::<()>("/api/v1/feeds/me/updates", &query)
+        .assert_forbidden();
+}
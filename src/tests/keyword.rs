@@ -110,3 +110,50 @@ fn update_crate() {
     assert_eq!(cnt("kw1"), 0);
     assert_eq!(cnt("kw2"), 0);
 }
+
+#[test]
+fn index_seek_pagination() {
+    #[derive(Deserialize)]
+    struct SeekList {
+        keywords: Vec<EncodableKeyword>,
+        meta: SeekMeta,
+    }
+    #[derive(Deserialize)]
+    struct SeekMeta {
+        total: Option<i64>,
+        next_page: Option<String>,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("seek_kw1", user.id)
+            .keyword("kw_a")
+            .keyword("kw_b")
+            .expect_build(conn);
+        CrateBuilder::new("seek_kw2", user.id)
+            .keyword("kw_b")
+            .keyword("kw_c")
+            .expect_build(conn);
+    });
+
+    let walk = |query: &str| {
+        let mut keywords = vec![];
+        let mut query = query.to_string();
+        loop {
+            let json: SeekList = anon.get_with_query("/api/v1/keywords", &query).good();
+            assert_eq!(json.meta.total, None);
+            keywords.extend(json.keywords.into_iter().map(|kw| kw.keyword));
+            match json.meta.next_page {
+                Some(next_page) => query = next_page.trim_start_matches('?').to_string(),
+                None => return keywords,
+            }
+        }
+    };
+
+    assert_eq!(walk("per_page=1&seek="), vec!["kw_a", "kw_b", "kw_c"]);
+    assert_eq!(
+        walk("sort=crates&per_page=2&seek="),
+        vec!["kw_b", "kw_a", "kw_c"]
+    );
+}
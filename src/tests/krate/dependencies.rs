@@ -1,6 +1,7 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
-use cargo_registry::views::EncodableDependency;
+use cargo_registry::models::DependencyKind;
+use cargo_registry::views::{EncodableDependency, EncodableDependencyTreeNode};
 use http::StatusCode;
 
 #[derive(Deserialize)]
@@ -8,6 +9,29 @@ pub struct Deps {
     pub dependencies: Vec<EncodableDependency>,
 }
 
+#[derive(Deserialize)]
+struct Tree {
+    nodes: Vec<EncodableDependencyTreeNode>,
+    meta: TreeMeta,
+}
+
+#[derive(Deserialize)]
+struct TreeMeta {
+    truncated: bool,
+}
+
+impl Tree {
+    fn versions(&self) -> Vec<String> {
+        let mut versions = self
+            .nodes
+            .iter()
+            .map(|node| format!("{} {}", node.krate, node.num))
+            .collect::<Vec<_>>();
+        versions.sort();
+        versions
+    }
+}
+
 #[test]
 fn dependencies() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -33,3 +57,99 @@ fn dependencies() {
         json!({ "errors": [{ "detail": "crate `foo_deps` does not have a version `1.0.2`" }] })
     );
 }
+
+#[test]
+fn dependency_tree() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let root = CrateBuilder::new("tree_root", user.id).expect_build(conn);
+        let a = CrateBuilder::new("tree_a", user.id).expect_build(conn);
+        let b = CrateBuilder::new("tree_b", user.id).expect_build(conn);
+        let optional = CrateBuilder::new("tree_optional", user.id).expect_build(conn);
+        let windows = CrateBuilder::new("tree_windows", user.id).expect_build(conn);
+        let dev = CrateBuilder::new("tree_dev", user.id).expect_build(conn);
+
+        VersionBuilder::new("0.1.0").expect_build(b.id, user.id, conn);
+        VersionBuilder::new("1.0.0").expect_build(a.id, user.id, conn);
+        VersionBuilder::new("1.2.0")
+            .dependency_with(&b, "^0.1", DependencyKind::Normal, false)
+            .expect_build(a.id, user.id, conn);
+        VersionBuilder::new("1.3.0")
+            .yanked(true)
+            .expect_build(a.id, user.id, conn);
+        VersionBuilder::new("2.0.0").expect_build(a.id, user.id, conn);
+        VersionBuilder::new("1.0.0").expect_build(optional.id, user.id, conn);
+        VersionBuilder::new("1.0.0").expect_build(windows.id, user.id, conn);
+        VersionBuilder::new("1.0.0").expect_build(dev.id, user.id, conn);
+
+        VersionBuilder::new("1.0.0")
+            .feature("default", &["fast"])
+            .feature("fast", &["tree_optional"])
+            .dependency_with(&a, "^1", DependencyKind::Normal, false)
+            .dependency_with(&b, "^0.2", DependencyKind::Build, false)
+            .dependency_with(&optional, "^1", DependencyKind::Normal, true)
+            .dependency_with(&dev, "^1", DependencyKind::Dev, false)
+            .dependency(&windows, Some("cfg(windows)"))
+            .expect_build(root.id, user.id, conn);
+    });
+
+    let url = "/api/v1/crates/tree_root/1.0.0/dependency_tree";
+    let tree: Tree = anon.get(url).good();
+    assert_eq!(
+        tree.versions(),
+        [
+            "tree_a 1.2.0",
+            "tree_b 0.1.0",
+            "tree_optional 1.0.0",
+            "tree_root 1.0.0",
+            "tree_windows 1.0.0",
+        ]
+    );
+    assert!(!tree.meta.truncated);
+    let root = &tree.nodes[0];
+    assert_eq!(root.krate, "tree_root");
+    assert_eq!(root.features, ["default", "fast"]);
+    let b = root
+        .dependencies
+        .iter()
+        .find(|dep| dep.crate_id == "tree_b")
+        .unwrap();
+    assert_eq!(b.node, None, "no version of tree_b matches `^0.2`");
+    assert!(root
+        .dependencies
+        .iter()
+        .all(|dep| dep.crate_id != "tree_dev"));
+
+    let tree: Tree = anon.get_with_query(url, "default_features=no").good();
+    assert!(!tree.versions().contains(&"tree_optional 1.0.0".into()));
+    let tree: Tree = anon
+        .get_with_query(url, "default_features=no&features=fast")
+        .good();
+    assert!(tree.versions().contains(&"tree_optional 1.0.0".into()));
+
+    let tree: Tree = anon
+        .get_with_query(url, "target=x86_64-unknown-linux-gnu")
+        .good();
+    assert!(!tree.versions().contains(&"tree_windows 1.0.0".into()));
+    let tree: Tree = anon
+        .get_with_query(url, "target=x86_64-pc-windows-msvc")
+        .good();
+    assert!(tree.versions().contains(&"tree_windows 1.0.0".into()));
+
+    let tree: Tree = anon.get_with_query(url, "depth=1").good();
+    assert!(tree.meta.truncated);
+    assert!(!tree.versions().contains(&"tree_b 0.1.0".into()));
+    let a = tree
+        .nodes
+        .iter()
+        .find(|node| node.krate == "tree_a")
+        .unwrap();
+    assert!(a.truncated);
+
+    let response = anon.get_with_query::<()>(url, "depth=11");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = anon.get_with_query::<()>(url, "default_features=maybe");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
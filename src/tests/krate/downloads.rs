@@ -1,7 +1,9 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
-use cargo_registry::views::EncodableVersionDownload;
-use chrono::{Duration, Utc};
+use cargo_registry::views::{EncodableVersionDownload, EncodableVersionDownloadByClient};
+use chrono::{Duration, NaiveDate, Utc};
+use conduit::{header, Method};
+use diesel::prelude::*;
 use http::StatusCode;
 
 #[derive(Deserialize)]
@@ -67,7 +69,7 @@ fn download() {
     let yesterday = (Utc::today() + Duration::days(-1)).format("%F");
     let query = format!("before_date={}", yesterday);
     assert_dl_count("FOO_DOWNLOAD/1.0.0", Some(&query), 0);
-    // crate/downloads always returns the last 90 days and ignores date params
+    // crate/downloads ignores the legacy `before_date` param
     assert_dl_count("FOO_DOWNLOAD", Some(&query), 2);
 
     let tomorrow = (Utc::today() + Duration::days(1)).format("%F");
@@ -76,6 +78,203 @@ fn download() {
     assert_dl_count("FOO_DOWNLOAD", Some(&query), 2);
 }
 
+#[derive(Deserialize)]
+struct DownloadsByClient {
+    version_downloads_by_client: Vec<EncodableVersionDownloadByClient>,
+    meta: DownloadsByClientMeta,
+}
+
+#[derive(Deserialize)]
+struct DownloadsByClientMeta {
+    clients: Vec<ClientDownloads>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct ClientDownloads {
+    client: String,
+    downloads: i64,
+}
+
+#[test]
+fn downloads_by_client() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_clients", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .version(VersionBuilder::new("1.1.0"))
+            .expect_build(conn);
+    });
+
+    let download = |version: &str, user_agent: &str| {
+        let url = format!("/api/v1/crates/foo_clients/{}/download", version);
+        let mut request = anon.request_builder(Method::GET, &url);
+        request.header(header::USER_AGENT, user_agent);
+        assert_eq!(anon.run::<()>(request).status(), StatusCode::FOUND);
+    };
+
+    download("1.0.0", "cargo 1.50.0 (f04e7fab7 2021-02-04)");
+    download("1.1.0", "cargo 1.50.0 (f04e7fab7 2021-02-04)");
+    download("1.1.0", "cargo 1.51.0 (43b129a20 2021-03-16)");
+    download("1.1.0", "curl/7.64.1");
+    download("1.1.0", "Googlebot/2.1 (+http://www.google.com/bot.html)");
+    app.as_inner()
+        .downloads_counter
+        .persist_all_shards(app.as_inner())
+        .expect("failed to persist downloads count");
+
+    let json: DownloadsByClient = anon
+        .get_with_query("/api/v1/crates/foo_clients/downloads", "by=client")
+        .good();
+    assert_eq!(json.version_downloads_by_client.len(), 5);
+    let client = |client: &str, downloads| ClientDownloads {
+        client: client.into(),
+        downloads,
+    };
+    assert_eq!(
+        json.meta.clients,
+        vec![
+            client("cargo 1.50", 2),
+            client("cargo 1.51", 1),
+            client("curl", 1),
+            client("other", 1),
+        ]
+    );
+
+    let response = anon.get_with_query::<()>("/api/v1/crates/foo_clients/downloads", "by=os");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn duplicate_downloads_are_not_counted() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.downloads_dedup_window_secs = 60)
+        .with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_dedup", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .version(VersionBuilder::new("1.1.0"))
+            .expect_build(conn);
+    });
+
+    let download = |version: &str, ip: &str| {
+        let url = format!("/api/v1/crates/foo_dedup/{}/download", version);
+        let mut request = anon.request_builder(Method::GET, &url);
+        request.header("x-real-ip", ip);
+        // Discarded downloads are still served
+        assert_eq!(anon.run::<()>(request).status(), StatusCode::FOUND);
+    };
+
+    download("1.0.0", "192.0.2.1");
+    download("1.0.0", "192.0.2.1");
+    download("1.0.0", "192.0.2.2");
+    download("1.1.0", "192.0.2.1");
+    app.as_inner()
+        .downloads_counter
+        .persist_all_shards(app.as_inner())
+        .expect("failed to persist downloads count");
+
+    let json: Downloads = anon.get("/api/v1/crates/foo_dedup/downloads").good();
+    let total: i32 = json.version_downloads.iter().map(|vd| vd.downloads).sum();
+    assert_eq!(total, 3);
+
+    let discarded = app
+        .as_inner()
+        .instance_metrics
+        .downloads_discarded_total
+        .with_label_values(&["duplicate"])
+        .get();
+    assert_eq!(discarded, 1);
+}
+
+#[test]
+fn downloads_rollups() {
+    use cargo_registry::schema::{version_downloads_rollups, versions};
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_rollups", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+        let version_id: i32 = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .select(versions::id)
+            .first(conn)
+            .unwrap();
+
+        let rollups = [
+            ("month", NaiveDate::from_ymd(2020, 1, 1), 10),
+            ("month", NaiveDate::from_ymd(2020, 2, 1), 20),
+            ("month", NaiveDate::from_ymd(2020, 3, 1), 30),
+            ("week", NaiveDate::from_ymd(2020, 3, 2), 5),
+        ];
+        for &(granularity, period, downloads) in &rollups {
+            diesel::insert_into(version_downloads_rollups::table)
+                .values((
+                    version_downloads_rollups::version_id.eq(version_id),
+                    version_downloads_rollups::granularity.eq(granularity),
+                    version_downloads_rollups::period.eq(period),
+                    version_downloads_rollups::downloads.eq(downloads),
+                ))
+                .execute(conn)
+                .unwrap();
+        }
+    });
+
+    let dates = |url: &str, query: &str| {
+        let json: Downloads = anon.get_with_query(url, query).good();
+        json.version_downloads
+            .into_iter()
+            .map(|download| (download.date, download.downloads))
+            .collect::<Vec<_>>()
+    };
+
+    for url in &[
+        "/api/v1/crates/foo_rollups/downloads",
+        "/api/v1/crates/foo_rollups/1.0.0/downloads",
+    ] {
+        assert_eq!(
+            dates(url, "granularity=month"),
+            vec![
+                ("2020-01-01".to_string(), 10),
+                ("2020-02-01".to_string(), 20),
+                ("2020-03-01".to_string(), 30),
+            ]
+        );
+        assert_eq!(
+            dates(url, "granularity=month&since=2020-02-01&until=2020-02-29"),
+            vec![("2020-02-01".to_string(), 20)]
+        );
+        assert_eq!(
+            dates(url, "granularity=week"),
+            vec![("2020-03-02".to_string(), 5)]
+        );
+        // Daily downloads default to the last 90 days
+        assert_eq!(dates(url, "granularity=day"), vec![]);
+
+        let response = anon.get_with_query::<()>(url, "granularity=year");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = anon.get_with_query::<()>(url, "since=yesterday");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response =
+            anon.get_with_query::<()>(url, "granularity=month&since=2020-03-01&until=2020-02-01");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Daily downloads are limited to 90 days
+        assert_eq!(
+            dates(url, "granularity=day&since=2020-01-01&until=2020-03-30"),
+            vec![]
+        );
+        let response =
+            anon.get_with_query::<()>(url, "granularity=day&since=2020-01-01&until=2020-03-31");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
 #[test]
 fn download_nonexistent_version_of_existing_crate_404s() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -6,6 +6,7 @@ mod publish;
 mod reverse_dependencies;
 mod search;
 mod show;
+mod suggest;
 mod summary;
 mod versions;
 mod yanking;
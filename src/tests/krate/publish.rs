@@ -1,6 +1,6 @@
 use crate::builders::{CrateBuilder, DependencyBuilder, PublishBuilder};
-use crate::new_category;
 use crate::util::{RequestHelper, TestApp};
+use crate::{new_category, VersionResponse};
 use cargo_registry::controllers::krate::publish::{
     missing_metadata_error_message, MISSING_RIGHTS_ERROR_MESSAGE, WILDCARD_ERROR_MESSAGE,
 };
@@ -109,6 +109,8 @@ fn invalid_names() {
     bad_name("compiler-rt", error_message);
     bad_name("compiler_rt", error_message);
     bad_name("coMpiLer_Rt", error_message);
+    // Reserved so that `GET /api/v1/crates/suggest` doesn't shadow a crate
+    bad_name("suggest", error_message);
 }
 
 #[test]
@@ -144,6 +146,30 @@ fn new_krate_weird_version() {
     assert_eq!(json.krate.max_version, "0.0.0-pre");
 }
 
+#[test]
+fn new_krate_with_rust_version() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_msrv").rust_version("1.50");
+    token.enqueue_publish(crate_to_publish).good();
+
+    let json: VersionResponse = anon.show_version("foo_msrv", "1.0.0");
+    assert_eq!(json.version.rust_version.as_deref(), Some("1.50"));
+}
+
+#[test]
+fn new_krate_with_invalid_rust_version() {
+    let (_, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_msrv").rust_version("^1.50");
+    let response = token.enqueue_publish(crate_to_publish);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "invalid rust-version `^1.50`; it must be a bare version number with two or three components, like `1.50`" }] })
+    );
+}
+
 #[test]
 fn new_with_renamed_dependency() {
     let (app, _, user, token) = TestApp::full().with_token();
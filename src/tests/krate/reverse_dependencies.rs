@@ -215,3 +215,44 @@ fn reverse_dependencies_query_supports_u64_version_number_parts() {
     assert_eq!(deps.versions[0].krate, "c2");
     assert_eq!(deps.versions[0].num, large_but_valid_version_number);
 }
+
+#[test]
+fn reverse_dependencies_seek_pagination() {
+    #[derive(Deserialize)]
+    struct SeekRevDeps {
+        versions: Vec<EncodableVersion>,
+        meta: SeekMeta,
+    }
+    #[derive(Deserialize)]
+    struct SeekMeta {
+        total: Option<i64>,
+        next_page: Option<String>,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c1 = CrateBuilder::new("c1", user.id).expect_build(conn);
+        for (name, downloads) in &[("c2", 10), ("c3", 30), ("c4", 10)] {
+            CrateBuilder::new(name, user.id)
+                .downloads(*downloads)
+                .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+                .expect_build(conn);
+        }
+    });
+
+    let url = "/api/v1/crates/c1/reverse_dependencies";
+    let mut dependents = vec![];
+    let mut query = "per_page=1&seek=".to_string();
+    loop {
+        let json: SeekRevDeps = anon.get_with_query(url, &query).good();
+        assert_eq!(json.meta.total, None);
+        dependents.extend(json.versions.into_iter().map(|version| version.krate));
+        match json.meta.next_page {
+            Some(next_page) => query = next_page.trim_start_matches('?').to_string(),
+            None => break,
+        }
+    }
+    assert_eq!(dependents, vec!["c3", "c2", "c4"]);
+}
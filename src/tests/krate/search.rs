@@ -1,8 +1,10 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
-use crate::util::{RequestHelper, TestApp};
+use crate::util::{MockAnonymousUser, RequestHelper, TestApp};
 use crate::{new_category, new_user};
-use cargo_registry::models::Category;
+use cargo_registry::models::{Category, DependencyKind};
 use cargo_registry::schema::crates;
+use cargo_registry::SearchRankingWeights;
+use chrono::{Duration, Utc};
 use diesel::{dsl::*, prelude::*, update};
 use http::StatusCode;
 
@@ -172,6 +174,108 @@ fn exact_match_first_on_queries() {
     assert_eq!(json.crates[2].name, "foo_exact");
 }
 
+#[test]
+fn fuzzy_search_and_suggestions() {
+    #[derive(Deserialize)]
+    struct SearchResponse {
+        crates: Vec<cargo_registry::views::EncodableCrate>,
+        meta: SearchMeta,
+    }
+    #[derive(Deserialize)]
+    struct SearchMeta {
+        suggestion: Option<String>,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("tokio", user.id).expect_build(conn);
+        CrateBuilder::new("serde", user.id).expect_build(conn);
+        CrateBuilder::new("serde_json", user.id).expect_build(conn);
+    });
+
+    let search = |query: &str| {
+        let json: SearchResponse = anon.get_with_query("/api/v1/crates", query).good();
+        let names = json
+            .crates
+            .into_iter()
+            .map(|krate| krate.name)
+            .collect::<Vec<_>>();
+        (names, json.meta.suggestion)
+    };
+
+    // Nothing matches the typos exactly, so crates with a similar name are returned instead
+    assert_eq!(
+        search("q=tokoi"),
+        (vec!["tokio".to_string()], Some("tokio".to_string()))
+    );
+    assert_eq!(
+        search("q=serde_jsn"),
+        (
+            vec!["serde_json".to_string(), "serde".to_string()],
+            Some("serde_json".to_string())
+        )
+    );
+
+    // No suggestion is made when a crate has exactly the name being searched for
+    assert_eq!(search("q=tokio"), (vec!["tokio".to_string()], None));
+    assert_eq!(search("q=unrelated"), (vec![], None));
+}
+
+#[test]
+fn relevance_blends_popularity_and_recency() {
+    fn search_parser(app: TestApp, anon: MockAnonymousUser, owner_id: i32) -> Vec<String> {
+        app.db(|conn| {
+            let popular = CrateBuilder::new("popular", owner_id)
+                .description("A parser")
+                .recent_downloads(1000)
+                .expect_build(conn);
+            // Keyword stuffing gives a better text rank, but nobody uses this crate anymore
+            CrateBuilder::new("abandoned", owner_id)
+                .description("parser parser parser parsing parser")
+                .version(
+                    VersionBuilder::new("1.0.0")
+                        .created_at(Utc::now().naive_utc() - Duration::days(3 * 365)),
+                )
+                .expect_build(conn);
+            CrateBuilder::new("dependent", owner_id)
+                .version(VersionBuilder::new("1.0.0").dependency(&popular, None))
+                .expect_build(conn);
+
+            no_arg_sql_function!(refresh_crate_rankings, ());
+            select(refresh_crate_rankings).execute(conn).unwrap();
+        });
+
+        anon.search("q=parser")
+            .crates
+            .into_iter()
+            .map(|krate| krate.name)
+            .collect()
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    assert_eq!(
+        search_parser(app, anon, user.as_model().id),
+        ["popular", "abandoned"]
+    );
+
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.search_ranking_weights = SearchRankingWeights {
+                text: 1.0,
+                downloads: 0.0,
+                dependents: 0.0,
+                recency: 0.0,
+            };
+        })
+        .with_user();
+    assert_eq!(
+        search_parser(app, anon, user.as_model().id),
+        ["abandoned", "popular"]
+    );
+}
+
 #[test]
 #[allow(clippy::cognitive_complexity)]
 fn index_sorting() {
@@ -512,6 +616,281 @@ fn index_include_yanked() {
     assert_eq!(json.crates[2].name, "unyanked");
 }
 
+#[test]
+fn filters_can_be_combined() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("mit_readme", user.id)
+            .readme("readme")
+            .keyword("kw1")
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT OR Apache-2.0")))
+            .expect_build(conn);
+
+        CrateBuilder::new("mit_no_readme", user.id)
+            .keyword("kw1")
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("MIT/Apache-2.0"))
+                    .rust_version("1.50"),
+            )
+            .expect_build(conn);
+
+        CrateBuilder::new("mit0_readme", user.id)
+            .readme("readme")
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("MIT-CMU"))
+                    .rust_version("1.51.0"),
+            )
+            .expect_build(conn);
+
+        // The license and rust-version of the latest non-yanked version are used
+        CrateBuilder::new("relicensed", user.id)
+            .keyword("kw1")
+            .version(VersionBuilder::new("1.0.0").license(Some("GPL-3.0")))
+            .version(
+                VersionBuilder::new("2.0.0")
+                    .license(Some("MIT"))
+                    .rust_version("1.45"),
+            )
+            .version(
+                VersionBuilder::new("3.0.0")
+                    .license(Some("GPL-3.0"))
+                    .yanked(true),
+            )
+            .expect_build(conn);
+
+        let old = CrateBuilder::new("old", user.id).expect_build(conn);
+        update(&old)
+            .set(crates::updated_at.eq(now - 100.days()))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let names = |query: &str| {
+        anon.search(&format!("{}&sort=alpha", query))
+            .crates
+            .into_iter()
+            .map(|krate| krate.name)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        names("license=mit"),
+        ["mit_no_readme", "mit_readme", "relicensed"]
+    );
+    assert_eq!(names("license=MIT-CMU"), ["mit0_readme"]);
+    assert_eq!(names("license=GPL-3.0"), Vec::<String>::new());
+    assert_eq!(names("has_readme=yes"), ["mit0_readme", "mit_readme"]);
+    assert_eq!(
+        names("has_readme=no"),
+        ["mit_no_readme", "old", "relicensed"]
+    );
+    assert_eq!(names("rust_version=1.50"), ["mit_no_readme", "relicensed"]);
+    assert_eq!(
+        names("rust_version=1.51"),
+        ["mit0_readme", "mit_no_readme", "relicensed"]
+    );
+    assert_eq!(
+        names("updated_within=30"),
+        ["mit0_readme", "mit_no_readme", "mit_readme", "relicensed"]
+    );
+
+    assert_eq!(names("license=MIT&has_readme=yes"), ["mit_readme"]);
+    assert_eq!(
+        names("keyword=kw1&license=MIT&rust_version=1.50.0"),
+        ["mit_no_readme", "relicensed"]
+    );
+    assert_eq!(
+        names("letter=m&keyword=kw1"),
+        ["mit_no_readme", "mit_readme"]
+    );
+    assert_eq!(names(&format!("user_id={}&letter=o", user.id)), ["old"]);
+
+    for query in &[
+        "license=MIT%20OR%20Apache-2.0",
+        "has_readme=maybe",
+        "rust_version=1",
+        "updated_within=0",
+    ] {
+        let response = anon.get_with_query::<()>("/api/v1/crates", query);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[test]
+fn depends_on() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let shared = CrateBuilder::new("shared", user.id)
+            .version("1.0.0")
+            .version("1.5.0")
+            .version("2.0.0")
+            .expect_build(conn);
+
+        CrateBuilder::new("a_normal", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency_with(
+                &shared,
+                "^1.2",
+                DependencyKind::Normal,
+                false,
+            ))
+            .expect_build(conn);
+        CrateBuilder::new("b_dev", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency_with(
+                &shared,
+                "^1",
+                DependencyKind::Dev,
+                false,
+            ))
+            .expect_build(conn);
+        CrateBuilder::new("c_optional", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency_with(
+                &shared,
+                "^2",
+                DependencyKind::Normal,
+                true,
+            ))
+            .expect_build(conn);
+        // Only the latest version of a crate is considered
+        CrateBuilder::new("d_dropped", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&shared, None))
+            .version("2.0.0")
+            .expect_build(conn);
+    });
+
+    let names = |query: &str| {
+        anon.search(&format!("{}&sort=alpha", query))
+            .crates
+            .into_iter()
+            .map(|krate| krate.name)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        names("depends_on=shared"),
+        ["a_normal", "b_dev", "c_optional"]
+    );
+    assert_eq!(names("depends_on=shared&kind=dev"), ["b_dev"]);
+    assert_eq!(names("depends_on=shared&optional=yes"), ["c_optional"]);
+    assert_eq!(
+        names("depends_on=shared&optional=no&kind=normal"),
+        ["a_normal"]
+    );
+
+    // Requirements match the published versions of `shared`
+    assert_eq!(names("depends_on=shared&req=^1"), ["a_normal", "b_dev"]);
+    assert_eq!(names("depends_on=shared&req=^1&kind=normal"), ["a_normal"]);
+    assert_eq!(names("depends_on=shared&req=>=2"), ["c_optional"]);
+    // Versions are matched even when they aren't published
+    assert_eq!(names("depends_on=shared&req=1.1.0"), ["b_dev"]);
+    assert_eq!(names("depends_on=shared&req=2.1.0"), ["c_optional"]);
+    assert_eq!(names("depends_on=shared&req=3.0.0"), Vec::<String>::new());
+
+    // Other filters can be combined with `depends_on`
+    assert_eq!(names("depends_on=shared&letter=b"), ["b_dev"]);
+    assert_eq!(names("depends_on=unknown"), Vec::<String>::new());
+
+    for query in &[
+        "kind=dev",
+        "depends_on=shared&kind=peer",
+        "depends_on=shared&optional=maybe",
+        "depends_on=shared&req=not-a-req",
+    ] {
+        let response = anon.get_with_query::<()>("/api/v1/crates", query);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[test]
+fn search_facets() {
+    #[derive(Deserialize)]
+    struct FacetsResponse {
+        meta: FacetsMeta,
+    }
+    #[derive(Deserialize)]
+    struct FacetsMeta {
+        facets: Option<Facets>,
+    }
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Facets {
+        categories: Vec<FacetCount>,
+        keywords: Vec<FacetCount>,
+    }
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct FacetCount {
+        id: String,
+        count: i64,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        new_category("Category 1", "cat1", "Category 1 crates")
+            .create_or_update(conn)
+            .unwrap();
+        new_category("Category 2", "cat2", "Category 2 crates")
+            .create_or_update(conn)
+            .unwrap();
+
+        let foo = CrateBuilder::new("foo", user.id)
+            .keyword("kw1")
+            .keyword("kw2")
+            .expect_build(conn);
+        let foo_bar = CrateBuilder::new("foo_bar", user.id)
+            .keyword("kw1")
+            .expect_build(conn);
+        let bar = CrateBuilder::new("bar", user.id)
+            .keyword("kw2")
+            .expect_build(conn);
+        Category::update_crate(conn, &foo, &["cat1", "cat2"]).unwrap();
+        Category::update_crate(conn, &foo_bar, &["cat1"]).unwrap();
+        Category::update_crate(conn, &bar, &["cat2"]).unwrap();
+    });
+
+    let facets = |query: &str| {
+        let json: FacetsResponse = anon.get_with_query("/api/v1/crates", query).good();
+        json.meta.facets
+    };
+    let counts = |counts: &[(&str, i64)]| {
+        counts
+            .iter()
+            .map(|&(id, count)| FacetCount {
+                id: id.into(),
+                count,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(facets(""), None);
+    assert_eq!(
+        facets("include_facets=yes"),
+        Some(Facets {
+            categories: counts(&[("cat1", 2), ("cat2", 2)]),
+            keywords: counts(&[("kw1", 2), ("kw2", 2)]),
+        })
+    );
+    assert_eq!(
+        facets("include_facets=yes&q=foo"),
+        Some(Facets {
+            categories: counts(&[("cat1", 2), ("cat2", 1)]),
+            keywords: counts(&[("kw1", 2), ("kw2", 1)]),
+        })
+    );
+    assert_eq!(
+        facets("include_facets=yes&category=cat2&keyword=kw2"),
+        Some(Facets {
+            categories: counts(&[("cat2", 2), ("cat1", 1)]),
+            keywords: counts(&[("kw2", 2), ("kw1", 1)]),
+        })
+    );
+}
+
 #[test]
 fn yanked_versions_are_not_considered_for_max_version() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -708,6 +1087,69 @@ fn pagination_links_included_if_applicable() {
     assert_eq!(Some("?page=2&per_page=1".to_string()), page3.meta.prev_page);
 }
 
+#[test]
+fn seek_pagination_walks_all_crates() {
+    #[derive(Deserialize)]
+    struct SeekList {
+        crates: Vec<cargo_registry::views::EncodableCrate>,
+        meta: SeekMeta,
+    }
+    #[derive(Deserialize)]
+    struct SeekMeta {
+        total: Option<i64>,
+        next_page: Option<String>,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("seek_a", user.id)
+            .downloads(20)
+            .expect_build(conn);
+        CrateBuilder::new("seek_b", user.id)
+            .downloads(30)
+            .expect_build(conn);
+        CrateBuilder::new("seek_c", user.id)
+            .downloads(20)
+            .expect_build(conn);
+    });
+
+    let walk = |query: &str| {
+        let mut names = vec![];
+        let mut query = query.to_string();
+        loop {
+            let json: SeekList = anon.get_with_query("/api/v1/crates", &query).good();
+            assert_eq!(json.meta.total, None);
+            names.extend(json.crates.into_iter().map(|krate| krate.name));
+            match json.meta.next_page {
+                Some(next_page) => query = next_page.trim_start_matches('?').to_string(),
+                None => return names,
+            }
+        }
+    };
+
+    assert_eq!(walk("per_page=1&seek="), vec!["seek_a", "seek_b", "seek_c"]);
+    assert_eq!(
+        walk("sort=downloads&per_page=2&seek="),
+        vec!["seek_b", "seek_a", "seek_c"]
+    );
+
+    for query in &["seek=invalid", "seek=&page=2", "seek=&q=seek"] {
+        let response = anon.get_with_query::<()>("/api/v1/crates", query);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // A cursor can't be used with a different sort
+    let json: SeekList = anon
+        .get_with_query("/api/v1/crates", "per_page=1&seek=")
+        .good();
+    let next_page = json.meta.next_page.unwrap();
+    let query = format!("sort=new&{}", next_page.trim_start_matches('?'));
+    let response = anon.get_with_query::<()>("/api/v1/crates", &query);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
 #[test]
 fn pagination_parameters_only_accept_integers() {
     let (app, anon, user) = TestApp::init().with_user();
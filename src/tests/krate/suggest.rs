@@ -0,0 +1,52 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use conduit::header;
+use http::StatusCode;
+
+#[derive(Deserialize)]
+struct Suggestions {
+    crates: Vec<String>,
+}
+
+#[test]
+fn suggest() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("tokio", user.id)
+            .recent_downloads(100)
+            .expect_build(conn);
+        CrateBuilder::new("token", user.id)
+            .recent_downloads(1000)
+            .expect_build(conn);
+        CrateBuilder::new("tokio-util", user.id)
+            .recent_downloads(10)
+            .expect_build(conn);
+        CrateBuilder::new("serde", user.id).expect_build(conn);
+    });
+
+    let suggest = |query: &str| {
+        let json: Suggestions = anon.get_with_query("/api/v1/crates/suggest", query).good();
+        json.crates
+    };
+
+    assert_eq!(suggest("q=tok"), vec!["token", "tokio", "tokio-util"]);
+    assert_eq!(suggest("q=TOK&limit=2"), vec!["token", "tokio"]);
+    // Crates with a similar name are suggested after the ones matching the prefix
+    assert_eq!(suggest("q=tokio_"), vec!["tokio-util", "tokio", "token"]);
+    // The wildcards of `LIKE` are matched literally
+    assert_eq!(suggest("q=%25"), Vec::<String>::new());
+    assert_eq!(suggest("q=sedre"), Vec::<String>::new());
+    assert_eq!(suggest("q=%20"), Vec::<String>::new());
+    assert_eq!(suggest(""), Vec::<String>::new());
+
+    let response = anon.get_with_query::<()>("/api/v1/crates/suggest", "q=tok");
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public,max-age=600"
+    );
+
+    let response = anon.get_with_query::<()>("/api/v1/crates/suggest", "q=tok&limit=100");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -7,6 +7,12 @@ use diesel::{prelude::*, update};
 #[derive(Deserialize)]
 struct VersionsList {
     versions: Vec<EncodableVersion>,
+    meta: VersionsMeta,
+}
+
+#[derive(Deserialize)]
+struct VersionsMeta {
+    next_page: Option<String>,
 }
 
 #[test]
@@ -41,3 +47,34 @@ fn versions() {
         user.gh_login
     );
 }
+
+#[test]
+fn versions_seek_pagination() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_versions_seek", user.id)
+            .version("0.5.1")
+            .version("1.0.0")
+            .version("0.5.0")
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/crates/foo_versions_seek/versions";
+    let json: VersionsList = anon.get(url).good();
+    assert_eq!(json.versions.len(), 3);
+    assert_none!(json.meta.next_page);
+
+    // The versions are published in the same transaction, so they're sorted by ID
+    let mut nums = vec![];
+    let mut query = "per_page=2&seek=".to_string();
+    loop {
+        let json: VersionsList = anon.get_with_query(url, &query).good();
+        nums.extend(json.versions.into_iter().map(|version| version.num));
+        match json.meta.next_page {
+            Some(next_page) => query = next_page.trim_start_matches('?').to_string(),
+            None => break,
+        }
+    }
+    assert_eq!(nums, vec!["0.5.0", "1.0.0", "0.5.1"]);
+}
@@ -245,3 +245,53 @@ fn unyank_records_an_audit_action() {
     assert_eq!(action.action, "unyank");
     assert_eq!(action.user.id, token.as_model().user_id);
 }
+
+#[test]
+fn publish_and_yank_notify_the_other_owners() {
+    use cargo_registry::models::{CrateOwner, Rights};
+    use cargo_registry::schema::crate_owners;
+    use diesel::prelude::*;
+
+    let (app, _, cookie, token) = TestApp::full().with_token();
+    let opted_in = app.db_new_user("opted_in");
+    let opted_out = app.db_new_user("opted_out");
+    app.db(|conn| {
+        let krate = CrateBuilder::new("fyk", cookie.as_model().id)
+            .version("0.1.0")
+            .expect_build(conn);
+        for (owner, email_notifications) in &[(&opted_in, true), (&opted_out, false)] {
+            diesel::insert_into(crate_owners::table)
+                .values(&CrateOwner {
+                    crate_id: krate.id,
+                    owner_id: owner.as_model().id,
+                    created_by: cookie.as_model().id,
+                    owner_kind: 0,
+                    email_notifications: *email_notifications,
+                    rights: Rights::FULL,
+                })
+                .execute(conn)
+                .unwrap();
+        }
+    });
+
+    token.enqueue_publish(PublishBuilder::new("fyk")).good();
+    app.run_pending_background_jobs();
+    token.yank("fyk", "1.0.0").good();
+    token.unyank("fyk", "1.0.0").good();
+    cookie.yank("fyk", "1.0.0").good();
+
+    // Only the owner who opted in is notified, and unyanking isn't worth a notification
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 3);
+    assert_eq!(emails[0].subject, "fyk 1.0.0 was published");
+    assert!(emails[0]
+        .body
+        .contains("was published by foo using the API token \"bar\""));
+    assert_eq!(emails[1].subject, "fyk 1.0.0 was yanked");
+    assert!(emails[1]
+        .body
+        .contains("was yanked by foo using the API token \"bar\""));
+    assert!(emails[2]
+        .body
+        .contains("was yanked by foo using a browser session"));
+}
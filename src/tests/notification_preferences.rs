@@ -0,0 +1,208 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use cargo_registry::schema::crate_owner_invitations;
+use cargo_registry::tasks;
+use cargo_registry::views::{
+    EncodableCrateNotificationPreferences, EncodableNotificationPreferences,
+};
+use chrono::{Duration, Utc};
+use conduit::StatusCode;
+use diesel::prelude::*;
+use std::fs;
+use swirl::Job;
+
+#[derive(Deserialize)]
+struct PreferencesResponse {
+    notification_preferences: EncodableNotificationPreferences,
+    crates: Vec<EncodableCrateNotificationPreferences>,
+}
+
+#[derive(Deserialize)]
+struct UpdatedPreferencesResponse {
+    notification_preferences: EncodableNotificationPreferences,
+}
+
+fn changes_body(changes: serde_json::Value) -> Vec<u8> {
+    json!({ "notification_preferences": changes })
+        .to_string()
+        .into_bytes()
+}
+
+#[test]
+fn preferences_can_be_changed() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_prefs", user.as_model().id).expect_build(conn);
+    });
+
+    // Users receive all the notifications until they change their preferences
+    let json: PreferencesResponse = user.get("/api/v1/me/notification_preferences").good();
+    let preferences = json.notification_preferences;
+    assert!(preferences.publish && preferences.yank && preferences.ownership);
+    assert!(preferences.advisories && preferences.token_expiry);
+    assert_eq!(json.crates.len(), 1);
+    assert_eq!(json.crates[0].name, "foo_prefs");
+    assert!(json.crates[0].email_notifications && json.crates[0].publish);
+
+    let body = changes_body(json!({ "yank": false, "advisories": false }));
+    let json: UpdatedPreferencesResponse = user
+        .put("/api/v1/me/notification_preferences", &body)
+        .good();
+    let preferences = json.notification_preferences;
+    assert!(!preferences.yank && !preferences.advisories);
+    assert!(preferences.publish && preferences.ownership && preferences.token_expiry);
+
+    let body = changes_body(json!({ "publish": false }));
+    user.put::<OkBool>("/api/v1/crates/foo_prefs/notification_preferences", &body)
+        .good();
+    let json: PreferencesResponse = user.get("/api/v1/me/notification_preferences").good();
+    assert!(!json.notification_preferences.yank);
+    assert!(!json.crates[0].publish);
+    assert!(json.crates[0].yank && json.crates[0].ownership && json.crates[0].advisories);
+
+    let response = user.put::<()>(
+        "/api/v1/me/notification_preferences",
+        &changes_body(json!({})),
+    );
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Only owners have preferences for a crate
+    let other_user = app.db_new_user("bar");
+    let json = other_user
+        .put::<()>("/api/v1/crates/foo_prefs/notification_preferences", &body)
+        .json();
+    assert_eq!(
+        json,
+        json!({ "errors": [{ "detail": "only owners can change their notification preferences for a crate" }] })
+    );
+}
+
+#[test]
+fn emails_can_be_unsubscribed_from_in_one_click() {
+    let (app, anon, owner, owner_token) = TestApp::init().with_token();
+    let invited = app.db_new_user("invited_user");
+    app.db(|conn| {
+        CrateBuilder::new("foo_unsubscribe", owner.as_model().id).expect_build(conn);
+        CrateBuilder::new("bar_unsubscribe", owner.as_model().id).expect_build(conn);
+    });
+
+    owner_token.add_user_owner("foo_unsubscribe", "invited_user");
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 1);
+    let url = emails[0]
+        .unsubscribe_url
+        .as_ref()
+        .expect("missing unsubscribe URL");
+    assert!(url.ends_with("/ownership"));
+    let path = &url[url.find("/api/v1/").unwrap()..];
+
+    // Opening the link only asks for a confirmation
+    let response = anon.get::<()>(path);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/html; charset=utf-8"
+    );
+    assert!(response.text().contains("<form method=\"post\">"));
+    let json: PreferencesResponse = invited.get("/api/v1/me/notification_preferences").good();
+    assert!(json.notification_preferences.ownership);
+
+    // Email clients unsubscribe users without them being logged in
+    anon.post::<OkBool>(path, b"List-Unsubscribe=One-Click")
+        .good();
+    let json: PreferencesResponse = invited.get("/api/v1/me/notification_preferences").good();
+    assert!(!json.notification_preferences.ownership);
+    assert!(json.notification_preferences.publish);
+
+    // The invitation is still created, but no email is sent about it
+    owner_token.add_user_owner("bar_unsubscribe", "invited_user");
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 1);
+
+    anon.post::<()>("/api/v1/unsubscribe/unknown-token/ownership", b"")
+        .assert_not_found();
+    anon.get::<()>("/api/v1/unsubscribe/unknown-token/ownership")
+        .assert_not_found();
+    let response = anon.post::<()>(&path.replace("/ownership", "/downloads"), b"");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn crate_preferences_suppress_emails_about_the_crate() {
+    let (app, _, owner, owner_token) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_token();
+    app.db_new_user("invited_user");
+    app.db(|conn| {
+        CrateBuilder::new("foo_quiet", owner.as_model().id).expect_build(conn);
+        CrateBuilder::new("foo_noisy", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = changes_body(json!({ "ownership": false }));
+    owner
+        .put::<OkBool>("/api/v1/crates/foo_quiet/notification_preferences", &body)
+        .good();
+
+    owner_token.add_user_owner("foo_quiet", "invited_user");
+    owner_token.add_user_owner("foo_noisy", "invited_user");
+    let expiration_days = app.as_inner().config.ownership_invitations_expiration_days;
+    app.db(|conn| {
+        let created_at = (Utc::now() - Duration::days(expiration_days as i64)).naive_utc();
+        diesel::update(crate_owner_invitations::table)
+            .set(crate_owner_invitations::created_at.eq(created_at))
+            .execute(conn)
+            .unwrap();
+        tasks::expire_owner_invitations(expiration_days as i32)
+            .enqueue(conn)
+            .unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    // Only the expired invitation to the other crate is notified to the owner
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 3);
+    let notification = emails.last().unwrap();
+    assert_eq!(notification.subject, "Crate ownership invitation expired");
+    assert!(notification.body.contains("foo_noisy"));
+}
+
+#[test]
+fn emails_are_delivered_by_a_background_job() {
+    let dir = tempfile::tempdir().unwrap();
+    let (app, _, owner, owner_token) = TestApp::init()
+        .with_emails_in(dir.path())
+        .with_git_index()
+        .with_job_runner()
+        .with_token();
+    app.db_new_user("invited_user");
+    app.db(|conn| {
+        CrateBuilder::new("foo_delivered", owner.as_model().id).expect_build(conn);
+    });
+    let emails = || {
+        fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "eml"))
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    owner_token.add_user_owner("foo_delivered", "invited_user");
+    assert!(emails().is_empty());
+    app.run_pending_background_jobs();
+
+    let emails = emails();
+    assert_eq!(emails.len(), 1);
+    let email = &emails[0];
+    assert!(email.contains("To: something@example.com"), "{}", email);
+    assert!(
+        email.contains("Subject: Crate ownership invitation"),
+        "{}",
+        email
+    );
+    assert!(email.contains("/ownership>\r\n"), "{}", email);
+    assert!(email.contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n"));
+    assert!(email.contains("Content-Type: text/plain"), "{}", email);
+    assert!(email.contains("Content-Type: text/html"), "{}", email);
+}
@@ -3,12 +3,13 @@ use crate::{
     builders::{CrateBuilder, PublishBuilder},
     new_team,
     util::{MockAnonymousUser, MockCookieUser, MockTokenUser, RequestHelper, Response},
-    TestApp,
+    CrateResponse, OkBool, TestApp,
 };
 use cargo_registry::{
     models::Crate,
     views::{
-        EncodableCrateOwnerInvitation, EncodableOwner, EncodablePublicUser, InvitationResponse,
+        EncodableAuditAction, EncodableCrateOwnerInvitation, EncodableOwner, EncodablePublicUser,
+        InvitationResponse,
     },
     Emails,
 };
@@ -25,6 +26,10 @@ struct TeamResponse {
 struct UserResponse {
     users: Vec<EncodableOwner>,
 }
+#[derive(Deserialize)]
+struct OwnerActionsResponse {
+    owner_actions: Vec<EncodableAuditAction>,
+}
 #[derive(Deserialize, Debug, PartialEq, Eq)]
 struct InvitationListResponse {
     crate_owner_invitations: Vec<EncodableCrateOwnerInvitation>,
@@ -405,7 +410,7 @@ fn deleted_ownership_isnt_in_owner_user() {
     app.db(|conn| {
         let krate = CrateBuilder::new("foo_my_packages", user.id).expect_build(conn);
         krate
-            .owner_remove(app.as_inner(), conn, user, &user.gh_login)
+            .owner_remove(app.as_inner(), conn, user, &user.gh_login, None)
             .unwrap();
     });
 
@@ -498,6 +503,103 @@ fn invitations_list_does_not_include_expired_invites() {
     );
 }
 
+#[test]
+fn owner_actions_are_recorded() {
+    let (app, anon, owner, owner_token) = TestApp::init().with_token();
+    let owner = owner.as_model();
+    let invited_user = app.db_new_user("user_bar");
+    let krate = app.db(|conn| CrateBuilder::new("audited", owner.id).expect_build(conn));
+
+    owner_token.add_user_owner("audited", "user_bar");
+    invited_user.accept_ownership_invitation(&krate.name, krate.id);
+    owner_token.remove_named_owner("audited", "user_bar").good();
+    owner_token.add_user_owner("audited", "user_bar");
+    invited_user.decline_ownership_invitation(&krate.name, krate.id);
+
+    // Removing an owner who was already removed isn't recorded
+    owner_token.remove_named_owner("audited", "user_bar").good();
+
+    let json: OwnerActionsResponse = anon.get("/api/v1/crates/audited/owner_actions").good();
+    let actions = json
+        .owner_actions
+        .iter()
+        .map(|a| {
+            let target = a.target.as_ref().map(|target| target.login.as_str());
+            (a.action.as_str(), a.user.login.as_str(), target)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        actions,
+        vec![
+            ("invite", "foo", Some("user_bar")),
+            ("invite_accept", "user_bar", None),
+            ("remove", "foo", Some("user_bar")),
+            ("invite", "foo", Some("user_bar")),
+            ("invite_decline", "user_bar", None),
+        ]
+    );
+
+    let json = anon.show_crate("audited");
+    assert!(json.krate.owner_actions.is_none());
+    let json: CrateResponse = anon
+        .get("/api/v1/crates/audited?include=owner_actions")
+        .good();
+    assert_eq!(json.krate.owner_actions.unwrap().len(), 5);
+}
+
+#[test]
+fn owners_list_includes_rights() {
+    let (app, anon, owner) = TestApp::init().with_user();
+    let owner = owner.as_model();
+    app.db(|conn| CrateBuilder::new("rights_listed", owner.id).expect_build(conn));
+
+    let json = anon.show_crate_owners("rights_listed");
+    assert_eq!(json.users.len(), 1);
+    assert_eq!(json.users[0].rights.as_deref(), Some("full"));
+}
+
+#[test]
+fn rights_of_individual_owners_cannot_be_changed() {
+    let (app, _, owner, owner_token) = TestApp::init().with_token();
+    let owner = owner.as_model();
+    let krate = app.db(|conn| CrateBuilder::new("fixed_rights", owner.id).expect_build(conn));
+    create_and_add_owner(&app, &owner_token, "co_owner", &krate);
+
+    let body = json!({ "owners": ["co_owner"], "rights": "yank" });
+    let response = owner_token.put::<()>(
+        "/api/v1/crates/fixed_rights/owner_rights",
+        body.to_string().as_bytes(),
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "only the rights of team owners can be changed" }] })
+    );
+
+    let body = json!({ "owners": ["co_owner"], "rights": "everything" });
+    let response = owner_token.put::<()>(
+        "/api/v1/crates/fixed_rights/owner_rights",
+        body.to_string().as_bytes(),
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "invalid rights, expected `full` or a comma separated list of `publish` and `yank`" }] })
+    );
+
+    app.db_new_user("new_user");
+    let body = json!({ "owners": ["new_user"], "rights": "publish" });
+    let response = owner_token.put::<()>(
+        "/api/v1/crates/fixed_rights/owners",
+        body.to_string().as_bytes(),
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "individual owners always have full rights" }] })
+    );
+}
+
 /*  Given a user inviting a different user to be a crate
     owner, check that the user invited can accept their
     invitation, the invitation will be deleted from
@@ -564,7 +666,8 @@ fn test_accept_invitation_by_mail() {
     owner_token.add_user_owner("accept_invitation", "user_bar");
 
     // Retrieve the ownership invitation
-    let invite_token = extract_token_from_invite_email(&app.as_inner().emails);
+    let invite_token =
+        extract_token_from_email(&app.as_inner().emails, "invitation", "/accept-invite/");
 
     // Accept the invitation anonymously with a token
     anon.accept_ownership_invitation_by_token(&invite_token);
@@ -663,7 +766,8 @@ fn test_accept_expired_invitation_by_mail() {
     expire_invitation(&app, krate.id);
 
     // Retrieve the ownership invitation
-    let invite_token = extract_token_from_invite_email(&app.as_inner().emails);
+    let invite_token =
+        extract_token_from_email(&app.as_inner().emails, "invitation", "/accept-invite/");
 
     // Try to accept the invitation, and ensure it fails.
     let resp = anon.try_accept_ownership_invitation_by_token::<()>(&invite_token);
@@ -685,6 +789,45 @@ fn test_accept_expired_invitation_by_mail() {
     assert_eq!(json.users.len(), 1);
 }
 
+#[test]
+fn expired_invitations_are_deleted_by_background_job() {
+    use cargo_registry::tasks;
+    use swirl::Job;
+
+    let (app, _, owner, owner_token) = TestApp::full().with_token();
+    let owner = owner.as_model();
+    let expired_user = app.db_new_user("expired_user");
+    let pending_user = app.db_new_user("pending_user");
+    let expired = app.db(|conn| CrateBuilder::new("expired_crate", owner.id).expect_build(conn));
+    app.db(|conn| CrateBuilder::new("pending_crate", owner.id).expect_build(conn));
+
+    owner_token.add_user_owner("expired_crate", "expired_user");
+    owner_token.add_user_owner("pending_crate", "pending_user");
+    expire_invitation(&app, expired.id);
+
+    let expiration_days = app.as_inner().config.ownership_invitations_expiration_days as i32;
+    app.db(|conn| {
+        tasks::expire_owner_invitations(expiration_days)
+            .enqueue(conn)
+            .unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    // Only the expired invitation was removed
+    let json = expired_user.list_invitations();
+    assert_eq!(json.crate_owner_invitations.len(), 0);
+    let json = pending_user.list_invitations();
+    assert_eq!(json.crate_owner_invitations.len(), 1);
+
+    // The inviter was told about the expired invitation
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 3);
+    let notification = emails.last().unwrap();
+    assert_eq!(notification.subject, "Crate ownership invitation expired");
+    assert!(notification.body.contains("expired_user"));
+    assert!(notification.body.contains("expired_crate"));
+}
+
 #[test]
 fn inactive_users_dont_get_invitations() {
     use cargo_registry::models::NewUser;
@@ -742,19 +885,122 @@ fn highest_gh_id_is_most_recent_account_we_know_of() {
     assert_eq!(json.crate_owner_invitations.len(), 1);
 }
 
-fn extract_token_from_invite_email(emails: &Emails) -> String {
+/// Extracts the token following `before_token` in the first email whose subject contains `subject`.
+fn extract_token_from_email(emails: &Emails, subject: &str, before_token: &str) -> String {
     let message = emails
         .mails_in_memory()
         .unwrap()
         .into_iter()
-        .find(|m| m.subject.contains("invitation"))
+        .find(|m| m.subject.contains(subject))
         .expect("missing email");
 
     // Simple (but kinda fragile) parser to extract the token.
-    let before_token = "/accept-invite/";
     let after_token = " ";
     let body = message.body.as_str();
     let before_pos = body.find(before_token).unwrap() + before_token.len();
     let after_pos = before_pos + (&body[before_pos..]).find(after_token).unwrap();
     body[before_pos..after_pos].to_string()
 }
+
+fn try_request_transfer<T: serde::de::DeserializeOwned>(
+    user: &impl RequestHelper,
+    krate_name: &str,
+    owner: &str,
+) -> Response<T> {
+    let url = format!("/api/v1/crates/{}/transfer", krate_name);
+    let body = json!({ "owner": owner });
+    user.put(&url, body.to_string().as_bytes())
+}
+
+fn try_accept_transfer<T: serde::de::DeserializeOwned>(
+    user: &impl RequestHelper,
+    token: &str,
+) -> Response<T> {
+    let url = format!("/api/v1/me/crate_ownership_transfers/accept/{}", token);
+    user.put(&url, &[])
+}
+
+#[test]
+fn transfer_crate_to_user() {
+    let (app, anon, owner, owner_token) = TestApp::init().with_token();
+    let owner = owner.as_model();
+    let krate = app.db(|conn| CrateBuilder::new("transferred", owner.id).expect_build(conn));
+    create_and_add_owner(&app, &owner_token, "co_owner", &krate);
+    let recipient = app.db_new_user("recipient");
+
+    let resp = try_request_transfer::<()>(&owner_token, "transferred", "recipient");
+    assert_eq!(resp.status(), StatusCode::OK);
+    let token = extract_token_from_email(
+        &app.as_inner().emails,
+        "ownership transfer",
+        "/accept-transfer/",
+    );
+
+    // Only the recipient of the transfer may accept it
+    let resp = try_accept_transfer::<()>(&anon, &token);
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let other_user = app.db_new_user("other_user");
+    let resp = try_accept_transfer::<()>(&other_user, &token);
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let json = anon.show_crate_owners("transferred");
+    assert_eq!(json.users.len(), 2);
+
+    assert!(try_accept_transfer::<OkBool>(&recipient, &token).good().ok);
+
+    // All previous owners have been replaced by the recipient
+    let json = anon.show_crate_owners("transferred");
+    assert_eq!(json.users.len(), 1);
+    assert_eq!(json.users[0].login, "recipient");
+
+    // The transfer can't be accepted twice
+    let resp = try_accept_transfer::<()>(&recipient, &token);
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn only_owners_can_request_transfers() {
+    let (app, anon, owner) = TestApp::init().with_user();
+    let owner = owner.as_model();
+    app.db(|conn| CrateBuilder::new("not_yours", owner.id).expect_build(conn));
+    let other_user = app.db_new_user("other_user");
+
+    let resp = try_request_transfer::<()>(&other_user, "not_yours", "other_user");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        resp.json(),
+        json!({ "errors": [{ "detail": "only owners have permission to transfer crates" }] })
+    );
+
+    let json = anon.show_crate_owners("not_yours");
+    assert_eq!(json.users.len(), 1);
+    assert_eq!(json.users[0].login, "foo");
+}
+
+#[test]
+fn cancelled_transfers_cannot_be_accepted() {
+    let (app, anon, owner, owner_token) = TestApp::init().with_token();
+    let owner = owner.as_model();
+    app.db(|conn| CrateBuilder::new("cancelled", owner.id).expect_build(conn));
+    let recipient = app.db_new_user("recipient");
+
+    let resp = try_request_transfer::<()>(&owner_token, "cancelled", "recipient");
+    assert_eq!(resp.status(), StatusCode::OK);
+    let token = extract_token_from_email(
+        &app.as_inner().emails,
+        "ownership transfer",
+        "/accept-transfer/",
+    );
+
+    let ok: OkBool = owner_token
+        .delete("/api/v1/crates/cancelled/transfer")
+        .good();
+    assert!(ok.ok);
+
+    let resp = try_accept_transfer::<()>(&recipient, &token);
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let json = anon.show_crate_owners("cancelled");
+    assert_eq!(json.users.len(), 1);
+    assert_eq!(json.users[0].login, "foo");
+}
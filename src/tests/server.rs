@@ -74,3 +74,25 @@ fn block_traffic_via_arbitrary_header_and_value() {
     let resp = anon.run::<()>(req);
     assert_eq!(resp.status(), StatusCode::FOUND);
 }
+
+#[test]
+fn blocked_user_agents_can_download_without_being_counted() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.blocked_traffic = vec![("User-Agent".into(), vec!["1".into()])];
+            config.blocked_traffic_exempt_downloads = true;
+        })
+        .with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("dl_blocked_ua", user.as_model().id).expect_build(conn);
+    });
+
+    let mut req = anon.request_builder(Method::GET, "/api/v1/crates/dl_blocked_ua/0.99.0/download");
+    req.header(header::USER_AGENT, "1");
+    let resp = anon.run::<()>(req);
+    assert_eq!(resp.status(), StatusCode::FOUND);
+
+    let metric = &app.as_inner().instance_metrics.downloads_discarded_total;
+    assert_eq!(metric.with_label_values(&["blocked_user_agent"]).get(), 1);
+}
@@ -3,7 +3,7 @@ use crate::{
     builders::{CrateBuilder, PublishBuilder},
     new_team,
     record::GhUser,
-    OwnerTeamsResponse, RequestHelper, TestApp,
+    OkBool, OwnerTeamsResponse, RequestHelper, TestApp,
 };
 use cargo_registry::models::{Crate, NewUser};
 use std::sync::Once;
@@ -271,6 +271,247 @@ fn publish_owned() {
     user_on_one_team.enqueue_publish(crate_to_publish).good();
 }
 
+// Test trying to publish a krate owned by a team that may only yank versions
+#[test]
+fn publish_with_yank_rights() {
+    let (app, _) = TestApp::with_proxy().empty();
+    let user_on_both_teams = app.db_new_user(mock_user_on_both_teams().gh_login);
+    let token_on_both_teams = user_on_both_teams.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_yank_only", user_on_both_teams.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "owners": ["github:crates-test-org:core"], "rights": "yank" });
+    token_on_both_teams
+        .put::<OkBool>(
+            "/api/v1/crates/foo_yank_only/owners",
+            body.to_string().as_bytes(),
+        )
+        .good();
+
+    let user_on_one_team = app.db_new_user(mock_user_on_only_one_team().gh_login);
+
+    let crate_to_publish = PublishBuilder::new("foo_yank_only").version("2.0.0");
+    let response = user_on_one_team.enqueue_publish(crate_to_publish);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "this crate exists but you don't seem to be an owner. If you believe this is a mistake, perhaps you need to accept an invitation to be an owner before publishing." }] })
+    );
+}
+
+// Test that teams can't be given the right to manage owners, which team members never have
+#[test]
+fn teams_cannot_have_full_rights() {
+    let (app, _) = TestApp::with_proxy().empty();
+    let user_on_both_teams = app.db_new_user(mock_user_on_both_teams().gh_login);
+    let token_on_both_teams = user_on_both_teams.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_team_full", user_on_both_teams.as_model().id).expect_build(conn);
+    });
+
+    let expected = json!({ "errors": [{ "detail": "teams can only be given the `publish` and `yank` rights" }] });
+    let body = json!({ "owners": ["github:crates-test-org:core"], "rights": "full" });
+    let response = token_on_both_teams.put::<()>(
+        "/api/v1/crates/foo_team_full/owners",
+        body.to_string().as_bytes(),
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), expected);
+
+    token_on_both_teams
+        .add_named_owner("foo_team_full", "github:crates-test-org:core")
+        .good();
+    let response = token_on_both_teams.put::<()>(
+        "/api/v1/crates/foo_team_full/owner_rights",
+        body.to_string().as_bytes(),
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), expected);
+}
+
+// Test trying to yank a version of a crate owned by a team that may only publish
+#[test]
+fn yank_with_publish_rights() {
+    use cargo_registry::models::{CrateOwner, OwnerKind, Rights};
+    use cargo_registry::schema::{crate_owners, team_memberships};
+
+    let (app, _, owner) = TestApp::init().with_user();
+    let owner = owner.as_model();
+    let team_member = app.db_new_user("team_member");
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_publish_only", owner.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let team = new_team("github:test_org:publishers")
+            .create_or_update(conn)
+            .unwrap();
+        insert_into(crate_owners::table)
+            .values(&CrateOwner {
+                crate_id: krate.id,
+                owner_id: team.id,
+                created_by: owner.id,
+                owner_kind: OwnerKind::Team as i32,
+                email_notifications: true,
+                rights: Rights::PUBLISH,
+            })
+            .execute(conn)
+            .unwrap();
+        // The membership is cached so that Github isn't asked about it
+        insert_into(team_memberships::table)
+            .values((
+                team_memberships::team_id.eq(team.id),
+                team_memberships::user_id.eq(team_member.as_model().id),
+                team_memberships::is_member.eq(true),
+                team_memberships::checked_at.eq(dsl::now),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = team_member.delete::<()>("/api/v1/crates/foo_publish_only/1.0.0/yank");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "must already be an owner to yank or unyank" }] })
+    );
+}
+
+// Test that the membership of a team is only checked once on Github
+#[test]
+fn yank_uses_membership_cache() {
+    let (app, _) = TestApp::full().empty();
+    let user_on_both_teams = app.db_new_user(mock_user_on_both_teams().gh_login);
+    let token_on_both_teams = user_on_both_teams.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_cached", user_on_both_teams.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    token_on_both_teams
+        .add_named_owner("foo_cached", "github:crates-test-org:core")
+        .good();
+
+    let user_on_one_team = app.db_new_user(mock_user_on_only_one_team().gh_login);
+    user_on_one_team
+        .delete::<OkBool>("/api/v1/crates/foo_cached/1.0.0/yank")
+        .good();
+    // The answer of Github is cached by a background job
+    app.run_pending_background_jobs();
+    user_on_one_team
+        .put::<OkBool>("/api/v1/crates/foo_cached/1.0.0/unyank", b"")
+        .good();
+
+    let metric = &app
+        .as_inner()
+        .instance_metrics
+        .team_membership_cache_lookups_total;
+    assert_eq!(metric.with_label_values(&["miss"]).get(), 1);
+    assert_eq!(metric.with_label_values(&["hit"]).get(), 1);
+}
+
+// Test that a stale membership is used when Github is unavailable
+#[test]
+fn yank_falls_back_to_cached_membership() {
+    use cargo_registry::schema::{team_memberships, teams};
+    use diesel::dsl::IntervalDsl;
+
+    let (app, _) = TestApp::with_proxy().empty();
+    let user_on_both_teams = app.db_new_user(mock_user_on_both_teams().gh_login);
+    let token_on_both_teams = user_on_both_teams.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_fallback", user_on_both_teams.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    token_on_both_teams
+        .add_named_owner("foo_fallback", "github:crates-test-org:core")
+        .good();
+
+    let user_on_one_team = app.db_new_user(mock_user_on_only_one_team().gh_login);
+    app.db(|conn| {
+        let team_id: i32 = teams::table
+            .filter(teams::login.eq("github:crates-test-org:core"))
+            .select(teams::id)
+            .first(conn)
+            .unwrap();
+        insert_into(team_memberships::table)
+            .values((
+                team_memberships::team_id.eq(team_id),
+                team_memberships::user_id.eq(user_on_one_team.as_model().id),
+                team_memberships::is_member.eq(true),
+                team_memberships::checked_at.eq(dsl::now - 2.hours()),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    user_on_one_team
+        .delete::<OkBool>("/api/v1/crates/foo_fallback/1.0.0/yank")
+        .good();
+
+    let metric = &app
+        .as_inner()
+        .instance_metrics
+        .team_membership_cache_lookups_total;
+    assert_eq!(metric.with_label_values(&["fallback"]).get(), 1);
+}
+
+// Test that a membership older than the fallback limit isn't used when Github is unavailable
+#[test]
+fn yank_fails_with_outdated_cached_membership() {
+    use cargo_registry::schema::{team_memberships, teams};
+    use diesel::dsl::IntervalDsl;
+
+    let (app, _) = TestApp::with_proxy().empty();
+    let user_on_both_teams = app.db_new_user(mock_user_on_both_teams().gh_login);
+    let token_on_both_teams = user_on_both_teams.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_outdated", user_on_both_teams.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    token_on_both_teams
+        .add_named_owner("foo_outdated", "github:crates-test-org:core")
+        .good();
+
+    let user_on_one_team = app.db_new_user(mock_user_on_only_one_team().gh_login);
+    app.db(|conn| {
+        let team_id: i32 = teams::table
+            .filter(teams::login.eq("github:crates-test-org:core"))
+            .select(teams::id)
+            .first(conn)
+            .unwrap();
+        insert_into(team_memberships::table)
+            .values((
+                team_memberships::team_id.eq(team_id),
+                team_memberships::user_id.eq(user_on_one_team.as_model().id),
+                team_memberships::is_member.eq(true),
+                team_memberships::checked_at.eq(dsl::now - 2.days()),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = user_on_one_team.delete::<()>("/api/v1/crates/foo_outdated/1.0.0/yank");
+    assert!(response.status().is_server_error());
+
+    let metric = &app
+        .as_inner()
+        .instance_metrics
+        .team_membership_cache_lookups_total;
+    assert_eq!(metric.with_label_values(&["fallback"]).get(), 0);
+}
+
 // Test trying to change owners (when only on an owning team)
 #[test]
 fn add_owners_as_team_owner() {
@@ -330,7 +571,7 @@ fn crates_by_team_id_not_including_deleted_owners() {
         let krate = CrateBuilder::new("foo", user.id).expect_build(conn);
         add_team_to_crate(&t, &krate, user, conn).unwrap();
         krate
-            .owner_remove(app.as_inner(), conn, user, &t.login)
+            .owner_remove(app.as_inner(), conn, user, &t.login, None)
             .unwrap();
         t
     });
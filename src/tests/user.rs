@@ -198,7 +198,7 @@ fn crates_by_user_id_not_including_deleted_owners() {
     app.db(|conn| {
         let krate = CrateBuilder::new("foo_my_packages", user.id).expect_build(conn);
         krate
-            .owner_remove(app.as_inner(), conn, user, "foo")
+            .owner_remove(app.as_inner(), conn, user, "foo", None)
             .unwrap();
     });
 
@@ -212,6 +212,48 @@ fn api_token_cannot_get_user_updates() {
     token.get("/api/v1/me/updates").assert_forbidden();
 }
 
+#[test]
+fn following_seek_pagination() {
+    #[derive(Deserialize)]
+    struct R {
+        versions: Vec<EncodableVersion>,
+        meta: Meta,
+    }
+    #[derive(Deserialize)]
+    struct Meta {
+        more: bool,
+        next_page: Option<String>,
+    }
+
+    let (app, _, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+    app.db(|conn| {
+        for name in &["seek_first", "seek_second", "seek_third"] {
+            CrateBuilder::new(name, user_id)
+                .version(VersionBuilder::new("1.0.0"))
+                .expect_build(conn);
+        }
+    });
+    for name in &["seek_first", "seek_second", "seek_third"] {
+        let url = format!("/api/v1/crates/{}/follow", name);
+        user.put::<OkBool>(&url, b"").good();
+    }
+
+    // The versions are published in the same transaction, so they're sorted by ID
+    let mut crates = vec![];
+    let mut query = "per_page=2&seek=".to_string();
+    loop {
+        let r: R = user.get_with_query("/api/v1/me/updates", &query).good();
+        crates.extend(r.versions.into_iter().map(|version| version.krate));
+        assert_eq!(r.meta.more, r.meta.next_page.is_some());
+        match r.meta.next_page {
+            Some(next_page) => query = next_page.trim_start_matches('?').to_string(),
+            None => break,
+        }
+    }
+    assert_eq!(crates, vec!["seek_third", "seek_second", "seek_first"]);
+}
+
 #[test]
 fn following() {
     use cargo_registry::schema::versions;
@@ -330,7 +372,7 @@ fn user_total_downloads() {
             .execute(conn)
             .unwrap();
         no_longer_my_krate
-            .owner_remove(app.as_inner(), conn, user, &user.gh_login)
+            .owner_remove(app.as_inner(), conn, user, &user.gh_login, None)
             .unwrap();
     });
 
@@ -630,7 +672,7 @@ fn test_user_owned_crates_doesnt_include_deleted_ownership() {
     app.db(|conn| {
         let krate = CrateBuilder::new("foo_my_packages", user_model.id).expect_build(conn);
         krate
-            .owner_remove(app.as_inner(), conn, user_model, &user_model.gh_login)
+            .owner_remove(app.as_inner(), conn, user_model, &user_model.gh_login, None)
             .unwrap();
     });
 
@@ -118,6 +118,14 @@ pub trait RequestHelper {
         self.run(request)
     }
 
+    /// Issue a POST request
+    #[track_caller]
+    fn post<T>(&self, path: &str, body: &[u8]) -> Response<T> {
+        let mut request = self.request_builder(Method::POST, path);
+        request.with_body(body);
+        self.run(request)
+    }
+
     /// Issue a DELETE request
     #[track_caller]
     fn delete<T>(&self, path: &str) -> Response<T> {
@@ -43,10 +43,20 @@ impl<T> Response<T> {
         json(&mut self.response)
     }
 
+    /// Consume the response body and convert it to a string
+    #[track_caller]
+    pub fn text(mut self) -> String {
+        String::from_utf8(take_body(&mut self.response).into_owned()).unwrap()
+    }
+
     pub fn status(&self) -> StatusCode {
         self.response.status()
     }
 
+    pub fn headers(&self) -> &header::HeaderMap {
+        self.response.headers()
+    }
+
     #[track_caller]
     pub fn assert_redirect_ends_with(&self, target: &str) -> &Self {
         assert!(self
@@ -79,15 +89,7 @@ fn json<T>(r: &mut AppResponse) -> T
 where
     for<'de> T: serde::Deserialize<'de>,
 {
-    use conduit::Body::*;
-
-    let mut body = Body::empty();
-    std::mem::swap(r.body_mut(), &mut body);
-    let body: std::borrow::Cow<'static, [u8]> = match body {
-        Static(slice) => slice.into(),
-        Owned(vec) => vec.into(),
-        File(_) => unimplemented!(),
-    };
+    let body = take_body(r);
 
     assert_eq!(
         r.headers()
@@ -111,3 +113,15 @@ where
         Err(e) => panic!("failed to decode: {:?}", e),
     }
 }
+
+fn take_body(r: &mut AppResponse) -> std::borrow::Cow<'static, [u8]> {
+    use conduit::Body::*;
+
+    let mut body = Body::empty();
+    std::mem::swap(r.body_mut(), &mut body);
+    match body {
+        Static(slice) => slice.into(),
+        Owned(vec) => vec.into(),
+        File(_) => unimplemented!(),
+    }
+}
@@ -5,9 +5,10 @@ use cargo_registry::{
     background_jobs::Environment,
     db::DieselPool,
     git::{Credentials, RepositoryConfig},
+    github::GitHubClient,
     App, Config, DbPoolConfig, Emails, Env, Replica, Uploader,
 };
-use std::{rc::Rc, sync::Arc, time::Duration};
+use std::{path::Path, rc::Rc, sync::Arc, time::Duration};
 
 use cargo_registry::git::Repository as WorkerRepository;
 use diesel::PgConnection;
@@ -73,6 +74,7 @@ impl TestApp {
             bomb: None,
             index: None,
             build_job_runner: false,
+            emails: None,
         }
     }
 
@@ -186,6 +188,7 @@ pub struct TestAppBuilder {
     bomb: Option<record::Bomb>,
     index: Option<UpstreamRepository>,
     build_job_runner: bool,
+    emails: Option<Emails>,
 }
 
 impl TestAppBuilder {
@@ -220,7 +223,7 @@ impl TestAppBuilder {
             (None, None)
         };
 
-        let (app, middle) = build_app(self.config, self.proxy);
+        let (app, middle) = build_app(self.config, self.proxy, self.emails);
 
         let runner = if self.build_job_runner {
             let repository_config = RepositoryConfig {
@@ -228,10 +231,18 @@ impl TestAppBuilder {
                 credentials: Credentials::Missing,
             };
             let index = WorkerRepository::open(&repository_config).expect("Could not clone index");
+            let github = GitHubClient::new(
+                Some(app.http_client().clone()),
+                app.config.gh_base_url.clone(),
+            );
             let environment = Environment::new(
                 index,
                 app.config.uploader.clone(),
                 app.http_client().clone(),
+                github,
+                app.emails.clone(),
+                app.primary_database.clone(),
+                app.config.webhooks_allow_private_urls,
             );
 
             Some(
@@ -313,6 +324,13 @@ impl TestAppBuilder {
         self
     }
 
+    /// Store the emails as files in `path` instead of in memory, so that they're sent by the
+    /// `send_email` background job like in production.
+    pub fn with_emails_in(mut self, path: &Path) -> Self {
+        self.emails = Some(Emails::new_in_directory(path.into()));
+        self
+    }
+
     pub fn with_slow_real_db_pool(mut self) -> Self {
         self.config.use_test_database_pool = false;
         self
@@ -361,11 +379,18 @@ fn simple_config() -> Config {
         api_protocol: String::from("http"),
         publish_rate_limit: Default::default(),
         blocked_traffic: Default::default(),
+        blocked_traffic_exempt_downloads: false,
         domain_name: "crates.io".into(),
         allowed_origins: Vec::new(),
         downloads_persist_interval_ms: 1000,
+        downloads_spill_path: None,
+        downloads_dedup_window_secs: 0,
         ownership_invitations_expiration_days: 30,
+        team_membership_cache_ttl_minutes: 60,
+        team_membership_fallback_max_age_hours: 24,
         metrics_authorization_token: None,
+        search_ranking_weights: Default::default(),
+        webhooks_allow_private_urls: false,
         use_test_database_pool: true,
     }
 }
@@ -373,6 +398,7 @@ fn simple_config() -> Config {
 fn build_app(
     config: Config,
     proxy: Option<String>,
+    emails: Option<Emails>,
 ) -> (Arc<App>, conduit_middleware::MiddlewareBuilder) {
     let client = if let Some(proxy) = proxy {
         let mut builder = Client::builder();
@@ -385,9 +411,10 @@ fn build_app(
 
     let mut app = App::new(config, client);
 
-    // Use the in-memory email backend for all tests, allowing tests to analyze the emails sent by
-    // the application. This will also prevent cluttering the filesystem.
-    app.emails = Emails::new_in_memory();
+    // Use the in-memory email backend for the tests which don't pick another one, allowing tests
+    // to analyze the emails sent by the application. This will also prevent cluttering the
+    // filesystem.
+    app.emails = Arc::new(emails.unwrap_or_else(Emails::new_in_memory));
 
     let app = Arc::new(app);
     let handler = cargo_registry::build_handler(Arc::clone(&app));
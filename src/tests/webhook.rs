@@ -0,0 +1,331 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use cargo_registry::models::{CrateOwner, NewWebhook, Rights};
+use cargo_registry::schema::{crate_owners, webhook_deliveries};
+use cargo_registry::tasks;
+use cargo_registry::views::{
+    EncodableWebhook, EncodableWebhookDelivery, EncodableWebhookWithSecret,
+};
+use conduit::StatusCode;
+use diesel::prelude::*;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use swirl::Job;
+
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct WebhookResponse {
+    webhook: EncodableWebhookWithSecret,
+}
+
+#[derive(Deserialize)]
+struct WebhookList {
+    webhooks: Vec<EncodableWebhook>,
+}
+
+#[derive(Deserialize)]
+struct DeliveryList {
+    deliveries: Vec<EncodableWebhookDelivery>,
+}
+
+/// A request received by a `receiver`.
+struct Received {
+    path: String,
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+impl Received {
+    fn payload(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+
+    fn is_signed_with(&self, secret: &str) -> bool {
+        let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).unwrap();
+        mac.update(self.body.as_bytes());
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        self.headers["x-crates-io-signature"] == signature
+    }
+}
+
+/// Starts a local HTTP server receiving webhook deliveries, which responds to each of them with
+/// the next of the given statuses.
+fn receiver(statuses: &[u16]) -> (String, mpsc::Receiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let statuses = statuses.to_vec();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        for status in statuses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let path = request_line.split(' ').nth(1).unwrap().to_string();
+
+            let mut headers = HashMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_at(line.find(':').unwrap());
+                headers.insert(name.to_lowercase(), value[1..].trim().to_string());
+            }
+
+            let mut body = vec![0; headers["content-length"].parse().unwrap()];
+            reader.read_exact(&mut body).unwrap();
+
+            let response = format!(
+                "HTTP/1.1 {} Webhook\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+
+            let body = String::from_utf8(body).unwrap();
+            tx.send(Received {
+                path,
+                headers,
+                body,
+            })
+            .unwrap();
+        }
+    });
+
+    (url, rx)
+}
+
+fn new_webhook_body(url: String, events: &[&str]) -> Vec<u8> {
+    json!({ "webhook": { "url": url, "events": events } })
+        .to_string()
+        .into_bytes()
+}
+
+#[test]
+fn webhooks_receive_signed_events() {
+    // The receiver listens on a local address over plain HTTP
+    let (app, _, user, token) = TestApp::init()
+        .with_config(|config| config.webhooks_allow_private_urls = true)
+        .with_git_index()
+        .with_job_runner()
+        .with_token();
+    let other_owner = app.db_new_user("bar");
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_webhook", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        diesel::insert_into(crate_owners::table)
+            .values(&CrateOwner {
+                crate_id: krate.id,
+                owner_id: other_owner.as_model().id,
+                created_by: user.as_model().id,
+                owner_kind: 0,
+                email_notifications: true,
+                rights: Rights::FULL,
+            })
+            .execute(conn)
+            .unwrap();
+    });
+
+    let (url, received) = receiver(&[200, 500, 200, 200]);
+    let body = new_webhook_body(format!("{}/crate", url), &["yank", "unyank"]);
+    let crate_webhook = user
+        .put::<WebhookResponse>("/api/v1/crates/foo_webhook/webhooks", &body)
+        .good()
+        .webhook;
+    assert_eq!(crate_webhook.krate.as_deref(), Some("foo_webhook"));
+    let body = new_webhook_body(format!("{}/user", url), &["owner_change"]);
+    let user_webhook = user
+        .put::<WebhookResponse>("/api/v1/me/webhooks", &body)
+        .good()
+        .webhook;
+    assert_eq!(user_webhook.krate, None);
+
+    token
+        .delete::<OkBool>("/api/v1/crates/foo_webhook/1.0.0/yank")
+        .good();
+    app.run_pending_background_jobs();
+
+    let delivery = received.recv_timeout(RECEIVE_TIMEOUT).unwrap();
+    assert_eq!(delivery.path, "/crate");
+    assert_eq!(delivery.headers["x-crates-io-event"], "yank");
+    assert!(delivery.is_signed_with(&crate_webhook.secret));
+    assert_eq!(
+        delivery.payload(),
+        json!({ "event": "yank", "crate": "foo_webhook", "version": "1.0.0", "user": "foo" })
+    );
+
+    // Failed deliveries are logged and retried later
+    token
+        .put::<OkBool>("/api/v1/crates/foo_webhook/1.0.0/unyank", b"")
+        .good();
+    app.run_pending_background_jobs();
+    let delivery = received.recv_timeout(RECEIVE_TIMEOUT).unwrap();
+    assert_eq!(delivery.headers["x-crates-io-event"], "unyank");
+
+    let log_url = format!(
+        "/api/v1/crates/foo_webhook/webhooks/{}/deliveries",
+        crate_webhook.id
+    );
+    let json: DeliveryList = user.get(&log_url).good();
+    assert_eq!(json.deliveries.len(), 2);
+    let failed = &json.deliveries[0];
+    assert_eq!(failed.event, "unyank");
+    assert_eq!(failed.attempts, 1);
+    assert_eq!(failed.status_code, Some(500));
+    assert!(failed.delivered_at.is_none());
+    assert!(failed.next_attempt_at.is_some());
+    assert_eq!(json.deliveries[1].status_code, Some(200));
+    assert!(json.deliveries[1].delivered_at.is_some());
+
+    app.db(|conn| {
+        diesel::update(webhook_deliveries::table)
+            .filter(webhook_deliveries::next_attempt_at.is_not_null())
+            .set(webhook_deliveries::next_attempt_at.eq(diesel::dsl::now))
+            .execute(conn)
+            .unwrap();
+        tasks::deliver_webhooks().enqueue(conn).unwrap();
+    });
+    app.run_pending_background_jobs();
+    let delivery = received.recv_timeout(RECEIVE_TIMEOUT).unwrap();
+    assert_eq!(
+        delivery.headers["x-crates-io-delivery"],
+        failed.id.to_string()
+    );
+
+    let json: DeliveryList = user.get(&log_url).good();
+    let retried = &json.deliveries[0];
+    assert_eq!(retried.attempts, 2);
+    assert_eq!(retried.status_code, Some(200));
+    assert!(retried.error.is_none());
+    assert!(retried.delivered_at.is_some());
+    assert!(retried.next_attempt_at.is_none());
+
+    // Webhooks of users receive the events of all the crates they own
+    token.remove_named_owner("foo_webhook", "bar").good();
+    app.run_pending_background_jobs();
+    let delivery = received.recv_timeout(RECEIVE_TIMEOUT).unwrap();
+    assert_eq!(delivery.path, "/user");
+    assert!(delivery.is_signed_with(&user_webhook.secret));
+    assert_eq!(
+        delivery.payload(),
+        json!({
+            "event": "owner_change",
+            "crate": "foo_webhook",
+            "action": "remove",
+            "user": "foo",
+            "owners": ["foo"],
+        })
+    );
+
+    let url = format!("/api/v1/me/webhooks/{}", user_webhook.id);
+    user.delete::<OkBool>(&url).good();
+    let json: WebhookList = user.get("/api/v1/me/webhooks").good();
+    assert!(json.webhooks.is_empty());
+    let json: WebhookList = user.get("/api/v1/crates/foo_webhook/webhooks").good();
+    assert_eq!(json.webhooks.len(), 1);
+}
+
+#[test]
+fn webhooks_are_validated() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_webhook", user.as_model().id).expect_build(conn);
+    });
+
+    let body = new_webhook_body("ftp://203.0.113.1".into(), &["publish"]);
+    let response = user.put::<()>("/api/v1/me/webhooks", &body);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = new_webhook_body("https://203.0.113.1".into(), &["download"]);
+    let response = user.put::<()>("/api/v1/me/webhooks", &body);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Webhooks can't be used to reach the internal network
+    let bad_url = |url: &str, error: &str| {
+        let body = new_webhook_body(url.into(), &["publish"]);
+        let json = user.put::<()>("/api/v1/me/webhooks", &body).json();
+        assert_eq!(json, json!({ "errors": [{ "detail": error }] }), "{}", url);
+    };
+    bad_url(
+        "http://203.0.113.1/hook",
+        "the webhook URL must be an HTTPS URL",
+    );
+    let error = "the webhook host must only resolve to public IP addresses";
+    bad_url("https://127.0.0.1:8080/hook", error);
+    bad_url("https://localhost/hook", error);
+    bad_url("https://169.254.169.254/latest/meta-data", error);
+    bad_url("https://[fd00::1]/hook", error);
+
+    // Only owners can manage the webhooks of a crate
+    let other_user = app.db_new_user("bar");
+    let body = new_webhook_body("https://203.0.113.1".into(), &["publish"]);
+    let json = other_user
+        .put::<()>("/api/v1/crates/foo_webhook/webhooks", &body)
+        .json();
+    assert_eq!(
+        json,
+        json!({ "errors": [{ "detail": "only owners have permission to manage the webhooks of a crate" }] })
+    );
+
+    // Webhooks of other users can't be deleted or inspected
+    let json: WebhookResponse = user.put("/api/v1/me/webhooks", &body).good();
+    let url = format!("/api/v1/me/webhooks/{}", json.webhook.id);
+    other_user.delete::<()>(&url).assert_not_found();
+    other_user
+        .get::<()>(&format!("{}/deliveries", url))
+        .assert_not_found();
+}
+
+#[test]
+fn deliveries_to_private_addresses_are_refused() {
+    let (app, _, user, token) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_token();
+    let (listener_url, received) = receiver(&[200]);
+    let webhook_id = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_webhook", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        // As if the host of the webhook resolved to a private address after its registration
+        let url = format!("{}/hook", listener_url.replace("http://", "https://"));
+        let events = vec!["yank".to_string()];
+        NewWebhook::new(Some(krate.id), user.as_model().id, &url, &events)
+            .create(conn)
+            .unwrap()
+            .id
+    });
+
+    token
+        .delete::<OkBool>("/api/v1/crates/foo_webhook/1.0.0/yank")
+        .good();
+    app.run_pending_background_jobs();
+
+    assert!(received.recv_timeout(Duration::from_millis(500)).is_err());
+    let url = format!(
+        "/api/v1/crates/foo_webhook/webhooks/{}/deliveries",
+        webhook_id
+    );
+    let json: DeliveryList = user.get(&url).good();
+    assert_eq!(json.deliveries.len(), 1);
+    let delivery = &json.deliveries[0];
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.status_code, None);
+    assert_eq!(
+        delivery.error.as_deref(),
+        Some("the webhook host must only resolve to public IP addresses")
+    );
+    assert!(delivery.next_attempt_at.is_some());
+}
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use chrono::NaiveDate;
 use conduit::RequestExt;
 use flate2::read::GzDecoder;
 use reqwest::{blocking::Client, header};
@@ -10,6 +11,7 @@ use crate::util::{LimitErrorReader, Maximums};
 use std::env;
 use std::fs::{self, File};
 use std::io::{Cursor, Read};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::middleware::app::RequestApp;
@@ -75,6 +77,27 @@ impl Uploader {
         }
     }
 
+    /// Returns the URL of an uploaded archive of the daily downloads of a month.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn version_downloads_archive_location(&self, month: NaiveDate) -> String {
+        match *self {
+            Uploader::S3 {
+                ref bucket,
+                ref cdn,
+                ..
+            } => {
+                let host = match *cdn {
+                    Some(ref s) => s.clone(),
+                    None => bucket.host(),
+                };
+                let path = Uploader::version_downloads_archive_path(month);
+                format!("https://{}/{}", host, path)
+            }
+            Uploader::Local => format!("/{}", Uploader::version_downloads_archive_path(month)),
+        }
+    }
+
     /// Returns the internal path of an uploaded crate's version archive.
     fn crate_path(name: &str, version: &str) -> String {
         // No slash in front so we can use join
@@ -86,6 +109,16 @@ impl Uploader {
         format!("readmes/{}/{}-{}.html", name, name, version)
     }
 
+    /// Returns the internal path of the archive of the daily downloads of a month.
+    pub fn version_downloads_archive_path(month: NaiveDate) -> String {
+        format!("archive/version-downloads/{}.csv.gz", month.format("%Y-%m"))
+    }
+
+    /// Returns the location on disk of a file uploaded with `Uploader::Local`.
+    pub fn local_uploads_path(path: &str) -> PathBuf {
+        env::current_dir().unwrap().join("local_uploads").join(path)
+    }
+
     /// Uploads a file using the configured uploader (either `S3`, `Local`).
     ///
     /// It returns the path of the uploaded file.
@@ -116,7 +149,7 @@ impl Uploader {
                 Ok(Some(String::from(path)))
             }
             Uploader::Local => {
-                let filename = env::current_dir().unwrap().join("local_uploads").join(path);
+                let filename = Uploader::local_uploads_path(path);
                 let dir = filename.parent().unwrap();
                 fs::create_dir_all(dir)?;
                 let mut file = File::create(&filename)?;
@@ -252,6 +252,28 @@ impl fmt::Display for OwnershipInvitationExpired {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct OwnershipTransferExpired {
+    pub(crate) crate_name: String,
+}
+
+impl AppError for OwnershipTransferExpired {
+    fn response(&self) -> Option<AppResponse> {
+        Some(json_error(&self.to_string(), StatusCode::GONE))
+    }
+}
+
+impl fmt::Display for OwnershipTransferExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The request to transfer ownership of the {} crate expired. \
+             Please reach out to an owner of the crate to request a new transfer.",
+            self.crate_name
+        )
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct MetricsDisabled;
 
@@ -1,12 +1,13 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use std::collections::HashMap;
 use url::Url;
 
 use crate::github;
 use crate::models::{
-    Badge, Category, Crate, CrateOwnerInvitation, CreatedApiToken, Dependency, DependencyKind,
-    Keyword, Owner, ReverseDependency, Team, TopVersions, User, Version, VersionDownload,
-    VersionOwnerAction,
+    Advisory, Badge, Category, Crate, CrateOwnerAction, CrateOwnerInvitation, CreatedApiToken,
+    Dependency, DependencyKind, Keyword, NotificationPreferences, Owner, ReverseDependency, Team,
+    TopVersions, User, Version, VersionDownload, VersionDownloadByClient, VersionDownloadRollup,
+    VersionOwnerAction, Webhook, WebhookDelivery,
 };
 use crate::util::rfc3339;
 
@@ -27,6 +28,60 @@ impl From<Badge> for EncodableBadge {
     }
 }
 
+/// A security advisory, see `Advisory`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAdvisory {
+    pub id: String,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub title: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub date: NaiveDate,
+    pub informational: Option<String>,
+    pub aliases: Vec<String>,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+    /// The published versions of the crate affected by the advisory.
+    pub affected_versions: Vec<String>,
+}
+
+impl EncodableAdvisory {
+    pub fn from(advisory: Advisory, crate_name: &str, versions: &[semver::Version]) -> Self {
+        let affected_versions = versions
+            .iter()
+            .filter(|version| advisory.affects(version))
+            .map(|version| version.to_string())
+            .collect();
+        let Advisory {
+            id,
+            title,
+            description,
+            url,
+            date,
+            informational,
+            aliases,
+            patched,
+            unaffected,
+            ..
+        } = advisory;
+
+        Self {
+            id,
+            krate: crate_name.to_string(),
+            title,
+            description,
+            url,
+            date,
+            informational,
+            aliases,
+            patched,
+            unaffected,
+            affected_versions,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableCategory {
     pub id: String,
@@ -152,6 +207,38 @@ impl EncodableDependency {
     }
 }
 
+/// A crate version of a resolved dependency tree.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencyTreeNode {
+    /// The index of the node in the tree, the root being `0`.
+    pub id: usize,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    /// The enabled features of the version.
+    pub features: Vec<String>,
+    /// The length of the shortest path from the root.
+    pub depth: usize,
+    pub dependencies: Vec<EncodableDependencyTreeEdge>,
+    /// Whether some dependencies of this version are missing because of the depth or node
+    /// count limits.
+    pub truncated: bool,
+}
+
+/// A dependency between two nodes of a resolved dependency tree.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencyTreeEdge {
+    /// The `id` of the node of the resolved version, or `None` if no published version matches `req`.
+    pub node: Option<usize>,
+    pub crate_id: String,
+    pub req: String,
+    pub optional: bool,
+    pub default_features: bool,
+    pub features: Vec<String>,
+    pub target: Option<String>,
+    pub kind: DependencyKind,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableVersionDownload {
     pub version: i32,
@@ -169,6 +256,35 @@ impl From<VersionDownload> for EncodableVersionDownload {
     }
 }
 
+impl From<VersionDownloadRollup> for EncodableVersionDownload {
+    fn from(rollup: VersionDownloadRollup) -> Self {
+        Self {
+            version: rollup.version_id,
+            downloads: rollup.downloads,
+            date: rollup.period.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionDownloadByClient {
+    pub version: i32,
+    pub client: String,
+    pub downloads: i32,
+    pub date: String,
+}
+
+impl From<VersionDownloadByClient> for EncodableVersionDownloadByClient {
+    fn from(download: VersionDownloadByClient) -> Self {
+        Self {
+            version: download.version_id,
+            client: download.client,
+            downloads: download.downloads,
+            date: download.date.to_string(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableKeyword {
     pub id: String,
@@ -220,6 +336,9 @@ pub struct EncodableCrate {
     pub repository: Option<String>,
     pub links: EncodableCrateLinks,
     pub exact_match: bool,
+    pub owner_actions: Option<Vec<EncodableAuditAction>>,
+    /// The security advisories of the crate which weren't withdrawn.
+    pub advisories: Option<Vec<EncodableAdvisory>>,
 }
 
 impl EncodableCrate {
@@ -233,6 +352,8 @@ impl EncodableCrate {
         badges: Option<Vec<Badge>>,
         exact_match: bool,
         recent_downloads: Option<i64>,
+        owner_actions: Option<Vec<(CrateOwnerAction, User, Option<Owner>)>>,
+        advisories: Option<Vec<EncodableAdvisory>>,
     ) -> Self {
         let Crate {
             name,
@@ -253,6 +374,14 @@ impl EncodableCrate {
         let category_ids = categories.map(|cats| cats.iter().map(|cat| cat.slug.clone()).collect());
         let badges = badges.map(|bs| bs.into_iter().map(Badge::into).collect());
         let documentation = Self::remove_blocked_documentation_urls(documentation);
+        let owner_actions = owner_actions.map(|actions| {
+            actions
+                .into_iter()
+                .map(|(owner_action, user, target)| {
+                    EncodableAuditAction::from_crate_owner_action(owner_action, user, target)
+                })
+                .collect()
+        });
 
         let max_version = top_versions
             .highest
@@ -295,6 +424,8 @@ impl EncodableCrate {
                 owner_user: Some(format!("/api/v1/crates/{}/owner_user", name)),
                 reverse_dependencies: format!("/api/v1/crates/{}/reverse_dependencies", name),
             },
+            owner_actions,
+            advisories,
         }
     }
 
@@ -314,6 +445,8 @@ impl EncodableCrate {
             badges,
             exact_match,
             recent_downloads,
+            None,
+            None,
         )
     }
 
@@ -364,6 +497,7 @@ pub struct EncodableOwner {
     pub url: Option<String>,
     pub name: Option<String>,
     pub avatar: Option<String>,
+    pub rights: Option<String>,
 }
 
 impl From<Owner> for EncodableOwner {
@@ -384,6 +518,7 @@ impl From<Owner> for EncodableOwner {
                     url: Some(url),
                     name,
                     kind: String::from("user"),
+                    rights: None,
                 }
             }
             Owner::Team(Team {
@@ -401,6 +536,7 @@ impl From<Owner> for EncodableOwner {
                     avatar,
                     name,
                     kind: String::from("team"),
+                    rights: None,
                 }
             }
         }
@@ -465,6 +601,91 @@ impl From<CreatedApiToken> for EncodableApiTokenWithToken {
     }
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableWebhook {
+    pub id: i32,
+    /// The crate of the webhook, or `None` if it was registered by a user for all their crates.
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    pub url: String,
+    pub events: Vec<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableWebhook {
+    pub fn from(webhook: Webhook, crate_name: Option<&str>) -> Self {
+        Self {
+            id: webhook.id,
+            krate: crate_name.map(String::from),
+            url: webhook.url,
+            events: webhook.events,
+            created_at: webhook.created_at,
+        }
+    }
+}
+
+/// The serialization format for a webhook, including its secret.
+///
+/// This should only be used when initially creating a webhook, since the secret is needed to
+/// verify the signatures of the payloads.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableWebhookWithSecret {
+    pub id: i32,
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    pub url: String,
+    pub events: Vec<String>,
+    pub secret: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableWebhookWithSecret {
+    pub fn from(webhook: Webhook, crate_name: Option<&str>) -> Self {
+        Self {
+            id: webhook.id,
+            krate: crate_name.map(String::from),
+            url: webhook.url,
+            events: webhook.events,
+            secret: webhook.secret,
+            created_at: webhook.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableWebhookDelivery {
+    pub id: i32,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    #[serde(with = "rfc3339::option")]
+    pub delivered_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339::option")]
+    pub next_attempt_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl From<WebhookDelivery> for EncodableWebhookDelivery {
+    fn from(delivery: WebhookDelivery) -> Self {
+        Self {
+            id: delivery.id,
+            event: delivery.event,
+            payload: delivery.payload,
+            attempts: delivery.attempts,
+            status_code: delivery.status_code,
+            error: delivery.error,
+            delivered_at: delivery.delivered_at,
+            next_attempt_at: delivery.next_attempt_at,
+            created_at: delivery.created_at,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct OwnedCrate {
     pub id: i32,
@@ -472,6 +693,61 @@ pub struct OwnedCrate {
     pub email_notifications: bool,
 }
 
+/// The categories of notification emails a user is subscribed to.
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct EncodableNotificationPreferences {
+    pub publish: bool,
+    pub yank: bool,
+    pub ownership: bool,
+    pub advisories: bool,
+    pub token_expiry: bool,
+}
+
+impl Default for EncodableNotificationPreferences {
+    /// Users who never changed their preferences are subscribed to all the categories.
+    fn default() -> Self {
+        Self {
+            publish: true,
+            yank: true,
+            ownership: true,
+            advisories: true,
+            token_expiry: true,
+        }
+    }
+}
+
+impl From<NotificationPreferences> for EncodableNotificationPreferences {
+    fn from(preferences: NotificationPreferences) -> Self {
+        let NotificationPreferences {
+            publish,
+            yank,
+            ownership,
+            advisories,
+            token_expiry,
+            ..
+        } = preferences;
+        Self {
+            publish,
+            yank,
+            ownership,
+            advisories,
+            token_expiry,
+        }
+    }
+}
+
+/// The notification preferences of an owner for one of their crates.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableCrateNotificationPreferences {
+    pub id: i32,
+    pub name: String,
+    pub email_notifications: bool,
+    pub publish: bool,
+    pub yank: bool,
+    pub ownership: bool,
+    pub advisories: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableMe {
     pub user: EncodablePrivateUser,
@@ -559,10 +835,38 @@ impl From<User> for EncodablePublicUser {
 pub struct EncodableAuditAction {
     pub action: String,
     pub user: EncodablePublicUser,
+    /// The owner who was invited, added or removed by the user, for actions on crate owners,
+    /// along with the rights it was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<EncodableOwner>,
     #[serde(with = "rfc3339")]
     pub time: NaiveDateTime,
 }
 
+impl EncodableAuditAction {
+    pub fn from_crate_owner_action(
+        owner_action: CrateOwnerAction,
+        user: User,
+        target: Option<Owner>,
+    ) -> Self {
+        Self {
+            action: owner_action.action.into(),
+            user: user.into(),
+            target: target.map(|target| {
+                let rights = owner_action.rights.map(|rights| {
+                    let rights: &'static str = rights.into();
+                    rights.to_string()
+                });
+                EncodableOwner {
+                    rights,
+                    ..target.into()
+                }
+            }),
+            time: owner_action.time,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableVersion {
     pub id: i32,
@@ -581,10 +885,14 @@ pub struct EncodableVersion {
     pub yanked: bool,
     // NOTE: Used by shields.io, altering `license` requires a PR with shields.io
     pub license: Option<String>,
+    /// The minimum supported Rust version declared by the `rust-version` manifest field.
+    pub rust_version: Option<String>,
     pub links: EncodableVersionLinks,
     pub crate_size: Option<i32>,
     pub published_by: Option<EncodablePublicUser>,
     pub audit_actions: Vec<EncodableAuditAction>,
+    /// The IDs of the security advisories affecting this version.
+    pub advisories: Vec<String>,
 }
 
 impl EncodableVersion {
@@ -593,6 +901,7 @@ impl EncodableVersion {
         crate_name: &str,
         published_by: Option<User>,
         audit_actions: Vec<(VersionOwnerAction, User)>,
+        advisories: Vec<String>,
     ) -> Self {
         let Version {
             id,
@@ -604,6 +913,7 @@ impl EncodableVersion {
             yanked,
             license,
             crate_size,
+            rust_version,
             ..
         } = version;
 
@@ -621,6 +931,7 @@ impl EncodableVersion {
             features,
             yanked,
             license,
+            rust_version,
             links: EncodableVersionLinks {
                 dependencies: format!("/api/v1/crates/{}/{}/dependencies", crate_name, num),
                 version_downloads: format!("/api/v1/crates/{}/{}/downloads", crate_name, num),
@@ -633,9 +944,11 @@ impl EncodableVersion {
                 .map(|(audit_action, user)| EncodableAuditAction {
                     action: audit_action.action.into(),
                     user: user.into(),
+                    target: None,
                     time: audit_action.time,
                 })
                 .collect(),
+            advisories,
         }
     }
 }
@@ -667,7 +980,6 @@ pub use self::krate_publish::{EncodableCrateDependency, EncodableCrateUpload};
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::NaiveDate;
 
     #[test]
     fn category_dates_serializes_to_rfc3339() {
@@ -731,6 +1043,7 @@ mod tests {
             features: serde_json::from_str("{}").unwrap(),
             yanked: false,
             license: None,
+            rust_version: None,
             links: EncodableVersionLinks {
                 dependencies: "".to_string(),
                 version_downloads: "".to_string(),
@@ -747,8 +1060,10 @@ mod tests {
                     avatar: None,
                     url: None,
                 },
+                target: None,
                 time: NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 12),
             }],
+            advisories: vec![],
         };
         let json = serde_json::to_string(&ver).unwrap();
         assert_some!(json
@@ -789,6 +1104,8 @@ mod tests {
                 reverse_dependencies: "".to_string(),
             },
             exact_match: false,
+            owner_actions: None,
+            advisories: None,
         };
         let json = serde_json::to_string(&crt).unwrap();
         assert_some!(json
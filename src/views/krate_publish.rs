@@ -34,6 +34,8 @@ pub struct EncodableCrateUpload {
     pub badges: Option<HashMap<String, HashMap<String, String>>>,
     #[serde(default)]
     pub links: Option<String>,
+    #[serde(default)]
+    pub rust_version: Option<String>,
 }
 
 #[derive(PartialEq, Eq, Hash, Serialize, Debug, Deref)]